@@ -0,0 +1,165 @@
+//! Uploading of archived versions to archive.org items through its S3-compatible API.
+
+use crate::http::file_body;
+use crate::{ArchiveError, RetryPolicy, Version};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::header::CONTENT_LENGTH;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Endpoint of the S3-compatible API of archive.org.
+pub const S3_URL: &str = "https://s3.us.archive.org";
+
+/// Default template of the identifier of the item a version is uploaded to.
+pub const DEFAULT_IDENTIFIER_TEMPLATE: &str = "cosmic-reach-{artifact}-{id}";
+
+/// Default templates of the metadata of the item a version is uploaded to.
+pub const DEFAULT_METADATA: [(&str, &str); 5] = [
+    ("mediatype", "software"),
+    ("title", "Cosmic Reach {id} ({artifact})"),
+    ("date", "{release_date}"),
+    ("creator", "FinalForEach"),
+    (
+        "description",
+        "Archived {artifact} JAR of Cosmic Reach {id}, with sha256 hash {sha256}.",
+    ),
+];
+
+/// An archive.org item to upload a version to, along with the credentials to do so.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchiveOrgItem {
+    /// Access key of the S3-compatible API.
+    pub access_key: String,
+    /// Secret key of the S3-compatible API.
+    pub secret_key: String,
+    /// Identifier of the item.
+    pub identifier: String,
+    /// Collection the item is created in, if any.
+    pub collection: Option<String>,
+    /// Metadata the item is created with.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ArchiveOrgItem {
+    /// Creates the item of `version`, rendering its identifier from `identifier_template` and its
+    /// metadata from the [`DEFAULT_METADATA`] overridden by `metadata_templates`.
+    ///
+    /// See [`Version::render`] for the placeholders of every template.
+    pub fn for_version(
+        access_key: String,
+        secret_key: String,
+        identifier_template: &str,
+        collection: Option<String>,
+        metadata_templates: &BTreeMap<String, String>,
+        version: &Version,
+    ) -> Self {
+        let mut metadata = DEFAULT_METADATA
+            .into_iter()
+            .map(|(key, template)| (key.to_owned(), template.to_owned()))
+            .collect::<BTreeMap<_, _>>();
+        metadata.extend(metadata_templates.clone());
+
+        Self {
+            access_key,
+            secret_key,
+            identifier: version.render(identifier_template),
+            collection,
+            metadata: metadata
+                .into_iter()
+                .map(|(key, template)| (key, version.render(&template)))
+                .collect(),
+        }
+    }
+
+    /// Returns the URL to the details page of the item.
+    pub fn details_url(&self) -> String {
+        format!("https://archive.org/details/{}", self.identifier)
+    }
+}
+
+/// Uploads the game JAR at `jar` of `version` and its entry to `item`, creating it if absent. The
+/// game JAR is streamed rather than read into memory.
+pub async fn upload_version(
+    client: &itch_io::Client,
+    item: &ArchiveOrgItem,
+    version: &Version,
+    jar: &Path,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let file_name = jar
+        .file_name()
+        .and_then(|it| it.to_str())
+        .ok_or_else(|| ArchiveError::UnknownVersionId(jar.to_path_buf()))?;
+    let entry_name = format!("{}.json", version.id);
+
+    let jar_file = File::open(jar)?;
+    let entry_bytes = serde_json::to_vec_pretty(version)?;
+
+    upload_file(client, item, file_name, || file_body(&jar_file), retry).await?;
+    let entry_body = || Ok((entry_bytes.clone().into(), entry_bytes.len() as u64));
+    upload_file(client, item, &entry_name, entry_body, retry).await?;
+
+    warn!("Uploaded {} to {}", version.id, item.details_url());
+    Ok(())
+}
+
+/// Uploads the file named `file_name` to `item`, sending the body returned by `body` along with its
+/// size in bytes on every attempt.
+async fn upload_file<F>(
+    client: &itch_io::Client,
+    item: &ArchiveOrgItem,
+    file_name: &str,
+    body: F,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError>
+where
+    F: Fn() -> Result<(reqwest::Body, u64), ArchiveError>,
+{
+    let url = format!(
+        "{S3_URL}/{}/{}",
+        item.identifier,
+        utf8_percent_encode(file_name, NON_ALPHANUMERIC)
+    );
+    let (url, body) = (&url, &body);
+
+    retry
+        .retry("upload to archive.org", || async move {
+            let (body, size) = body()?;
+            info!("Sending PUT request to archive.org ({url})...");
+            let mut request = client
+                .client
+                .put(url)
+                .header(
+                    "authorization",
+                    format!("LOW {}:{}", item.access_key, item.secret_key),
+                )
+                .header("x-archive-auto-make-bucket", "1")
+                .header("x-archive-size-hint", size.to_string())
+                .header(CONTENT_LENGTH, size)
+                .body(body);
+            if let Some(collection) = &item.collection {
+                request = request.header("x-archive-meta-collection", header_value(collection));
+            }
+            for (key, value) in &item.metadata {
+                // NOTE: archive.org reads `--` in header names as `_`
+                let name = format!("x-archive-meta-{}", key.replace('_', "--"));
+                request = request.header(name, header_value(value));
+            }
+
+            request.send().await?.error_for_status()?;
+            Ok::<_, ArchiveError>(())
+        })
+        .await
+}
+
+/// Encodes `value` as a metadata header value, which archive.org expects to be `uri(...)`
+/// percent-encoded unless it is printable ASCII.
+fn header_value(value: &str) -> String {
+    if value.bytes().all(|it| it.is_ascii_graphic() || it == b' ') {
+        value.to_owned()
+    } else {
+        format!("uri({})", utf8_percent_encode(value, NON_ALPHANUMERIC))
+    }
+}
@@ -0,0 +1,43 @@
+//! Atomic writes of local files, which never leave a partially written file behind.
+
+use crate::ArchiveError;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Writes the file at `destination` through `write`.
+///
+/// `write` writes a temporary file next to `destination`, which only replaces it once `write`
+/// succeeds and is otherwise removed.
+pub fn write_atomically<T, F>(destination: &Path, write: F) -> Result<T, ArchiveError>
+where
+    F: FnOnce(&mut File) -> Result<T, ArchiveError>,
+{
+    let temporary = temporary_path(destination);
+    let written = File::create(&temporary)
+        .map_err(ArchiveError::from)
+        .and_then(|mut file| {
+            let written = write(&mut file)?;
+            file.sync_all()?;
+            Ok(written)
+        });
+
+    match written {
+        Ok(it) => {
+            fs::rename(&temporary, destination)?;
+            Ok(it)
+        }
+        Err(cause) => {
+            // NOTE: the write already failed, which is more worth reporting
+            let _ = fs::remove_file(&temporary);
+            Err(cause)
+        }
+    }
+}
+
+/// Returns the path of the temporary file written in place of `destination`, e.g.
+/// `versions.json.tmp`.
+pub fn temporary_path(destination: &Path) -> PathBuf {
+    let mut temporary = destination.as_os_str().to_owned();
+    temporary.push(".tmp");
+    PathBuf::from(temporary)
+}
@@ -0,0 +1,451 @@
+//! Authentication of the download info requests of itch.io uploads, either with an API key or a
+//! CSRF token, which can be of a session logged into and cached encrypted on disk.
+
+use crate::atomic::write_atomically;
+use crate::http::{client_builder, ClientOptions};
+use crate::{ArchiveError, RetryPolicy};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use regex::Regex;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::AUTHORIZATION;
+use sha1::Sha1;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Base URL of the API of itch.io, as used by its app and butler.
+pub const ITCH_API_URL: &str = "https://api.itch.io";
+
+/// URL of itch.io itself, which sessions are logged into.
+pub const ITCH_URL: &str = "https://itch.io";
+
+/// Size in bytes of the salt leading every encrypted session file, which its key is derived with.
+const SALT_SIZE: usize = 16;
+
+/// Size in bytes of the nonce following the salt of every encrypted session file.
+const NONCE_SIZE: usize = 12;
+
+/// Credentials to request the download info of itch.io uploads with.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ItchAuth {
+    /// API key of an itch.io account, tried first if present.
+    pub api_key: Option<String>,
+    /// Id of the download key of the game owned by the account of `api_key`, needed for games that
+    /// are NOT free.
+    pub download_key: Option<u64>,
+    /// CSRF token of a browser session, possibly empty, tried last.
+    pub csrf_token: String,
+}
+
+/// Way a download info request is authenticated.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum AuthMethod {
+    /// With the API key, through the itch.io API.
+    ApiKey,
+    /// With the CSRF token, through the game page.
+    CsrfToken,
+    /// With NO credentials at all, through the game page, with the CSRF token it hands out to
+    /// anonymous visitors, which only free uploads are downloaded with.
+    Anonymous,
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ApiKey => "the API key",
+            Self::CsrfToken => "the CSRF token",
+            Self::Anonymous => "NO credentials, as an anonymous visitor",
+        })
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ApiErrors {
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+impl ItchAuth {
+    /// Returns every way of authenticating with these credentials, in the order they are tried.
+    pub fn methods(&self) -> Vec<AuthMethod> {
+        let mut methods = Vec::new();
+        if self.api_key.is_some() {
+            methods.push(AuthMethod::ApiKey);
+        }
+        methods.push(if self.csrf_token.is_empty() {
+            AuthMethod::Anonymous
+        } else {
+            AuthMethod::CsrfToken
+        });
+        methods
+    }
+}
+
+/// Requests the download of `upload_id` through the itch.io API with `api_key`, returning the URL
+/// it redirects to.
+///
+/// The body of the download itself is never read, the returned URL being downloaded afterwards
+/// just like the one of the download info of the game page.
+pub async fn get_api_download_url(
+    client: &itch_io::Client,
+    api_key: &str,
+    download_key: Option<u64>,
+    upload_id: u64,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    let url = format!("{ITCH_API_URL}/uploads/{upload_id}/download");
+    let url = &url;
+
+    retry
+        .retry("get download info from the itch.io API", || async move {
+            info!("Requesting download of upload {upload_id} from the itch.io API...");
+            let mut request = client.client.get(url).header(AUTHORIZATION, api_key);
+            if let Some(download_key) = download_key {
+                request = request.query(&[("download_key_id", download_key)]);
+            }
+            let response = request.send().await?.error_for_status()?;
+
+            // NOTE: the API responds with its errors instead of redirecting if the request fails
+            if response.url().as_str().starts_with(ITCH_API_URL) {
+                let errors = response.json::<ApiErrors>().await?.errors;
+                return Err(ArchiveError::ItchApi(if errors.is_empty() {
+                    "NO download was redirected to".to_owned()
+                } else {
+                    errors.join(", ")
+                }));
+            }
+            Ok::<_, ArchiveError>(response.url().to_string())
+        })
+        .await
+}
+
+/// Credentials of an itch.io account to log in with.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Credentials {
+    /// Username or email of the account.
+    pub username: String,
+    /// Password of the account.
+    pub password: String,
+    /// Base32 secret of the two-factor authentication of the account, if enabled.
+    pub totp_secret: Option<String>,
+}
+
+/// A session of an itch.io account, whose CSRF token and cookies authenticate download info
+/// requests.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// CSRF token of the session.
+    pub csrf_token: String,
+    /// Cookies of the session, as the value of a `Cookie` header.
+    pub cookies: String,
+    /// Unix timestamp, in seconds, of when the session was logged into.
+    pub logged_in_at: u64,
+}
+
+impl Session {
+    /// Returns how long ago the session was logged into.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.logged_in_at))
+    }
+
+    /// Reads the session encrypted with the passphrase `key` from the local file at `path`.
+    pub fn read_from<P: AsRef<Path>>(path: P, key: &str) -> Result<Self, ArchiveError> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < SALT_SIZE + NONCE_SIZE {
+            return Err(ArchiveError::Session("file is truncated".to_owned()));
+        }
+
+        let (salt, rest) = bytes.split_at(SALT_SIZE);
+        let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+        let plaintext = cipher(key, salt)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| ArchiveError::Session("wrong key, or the file is corrupt".to_owned()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Writes the session encrypted with the passphrase `key` to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P, key: &str) -> Result<(), ArchiveError> {
+        let mut salt = [0; SALT_SIZE];
+        let mut nonce = [0; NONCE_SIZE];
+        rand::thread_rng().fill(&mut salt);
+        rand::thread_rng().fill(&mut nonce);
+        let plaintext = serde_json::to_vec(self)?;
+        let ciphertext = cipher(key, &salt)?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| ArchiveError::Session("failed to encrypt".to_owned()))?;
+
+        write_atomically(path.as_ref(), |file| {
+            file.write_all(&salt)?;
+            file.write_all(&nonce)?;
+            Ok(file.write_all(&ciphertext)?)
+        })
+    }
+}
+
+/// Returns the cipher of session files encrypted with the passphrase `key`, whose key is derived
+/// with Argon2id and `salt`, so that stolen files are costly to brute-force.
+fn cipher(key: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, ArchiveError> {
+    let mut derived = [0; 32];
+    Argon2::default()
+        .hash_password_into(key.as_bytes(), salt, &mut derived)
+        .map_err(|cause| ArchiveError::Session(format!("failed to derive key: {cause}")))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&derived)))
+}
+
+/// Logs into itch.io with `credentials`, returning the fresh session.
+///
+/// Every request of the login is sent by a client built with `options`, which keeps the cookies
+/// it is given. Fails with [`ArchiveError::Login`] if itch.io rejects the credentials.
+pub async fn log_in(
+    options: &ClientOptions,
+    credentials: &Credentials,
+    retry: &RetryPolicy,
+) -> Result<Session, ArchiveError> {
+    let jar = Arc::new(Jar::default());
+    let client = client_builder(options)?
+        .cookie_provider(jar.clone())
+        .build()?;
+    let login_url = format!("{ITCH_URL}/login");
+    let (client, login_url) = (&client, &login_url);
+
+    let page = get_page(client, login_url, retry).await?;
+    let csrf_token = &find_csrf_token(&page)?;
+    let (url, page) = retry
+        .retry("log into itch.io", || async move {
+            warn!("Logging into itch.io as {}...", credentials.username);
+            let response = client
+                .post(login_url)
+                .form(&[
+                    ("csrf_token", csrf_token.as_str()),
+                    ("username", credentials.username.as_str()),
+                    ("password", credentials.password.as_str()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>((response.url().clone(), response.text().await?))
+        })
+        .await?;
+
+    let page = if url.path().starts_with("/totp") {
+        let Some(secret) = &credentials.totp_secret else {
+            return Err(ArchiveError::Login(
+                "the account needs a two-factor authentication code, see `--totp-secret`"
+                    .to_owned(),
+            ));
+        };
+        let csrf_token = find_csrf_token(&page)?;
+        let (url, csrf_token) = (&url, &csrf_token);
+        retry
+            .retry("verify two-factor authentication code", || async move {
+                info!("Verifying two-factor authentication code...");
+                let code = totp_code(secret, unix_now())?;
+                let response = client
+                    .post(url.clone())
+                    .form(&[("csrf_token", csrf_token.as_str()), ("code", code.as_str())])
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<_, ArchiveError>(response.text().await?)
+            })
+            .await?
+    } else {
+        page
+    };
+    if let Some(message) = form_error(&page) {
+        return Err(ArchiveError::Login(message));
+    }
+
+    let itch_url = url::Url::parse(ITCH_URL)?;
+    let Some(cookies) = jar.cookies(&itch_url) else {
+        return Err(ArchiveError::Login(
+            "NO session cookies were set".to_owned(),
+        ));
+    };
+    let page = get_page(client, &format!("{ITCH_URL}/my-feed"), retry).await?;
+    let session = Session {
+        csrf_token: find_csrf_token(&page)?,
+        cookies: cookies
+            .to_str()
+            .map_err(|_| ArchiveError::Login("session cookies are NOT text".to_owned()))?
+            .to_owned(),
+        logged_in_at: unix_now(),
+    };
+    warn!("Logged into itch.io as {}", credentials.username);
+    Ok(session)
+}
+
+async fn get_page(
+    client: &reqwest::Client,
+    url: &str,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    retry
+        .retry("get itch.io page", || async move {
+            info!("Sending GET request to {url}...");
+            let response = client.get(url).send().await?.error_for_status()?;
+            Ok::<_, ArchiveError>(response.text().await?)
+        })
+        .await
+}
+
+/// Gets the CSRF token that the itch.io game page at `game_url` hands out to anonymous visitors,
+/// so that free uploads are downloaded without any credentials.
+///
+/// The token is tied to the session cookie set along with it, which the client keeps, see
+/// [`build_client`](crate::http::build_client). Fails with [`ArchiveError::AnonymousDownload`]
+/// if the page hands out NO token.
+pub async fn get_anonymous_csrf_token(
+    client: &itch_io::Client,
+    game_url: &str,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    let page = get_page(&client.client, game_url, retry).await?;
+    csrf_token_in(&page).ok_or_else(|| {
+        ArchiveError::AnonymousDownload("game page hands out NO CSRF token".to_owned())
+    })
+}
+
+/// Finds the CSRF token within the HTML of an itch.io `page`.
+fn find_csrf_token(page: &str) -> Result<String, ArchiveError> {
+    csrf_token_in(page).ok_or_else(|| ArchiveError::Login("page has NO CSRF token".to_owned()))
+}
+
+fn csrf_token_in(page: &str) -> Option<String> {
+    static CSRF_TOKEN: OnceLock<Regex> = OnceLock::new();
+    let csrf_token = CSRF_TOKEN.get_or_init(|| {
+        Regex::new(r#"name="csrf_token"[^>]*value="([^"]+)""#)
+            .expect("CSRF token regex should be valid")
+    });
+    csrf_token.captures(page).map(|it| it[1].to_owned())
+}
+
+/// Finds the first error of a form within the HTML of an itch.io `page`, e.g. of a wrong password.
+fn form_error(page: &str) -> Option<String> {
+    static FORM_ERROR: OnceLock<Regex> = OnceLock::new();
+    let form_error = FORM_ERROR.get_or_init(|| {
+        Regex::new(r#"(?s)class="form_errors".*?<li>([^<]+)</li>"#)
+            .expect("form error regex should be valid")
+    });
+
+    Some(form_error.captures(page)?[1].trim().to_owned())
+}
+
+/// Returns the 6 digit time-based one-time password of the base32 `secret` at the Unix timestamp
+/// `time`, see RFC 6238.
+pub fn totp_code(secret: &str, time: u64) -> Result<String, ArchiveError> {
+    let key = decode_base32(secret).ok_or_else(|| {
+        ArchiveError::Login("two-factor authentication secret is NOT valid base32".to_owned())
+    })?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).expect("HMAC should accept any key size");
+    mac.update(&(time / 30).to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let bytes = [
+        hash[offset],
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ];
+    let code = u32::from_be_bytes(bytes) & 0x7fff_ffff;
+    Ok(format!("{:06}", code % 1_000_000))
+}
+
+/// Decodes the base32 `text` of RFC 4648, ignoring its case, whitespace, and padding.
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for it in text.chars().filter(|it| !it.is_whitespace() && *it != '=') {
+        let value = match it.to_ascii_uppercase() {
+            it @ 'A'..='Z' => it as u32 - 'A' as u32,
+            it @ '2'..='7' => it as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |it| it.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `12345678901234567890` in base32, the secret of the SHA-1 test vectors of RFC 6238.
+    const RFC_6238_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn decodes_base32() {
+        assert_eq!(
+            decode_base32(RFC_6238_SECRET).as_deref(),
+            Some(&b"12345678901234567890"[..])
+        );
+        assert_eq!(
+            decode_base32("mzxw 6ytb oi======").as_deref(),
+            Some(&b"foobar"[..])
+        );
+        assert_eq!(decode_base32("MZXW1"), None);
+    }
+
+    #[test]
+    fn generates_rfc_6238_codes() {
+        for (time, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ] {
+            assert_eq!(totp_code(RFC_6238_SECRET, time).unwrap(), code);
+        }
+        assert!(matches!(
+            totp_code("NOT BASE32!", 59),
+            Err(ArchiveError::Login(_))
+        ));
+    }
+
+    #[test]
+    fn tries_api_key_before_csrf_token() {
+        let auth = ItchAuth {
+            api_key: Some("key".to_owned()),
+            download_key: None,
+            csrf_token: "token".to_owned(),
+        };
+        assert_eq!(auth.methods(), [AuthMethod::ApiKey, AuthMethod::CsrfToken]);
+        assert_eq!(ItchAuth::default().methods(), [AuthMethod::Anonymous]);
+    }
+
+    #[test]
+    fn finds_csrf_token_and_form_error() {
+        let page = r#"<input type="hidden" name="csrf_token" value="abc123"/>
+            <div class="form_errors"><ul><li> Incorrect username or password </li></ul></div>"#;
+        assert_eq!(csrf_token_in(page).as_deref(), Some("abc123"));
+        assert_eq!(
+            form_error(page).as_deref(),
+            Some("Incorrect username or password")
+        );
+        assert_eq!(csrf_token_in("<html></html>"), None);
+    }
+}
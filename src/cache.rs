@@ -0,0 +1,366 @@
+//! Caching of responses and downloaded archives between runs within a cache directory, so that
+//! unchanged resources are NOT downloaded again.
+
+use crate::atomic::write_atomically;
+use crate::hash::hash_reader;
+use crate::{ArchiveError, RetryPolicy, Sha256Hash};
+use reqwest::header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Seek, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// File name of the validators of every cached response within the cache directory.
+const RESPONSES_FILE: &str = "responses.json";
+
+/// Directory the bodies of cached responses are kept in within the cache directory.
+const RESPONSES_DIR: &str = "responses";
+
+/// File name of the hashes of every cached archive by its source within the cache directory.
+const ARCHIVES_FILE: &str = "archives.json";
+
+/// Directory cached archives are kept in by their sha256 hash within the cache directory.
+const ARCHIVES_DIR: &str = "archives";
+
+/// Cache of the responses to GET requests, revalidated with conditional requests once older than
+/// its time to live.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResponseCache {
+    directory: PathBuf,
+    ttl: Option<Duration>,
+}
+
+/// Validators of a cached response, as sent back in a conditional request.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    /// Unix timestamp, in seconds, of when the response was cached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cached_at: Option<u64>,
+}
+
+impl ResponseCache {
+    /// Creates the cache kept within `directory`, which is created once something is cached.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            ttl: None,
+        }
+    }
+
+    /// Returns the cache with its responses used without revalidating them until older than
+    /// `ttl`, always revalidating them if absent.
+    pub fn with_ttl(self, ttl: Option<Duration>) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Checks whether the response cached with `validators` is younger than the time to live.
+    fn is_fresh(&self, validators: &Validators) -> bool {
+        let (Some(ttl), Some(cached_at)) = (self.ttl, validators.cached_at) else {
+            return false;
+        };
+        Duration::from_secs(unix_now().saturating_sub(cached_at)) < ttl
+    }
+
+    /// Returns the cached body of `url` along with its validators, if cached.
+    fn lookup(&self, url: &str) -> Result<Option<(Validators, Vec<u8>)>, ArchiveError> {
+        let Some(validators) = self.read_validators()?.remove(url) else {
+            return Ok(None);
+        };
+        match fs::read(self.body_path(url)) {
+            Ok(body) => Ok(Some((validators, body))),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(cause) => Err(cause.into()),
+        }
+    }
+
+    /// Caches `body` of `url` along with its `validators`, replacing what was cached before.
+    fn store(&self, url: &str, validators: Validators, body: &[u8]) -> Result<(), ArchiveError> {
+        let body_path = self.body_path(url);
+        if let Some(parent) = body_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomically(&body_path, |file| Ok(file.write_all(body)?))?;
+
+        let mut responses = self.read_validators()?;
+        responses.insert(url.to_owned(), validators);
+        write_atomically(&self.directory.join(RESPONSES_FILE), |file| {
+            serde_json::to_writer_pretty(&mut *file, &responses)?;
+            Ok(file.write_all(b"\n")?)
+        })
+    }
+
+    fn read_validators(&self) -> Result<BTreeMap<String, Validators>, ArchiveError> {
+        match fs::read(self.directory.join(RESPONSES_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(cause) => Err(cause.into()),
+        }
+    }
+
+    fn body_path(&self, url: &str) -> PathBuf {
+        let key = hex::encode(Sha256::digest(url));
+        self.directory.join(RESPONSES_DIR).join(key)
+    }
+}
+
+/// Sends a GET request to `url`, returning the body of its response.
+///
+/// If `cache` has a response of `url`, the request is conditional on it having changed since, and
+/// the cached body is returned instead if it has NOT. The cached body is returned without any
+/// request while younger than the time to live of `cache`. Every changed response is cached.
+pub async fn get_cached(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    url: &str,
+    what: &str,
+    retry: &RetryPolicy,
+) -> Result<Vec<u8>, ArchiveError> {
+    let cached = match cache {
+        Some(cache) => cache.lookup(url)?,
+        None => None,
+    };
+    if let (Some(cache), Some((validators, body))) = (cache, &cached) {
+        if cache.is_fresh(validators) {
+            info!("'{url}' was cached within its time to live, using its cached response");
+            return Ok(body.clone());
+        }
+    }
+    let validators = cached.as_ref().map(|(it, _)| it);
+
+    let response = retry
+        .retry(what, || async move {
+            let mut request = client.client.get(url);
+            if let Some(validators) = validators {
+                if let Some(etag) = &validators.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &validators.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let response = request.send().await?;
+            if response.status() == StatusCode::NOT_MODIFIED && validators.is_some() {
+                return Ok(None);
+            }
+            let response = response.error_for_status()?;
+            let header = |name: HeaderName| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|it| it.to_str().ok())
+                    .map(String::from)
+            };
+            let validators = Validators {
+                etag: header(ETAG),
+                last_modified: header(LAST_MODIFIED),
+                cached_at: Some(unix_now()),
+            };
+            Ok::<_, ArchiveError>(Some((validators, response.bytes().await?.to_vec())))
+        })
+        .await?;
+
+    match (response, cached) {
+        (Some((validators, body)), _) => {
+            if let Some(cache) = cache {
+                let is_validated = validators.etag.is_some() || validators.last_modified.is_some();
+                if is_validated || cache.ttl.is_some() {
+                    cache.store(url, validators, &body)?;
+                }
+            }
+            Ok(body)
+        }
+        (None, Some((mut validators, body))) => {
+            info!("'{url}' is unchanged, using its cached response");
+            if let Some(cache) = cache.filter(|it| it.ttl.is_some()) {
+                validators.cached_at = Some(unix_now());
+                cache.store(url, validators, &body)?;
+            }
+            Ok(body)
+        }
+        (None, None) => unreachable!("only cached responses are revalidated"),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |it| it.as_secs())
+}
+
+/// Content-addressed cache of downloaded archives of any format, each looked up by the key of the
+/// source it was downloaded from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchiveCache {
+    directory: PathBuf,
+}
+
+impl ArchiveCache {
+    /// Creates the cache kept within `directory`, which is created once something is cached.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the key of the itch.io upload `download_id`, which is only known to be unchanged if
+    /// both its last modification date and size are known.
+    pub fn itch_key(
+        download_id: u64,
+        last_modified: Option<u64>,
+        size: Option<u64>,
+    ) -> Option<String> {
+        Some(format!("itch:{download_id}:{}:{}", last_modified?, size?))
+    }
+
+    /// Opens the cached archive downloaded from the source of `key`, if cached.
+    pub fn lookup(&self, key: &str) -> Result<Option<File>, ArchiveError> {
+        match self.read_keys()?.get(key) {
+            Some(&sha256) => self.lookup_hash(sha256),
+            None => Ok(None),
+        }
+    }
+
+    /// Opens the cached archive with hash `sha256`, if cached and intact.
+    pub fn lookup_hash(&self, sha256: Sha256Hash) -> Result<Option<File>, ArchiveError> {
+        let path = self.archive_path(sha256);
+        let mut file = match File::open(&path) {
+            Ok(it) => it,
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(cause) => return Err(cause.into()),
+        };
+
+        info!("Verifying cached archive '{}'...", path.display());
+        let (actual, _) = hash_reader(io::BufReader::new(&mut file))?;
+        if actual != sha256 {
+            warn!(
+                "Cached archive '{}' is corrupt, removing it",
+                path.display()
+            );
+            fs::remove_file(&path)?;
+            return Ok(None);
+        }
+
+        file.rewind()?;
+        Ok(Some(file))
+    }
+
+    /// Caches the downloaded `archive`, downloaded from the source of `key` if known. Returns its
+    /// hash.
+    pub fn store(&self, key: Option<&str>, archive: &mut File) -> Result<Sha256Hash, ArchiveError> {
+        archive.rewind()?;
+        let (sha256, _) = hash_reader(io::BufReader::new(&mut *archive))?;
+        let archive_path = self.archive_path(sha256);
+        if !archive_path.is_file() {
+            fs::create_dir_all(self.directory.join(ARCHIVES_DIR))?;
+            archive.rewind()?;
+            write_atomically(&archive_path, |file| {
+                io::copy(archive, file)?;
+                Ok(())
+            })?;
+            info!("Cached archive as '{}'", archive_path.display());
+        }
+
+        if let Some(key) = key {
+            let mut keys = self.read_keys()?;
+            keys.insert(key.to_owned(), sha256);
+            self.write_keys(&keys)?;
+        }
+        Ok(sha256)
+    }
+
+    /// Removes every cached archive and response last modified longer than `max_age` ago, along
+    /// with the keys of every removed archive. Returns how many files were removed and their total
+    /// size in bytes.
+    pub fn collect_garbage(&self, max_age: Duration) -> Result<(usize, u64), ArchiveError> {
+        let now = SystemTime::now();
+        let mut removed = (0, 0);
+        for directory in [ARCHIVES_DIR, RESPONSES_DIR] {
+            let entries = match fs::read_dir(self.directory.join(directory)) {
+                Ok(it) => it,
+                Err(cause) if cause.kind() == io::ErrorKind::NotFound => continue,
+                Err(cause) => return Err(cause.into()),
+            };
+            for entry in entries {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+                if metadata.is_file() && age > max_age {
+                    info!("Removing '{}'", entry.path().display());
+                    fs::remove_file(entry.path())?;
+                    removed.0 += 1;
+                    removed.1 += metadata.len();
+                }
+            }
+        }
+
+        let mut keys = self.read_keys()?;
+        keys.retain(|_, sha256| self.archive_path(*sha256).is_file());
+        self.write_keys(&keys)?;
+        Ok(removed)
+    }
+
+    fn read_keys(&self) -> Result<BTreeMap<String, Sha256Hash>, ArchiveError> {
+        match fs::read(self.directory.join(ARCHIVES_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(cause) => Err(cause.into()),
+        }
+    }
+
+    fn write_keys(&self, keys: &BTreeMap<String, Sha256Hash>) -> Result<(), ArchiveError> {
+        fs::create_dir_all(&self.directory)?;
+        write_atomically(&self.directory.join(ARCHIVES_FILE), |file| {
+            serde_json::to_writer_pretty(&mut *file, keys)?;
+            Ok(file.write_all(b"\n")?)
+        })
+    }
+
+    fn archive_path(&self, sha256: Sha256Hash) -> PathBuf {
+        // NOTE: named without an extension, as archives are NOT only zip archives
+        self.directory.join(ARCHIVES_DIR).join(sha256.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn stores_archive_without_path() {
+        // NOTE: segmented downloads only exist as an open file, never as the partial download
+        let directory = tempfile::tempdir().unwrap();
+        let cache = ArchiveCache::new(directory.path());
+        let mut archive = tempfile::tempfile().unwrap();
+        archive.write_all(b"archive").unwrap();
+
+        let sha256 = cache.store(Some("itch:1:2:7"), &mut archive).unwrap();
+        assert_eq!(sha256, hash_reader(&b"archive"[..]).unwrap().0);
+
+        let mut cached = String::new();
+        cache
+            .lookup("itch:1:2:7")
+            .unwrap()
+            .expect("archive should be cached by its key")
+            .read_to_string(&mut cached)
+            .unwrap();
+        assert_eq!(cached, "archive");
+    }
+
+    #[test]
+    fn looks_up_nothing_for_unknown_key() {
+        let directory = tempfile::tempdir().unwrap();
+        let cache = ArchiveCache::new(directory.path());
+        assert!(cache.lookup("itch:1:2:7").unwrap().is_none());
+    }
+}
@@ -0,0 +1,69 @@
+//! Checksum files of game JARs, in the same format that `sha256sum` prints and checks.
+
+use crate::atomic::write_atomically;
+use crate::{ArchiveError, Sha256Hash};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// File name of a combined checksum file.
+pub const SHA256SUMS: &str = "SHA256SUMS";
+
+/// Returns the line of the file named `file_name` with hash `sha256` in a checksum file.
+pub fn checksum_line(sha256: Sha256Hash, file_name: &str) -> String {
+    format!("{sha256}  {file_name}\n")
+}
+
+/// Returns the path of the checksum file of the file at `path`, e.g.
+/// `Cosmic Reach-0.1.44.jar.sha256`.
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut checksum_path = path.as_os_str().to_owned();
+    checksum_path.push(".sha256");
+    PathBuf::from(checksum_path)
+}
+
+/// Writes the checksum file next to the file at `path` with hash `sha256`, returning its path.
+pub fn write_checksum(path: &Path, sha256: Sha256Hash) -> Result<PathBuf, ArchiveError> {
+    let file_name = path
+        .file_name()
+        .and_then(|it| it.to_str())
+        .ok_or_else(|| ArchiveError::MissingFile(path.to_path_buf()))?;
+    let checksum_path = checksum_path(path);
+    let line = checksum_line(sha256, file_name);
+    write_atomically(&checksum_path, |file| Ok(file.write_all(line.as_bytes())?))?;
+
+    info!("Wrote checksum file '{}'", checksum_path.display());
+    Ok(checksum_path)
+}
+
+/// Adds the file at `path` with hash `sha256` to the combined checksum file at `sums`, replacing
+/// its previous line if present.
+///
+/// The file is listed relative to the directory of `sums` if within it, otherwise by its path.
+pub fn add_to_sums(sums: &Path, path: &Path, sha256: Sha256Hash) -> Result<(), ArchiveError> {
+    let directory = sums.parent().unwrap_or(Path::new(""));
+    let name = path.strip_prefix(directory).unwrap_or(path);
+    let name = name.to_string_lossy().replace('\\', "/");
+
+    let text = match fs::read_to_string(sums) {
+        Ok(it) => it,
+        Err(cause) if cause.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(cause) => return Err(cause.into()),
+    };
+    let file_name = |line: &str| {
+        line.split_once("  ")
+            .map(|(_, it)| it.trim_end().to_owned())
+    };
+    let mut lines = text
+        .lines()
+        .filter(|line| file_name(line).as_deref() != Some(name.as_str()))
+        .map(|line| format!("{line}\n"))
+        .collect::<Vec<_>>();
+    lines.push(checksum_line(sha256, &name));
+    lines.sort_by_key(|line| file_name(line));
+
+    write_atomically(sums, |file| Ok(file.write_all(lines.concat().as_bytes())?))?;
+    info!("Added '{name}' to '{}'", sums.display());
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! A minimal parser of Java class files, reading just their constant pools and fields.
+
+use std::fmt;
+
+/// A value of the constant pool of a class file that is of interest.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Constant {
+    /// A modified UTF-8 string, e.g. the name of a field or the text of a string literal.
+    Utf8(String),
+    /// An `int` literal.
+    Integer(i32),
+    /// A `long` literal.
+    Long(i64),
+    /// A string literal, by the index of its [`Constant::Utf8`].
+    String(u16),
+    /// Any other constant, or the unusable entry after a `long` or `double`.
+    Other,
+}
+
+/// The constant value of a `static final` field, as initialized without running any code.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConstantValue {
+    /// A `String` constant.
+    String(String),
+    /// An `int`, `short`, `char`, `byte`, or `boolean` constant.
+    Integer(i32),
+    /// A `long` constant.
+    Long(i64),
+}
+
+/// A field declared by a class.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Field {
+    /// Name of the field, e.g. `version`.
+    pub name: String,
+    /// Descriptor of the type of the field, e.g. `Ljava/lang/String;`.
+    pub descriptor: String,
+    /// Constant value of the field, if it is a `static final` one with a constant initializer.
+    pub constant_value: Option<ConstantValue>,
+}
+
+/// The parts of a class file that are read.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClassFile {
+    /// Major version of the class file format, e.g. 61 for Java 17.
+    pub major_version: u16,
+    /// Every entry of the constant pool, in order from index 1.
+    pub constants: Vec<Constant>,
+    /// Every field declared by the class, in order.
+    pub fields: Vec<Field>,
+}
+
+impl ClassFile {
+    /// Parses the class file `bytes`, or returns [`None`] if it is malformed.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut reader = bytes;
+
+        if take(&mut reader, 4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+            return None;
+        }
+        take(&mut reader, 2)?;
+        let major_version = take_u16(&mut reader)?;
+        let count = take_u16(&mut reader)?;
+
+        let mut constants = Vec::with_capacity(count.into());
+        while constants.len() + 1 < usize::from(count) {
+            let tag = take(&mut reader, 1)?[0];
+            let constant = match tag {
+                // Utf8
+                1 => {
+                    let len = take_u16(&mut reader)?;
+                    Constant::Utf8(
+                        String::from_utf8_lossy(take(&mut reader, len.into())?).into_owned(),
+                    )
+                }
+                // Integer
+                3 => Constant::Integer(i32::from_be_bytes(take(&mut reader, 4)?.try_into().ok()?)),
+                // Float, Fieldref, Methodref, InterfaceMethodref, NameAndType, Dynamic,
+                // InvokeDynamic
+                4 | 9 | 10 | 11 | 12 | 17 | 18 => {
+                    take(&mut reader, 4)?;
+                    Constant::Other
+                }
+                // Long, which takes up two entries
+                5 => {
+                    let long = i64::from_be_bytes(take(&mut reader, 8)?.try_into().ok()?);
+                    constants.push(Constant::Long(long));
+                    Constant::Other
+                }
+                // Double, which takes up two entries
+                6 => {
+                    take(&mut reader, 8)?;
+                    constants.push(Constant::Other);
+                    Constant::Other
+                }
+                // String
+                8 => Constant::String(take_u16(&mut reader)?),
+                // Class, MethodType, Module, Package
+                7 | 16 | 19 | 20 => {
+                    take(&mut reader, 2)?;
+                    Constant::Other
+                }
+                // MethodHandle
+                15 => {
+                    take(&mut reader, 3)?;
+                    Constant::Other
+                }
+                _ => return None,
+            };
+            constants.push(constant);
+        }
+        let mut class = Self {
+            major_version,
+            constants,
+            fields: Vec::new(),
+        };
+
+        // NOTE: access flags, this class, and super class
+        take(&mut reader, 6)?;
+        let interfaces = take_u16(&mut reader)?;
+        take(&mut reader, usize::from(interfaces) * 2)?;
+
+        let fields = take_u16(&mut reader)?;
+        for _ in 0..fields {
+            take(&mut reader, 2)?;
+            let name = class.utf8(take_u16(&mut reader)?)?.to_owned();
+            let descriptor = class.utf8(take_u16(&mut reader)?)?.to_owned();
+            let mut constant_value = None;
+            for _ in 0..take_u16(&mut reader)? {
+                let attribute = class.utf8(take_u16(&mut reader)?)?;
+                let len = u32::from_be_bytes(take(&mut reader, 4)?.try_into().ok()?);
+                let mut info = take(&mut reader, usize::try_from(len).ok()?)?;
+                if attribute == "ConstantValue" {
+                    constant_value = class.constant_value(take_u16(&mut info)?);
+                }
+            }
+            class.fields.push(Field {
+                name,
+                descriptor,
+                constant_value,
+            });
+        }
+
+        Some(class)
+    }
+
+    /// Returns the version of Java that introduced the format of the class file, e.g. 17, or
+    /// [`None`] if older than Java 1.2.
+    pub fn java_version(&self) -> Option<u16> {
+        java_version(self.major_version)
+    }
+
+    /// Returns the constant at `index` of the constant pool, if any.
+    pub fn constant(&self, index: u16) -> Option<&Constant> {
+        self.constants.get(usize::from(index).checked_sub(1)?)
+    }
+
+    /// Returns the modified UTF-8 string at `index` of the constant pool, if any.
+    pub fn utf8(&self, index: u16) -> Option<&str> {
+        match self.constant(index)? {
+            Constant::Utf8(it) => Some(it),
+            _ => None,
+        }
+    }
+
+    /// Returns every UTF-8 string in the constant pool, including the names of classes, fields,
+    /// and methods.
+    pub fn utf8_constants(&self) -> impl Iterator<Item = &str> {
+        self.constants.iter().filter_map(|it| match it {
+            Constant::Utf8(it) => Some(it.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the text of every string literal in the constant pool.
+    pub fn string_literals(&self) -> impl Iterator<Item = &str> {
+        self.constants.iter().filter_map(|it| match it {
+            Constant::String(index) => self.utf8(*index),
+            _ => None,
+        })
+    }
+
+    fn constant_value(&self, index: u16) -> Option<ConstantValue> {
+        match self.constant(index)? {
+            Constant::Integer(it) => Some(ConstantValue::Integer(*it)),
+            Constant::Long(it) => Some(ConstantValue::Long(*it)),
+            Constant::String(index) => Some(ConstantValue::String(self.utf8(*index)?.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConstantValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(it) => f.write_str(it),
+            Self::Integer(it) => write!(f, "{it}"),
+            Self::Long(it) => write!(f, "{it}"),
+        }
+    }
+}
+
+/// Returns the version of Java that introduced the class file format `major_version`, e.g. 17
+/// for 61, or [`None`] if older than Java 1.2.
+pub fn java_version(major_version: u16) -> Option<u16> {
+    major_version.checked_sub(44).filter(|it| *it >= 2)
+}
+
+/// Reads the major version of the class file format from the start of the class file `header`,
+/// or returns [`None`] if it is NOT one.
+pub fn major_version(header: &[u8]) -> Option<u16> {
+    let mut reader = header;
+    if take(&mut reader, 4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+        return None;
+    }
+    take(&mut reader, 2)?;
+    take_u16(&mut reader)
+}
+
+fn take_u16(reader: &mut &[u8]) -> Option<u16> {
+    Some(u16::from_be_bytes(take(reader, 2)?.try_into().ok()?))
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if reader.len() < len {
+        return None;
+    }
+    let (taken, rest) = reader.split_at(len);
+    *reader = rest;
+    Some(taken)
+}
@@ -0,0 +1,55 @@
+//! Classification of the type of a version, e.g. `pre_alpha`, from its id and the itch.io game
+//! page.
+
+use crate::pattern::Pattern;
+use regex::Regex;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// A rule classifying every version with an id matching `id` as of type `kind`.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TypeRule {
+    /// Pattern of the ids of the versions, see [`Pattern`].
+    pub id: Pattern,
+    /// Type of the versions.
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Classifies the type of the version `id`.
+///
+/// The type is the one of the first of `rules` matching `id`, otherwise the development stage
+/// within `id` itself, e.g. `beta` of `1.0.0-beta`, or else the first development stage mentioned
+/// in the HTML of the game `page` if present. Returns [`None`] if none of them are known.
+pub fn classify_version(id: &str, page: Option<&str>, rules: &[TypeRule]) -> Option<String> {
+    if let Some(rule) = rules.iter().find(|it| it.id.matches(id)) {
+        info!("Version {id} is {} by a configured rule", rule.kind);
+        return Some(rule.kind.clone());
+    }
+    if let Some(kind) = development_stage(id) {
+        info!("Version {id} is {kind} by its id");
+        return Some(kind.to_owned());
+    }
+
+    let kind = development_stage(page?)?;
+    info!("Version {id} is {kind} by the game page");
+    Some(kind.to_owned())
+}
+
+/// Finds the first development stage mentioned in `text`, as its version type.
+fn development_stage(text: &str) -> Option<&'static str> {
+    static STAGE: OnceLock<Regex> = OnceLock::new();
+    let stage = STAGE.get_or_init(|| {
+        Regex::new(r"(?i)\b(pre[-_ ]?alpha|alpha|beta)\b").expect("stage regex should be valid")
+    });
+
+    let stage = stage.find(text)?.as_str().to_ascii_lowercase();
+    Some(if stage.starts_with("pre") {
+        "pre_alpha"
+    } else if stage == "alpha" {
+        "alpha"
+    } else {
+        "beta"
+    })
+}
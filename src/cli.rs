@@ -0,0 +1,1913 @@
+//! Arguments of every command of the updater, as parsed from the command line and merged with the
+//! config file.
+
+use crate::archive_org::{ArchiveOrgItem, DEFAULT_IDENTIFIER_TEMPLATE};
+use crate::auth::{ItchAuth, Session};
+use crate::cache::{ArchiveCache, ResponseCache};
+use crate::classify::TypeRule;
+use crate::config::EmailConfig;
+use crate::delta::PatchFormat;
+use crate::download::{Mirror, SecondaryTarget};
+use crate::export::ExportFormat;
+use crate::git::{DEFAULT_AUTHOR, DEFAULT_BRANCH_TEMPLATE};
+use crate::hash::HashAlgorithm;
+use crate::http::{build_client, ClientOptions};
+use crate::ipfs::{Ipfs, PinningService, DEFAULT_IPFS_API};
+use crate::notify::{MastodonAccount, MatrixRoom};
+use crate::pattern::Pattern;
+use crate::ratelimit::RateLimiter;
+use crate::rehost::MirrorLayout;
+use crate::release::parse_date;
+use crate::sign::Signer;
+use crate::storage::{S3Storage, Storage, DEFAULT_S3_REGION};
+use crate::torrent::{is_valid_piece_length, DEFAULT_PIECE_LENGTH, MIN_PIECE_LENGTH};
+use crate::update::UPDATER_REPOSITORY;
+use crate::{
+    ArchiveError, Artifact, Config, RetryPolicy, Version, ARCHIVED_JARS_URL, ARCHIVED_VERSIONS_URL,
+    ITCH_GAME_URL, SERVER_DOWNLOAD_TITLE, TARGET_DOWNLOAD_TITLE,
+};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::info;
+use tracing::level_filters::LevelFilter;
+
+#[derive(Debug, clap::Args)]
+pub struct GlobalArgs {
+    /// Log level filter, takes precedence over `RUST_LOG`
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub log_level: Option<LevelFilter>,
+
+    /// Format of every logged event
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Never draw progress bars, which are otherwise drawn when STDOUT is a terminal
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Log what would be written instead of writing any files
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Directory to spill downloads and archive entries into instead of memory [default: the
+    /// temporary directory of the system]
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        env = "COSMIC_ARCHIVE_TEMP_DIR"
+    )]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Config file, defaults to `cosmic-archive.toml` if present
+    ///
+    /// Every setting is taken from its command line flag first, then its environmental variable,
+    /// then the config file, and then its built-in default.
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        env = "COSMIC_ARCHIVE_CONFIG"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Directory to cache responses and downloaded archives in between runs, which are revalidated
+    /// or reused instead of downloaded again if unchanged
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        env = "COSMIC_ARCHIVE_CACHE_DIR"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Seconds a cached response of the archived versions data or an itch.io page is used without
+    /// revalidating it, e.g. to run many times an hour [default: always revalidate]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_CACHE_TTL"
+    )]
+    pub cache_ttl: Option<u64>,
+
+    /// URL to the archived versions data [default: CosmicArchive's versions.json]
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        env = "COSMIC_ARCHIVE_MANIFEST_URL"
+    )]
+    pub manifest_url: Option<String>,
+
+    /// Proxy of every request, e.g. socks5h://127.0.0.1:9050 [default: from `HTTPS_PROXY`,
+    /// `HTTP_PROXY`, or `ALL_PROXY`]
+    #[arg(long, global = true, value_name = "URL", env = "COSMIC_ARCHIVE_PROXY")]
+    pub proxy: Option<String>,
+
+    /// Never send requests through a proxy from the environmental variables
+    #[arg(long, global = true, conflicts_with = "proxy")]
+    pub no_proxy: bool,
+
+    /// User-Agent of every request, which should identify who to contact [default:
+    /// cosmicarchive-updater/<VERSION> (+https://github.com/CRModders/CosmicArchive)]
+    #[arg(
+        long,
+        global = true,
+        value_name = "AGENT",
+        env = "COSMIC_ARCHIVE_USER_AGENT"
+    )]
+    pub user_agent: Option<String>,
+
+    /// Extra header of every request, overriding the one of the same name in the config file
+    #[arg(
+        short = 'H',
+        long = "header",
+        global = true,
+        value_name = "NAME: VALUE",
+        value_parser = parse_header
+    )]
+    pub headers: Vec<(String, String)>,
+
+    #[arg(skip)]
+    pub config_headers: BTreeMap<String, String>,
+
+    /// Seconds before connecting to a server times out, 0 to never time out [default: 10]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_CONNECT_TIMEOUT"
+    )]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds before waiting for the next read from a server times out, detecting stalled
+    /// downloads however long they take, 0 to never time out [default: 60]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_READ_TIMEOUT"
+    )]
+    pub read_timeout: Option<u64>,
+
+    /// Seconds before every request scraping itch.io times out, 0 to never time out [default: 30]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_SCRAPE_TIMEOUT"
+    )]
+    pub scrape_timeout: Option<u64>,
+
+    /// Seconds before fetching the archived versions data times out, 0 to never time out
+    /// [default: 30]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_MANIFEST_TIMEOUT"
+    )]
+    pub manifest_timeout: Option<u64>,
+
+    /// Seconds before every attempt of downloading a file times out, resuming from where it
+    /// stopped on retry, 0 to never time out [default: 1800]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        env = "COSMIC_ARCHIVE_DOWNLOAD_TIMEOUT"
+    )]
+    pub download_timeout: Option<u64>,
+
+    /// Maximum attempts of every network request [default: 3]
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        env = "COSMIC_ARCHIVE_RETRY_ATTEMPTS"
+    )]
+    pub retry_attempts: Option<u32>,
+
+    /// Delay in milliseconds before the first retry, doubled for every retry after it [default: 1000]
+    #[arg(
+        long,
+        global = true,
+        value_name = "MILLIS",
+        env = "COSMIC_ARCHIVE_RETRY_DELAY"
+    )]
+    pub retry_delay: Option<u64>,
+
+    /// Upper bound in milliseconds of the random delay added to every retry [default: 500]
+    #[arg(
+        long,
+        global = true,
+        value_name = "MILLIS",
+        env = "COSMIC_ARCHIVE_RETRY_JITTER"
+    )]
+    pub retry_jitter: Option<u64>,
+
+    /// Average requests per minute sent to itch.io, shared by scraping and downloading, with
+    /// bursts of up to `--itch-rate-burst` [default: unlimited]
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        env = "COSMIC_ARCHIVE_ITCH_RATE_LIMIT"
+    )]
+    pub itch_rate_limit: Option<NonZeroU32>,
+
+    /// Requests sent to itch.io at once before being rate limited [default: 1]
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        env = "COSMIC_ARCHIVE_ITCH_RATE_BURST"
+    )]
+    pub itch_rate_burst: Option<NonZeroU32>,
+}
+
+/// Format of every logged event.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// A JSON object per line, along with the spans it occurred in
+    Json,
+}
+
+/// Order of listed versions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, clap::ValueEnum)]
+pub enum ListSort {
+    /// In the order of the archived versions data
+    Archived,
+    /// By their version ids
+    Id,
+    /// By their release times
+    Released,
+    /// By the sizes of their game JARs
+    Size,
+}
+
+/// Format of printed statistics.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, clap::ValueEnum)]
+pub enum StatsOutput {
+    /// Human readable tables
+    Text,
+    /// A single JSON object, e.g. for dashboards
+    Json,
+}
+
+impl GlobalArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.cache_dir, &config.cache_dir);
+        merge(&mut self.cache_ttl, &config.cache_ttl);
+        merge(&mut self.temp_dir, &config.temp_dir);
+        merge(&mut self.manifest_url, &config.manifest_url);
+        merge(&mut self.proxy, &config.proxy);
+        merge(&mut self.user_agent, &config.user_agent);
+        self.config_headers.clone_from(&config.headers);
+        merge(&mut self.retry_attempts, &config.retry.attempts);
+        merge(&mut self.retry_delay, &config.retry.delay);
+        merge(&mut self.retry_jitter, &config.retry.jitter);
+        merge(&mut self.connect_timeout, &config.timeouts.connect);
+        merge(&mut self.read_timeout, &config.timeouts.read);
+        merge(&mut self.scrape_timeout, &config.timeouts.scrape);
+        merge(&mut self.manifest_timeout, &config.timeouts.manifest);
+        merge(&mut self.download_timeout, &config.timeouts.download);
+        merge(&mut self.itch_rate_limit, &config.itch.rate_limit);
+        merge(&mut self.itch_rate_burst, &config.itch.rate_burst);
+    }
+
+    pub fn response_cache(&self) -> Option<ResponseCache> {
+        let cache = ResponseCache::new(self.cache_dir.as_deref()?);
+        Some(cache.with_ttl(self.cache_ttl.map(Duration::from_secs)))
+    }
+
+    pub fn archive_cache(&self) -> Option<ArchiveCache> {
+        self.cache_dir.as_deref().map(ArchiveCache::new)
+    }
+
+    pub fn manifest_url(&self) -> &str {
+        self.manifest_url
+            .as_deref()
+            .unwrap_or(ARCHIVED_VERSIONS_URL)
+    }
+
+    pub fn client(&self) -> Result<itch_io::Client, ArchiveError> {
+        build_client(&self.client_options())
+    }
+
+    /// Builds the client sending the cookies of the itch.io session of `itch` if any.
+    pub fn itch_client(&self, itch: &ItchAuthArgs) -> Result<itch_io::Client, ArchiveError> {
+        build_client(&ClientOptions {
+            cookies: itch.session.as_ref().map(|it| it.cookies.clone()),
+            external_hosts: itch.external_hosts.clone(),
+            ..self.client_options()
+        })
+    }
+
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            proxy: self.proxy.clone(),
+            no_proxy: self.no_proxy,
+            connect_timeout: timeout(self.connect_timeout, 10),
+            read_timeout: timeout(self.read_timeout, 60),
+            user_agent: self.user_agent.clone(),
+            headers: self
+                .config_headers
+                .clone()
+                .into_iter()
+                .chain(self.headers.iter().cloned())
+                .collect(),
+            cookies: None,
+            external_hosts: Vec::new(),
+        }
+    }
+
+    pub fn scrape_policy(&self) -> RetryPolicy {
+        self.retry_policy()
+            .with_timeout(timeout(self.scrape_timeout, 30))
+            .with_rate_limit(self.itch_rate_limiter())
+    }
+
+    pub fn manifest_policy(&self) -> RetryPolicy {
+        self.retry_policy()
+            .with_timeout(timeout(self.manifest_timeout, 30))
+    }
+
+    pub fn temp_dir(&self) -> PathBuf {
+        self.temp_dir.clone().unwrap_or_else(std::env::temp_dir)
+    }
+
+    pub fn download_policy(&self) -> RetryPolicy {
+        self.retry_policy()
+            .with_timeout(timeout(self.download_timeout, 1800))
+    }
+
+    /// Returns the policy of downloads from itch.io, which unlike other downloads are rate limited.
+    pub fn itch_download_policy(&self) -> RetryPolicy {
+        self.download_policy()
+            .with_rate_limit(self.itch_rate_limiter())
+    }
+
+    /// Returns the limiter shared by every request to itch.io, if rate limited.
+    pub fn itch_rate_limiter(&self) -> Option<&'static RateLimiter> {
+        static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+        let requests_per_minute = self.itch_rate_limit?;
+        let burst = self.itch_rate_burst.unwrap_or(NonZeroU32::MIN);
+        Some(LIMITER.get_or_init(|| RateLimiter::new(requests_per_minute, burst)))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            attempts: self.retry_attempts.unwrap_or(default.attempts).max(1),
+            base_delay: self
+                .retry_delay
+                .map_or(default.base_delay, Duration::from_millis),
+            jitter: self
+                .retry_jitter
+                .map_or(default.jitter, Duration::from_millis),
+            timeout: None,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Parses the size in bytes of every piece of a torrent.
+fn parse_piece_length(s: &str) -> Result<u64, String> {
+    let piece_length = s.parse::<u64>().map_err(|cause| cause.to_string())?;
+    if is_valid_piece_length(piece_length) {
+        Ok(piece_length)
+    } else {
+        Err(format!(
+            "expected a power of two of at least {MIN_PIECE_LENGTH}"
+        ))
+    }
+}
+
+/// Parses a date in UTC from `YYYY-MM-DD`, as the Unix timestamp in seconds of its start.
+fn parse_date_arg(s: &str) -> Result<u64, String> {
+    parse_date(s).ok_or_else(|| format!("expected 'YYYY-MM-DD', but was '{s}'"))
+}
+
+/// Parses a request header from `Name: value`.
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected 'NAME: VALUE', but was '{s}'"))?;
+    Ok((name.trim().to_owned(), value.trim().to_owned()))
+}
+
+/// Returns the timeout of `secs` seconds, or `default` seconds if absent, where 0 disables it.
+pub(crate) fn timeout(secs: Option<u64>, default: u64) -> Option<Duration> {
+    match secs.unwrap_or(default) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Fills `arg` with the value from the config file if it was given neither as a flag nor as an
+/// environmental variable.
+fn merge<T: Clone>(arg: &mut Option<T>, config: &Option<T>) {
+    if arg.is_none() {
+        arg.clone_from(config);
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FetchArgs {
+    /// URL to the itch.io game page [default: https://finalforeach.itch.io/cosmic-reach]
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_GAME_URL")]
+    pub game_url: Option<String>,
+
+    /// Title of the download option containing the game JAR, either exact, a glob with `*` or `?`,
+    /// or a regex prefixed with `regex:` [default: cosmic-reach-jar.zip]
+    #[arg(long, value_name = "PATTERN", env = "COSMIC_ARCHIVE_DOWNLOAD_TITLE")]
+    pub download_title: Option<Pattern>,
+
+    /// Title of the download option containing the dedicated server JAR, see `--download-title`
+    /// [default: cosmic-reach-server.zip]
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        env = "COSMIC_ARCHIVE_SERVER_DOWNLOAD_TITLE"
+    )]
+    pub server_download_title: Option<Pattern>,
+
+    /// Id of the itch.io upload containing the game JAR, skipping finding it by its title on the
+    /// game page, e.g. when its structure changes or to fetch a specific older upload. The date
+    /// the upload was updated at is then left unknown
+    #[arg(long, value_name = "ID", env = "COSMIC_ARCHIVE_DOWNLOAD_ID")]
+    pub download_id: Option<u64>,
+
+    /// Id of the itch.io upload containing the dedicated server JAR, see `--download-id`
+    #[arg(long, value_name = "ID", env = "COSMIC_ARCHIVE_SERVER_DOWNLOAD_ID")]
+    pub server_download_id: Option<u64>,
+
+    /// Name of the game JAR within the archive, see `--download-title` [default: the one either
+    /// starting with `Cosmic Reach-` or ending with `.jar`, see `--preferred-jar`]
+    #[arg(long, value_name = "PATTERN", env = "COSMIC_ARCHIVE_JAR_NAME")]
+    pub jar_name: Option<Pattern>,
+
+    /// Name of the dedicated server JAR within the archive, see `--jar-name`
+    #[arg(long, value_name = "PATTERN", env = "COSMIC_ARCHIVE_SERVER_JAR_NAME")]
+    pub server_jar_name: Option<Pattern>,
+
+    /// Name of the game JAR to prefer when the archive contains several, after the ones named
+    /// `Cosmic Reach-*.jar` and then the largest ones. The others are archived as secondary files
+    /// of the `<ARTIFACT>-extra` target
+    #[arg(long, value_name = "PATTERN", env = "COSMIC_ARCHIVE_PREFERRED_JAR")]
+    pub preferred_jar: Option<Pattern>,
+
+    /// Kinds of game JAR to fetch, each looked up separately in the archived versions data
+    /// [default: client]
+    #[arg(
+        long = "artifact",
+        value_name = "KIND",
+        value_delimiter = ',',
+        env = "COSMIC_ARCHIVE_ARTIFACTS"
+    )]
+    pub artifacts: Option<Vec<Artifact>>,
+
+    /// Maximum number of kinds of game JAR fetched at once [default: 2]
+    #[arg(long, value_name = "N", env = "COSMIC_ARCHIVE_CONCURRENCY")]
+    pub concurrency: Option<NonZeroUsize>,
+
+    /// Number of ranged requests to download the archive from itch.io over at once, if its size
+    /// is known and NO partial download is left to resume [default: 1]
+    #[arg(long, value_name = "N", env = "COSMIC_ARCHIVE_CONNECTIONS")]
+    pub connections: Option<NonZeroUsize>,
+
+    #[command(flatten)]
+    pub itch: ItchAuthArgs,
+
+    /// Path of the extracted client game JAR, defaults to its name within the archive
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Directory to extract the game JAR into when `--output` is absent [default: .]
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_OUTPUT_DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Path of the extracted game JAR relative to `--output-dir`, with placeholders {version},
+    /// {filename}, and {artifact}, e.g. {version}/{filename} [default: its name within the archive]
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_OUTPUT_TEMPLATE")]
+    pub output_template: Option<String>,
+
+    /// Also list the extracted game JAR in the SHA256SUMS file of `--output-dir`, next to its own
+    /// `.sha256` checksum file
+    #[arg(long)]
+    pub sha256sums: bool,
+
+    /// Local git repository to check for an already existing branch before downloading
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_GIT_REPO")]
+    pub git_repo: Option<PathBuf>,
+
+    /// Also check the branches and tags of `--github-repository` for an already existing branch
+    /// before downloading, without needing a local clone
+    #[arg(long)]
+    pub check_github: bool,
+
+    /// Remote whose remote-tracking branches are also checked [default: origin]
+    #[arg(long, value_name = "NAME", env = "COSMIC_ARCHIVE_GIT_REMOTE")]
+    pub git_remote: Option<String>,
+
+    /// Template of the branch name, where `{download_id}` is the itch.io upload id, `{version}` the
+    /// version id, and `{artifact}` the kind of game JAR [default: upload-{download_id}]
+    ///
+    /// Templates with `{version}` can only be checked after downloading.
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_BRANCH_TEMPLATE")]
+    pub branch_template: Option<String>,
+
+    /// Template of the message of archiving commits, where `{id}`, `{type}`, `{artifact}`,
+    /// `{release_time}`, `{release_date}`, `{sha256}`, `{size}`, and `{url}` are those of the
+    /// version [default: Archive {id}, followed by ` server` for server JARs]
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_COMMIT_MESSAGE")]
+    pub commit_message: Option<String>,
+
+    /// Commit the NOT yet archived game JAR and its entry in the local archived versions data onto
+    /// a new branch of `--git-repo`
+    #[arg(long)]
+    pub commit: bool,
+
+    /// Push the new branch to `--git-remote` after committing
+    #[arg(long, requires = "commit")]
+    pub push: bool,
+
+    /// Open a pull request of the pushed branch against `--pr-base` of `--github-repository`
+    #[arg(long, requires = "push")]
+    pub pull_request: bool,
+
+    /// Branch that pull requests are opened against [default: main]
+    #[arg(long, value_name = "BRANCH", env = "COSMIC_ARCHIVE_PR_BASE")]
+    pub pr_base: Option<String>,
+
+    /// Template of the title of pull requests, with placeholders such as {id} and {sha256}
+    /// [default: Archive {id}]
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_PR_TITLE")]
+    pub pr_title: Option<String>,
+
+    /// Template of the body of pull requests, see `--pr-title` [default: a table of the artifact,
+    /// its size, and its hash]
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_PR_BODY")]
+    pub pr_body: Option<String>,
+
+    /// Local archived versions data to append to [default: versions.json of `--git-repo`]
+    #[arg(long, value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Token to authenticate pushing and publishing GitHub Releases with
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    pub git_token: Option<String>,
+
+    /// Publish the NOT yet archived game JAR and its checksum file as a GitHub Release tagged with
+    /// its version id
+    #[arg(long)]
+    pub github_release: bool,
+
+    /// Repository to publish GitHub Releases to, e.g. CRModders/CosmicArchive
+    #[arg(
+        long,
+        value_name = "OWNER/REPO",
+        env = "COSMIC_ARCHIVE_GITHUB_REPOSITORY"
+    )]
+    pub github_repository: Option<String>,
+
+    /// Name of the author of archiving commits [default: github-actions[bot]]
+    #[arg(long, value_name = "NAME", env = "COSMIC_ARCHIVE_GIT_AUTHOR_NAME")]
+    pub git_author_name: Option<String>,
+
+    /// Email of the author of archiving commits
+    #[arg(long, value_name = "EMAIL", env = "COSMIC_ARCHIVE_GIT_AUTHOR_EMAIL")]
+    pub git_author_email: Option<String>,
+
+    /// Discord webhook to notify of NOT yet archived versions, or of archived ones with `--commit`
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "COSMIC_ARCHIVE_DISCORD_WEBHOOK",
+        hide_env_values = true
+    )]
+    pub discord_webhook: Option<String>,
+
+    #[arg(skip)]
+    pub discord_payload: Option<String>,
+
+    /// Upload the NOT yet archived game JAR and its entry to an archive.org item
+    #[arg(long)]
+    pub archive_org: bool,
+
+    /// Template of the identifier of the archive.org item, with placeholders such as {id} and
+    /// {artifact} [default: cosmic-reach-{artifact}-{id}]
+    #[arg(long, value_name = "TEMPLATE", env = "COSMIC_ARCHIVE_IA_IDENTIFIER")]
+    pub ia_identifier: Option<String>,
+
+    /// Collection the archive.org item is created in
+    #[arg(long, value_name = "NAME", env = "COSMIC_ARCHIVE_IA_COLLECTION")]
+    pub ia_collection: Option<String>,
+
+    /// Access key of the archive.org S3-compatible API
+    #[arg(long, env = "COSMIC_ARCHIVE_IA_ACCESS_KEY", hide_env_values = true)]
+    pub ia_access_key: Option<String>,
+
+    /// Secret key of the archive.org S3-compatible API
+    #[arg(long, env = "COSMIC_ARCHIVE_IA_SECRET_KEY", hide_env_values = true)]
+    pub ia_secret_key: Option<String>,
+
+    #[arg(skip)]
+    pub ia_metadata: BTreeMap<String, String>,
+
+    #[arg(skip)]
+    pub email: EmailConfig,
+
+    #[arg(skip)]
+    pub version_types: Vec<TypeRule>,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
+
+    #[command(flatten)]
+    pub s3: S3Args,
+
+    /// Add the NOT yet archived game JAR to IPFS, recording its CID in its entry
+    #[arg(long)]
+    pub ipfs: bool,
+
+    #[command(flatten)]
+    pub ipfs_node: IpfsArgs,
+
+    #[command(flatten)]
+    pub matrix: MatrixArgs,
+
+    #[command(flatten)]
+    pub mastodon: MastodonArgs,
+
+    /// Also unpack every file of the archive into a directory named after the version within DIR
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_EXTRACT_ALL")]
+    pub extract_all: Option<PathBuf>,
+
+    /// Also keep the downloaded zip archive as is within DIR, named after the game JAR and along
+    /// with its own checksum file
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_KEEP_ZIP")]
+    pub keep_zip: Option<PathBuf>,
+
+    /// Re-zip the kept, staged, and mirrored zip archives reproducibly instead of storing them as
+    /// downloaded, so that the same upstream bytes always yield byte-identical archives
+    #[arg(long)]
+    pub normalize_zip: bool,
+
+    /// Cross-check the download against the checksums published on the itch.io game page or in
+    /// the devlog post of its version, if any, failing on a mismatch as it may have been tampered
+    /// with or corrupted
+    #[arg(long, env = "COSMIC_ARCHIVE_CHECK_UPSTREAM_HASHES")]
+    pub check_upstream_hashes: bool,
+
+    /// Stage the NOT yet archived game JAR within DIR along with its pending entry, instead of
+    /// archiving it anywhere until promoted with `promote`
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_STAGE")]
+    pub stage: Option<PathBuf>,
+
+    /// Also write a CycloneDX SBOM of what the NOT yet archived game JAR bundles next to it
+    #[arg(long)]
+    pub sbom: bool,
+
+    /// Also write an index of the class files of the NOT yet archived game JAR next to it
+    #[arg(long)]
+    pub class_index: bool,
+
+    /// Also write an index of the native libraries and LWJGL modules the NOT yet archived game JAR
+    /// bundles next to it
+    #[arg(long)]
+    pub natives: bool,
+
+    /// Also archive a slim JAR of the NOT yet archived game JAR next to it, stripped of its
+    /// bundled libraries, linking it from its entry
+    #[arg(long)]
+    pub slim: bool,
+
+    /// Also archive the devlog post of the NOT yet archived version next to it as Markdown,
+    /// linking it from its entry
+    #[arg(long)]
+    pub devlog: bool,
+
+    /// Mirror of the client zip archive tried in order when downloading from itch.io fails, optionally
+    /// followed by `#sha256=<hex>` for its expected hash
+    #[arg(long = "mirror", value_name = "URL")]
+    pub mirrors: Vec<Mirror>,
+
+    /// Download option besides the game JARs to also archive as is under NAME, with a title
+    /// matching TITLE, see `--download-title`
+    #[arg(long = "secondary", value_name = "NAME=TITLE")]
+    pub secondaries: Vec<SecondaryTarget>,
+}
+
+impl FetchArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.game_url, &config.game_url);
+        merge(&mut self.download_title, &config.download_title);
+        merge(
+            &mut self.server_download_title,
+            &config.server_download_title,
+        );
+        merge(&mut self.jar_name, &config.jar_name);
+        merge(&mut self.server_jar_name, &config.server_jar_name);
+        merge(&mut self.preferred_jar, &config.preferred_jar);
+        merge(&mut self.artifacts, &config.artifacts);
+        merge(&mut self.concurrency, &config.concurrency);
+        merge(&mut self.connections, &config.connections);
+        merge(&mut self.output_dir, &config.output_dir);
+        merge(&mut self.output_template, &config.output_template);
+        merge(&mut self.stage, &config.stage);
+        merge(&mut self.git_repo, &config.git_repo);
+        merge(&mut self.git_remote, &config.git_remote);
+        merge(&mut self.branch_template, &config.branch_template);
+        merge(&mut self.commit_message, &config.commit_message);
+        merge(&mut self.discord_payload, &config.discord_payload);
+        merge(&mut self.git_author_name, &config.git_author_name);
+        merge(&mut self.git_author_email, &config.git_author_email);
+        merge(&mut self.discord_webhook, &config.discord_webhook);
+        merge(&mut self.ia_identifier, &config.archive_org.identifier);
+        merge(&mut self.ia_collection, &config.archive_org.collection);
+        merge(&mut self.ia_access_key, &config.archive_org.access_key);
+        merge(&mut self.ia_secret_key, &config.archive_org.secret_key);
+        self.ia_metadata.clone_from(&config.archive_org.metadata);
+        self.email.clone_from(&config.email);
+        self.sign.merge(config);
+        self.s3.merge(config);
+        self.ipfs_node.merge(config);
+        self.itch.merge(config);
+        self.matrix.merge(config);
+        self.mastodon.merge(config);
+        merge(&mut self.git_token, &config.github.token);
+        merge(&mut self.github_repository, &config.github.repository);
+        merge(&mut self.pr_base, &config.github.pr_base);
+        merge(&mut self.pr_title, &config.github.pr_title);
+        merge(&mut self.pr_body, &config.github.pr_body);
+        if self.mirrors.is_empty() {
+            self.mirrors.clone_from(&config.mirrors);
+        }
+        if self.secondaries.is_empty() {
+            self.secondaries.clone_from(&config.secondary);
+        }
+        self.version_types.clone_from(&config.version_types);
+    }
+
+    pub fn game_url(&self) -> &str {
+        self.game_url.as_deref().unwrap_or(ITCH_GAME_URL)
+    }
+
+    pub fn artifacts(&self) -> &[Artifact] {
+        self.artifacts.as_deref().unwrap_or(&[Artifact::Client])
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.map_or(2, NonZeroUsize::get)
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.map_or(1, NonZeroUsize::get)
+    }
+
+    pub fn download_id(&self, artifact: Artifact) -> Option<u64> {
+        match artifact {
+            Artifact::Client => self.download_id,
+            Artifact::Server => self.server_download_id,
+        }
+    }
+
+    pub fn download_title(&self, artifact: Artifact) -> Pattern {
+        match artifact {
+            Artifact::Client => self
+                .download_title
+                .clone()
+                .unwrap_or_else(|| TARGET_DOWNLOAD_TITLE.into()),
+            Artifact::Server => self
+                .server_download_title
+                .clone()
+                .unwrap_or_else(|| SERVER_DOWNLOAD_TITLE.into()),
+        }
+    }
+
+    pub fn jar_name(&self, artifact: Artifact) -> Option<&Pattern> {
+        match artifact {
+            Artifact::Client => self.jar_name.as_ref(),
+            Artifact::Server => self.server_jar_name.as_ref(),
+        }
+    }
+
+    pub fn output(&self, artifact: Artifact) -> Option<&Path> {
+        match artifact {
+            Artifact::Client => self.output.as_deref(),
+            Artifact::Server => None,
+        }
+    }
+
+    pub fn mirrors(&self, artifact: Artifact) -> &[Mirror] {
+        match artifact {
+            Artifact::Client => &self.mirrors,
+            Artifact::Server => &[],
+        }
+    }
+
+    pub fn output_dir(&self) -> &Path {
+        self.output_dir.as_deref().unwrap_or(Path::new("."))
+    }
+
+    pub fn output_template(&self, artifact: Artifact) -> Option<String> {
+        let template = self.output_template.as_deref()?;
+        Some(template.replace("{artifact}", artifact.name()))
+    }
+
+    pub fn git_remote(&self) -> &str {
+        self.git_remote.as_deref().unwrap_or("origin")
+    }
+
+    pub fn branch_template(&self, artifact: Artifact) -> String {
+        self.branch_template
+            .as_deref()
+            .unwrap_or(DEFAULT_BRANCH_TEMPLATE)
+            .replace("{artifact}", artifact.name())
+    }
+
+    pub fn archive_org_item(&self, version: &Version) -> Result<ArchiveOrgItem, ArchiveError> {
+        let (Some(access_key), Some(secret_key)) = (&self.ia_access_key, &self.ia_secret_key)
+        else {
+            return Err(ArchiveError::Upload(
+                "archive.org needs keys, see `--ia-access-key` and `--ia-secret-key`".to_owned(),
+            ));
+        };
+        Ok(ArchiveOrgItem::for_version(
+            access_key.clone(),
+            secret_key.clone(),
+            self.ia_identifier
+                .as_deref()
+                .unwrap_or(DEFAULT_IDENTIFIER_TEMPLATE),
+            self.ia_collection.clone(),
+            &self.ia_metadata,
+            version,
+        ))
+    }
+
+    pub fn git_author(&self) -> (&str, &str) {
+        (
+            self.git_author_name.as_deref().unwrap_or(DEFAULT_AUTHOR.0),
+            self.git_author_email.as_deref().unwrap_or(DEFAULT_AUTHOR.1),
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+
+    /// Interval between polls, e.g. `15m` or `1h 30m`, plus up to a tenth of it at random
+    #[arg(
+        long,
+        value_name = "DURATION",
+        env = "COSMIC_ARCHIVE_WATCH_INTERVAL",
+        default_value = "15m",
+        value_parser = humantime::parse_duration
+    )]
+    pub interval: Duration,
+
+    /// Address to serve the Prometheus metrics of the updater on at `/metrics`, and its health at
+    /// `/healthz`, e.g. `127.0.0.1:9898`
+    #[arg(long, value_name = "ADDRESS", env = "COSMIC_ARCHIVE_METRICS_ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
+
+    /// Path to write the health of the updater to as JSON after every poll
+    #[arg(long, value_name = "PATH", env = "COSMIC_ARCHIVE_HEARTBEAT_FILE")]
+    pub heartbeat_file: Option<PathBuf>,
+
+    /// How long after the last successful poll the updater is unhealthy, e.g. `1h` [default:
+    /// three times `--interval`]
+    #[arg(
+        long,
+        value_name = "DURATION",
+        env = "COSMIC_ARCHIVE_STALE_AFTER",
+        value_parser = humantime::parse_duration
+    )]
+    pub stale_after: Option<Duration>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillArgs {
+    /// URL to the itch.io game page [default: https://finalforeach.itch.io/cosmic-reach]
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_GAME_URL")]
+    pub game_url: Option<String>,
+
+    /// Title of the download option containing the dedicated server JAR, whose draft entries are
+    /// of the server artifact, see `fetch --download-title` [default: cosmic-reach-server.zip]
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        env = "COSMIC_ARCHIVE_SERVER_DOWNLOAD_TITLE"
+    )]
+    pub server_download_title: Option<Pattern>,
+
+    /// Extra itch.io upload ids to download, e.g. of older uploads no longer listed on the game
+    /// page
+    #[arg(long = "upload-id", value_name = "ID", value_delimiter = ',')]
+    pub upload_ids: Vec<u64>,
+
+    #[command(flatten)]
+    pub itch: ItchAuthArgs,
+
+    /// Directory to extract NOT yet archived game JARs into [default: .]
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_OUTPUT_DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Base URL that archived game JARs are downloadable from
+    #[arg(long, value_name = "URL", default_value = ARCHIVED_JARS_URL)]
+    pub base_url: url::Url,
+
+    #[arg(skip)]
+    pub version_types: Vec<TypeRule>,
+}
+
+impl BackfillArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.game_url, &config.game_url);
+        merge(
+            &mut self.server_download_title,
+            &config.server_download_title,
+        );
+        merge(&mut self.output_dir, &config.output_dir);
+        self.itch.merge(config);
+        self.version_types.clone_from(&config.version_types);
+    }
+
+    pub fn game_url(&self) -> &str {
+        self.game_url.as_deref().unwrap_or(ITCH_GAME_URL)
+    }
+
+    pub fn artifact(&self, download_title: Option<&str>) -> Artifact {
+        let is_server = download_title.is_some_and(|title| match &self.server_download_title {
+            Some(pattern) => pattern.matches(title),
+            None => title == SERVER_DOWNLOAD_TITLE,
+        });
+        if is_server {
+            Artifact::Server
+        } else {
+            Artifact::Client
+        }
+    }
+
+    pub fn output_dir(&self) -> &Path {
+        self.output_dir.as_deref().unwrap_or(Path::new("."))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct VerifyArgs {
+    /// Game JARs to look up in the archived versions data, audits every archived version if absent
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Directory of locally mirrored game JARs to audit instead of downloading them
+    #[arg(long, value_name = "DIR", conflicts_with = "paths")]
+    pub mirror: Option<PathBuf>,
+
+    /// Also check that every archived version with a CID is pinned to IPFS
+    #[arg(long, conflicts_with = "paths")]
+    pub ipfs: bool,
+
+    #[command(flatten)]
+    pub ipfs_node: IpfsArgs,
+
+    /// Only send HEAD requests to every archived URL instead, printing those that fail or
+    /// redirect
+    #[arg(long, conflicts_with_all = ["paths", "mirror", "ipfs"])]
+    pub links_only: bool,
+
+    /// Local archived versions data to audit instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Rewrite the failing URLs of game JARs within `--file` to the ones of the same file names
+    /// at URL, if they respond successfully
+    #[arg(
+        long,
+        value_name = "URL",
+        requires_all = ["links_only", "versions_file"]
+    )]
+    pub rewrite_to: Option<url::Url>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FsckArgs {
+    /// Directory of game JARs to compare, searched recursively
+    #[arg(value_name = "DIR", default_value = ".")]
+    pub directory: PathBuf,
+
+    /// Local archived versions data to compare
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct HashArgs {
+    /// Files to calculate the hashes of
+    #[arg(required = true, value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Hash algorithms to calculate, printed in BSD-style lines if more than one
+    #[arg(
+        short,
+        long = "algorithm",
+        value_name = "ALG",
+        value_delimiter = ',',
+        default_value = "sha256"
+    )]
+    pub algorithms: Vec<HashAlgorithm>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestAddArgs {
+    #[command(flatten)]
+    pub entry: EntryArgs,
+
+    /// Local archived versions data to append to
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestMigrateArgs {
+    /// Local archived versions data to migrate
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+
+    /// Schema to migrate to, where 1 is the one without a `schemaVersion` [default: the newest]
+    #[arg(long, value_name = "VERSION")]
+    pub to: Option<u32>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestFmtArgs {
+    /// Local archived versions data to rewrite
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+
+    /// Only check whether it is already written canonically, failing if NOT
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestLintArgs {
+    /// Local archived versions data to validate instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestExportArgs {
+    /// Format of the version manifest, either crlauncher or mojang, csv for a table of the
+    /// versions, or else toml or yaml for the archived versions data itself
+    #[arg(long, value_name = "FORMAT")]
+    pub format: ExportFormat,
+
+    /// Local archived versions data to export instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Write the version manifest to PATH instead of printing it
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ManifestRehostArgs {
+    /// Base URL of the mirror, below which every archived file is placed
+    #[arg(long, value_name = "URL")]
+    pub base_url: url::Url,
+
+    /// Where each archived file is placed below `--base-url`, either flat by its file name, or
+    /// within a directory of the id of its version or of its sha256 hash
+    #[arg(long, value_name = "LAYOUT", default_value_t = MirrorLayout::Flat)]
+    pub layout: MirrorLayout,
+
+    /// Local archived versions data to rewrite instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Write the rewritten archived versions data to PATH instead of printing it
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Print every moved file as tab-separated `FROM TO` URLs instead, for copying them over to
+    /// the mirror
+    #[arg(long, conflicts_with = "output")]
+    pub list: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SignArgs {
+    /// Minisign secret key file to sign the game JAR and the archived versions data with
+    #[arg(long, value_name = "PATH", env = "COSMIC_ARCHIVE_MINISIGN_KEY")]
+    pub minisign_key: Option<PathBuf>,
+
+    /// Contents of the minisign secret key file to sign with, instead of `--minisign-key`
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "COSMIC_ARCHIVE_MINISIGN_SECRET_KEY",
+        hide_env_values = true
+    )]
+    pub minisign_secret_key: Option<String>,
+
+    /// Password of the minisign secret key, if encrypted
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "COSMIC_ARCHIVE_MINISIGN_PASSWORD",
+        hide_env_values = true
+    )]
+    pub minisign_password: Option<String>,
+
+    /// Id of the GPG key to sign the game JAR and the archived versions data with
+    #[arg(long, value_name = "ID", env = "COSMIC_ARCHIVE_GPG_KEY")]
+    pub gpg_key: Option<String>,
+}
+
+impl SignArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.minisign_key, &config.signing.minisign_key);
+        merge(&mut self.gpg_key, &config.signing.gpg_key);
+    }
+
+    /// Loads every requested signer, which may be none.
+    pub fn signers(&self) -> Result<Vec<Signer>, ArchiveError> {
+        let mut signers = Vec::new();
+        let password = self.minisign_password.clone();
+        if let Some(secret_key) = &self.minisign_secret_key {
+            signers.push(Signer::minisign(secret_key, password)?);
+        } else if let Some(path) = &self.minisign_key {
+            signers.push(Signer::minisign_from_file(path, password)?);
+        }
+        if let Some(key_id) = &self.gpg_key {
+            signers.push(Signer::Gpg(key_id.clone()));
+        }
+        Ok(signers)
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct S3Args {
+    /// Upload the NOT yet archived game JAR and its zip archive to an S3-compatible bucket,
+    /// pointing its entry at the uploaded game JAR
+    #[arg(long = "s3")]
+    pub enabled: bool,
+
+    /// Endpoint of the S3-compatible API, e.g. https://s3.us-east-1.amazonaws.com
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_S3_ENDPOINT")]
+    pub s3_endpoint: Option<url::Url>,
+
+    /// Bucket to upload to
+    #[arg(long, value_name = "NAME", env = "COSMIC_ARCHIVE_S3_BUCKET")]
+    pub s3_bucket: Option<String>,
+
+    /// Prefix of the key of every uploaded object
+    #[arg(long, value_name = "PREFIX", env = "COSMIC_ARCHIVE_S3_PREFIX")]
+    pub s3_prefix: Option<String>,
+
+    /// Region the bucket is in [default: us-east-1]
+    #[arg(long, value_name = "REGION", env = "COSMIC_ARCHIVE_S3_REGION")]
+    pub s3_region: Option<String>,
+
+    /// Access key id to sign requests with
+    #[arg(long, env = "COSMIC_ARCHIVE_S3_ACCESS_KEY", hide_env_values = true)]
+    pub s3_access_key: Option<String>,
+
+    /// Secret access key to sign requests with
+    #[arg(long, env = "COSMIC_ARCHIVE_S3_SECRET_KEY", hide_env_values = true)]
+    pub s3_secret_key: Option<String>,
+
+    /// Public URL the bucket is served from, if other than the endpoint
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_S3_PUBLIC_URL")]
+    pub s3_public_url: Option<url::Url>,
+}
+
+impl S3Args {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.s3_endpoint, &config.s3.endpoint);
+        merge(&mut self.s3_bucket, &config.s3.bucket);
+        merge(&mut self.s3_prefix, &config.s3.prefix);
+        merge(&mut self.s3_region, &config.s3.region);
+        merge(&mut self.s3_access_key, &config.s3.access_key);
+        merge(&mut self.s3_secret_key, &config.s3.secret_key);
+        merge(&mut self.s3_public_url, &config.s3.public_url);
+    }
+
+    /// Returns the requested storage, if any.
+    pub fn storage(&self) -> Result<Option<Storage>, ArchiveError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let (Some(endpoint), Some(bucket)) = (&self.s3_endpoint, &self.s3_bucket) else {
+            return Err(ArchiveError::Upload(
+                "S3 needs a bucket, see `--s3-endpoint` and `--s3-bucket`".to_owned(),
+            ));
+        };
+        let (Some(access_key), Some(secret_key)) = (&self.s3_access_key, &self.s3_secret_key)
+        else {
+            return Err(ArchiveError::Upload(
+                "S3 needs keys, see `--s3-access-key` and `--s3-secret-key`".to_owned(),
+            ));
+        };
+        Ok(Some(Storage::S3(S3Storage {
+            endpoint: endpoint.clone(),
+            bucket: bucket.clone(),
+            prefix: self.s3_prefix.clone().unwrap_or_default(),
+            region: self
+                .s3_region
+                .clone()
+                .unwrap_or_else(|| DEFAULT_S3_REGION.to_owned()),
+            access_key: access_key.clone(),
+            secret_key: secret_key.clone(),
+            public_url: self.s3_public_url.clone(),
+        })))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ItchAuthArgs {
+    /// CSRF token used to request the download info, when NO API key is given or it fails
+    #[arg(long, env = "CSRF_TOKEN", hide_env_values = true, default_value = "")]
+    pub csrf_token: String,
+
+    /// API key of an itch.io account to request the download info with through the itch.io API
+    #[arg(long, env = "ITCH_API_KEY", hide_env_values = true)]
+    pub itch_api_key: Option<String>,
+
+    /// Id of the download key of the game owned by the account of `--itch-api-key`, needed for
+    /// games that are NOT free
+    #[arg(long, value_name = "ID", env = "COSMIC_ARCHIVE_ITCH_DOWNLOAD_KEY")]
+    pub itch_download_key: Option<u64>,
+
+    /// Encrypted itch.io session written by `login`, whose CSRF token and cookies are used when
+    /// `--csrf-token` is absent
+    #[arg(long, value_name = "PATH", env = "COSMIC_ARCHIVE_ITCH_SESSION")]
+    pub itch_session: Option<PathBuf>,
+
+    /// Passphrase the itch.io session is encrypted with
+    #[arg(long, env = "COSMIC_ARCHIVE_ITCH_SESSION_KEY", hide_env_values = true)]
+    pub itch_session_key: Option<String>,
+
+    /// Host other than itch.io, along with its subdomains, that uploads hosted elsewhere are
+    /// allowed to be downloaded from, e.g. github.com
+    #[arg(
+        long = "external-host",
+        value_name = "HOST",
+        value_delimiter = ',',
+        env = "COSMIC_ARCHIVE_EXTERNAL_HOSTS"
+    )]
+    pub external_hosts: Vec<String>,
+
+    #[arg(skip)]
+    pub session: Option<Session>,
+}
+
+impl ItchAuthArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.itch_api_key, &config.itch.api_key);
+        merge(&mut self.itch_download_key, &config.itch.download_key);
+        merge(&mut self.itch_session, &config.itch.session);
+        if self.external_hosts.is_empty() {
+            self.external_hosts.clone_from(&config.itch.external_hosts);
+        }
+    }
+
+    /// Reads the itch.io session of `--itch-session` if given.
+    pub fn load_session(&mut self) -> Result<(), ArchiveError> {
+        let Some(path) = &self.itch_session else {
+            return Ok(());
+        };
+        let Some(key) = &self.itch_session_key else {
+            return Err(ArchiveError::Session(
+                "reading the session needs its key, see `--itch-session-key`".to_owned(),
+            ));
+        };
+
+        info!("Reading itch.io session from '{}'...", path.display());
+        let session = Session::read_from(path, key)?;
+        info!(
+            "itch.io session was logged into {} ago",
+            humantime::format_duration(session.age())
+        );
+        self.session = Some(session);
+        Ok(())
+    }
+
+    pub fn auth(&self) -> ItchAuth {
+        let csrf_token = match &self.session {
+            Some(session) if self.csrf_token.is_empty() => session.csrf_token.clone(),
+            _ => self.csrf_token.clone(),
+        };
+        ItchAuth {
+            api_key: self.itch_api_key.clone(),
+            download_key: self.itch_download_key,
+            csrf_token,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PromoteArgs {
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+
+    /// Version ids of the staged game JARs, or targets of the staged secondary files, to promote
+    /// [default: every staged one]
+    #[arg(value_name = "ID")]
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RollbackArgs {
+    /// Version id of the entry to remove
+    #[arg(value_name = "ID")]
+    pub id: String,
+
+    /// Kind of game JAR of the entry, either client or server
+    #[arg(long, value_name = "KIND", default_value = "client")]
+    pub artifact: Artifact,
+
+    /// Local archived versions data to remove the entry from
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+
+    /// Local git repository to delete the branch archiving the version from, see `--branch`
+    #[arg(long, value_name = "DIR", env = "COSMIC_ARCHIVE_GIT_REPO")]
+    pub git_repo: Option<PathBuf>,
+
+    /// Local branch archiving the version to delete
+    #[arg(long, value_name = "NAME", requires = "git_repo")]
+    pub branch: Option<String>,
+
+    /// Rename the branch to `rolled-back/NAME` instead of deleting it
+    #[arg(long, requires = "branch")]
+    pub keep_branch: bool,
+
+    /// Directory to delete the game JAR of the version and the files next to it from
+    #[arg(long, value_name = "DIR")]
+    pub files_dir: Option<PathBuf>,
+
+    /// Move the files into DIR instead of deleting them
+    #[arg(long, value_name = "DIR", requires = "files_dir")]
+    pub move_files_to: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, clap::Args)]
+pub struct DbSyncArgs {
+    /// Local archived versions data to mirror instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// SQLite database to mirror into, created if missing
+    #[arg(long, value_name = "PATH", default_value = "versions.db")]
+    pub db: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ListArgs {
+    /// Local archived versions data to list instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Only list versions of TYPE, e.g. pre_alpha
+    #[arg(long = "type", value_name = "TYPE")]
+    pub kind: Option<String>,
+
+    /// Only list game JARs of KIND, either client or server [default: both]
+    #[arg(long, value_name = "KIND")]
+    pub artifact: Option<Artifact>,
+
+    /// Only list versions released on or after DATE, e.g. 2024-01-01
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg)]
+    pub since: Option<u64>,
+
+    /// Only list versions released before DATE, e.g. 2025-01-01
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg)]
+    pub until: Option<u64>,
+
+    /// Order to list versions in
+    #[arg(long, value_name = "ORDER", default_value = "archived")]
+    pub sort: ListSort,
+
+    /// List versions in reverse order, e.g. the newest first
+    #[arg(short, long)]
+    pub reverse: bool,
+
+    /// List at most N versions, the first ones in order
+    #[arg(short = 'n', long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Print the tab-separated id, type, release time, and sha256 hash of each version instead,
+    /// without a header
+    #[arg(long)]
+    pub tsv: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ShowArgs {
+    /// Id of the version to show, e.g. 0.3.27
+    #[arg(value_name = "ID", required_unless_present_any = ["hash", "date"])]
+    pub id: Option<String>,
+
+    /// Show the versions whose game JARs have a hash starting with HEX, of any recorded algorithm
+    #[arg(long, value_name = "HEX")]
+    pub hash: Option<String>,
+
+    /// Show the versions released on DATE, e.g. 2024-08-15
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg)]
+    pub date: Option<u64>,
+
+    /// Only show game JARs of KIND, either client or server [default: both]
+    #[arg(long, value_name = "KIND")]
+    pub artifact: Option<Artifact>,
+
+    /// Local archived versions data to look up instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Directory of archived game JARs to also summarize the files written next to them from,
+    /// e.g. their class and natives indices
+    #[arg(long, value_name = "DIR")]
+    pub files_dir: Option<PathBuf>,
+
+    /// Print the entries as JSON instead
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct StatsArgs {
+    /// Local archived versions data to summarize instead of the one at `--manifest-url`
+    #[arg(long = "file", value_name = "PATH")]
+    pub versions_file: Option<PathBuf>,
+
+    /// Format to print the statistics in
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub output: StatsOutput,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LoginArgs {
+    /// Username or email of the itch.io account
+    #[arg(long, env = "ITCH_USERNAME")]
+    pub username: String,
+
+    /// Password of the itch.io account
+    #[arg(long, env = "ITCH_PASSWORD", hide_env_values = true)]
+    pub password: String,
+
+    /// Base32 secret of the two-factor authentication of the account, if enabled
+    #[arg(long, env = "ITCH_TOTP_SECRET", hide_env_values = true)]
+    pub totp_secret: Option<String>,
+
+    /// Path to write the encrypted session to
+    #[arg(short, long, value_name = "PATH", env = "COSMIC_ARCHIVE_ITCH_SESSION")]
+    pub output: PathBuf,
+
+    /// Passphrase to encrypt the session with
+    #[arg(long, env = "COSMIC_ARCHIVE_ITCH_SESSION_KEY", hide_env_values = true)]
+    pub session_key: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct MatrixArgs {
+    /// Homeserver of the Matrix room to notify of NOT yet archived versions, or of archived ones
+    /// with `--commit`
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_MATRIX_HOMESERVER")]
+    pub matrix_homeserver: Option<url::Url>,
+
+    /// Id of the Matrix room to notify, e.g. `!abcdefghijklmnop:matrix.org`
+    #[arg(long, value_name = "ID", env = "COSMIC_ARCHIVE_MATRIX_ROOM_ID")]
+    pub matrix_room_id: Option<String>,
+
+    /// Access token of the Matrix account posting to the room
+    #[arg(
+        long,
+        env = "COSMIC_ARCHIVE_MATRIX_ACCESS_TOKEN",
+        hide_env_values = true
+    )]
+    pub matrix_access_token: Option<String>,
+
+    #[arg(skip)]
+    pub message: Option<String>,
+}
+
+impl MatrixArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.matrix_homeserver, &config.matrix.homeserver);
+        merge(&mut self.matrix_room_id, &config.matrix.room_id);
+        merge(&mut self.matrix_access_token, &config.matrix.access_token);
+        merge(&mut self.message, &config.matrix.message);
+    }
+
+    /// Returns the Matrix room to notify, if any.
+    pub fn room(&self) -> Result<Option<MatrixRoom>, ArchiveError> {
+        let Some(homeserver) = &self.matrix_homeserver else {
+            return Ok(None);
+        };
+        let (Some(room_id), Some(access_token)) = (&self.matrix_room_id, &self.matrix_access_token)
+        else {
+            return Err(ArchiveError::Upload(
+                "notifying Matrix needs a room and a token, see `--matrix-room-id` and `--matrix-access-token`"
+                    .to_owned(),
+            ));
+        };
+        Ok(Some(MatrixRoom {
+            homeserver: homeserver.clone(),
+            access_token: access_token.clone(),
+            room_id: room_id.clone(),
+            template: self.message.clone(),
+        }))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct MastodonArgs {
+    /// Mastodon instance to post a status to of every archived version
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_MASTODON_INSTANCE")]
+    pub mastodon_instance: Option<url::Url>,
+
+    /// Access token of the Mastodon account posting, with the `write:statuses` scope
+    #[arg(
+        long,
+        env = "COSMIC_ARCHIVE_MASTODON_ACCESS_TOKEN",
+        hide_env_values = true
+    )]
+    pub mastodon_access_token: Option<String>,
+
+    /// Visibility of the posted statuses, e.g. `unlisted` [default: public]
+    #[arg(
+        long,
+        value_name = "VISIBILITY",
+        env = "COSMIC_ARCHIVE_MASTODON_VISIBILITY"
+    )]
+    pub mastodon_visibility: Option<String>,
+
+    #[arg(skip)]
+    pub status: Option<String>,
+}
+
+impl MastodonArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.mastodon_instance, &config.mastodon.instance);
+        merge(
+            &mut self.mastodon_access_token,
+            &config.mastodon.access_token,
+        );
+        merge(&mut self.mastodon_visibility, &config.mastodon.visibility);
+        merge(&mut self.status, &config.mastodon.status);
+    }
+
+    /// Returns the Mastodon account to post with, if any.
+    pub fn account(&self) -> Result<Option<MastodonAccount>, ArchiveError> {
+        let Some(instance) = &self.mastodon_instance else {
+            return Ok(None);
+        };
+        let Some(access_token) = &self.mastodon_access_token else {
+            return Err(ArchiveError::Upload(
+                "posting to Mastodon needs a token, see `--mastodon-access-token`".to_owned(),
+            ));
+        };
+        Ok(Some(MastodonAccount {
+            instance: instance.clone(),
+            access_token: access_token.clone(),
+            visibility: self
+                .mastodon_visibility
+                .clone()
+                .unwrap_or_else(|| "public".to_owned()),
+            template: self.status.clone(),
+        }))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SelfUpdateArgs {
+    /// GitHub repository the updater is released in, as `OWNER/NAME`
+    #[arg(
+        long,
+        value_name = "OWNER/NAME",
+        env = "COSMIC_ARCHIVE_UPDATER_REPOSITORY",
+        default_value = UPDATER_REPOSITORY
+    )]
+    pub repository: String,
+
+    /// Token to authenticate requests to the GitHub REST API with, avoiding its lower anonymous
+    /// rate limit
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    pub github_token: Option<String>,
+
+    /// Also consider pre-releases
+    #[arg(long)]
+    pub prerelease: bool,
+
+    /// Only print whether a newer release is available, without downloading it
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct IpfsArgs {
+    /// RPC API of the Kubo node to add to and check pins of [default: http://127.0.0.1:5001]
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_IPFS_API")]
+    pub ipfs_api: Option<url::Url>,
+
+    /// Token to authenticate requests to the RPC API with
+    #[arg(long, env = "COSMIC_ARCHIVE_IPFS_TOKEN", hide_env_values = true)]
+    pub ipfs_token: Option<String>,
+
+    /// Endpoint of a remote pinning service to also pin to, and to check pins of instead
+    #[arg(long, value_name = "URL", env = "COSMIC_ARCHIVE_IPFS_PINNING_SERVICE")]
+    pub ipfs_pinning_service: Option<url::Url>,
+
+    /// Token to authenticate requests to the remote pinning service with
+    #[arg(
+        long,
+        env = "COSMIC_ARCHIVE_IPFS_PINNING_TOKEN",
+        hide_env_values = true
+    )]
+    pub ipfs_pinning_token: Option<String>,
+}
+
+impl IpfsArgs {
+    pub fn merge(&mut self, config: &Config) {
+        merge(&mut self.ipfs_api, &config.ipfs.api);
+        merge(&mut self.ipfs_token, &config.ipfs.token);
+        merge(&mut self.ipfs_pinning_service, &config.ipfs.pinning_service);
+        merge(&mut self.ipfs_pinning_token, &config.ipfs.pinning_token);
+    }
+
+    pub fn ipfs(&self) -> Result<Ipfs, ArchiveError> {
+        let pinning_service = match (&self.ipfs_pinning_service, &self.ipfs_pinning_token) {
+            (Some(endpoint), Some(token)) => Some(PinningService {
+                endpoint: endpoint.clone(),
+                token: token.clone(),
+            }),
+            (Some(_), None) => {
+                return Err(ArchiveError::Upload(
+                    "the pinning service needs a token, see `--ipfs-pinning-token`".to_owned(),
+                ))
+            }
+            (None, _) => None,
+        };
+        Ok(Ipfs {
+            api: match &self.ipfs_api {
+                Some(api) => api.clone(),
+                None => url::Url::parse(DEFAULT_IPFS_API)?,
+            },
+            token: self.ipfs_token.clone(),
+            pinning_service,
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CacheGcArgs {
+    /// Age of the cached files to remove, e.g. `30d` or `12h`
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "30d",
+        value_parser = humantime::parse_duration
+    )]
+    pub max_age: Duration,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TorrentArgs {
+    /// Ids of the archived versions to write torrents of, every archived version if absent
+    #[arg(value_name = "ID")]
+    pub ids: Vec<String>,
+
+    /// Directory of locally mirrored game JARs to read instead of downloading them
+    #[arg(long, value_name = "DIR")]
+    pub mirror: Option<PathBuf>,
+
+    /// Directory to write the torrents into
+    #[arg(short, long, value_name = "DIR", default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Announce URL of a tracker, in order of preference
+    #[arg(long = "tracker", value_name = "URL")]
+    pub trackers: Vec<url::Url>,
+
+    /// Web seed besides the URL of each version, where one ending with `/` is joined with the
+    /// file name
+    #[arg(long = "web-seed", value_name = "URL")]
+    pub web_seeds: Vec<url::Url>,
+
+    /// Size in bytes of every piece, a power of two of at least 16384
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = DEFAULT_PIECE_LENGTH,
+        value_parser = parse_piece_length
+    )]
+    pub piece_length: u64,
+}
+
+impl TorrentArgs {
+    pub fn merge(&mut self, config: &Config) {
+        if self.trackers.is_empty() {
+            self.trackers.clone_from(&config.torrent.trackers);
+        }
+        if self.web_seeds.is_empty() {
+            self.web_seeds.clone_from(&config.torrent.web_seeds);
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ClassIndexArgs {
+    /// Game JARs to write the class indices of
+    #[arg(required = true, value_name = "JAR")]
+    pub jars: Vec<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct NativesArgs {
+    /// Game JARs to write the natives indices of
+    #[arg(required = true, value_name = "JAR")]
+    pub jars: Vec<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct NormalizeArgs {
+    /// Zip archives or JARs to re-zip
+    #[arg(required = true, value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SlimArgs {
+    /// Game JARs to write the slim JARs of
+    #[arg(required = true, value_name = "JAR")]
+    pub jars: Vec<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SbomArgs {
+    /// Game JARs to write the SBOMs of
+    #[arg(required = true, value_name = "JAR")]
+    pub jars: Vec<PathBuf>,
+
+    /// Also print the bundled dependencies of each game JAR as tab-separated version id, Maven
+    /// coordinates, package URL, and whether guessed
+    #[arg(long)]
+    pub list: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct VerifySignaturesArgs {
+    /// Local archived versions data to verify along with its game JARs
+    #[arg(long = "file", value_name = "PATH", default_value = "versions.json")]
+    pub versions_file: PathBuf,
+
+    /// Directory of locally mirrored game JARs [default: directory of the archived versions data]
+    #[arg(long, value_name = "DIR")]
+    pub mirror: Option<PathBuf>,
+
+    /// Minisign public key, either bare or the contents of its file
+    #[arg(long, value_name = "KEY", env = "COSMIC_ARCHIVE_MINISIGN_PUBLIC_KEY")]
+    pub minisign_public_key: Option<String>,
+
+    /// Minisign public key file, instead of `--minisign-public-key`
+    #[arg(long, value_name = "PATH", conflicts_with = "minisign_public_key")]
+    pub minisign_public_key_file: Option<PathBuf>,
+
+    /// Also verify GPG signatures against the local keyring
+    #[arg(long)]
+    pub gpg: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct EntryArgs {
+    /// Game JAR to create the entry of
+    #[arg(value_name = "JAR")]
+    pub jar: PathBuf,
+
+    /// Version id, defaults to the one in the game JAR file name
+    #[arg(long)]
+    pub id: Option<String>,
+
+    /// Version type [default: classified from the version id by the `version-types` rules of
+    /// the config file, or else pre_alpha]
+    #[arg(long = "type", value_name = "TYPE")]
+    pub kind: Option<String>,
+
+    /// Kind of game JAR, either client or server
+    #[arg(long, value_name = "KIND", default_value = "client")]
+    pub artifact: Artifact,
+
+    /// Unix timestamp in seconds of the release, defaults to now
+    #[arg(long, value_name = "SECONDS")]
+    pub release_time: Option<u64>,
+
+    /// Download URL of the game JAR, defaults to its file name joined onto `--base-url`
+    #[arg(long, value_name = "URL")]
+    pub url: Option<url::Url>,
+
+    /// Base URL that archived game JARs are downloadable from
+    #[arg(long, value_name = "URL", default_value = ARCHIVED_JARS_URL)]
+    pub base_url: url::Url,
+
+    /// Hashes to record alongside sha256, any of md5, sha1, sha512, or blake3
+    #[arg(long = "extra-hash", value_name = "ALG", value_delimiter = ',')]
+    pub extra_hashes: Vec<HashAlgorithm>,
+
+    #[arg(skip)]
+    pub version_types: Vec<TypeRule>,
+
+    /// HTML of the itch.io game page to classify the version type from, if known.
+    #[arg(skip)]
+    pub game_page: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DiffPatchArgs {
+    /// Game JAR of the version to patch from
+    #[arg(value_name = "FROM")]
+    pub from: PathBuf,
+
+    /// Game JAR of the version to patch to
+    #[arg(value_name = "TO")]
+    pub to: PathBuf,
+
+    /// Version id to patch from, defaults to the one detected in its game JAR
+    #[arg(long, value_name = "ID")]
+    pub from_id: Option<String>,
+
+    /// Version id to patch to, defaults to the one detected in its game JAR
+    #[arg(long, value_name = "ID")]
+    pub to_id: Option<String>,
+
+    /// Format of the patch, either bsdiff or zstd
+    #[arg(long, value_name = "FORMAT", default_value = "bsdiff")]
+    pub format: PatchFormat,
+
+    /// Path of the patch, defaults to `<FROM ID>-<TO ID>.<FORMAT>.patch` next to `--patches`
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Local patches data to record the patch in
+    #[arg(long, value_name = "PATH", default_value = "patches.json")]
+    pub patches: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DiffJarArgs {
+    /// Game JAR of the older version
+    #[arg(value_name = "OLD")]
+    pub old: PathBuf,
+
+    /// Game JAR of the newer version
+    #[arg(value_name = "NEW")]
+    pub new: PathBuf,
+
+    /// Only print the changes of class files
+    #[arg(long)]
+    pub classes_only: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DiffApplyArgs {
+    /// Game JAR to apply the patch onto
+    #[arg(value_name = "FROM")]
+    pub from: PathBuf,
+
+    /// Patch to apply
+    #[arg(value_name = "PATCH")]
+    pub patch: PathBuf,
+
+    /// Path of the patched game JAR
+    #[arg(short, long, value_name = "PATH")]
+    pub output: PathBuf,
+
+    /// Format of the patch, defaults to the one recorded in `--patches`
+    #[arg(long, value_name = "FORMAT")]
+    pub format: Option<PatchFormat>,
+
+    /// Local patches data to look up the patch in, verifying the patched game JAR
+    #[arg(long, value_name = "PATH", default_value = "patches.json")]
+    pub patches: PathBuf,
+}
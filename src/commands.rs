@@ -0,0 +1,1121 @@
+//! Every other command of the updater, e.g. listing, editing, and indexing archived versions.
+
+use crate::atomic::write_atomically;
+use crate::auth::{log_in, Credentials};
+use crate::cache::get_cached;
+#[cfg(feature = "sqlite")]
+use crate::cli::DbSyncArgs;
+use crate::cli::{
+    timeout, CacheGcArgs, ClassIndexArgs, DiffApplyArgs, DiffJarArgs, DiffPatchArgs, FsckArgs,
+    GlobalArgs, HashArgs, ListArgs, ListSort, LoginArgs, ManifestAddArgs, ManifestExportArgs,
+    ManifestFmtArgs, ManifestLintArgs, ManifestMigrateArgs, ManifestRehostArgs, NativesArgs,
+    NormalizeArgs, SbomArgs, SelfUpdateArgs, ShowArgs, SlimArgs, StatsArgs, StatsOutput,
+    TorrentArgs, VerifySignaturesArgs,
+};
+#[cfg(feature = "sqlite")]
+use crate::db::Database;
+use crate::delta::{apply_patch, create_patch, Patch, PatchFormat, Patches};
+use crate::devlog::devlog_path;
+use crate::export::{export, serialize_export};
+use crate::hash::{hash_reader, hash_reader_with};
+use crate::jar::{
+    class_index_path, create_class_index, create_slim_jar, diff_jars, slim_jar_path, JarEntry,
+};
+use crate::manifest::{compare_version_ids, get_versions, Manifest, SCHEMA_VERSION};
+use crate::natives::{create_natives_index, natives_path};
+use crate::normalize::normalize_file;
+use crate::pipeline::{add_entry, jar_file_name, new_entry, resolve_version_id, sign_files};
+use crate::rehost::{rehost, Rehosted};
+use crate::release::{format_date, format_timestamp};
+use crate::sbom::{create_sbom, sbom_path};
+use crate::schema::lint_versions;
+use crate::sign::Verifier;
+use crate::stats::ArchiveStats;
+use crate::torrent::{torrent_path, Torrent};
+use crate::update::{download_binary, get_latest_release, is_newer, replace_executable};
+use crate::verify::{check_archived_jar, check_directory, open_archived_jar, url_file_name};
+use crate::{ArchiveError, Artifact, Version, Versions};
+use std::fs::{self, File};
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, iter};
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// Compares the local archived versions data of `args` against its local directory of game JARs.
+pub fn fsck(args: FsckArgs) -> Result<(), ArchiveError> {
+    let versions = Versions::read_from(&args.versions_file)?;
+    let report = check_directory(&versions, &args.directory)?;
+
+    for path in &report.unreferenced {
+        println!("unreferenced\t{}", path.display());
+    }
+    for id in &report.missing {
+        println!("missing\t{id}");
+    }
+    for (path, id) in &report.mismatched {
+        println!("mismatched\t{}\t{id}", path.display());
+    }
+
+    if report.is_clean() {
+        info!(
+            "All {} archived versions match '{}'",
+            versions.versions.len(),
+            args.directory.display()
+        );
+        Ok(())
+    } else {
+        Err(ArchiveError::CheckFailed {
+            unreferenced: report.unreferenced.len(),
+            missing: report.missing.len(),
+            mismatched: report.mismatched.len(),
+        })
+    }
+}
+
+/// Prints the sha256 hashes of the local files of `args`.
+pub fn hash(args: HashArgs) -> Result<(), ArchiveError> {
+    for path in &args.paths {
+        let file = File::open(path)?;
+        let (digests, _) =
+            hash_reader_with(io::BufReader::new(file), args.algorithms.iter().copied())?;
+
+        if let &[algorithm] = args.algorithms.as_slice() {
+            let digest = digests.get(algorithm).map(hex::encode).unwrap_or_default();
+            println!("{digest}  {}", path.display());
+        } else {
+            for (algorithm, digest) in digests.iter() {
+                let digest = hex::encode(digest);
+                println!("{} ({}) = {digest}", algorithm.tag(), path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Logs into itch.io, caching the session encrypted for later fetches.
+pub async fn login(global: &GlobalArgs, args: LoginArgs) -> Result<(), ArchiveError> {
+    let credentials = Credentials {
+        username: args.username,
+        password: args.password,
+        totp_secret: args.totp_secret,
+    };
+    let session = log_in(
+        &global.client_options(),
+        &credentials,
+        &global.scrape_policy(),
+    )
+    .await?;
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would write itch.io session to '{}'",
+            args.output.display()
+        );
+        return Ok(());
+    }
+    session.write_to(&args.output, &args.session_key)?;
+    warn!("Wrote itch.io session to '{}'", args.output.display());
+    Ok(())
+}
+
+/// Replaces this executable by the binary of the newest release of the updater if newer.
+pub async fn self_update(global: &GlobalArgs, args: &SelfUpdateArgs) -> Result<(), ArchiveError> {
+    let client = global.client()?;
+    let Some(release) = get_latest_release(
+        &client,
+        &args.repository,
+        args.github_token.as_deref(),
+        args.prerelease,
+        &global.manifest_policy(),
+    )
+    .await?
+    else {
+        warn!("Found NO release of the updater in {}", args.repository);
+        return Ok(());
+    };
+    let version = release.version().unwrap_or_default();
+    if !is_newer(version) {
+        warn!(
+            "Updater {} is up to date, the newest release is {version}",
+            env!("CARGO_PKG_VERSION")
+        );
+        return Ok(());
+    }
+    warn!(
+        "Updater {version} is available ({}), this is {}",
+        release.html_url,
+        env!("CARGO_PKG_VERSION")
+    );
+    if args.check {
+        println!("{version}");
+        return Ok(());
+    }
+
+    let executable = std::env::current_exe()?;
+    let download = global
+        .retry_policy()
+        .with_timeout(timeout(global.download_timeout, 1800));
+    let bytes = download_binary(&client, &release, &download)
+        .instrument(info_span!("download"))
+        .await?;
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would replace executable '{}' by {version}",
+            executable.display()
+        );
+        return Ok(());
+    }
+    replace_executable(&executable, &bytes)?;
+    println!("{version}");
+    Ok(())
+}
+
+/// Lists the archived versions matching `args` as a table.
+pub async fn list(global: &GlobalArgs, args: ListArgs) -> Result<(), ArchiveError> {
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            let client = global.client()?;
+            get_versions(
+                &client,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+
+    let mut listed = versions
+        .versions
+        .iter()
+        .filter(|it| args.kind.as_ref().is_none_or(|kind| &it.kind == kind))
+        .filter(|it| {
+            args.artifact
+                .is_none_or(|artifact| it.artifact() == artifact)
+        })
+        .filter(|it| args.since.is_none_or(|since| it.release_time >= since))
+        .filter(|it| args.until.is_none_or(|until| it.release_time < until))
+        .collect::<Vec<_>>();
+    match args.sort {
+        ListSort::Archived => {}
+        ListSort::Id => listed.sort_by(|a, b| compare_version_ids(&a.id, &b.id)),
+        ListSort::Released => listed.sort_by_key(|it| it.release_time),
+        ListSort::Size => listed.sort_by_key(|it| it.size),
+    }
+    if args.reverse {
+        listed.reverse();
+    }
+    listed.truncate(args.limit.unwrap_or(usize::MAX));
+
+    if args.tsv {
+        for version in &listed {
+            println!(
+                "{}\t{}\t{}\t{}",
+                version.id, version.kind, version.release_time, version.sha256
+            );
+        }
+        return Ok(());
+    }
+
+    let header = ["ID", "TYPE", "ARTIFACT", "RELEASED", "SIZE", "SHA256"].map(str::to_owned);
+    let rows = listed
+        .iter()
+        .map(|version| {
+            [
+                version.id.clone(),
+                version.kind.clone(),
+                version.artifact().to_string(),
+                format_date(version.release_time),
+                version.size.to_string(),
+                version.sha256.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = header.clone().map(|it| it.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// Prints a summary of the archived versions matching the id, hash, or release date of `args`.
+pub async fn show(global: &GlobalArgs, args: ShowArgs) -> Result<(), ArchiveError> {
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            let client = global.client()?;
+            get_versions(
+                &client,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+
+    let hash = args.hash.as_deref().map(str::to_ascii_lowercase);
+    let shown = versions
+        .versions
+        .iter()
+        .filter(|it| args.id.as_ref().is_none_or(|id| &it.id == id))
+        .filter(|it| {
+            args.artifact
+                .is_none_or(|artifact| it.artifact() == artifact)
+        })
+        .filter(|it| {
+            args.date
+                .is_none_or(|date| (date..date + 86400).contains(&it.release_time))
+        })
+        .filter(|it| hash.as_ref().is_none_or(|hash| has_hash_prefix(it, hash)))
+        .collect::<Vec<_>>();
+    if shown.is_empty() {
+        let query = [
+            args.id.clone(),
+            args.hash.clone(),
+            args.date.map(format_date),
+        ];
+        return Err(ArchiveError::UnknownVersion(
+            query.into_iter().flatten().collect::<Vec<_>>().join(" "),
+        ));
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&shown)?);
+        return Ok(());
+    }
+    for (index, version) in shown.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_summary(version, &versions, args.files_dir.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Prints statistics of the archived versions, e.g. their release cadence and size growth.
+pub async fn stats(global: &GlobalArgs, args: StatsArgs) -> Result<(), ArchiveError> {
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            let client = global.client()?;
+            get_versions(
+                &client,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+    let stats = ArchiveStats::of(&versions);
+
+    if args.output == StatsOutput::Json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    let artifacts = stats
+        .artifacts
+        .iter()
+        .map(|(artifact, count)| format!("{count} {artifact}"))
+        .collect::<Vec<_>>();
+    println!(
+        "Versions:      {} ({})",
+        stats.versions,
+        artifacts.join(", ")
+    );
+    println!("Archive size:  {} bytes", stats.total_size);
+    if let Some(cadence) = &stats.cadence {
+        println!(
+            "Cadence:       every {:.1} days on average, {:.1} by median, from {:.1} to {:.1}",
+            cadence.mean, cadence.median, cadence.min, cadence.max
+        );
+    }
+
+    println!();
+    println!("{:<16}{:>8}{:>16}", "TYPE", "COUNT", "SIZE");
+    for (kind, it) in &stats.types {
+        println!("{kind:<16}{:>8}{:>16}", it.count, it.total_size);
+    }
+
+    println!();
+    println!(
+        "{:<16}{:<8}{:<12}{:>12}{:>12}{:>16}",
+        "ID", "ARTIFACT", "RELEASED", "SIZE", "CHANGE", "CUMULATIVE"
+    );
+    for it in &stats.growth {
+        let change = it
+            .size_change
+            .map_or_else(|| "-".to_owned(), |it| format!("{it:+}"));
+        println!(
+            "{:<16}{:<8}{:<12}{:>12}{change:>12}{:>16}",
+            it.id,
+            it.artifact.to_string(),
+            format_date(it.release_time),
+            it.size,
+            it.cumulative_size
+        );
+    }
+    Ok(())
+}
+
+/// Returns whether any recorded hash of the game JAR of `version` starts with the lowercase `hex`.
+fn has_hash_prefix(version: &Version, hex: &str) -> bool {
+    let sha256 = version.sha256.to_string();
+    [
+        Some(&sha256),
+        version.sha1.as_ref(),
+        version.sha512.as_ref(),
+        version.md5.as_ref(),
+        version.blake3.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|it| it.starts_with(hex))
+}
+
+/// Prints the summary of the entry of `version` of `versions`, along with the files written next
+/// to its game JAR within `files_dir`, if any.
+fn print_summary(
+    version: &Version,
+    versions: &Versions,
+    files_dir: Option<&Path>,
+) -> Result<(), ArchiveError> {
+    let is_latest = version.artifact() == Artifact::Client
+        && versions.latest.get(&version.kind) == Some(&version.id);
+    println!(
+        "{} ({} {}{})",
+        version.id,
+        version.kind,
+        version.artifact(),
+        if is_latest { ", latest" } else { "" }
+    );
+
+    let field = |name: &str, value: &dyn fmt::Display| println!("  {name:<16}{value}");
+    field("Released", &format_timestamp(version.release_time));
+    if let Some(updated_at) = version.updated_at {
+        field("Updated", &format_timestamp(updated_at));
+    }
+    field("URL", &version.url);
+    field("Size", &format!("{} bytes", version.size));
+    if let Some(zip_size) = version.zip_size {
+        let compression = match &version.archive_format {
+            Some(format) => format,
+            None => version.compression.as_deref().unwrap_or("unknown"),
+        };
+        field("Zip size", &format!("{zip_size} bytes, {compression}"));
+    }
+    field("SHA-256", &version.sha256);
+    let hashes = [
+        ("SHA-1", &version.sha1),
+        ("SHA-512", &version.sha512),
+        ("MD5", &version.md5),
+        ("BLAKE3", &version.blake3),
+    ];
+    for (name, hash) in hashes {
+        if let Some(hash) = hash {
+            field(name, hash);
+        }
+    }
+    if let Some(cid) = &version.ipfs_cid {
+        field("IPFS CID", cid);
+    }
+    if let Some(java_version) = version.java_version {
+        field("Java", &java_version);
+    }
+    if let Some(compatibility) = &version.compatibility {
+        if let Some(save_version) = &compatibility.save_version {
+            field("Save format", save_version);
+        }
+        if let Some(protocol_version) = &compatibility.protocol_version {
+            field("Protocol", protocol_version);
+        }
+    }
+    if let Some(build) = &version.build {
+        let details = [
+            build.build_number.as_ref().map(|it| format!("build {it}")),
+            build.build_time.as_ref().map(|it| format!("built {it}")),
+            build.build_jdk.as_ref().map(|it| format!("JDK {it}")),
+        ];
+        let details = details.into_iter().flatten().collect::<Vec<_>>();
+        if !details.is_empty() {
+            field("Build", &details.join(", "));
+        }
+        if let Some(main_class) = &build.main_class {
+            field("Main class", main_class);
+        }
+    }
+    if let Some(devlog) = &version.devlog {
+        field("Devlog", devlog);
+    }
+    if let Some(slim) = &version.slim {
+        field("Slim JAR", &format!("{} ({} bytes)", slim.url, slim.size));
+    }
+
+    let Some(files_dir) = files_dir else {
+        return Ok(());
+    };
+    let jar = files_dir.join(url_file_name(&version.url)?);
+    let read_json = |path: PathBuf| -> Result<Option<serde_json::Value>, ArchiveError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(cause) => Err(cause.into()),
+        }
+    };
+    field(
+        "Local JAR",
+        &if jar.is_file() {
+            jar.display().to_string()
+        } else {
+            "missing".to_owned()
+        },
+    );
+    if let Some(index) = read_json(class_index_path(&jar))? {
+        let classes = index["classes"].as_array().map_or(0, Vec::len);
+        field("Classes", &classes);
+    }
+    if let Some(index) = read_json(natives_path(&jar))? {
+        let libraries = index["libraries"].as_array().map_or(0, Vec::len);
+        field("Native libraries", &libraries);
+        if let Some(lwjgl_version) = index["lwjglVersion"].as_str() {
+            field("LWJGL", &lwjgl_version);
+        }
+    }
+    if let Some(bom) = read_json(sbom_path(&jar))? {
+        let components = bom["components"].as_array().map_or(0, Vec::len);
+        field("Dependencies", &components);
+    }
+    let devlog = devlog_path(&jar);
+    if devlog.is_file() {
+        field("Local devlog", &devlog.display());
+    }
+    Ok(())
+}
+
+/// Appends the entry of the game JAR of `args` to a local archived versions data.
+pub fn manifest_add(global: &GlobalArgs, args: ManifestAddArgs) -> Result<(), ArchiveError> {
+    let version = new_entry(&args.entry)?;
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would add the following entry to '{}':",
+            args.versions_file.display()
+        );
+        println!("{}", serde_json::to_string_pretty(&version)?);
+        return Ok(());
+    }
+
+    let signers = args.sign.signers()?;
+    add_entry(&args.versions_file, &args.entry.jar, version)?;
+    sign_files(&signers, &[&args.entry.jar, &args.versions_file])?;
+    Ok(())
+}
+
+/// Rewrites a local archived versions data in the schema of `args`.
+pub fn manifest_migrate(
+    global: &GlobalArgs,
+    args: ManifestMigrateArgs,
+) -> Result<(), ArchiveError> {
+    let mut manifest = Manifest::read_from(&args.versions_file)?;
+    let from = manifest.schema_version;
+    manifest.migrate(args.to.unwrap_or(SCHEMA_VERSION))?;
+    if manifest.schema_version == from {
+        return Ok(());
+    }
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would rewrite '{}' as the following:",
+            args.versions_file.display()
+        );
+        println!("{}", serde_json::to_string_pretty(&manifest.to_value()?)?);
+        return Ok(());
+    }
+
+    manifest.write_to(&args.versions_file)?;
+    info!(
+        "Migrated '{}' from schema {from} to {}",
+        args.versions_file.display(),
+        manifest.schema_version
+    );
+    Ok(())
+}
+
+/// Rewrites a local archived versions data canonically, with its versions sorted by their release
+/// times.
+pub fn manifest_fmt(global: &GlobalArgs, args: ManifestFmtArgs) -> Result<(), ArchiveError> {
+    let bytes = fs::read(&args.versions_file)?;
+    let canonical = Manifest::from_slice(&bytes)?.to_canonical_bytes()?;
+    if bytes == canonical {
+        info!(
+            "'{}' is already written canonically",
+            args.versions_file.display()
+        );
+        return Ok(());
+    }
+    if args.check {
+        return Err(ArchiveError::NotCanonical(args.versions_file));
+    }
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would rewrite '{}' canonically",
+            args.versions_file.display()
+        );
+        return Ok(());
+    }
+    write_atomically(&args.versions_file, |file| Ok(file.write_all(&canonical)?))?;
+    info!("Rewrote '{}' canonically", args.versions_file.display());
+    Ok(())
+}
+
+/// Validates a local or the remote archived versions data against its JSON Schema, printing every
+/// problem found.
+pub async fn manifest_lint(
+    global: &GlobalArgs,
+    args: ManifestLintArgs,
+) -> Result<(), ArchiveError> {
+    let (bytes, source) = match &args.versions_file {
+        Some(path) => (fs::read(path)?, path.display().to_string()),
+        None => {
+            let bytes = get_cached(
+                &global.client()?,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                "fetch archived versions data",
+                &global.manifest_policy(),
+            )
+            .await?;
+            (bytes, global.manifest_url().to_owned())
+        }
+    };
+
+    let problems = lint_versions(&bytes)?;
+    for problem in &problems {
+        let path = if problem.path.is_empty() {
+            "/"
+        } else {
+            &problem.path
+        };
+        println!("{source}:{path}: {}", problem.message);
+    }
+    if !problems.is_empty() {
+        return Err(ArchiveError::InvalidManifest(problems.len()));
+    }
+    // NOTE: also check it deserializes, e.g. that its schema version is supported
+    Manifest::from_slice(&bytes)?;
+    info!("'{source}' matches its schema");
+    Ok(())
+}
+
+/// Prints the archived versions data as the version manifest of a launcher.
+pub async fn manifest_export(
+    global: &GlobalArgs,
+    args: ManifestExportArgs,
+) -> Result<(), ArchiveError> {
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            get_versions(
+                &global.client()?,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+    let manifest = serialize_export(&export(&versions, args.format)?, args.format)?;
+
+    match &args.output {
+        Some(path) if global.dry_run => {
+            warn!(
+                "[DRY RUN] Would write {} version manifest to '{}'",
+                args.format,
+                path.display()
+            );
+        }
+        Some(path) => {
+            write_atomically(path, |file| Ok(file.write_all(manifest.as_bytes())?))?;
+            info!(
+                "Wrote {} version manifest to '{}'",
+                args.format,
+                path.display()
+            );
+        }
+        None => print!("{manifest}"),
+    }
+    Ok(())
+}
+
+/// Prints the archived versions data with every URL rewritten to a mirror.
+pub async fn manifest_rehost(
+    global: &GlobalArgs,
+    args: ManifestRehostArgs,
+) -> Result<(), ArchiveError> {
+    let mut manifest = match &args.versions_file {
+        Some(path) => Manifest::read_from(path)?,
+        None => Manifest {
+            schema_version: SCHEMA_VERSION,
+            versions: get_versions(
+                &global.client()?,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?,
+        },
+    };
+    let moved = rehost(&mut manifest.versions, &args.base_url, args.layout)?;
+    info!(
+        "Rehosted {} archived files to {}",
+        moved.len(),
+        args.base_url
+    );
+    if args.list {
+        for Rehosted { from, to } in &moved {
+            println!("{from}\t{to}");
+        }
+        return Ok(());
+    }
+
+    let bytes = manifest.to_canonical_bytes()?;
+    match &args.output {
+        Some(path) if global.dry_run => {
+            warn!(
+                "[DRY RUN] Would write rehosted archived versions data to '{}'",
+                path.display()
+            );
+        }
+        Some(path) => {
+            write_atomically(path, |file| Ok(file.write_all(&bytes)?))?;
+            info!(
+                "Wrote rehosted archived versions data to '{}'",
+                path.display()
+            );
+        }
+        None => io::stdout().write_all(&bytes)?,
+    }
+    Ok(())
+}
+
+/// Writes a torrent of the archived game JARs of `args`, printing their magnet links.
+pub async fn torrent(global: &GlobalArgs, args: TorrentArgs) -> Result<(), ArchiveError> {
+    let client = global.client()?;
+    let retry = global.download_policy();
+    let versions = get_versions(
+        &client,
+        global.response_cache().as_ref(),
+        global.manifest_url(),
+        &global.manifest_policy(),
+    )
+    .await?;
+
+    let mut selected = Vec::new();
+    if args.ids.is_empty() {
+        selected.extend(&versions.versions);
+    } else {
+        for id in &args.ids {
+            let matching = versions.versions.iter().filter(|it| &it.id == id);
+            let count = selected.len();
+            selected.extend(matching);
+            if selected.len() == count {
+                return Err(ArchiveError::UnknownVersion(id.clone()));
+            }
+        }
+    }
+
+    let temp_dir = global.temp_dir();
+    for version in selected {
+        let (subject, mut file) =
+            open_archived_jar(&client, version, args.mirror.as_deref(), &temp_dir, &retry).await?;
+        check_archived_jar(version, &subject, &mut file)?;
+        file.rewind()?;
+
+        let file_name = url_file_name(&version.url)?;
+        let mut torrent = Torrent::from_reader(
+            io::BufReader::new(file),
+            file_name.clone(),
+            args.piece_length,
+        )?;
+        torrent.trackers.clone_from(&args.trackers);
+        torrent.web_seeds.push(version.url.clone());
+        torrent.web_seeds.extend(args.web_seeds.iter().cloned());
+
+        let path = torrent_path(&args.output_dir, &file_name);
+        if global.dry_run {
+            warn!(
+                "[DRY RUN] Would write torrent of {} to '{}'",
+                version.id,
+                path.display()
+            );
+        } else {
+            torrent.write_to(&path)?;
+            info!("Wrote torrent of {} to '{}'", version.id, path.display());
+        }
+        println!("{}\t{}", version.id, torrent.magnet_link());
+    }
+    Ok(())
+}
+
+/// Writes an index of the class files of the local game JARs of `args` next to each of them.
+pub fn class_index(global: &GlobalArgs, args: ClassIndexArgs) -> Result<(), ArchiveError> {
+    for jar in &args.jars {
+        let id = resolve_version_id(jar, jar_file_name(jar)?)?;
+        let index = create_class_index(jar, &id)?;
+        let path = class_index_path(jar);
+
+        if global.dry_run {
+            warn!(
+                "[DRY RUN] Would write class index of {} classes to '{}'",
+                index.classes.len(),
+                path.display()
+            );
+        } else {
+            index.write_to(&path)?;
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Writes an index of the native libraries and LWJGL modules the local game JARs of `args` bundle
+/// next to each of them.
+pub fn natives(global: &GlobalArgs, args: NativesArgs) -> Result<(), ArchiveError> {
+    for jar in &args.jars {
+        let id = resolve_version_id(jar, jar_file_name(jar)?)?;
+        let index = create_natives_index(jar, &id)?;
+        let path = natives_path(jar);
+
+        if global.dry_run {
+            warn!(
+                "[DRY RUN] Would write natives index of {} native libraries to '{}'",
+                index.libraries.len(),
+                path.display()
+            );
+        } else {
+            index.write_to(&path)?;
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Writes a slim JAR of the local game JARs of `args` next to each of them, stripped of their
+/// bundled libraries, printing their sha256 hashes.
+pub fn slim(global: &GlobalArgs, args: SlimArgs) -> Result<(), ArchiveError> {
+    for jar in &args.jars {
+        let path = slim_jar_path(jar);
+
+        if global.dry_run {
+            warn!("[DRY RUN] Would write slim JAR to '{}'", path.display());
+        } else {
+            create_slim_jar(jar, &path)?;
+            let (sha256, _) = hash_reader(io::BufReader::new(File::open(&path)?))?;
+            println!("{sha256}\t{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Re-zips the local zip archives and JARs of `args` reproducibly in place, printing their sha256
+/// hashes.
+pub fn normalize(global: &GlobalArgs, args: NormalizeArgs) -> Result<(), ArchiveError> {
+    for path in &args.files {
+        if global.dry_run {
+            warn!("[DRY RUN] Would normalize '{}'", path.display());
+        } else {
+            let count = normalize_file(path)?;
+            let (sha256, _) = hash_reader(io::BufReader::new(File::open(path)?))?;
+            info!("Normalized {count} entries of '{}'", path.display());
+            println!("{sha256}\t{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Writes a CycloneDX SBOM of what the local game JARs of `args` bundle next to each of them.
+pub fn sbom(global: &GlobalArgs, args: SbomArgs) -> Result<(), ArchiveError> {
+    for jar in &args.jars {
+        let id = resolve_version_id(jar, jar_file_name(jar)?)?;
+        let bom = create_sbom(jar, &id)?;
+        let path = sbom_path(jar);
+
+        if args.list {
+            for component in &bom.components {
+                println!(
+                    "{id}\t{}\t{}\t{}",
+                    component.coordinates(),
+                    component.purl.as_deref().unwrap_or("-"),
+                    if component.is_guessed() {
+                        "guessed"
+                    } else {
+                        "known"
+                    },
+                );
+            }
+        }
+
+        if global.dry_run {
+            warn!(
+                "[DRY RUN] Would write SBOM of {} components to '{}'",
+                bom.components.len(),
+                path.display()
+            );
+        } else {
+            bom.write_to(&path)?;
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Mirrors a local or the remote archived versions data into a SQLite database.
+#[cfg(feature = "sqlite")]
+pub async fn db_sync(global: &GlobalArgs, args: DbSyncArgs) -> Result<(), ArchiveError> {
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            get_versions(
+                &global.client()?,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would mirror {} versions into '{}'",
+            versions.versions.len(),
+            args.db.display()
+        );
+        return Ok(());
+    }
+    let stats = Database::open(&args.db)?.sync(&versions)?;
+    info!(
+        "Mirrored {} versions, {} latest versions, and {} files into '{}'",
+        stats.versions,
+        stats.latest,
+        stats.files,
+        args.db.display()
+    );
+    Ok(())
+}
+
+/// Removes the cached archives and responses that were NOT used for a while.
+pub fn cache_gc(global: &GlobalArgs, args: CacheGcArgs) -> Result<(), ArchiveError> {
+    let cache = global.archive_cache().ok_or(ArchiveError::NoCacheDir)?;
+    let max_age = humantime::format_duration(args.max_age);
+
+    if global.dry_run {
+        warn!("[DRY RUN] Would remove cached files older than {max_age}");
+        return Ok(());
+    }
+
+    let (count, size) = cache.collect_garbage(args.max_age)?;
+    warn!("Removed {count} cached files older than {max_age}, freeing {size} bytes");
+    Ok(())
+}
+
+/// Verifies the detached signatures of a local archived versions data and its game JARs.
+pub fn verify_signatures(args: VerifySignaturesArgs) -> Result<(), ArchiveError> {
+    let mut verifiers = Vec::new();
+    if let Some(public_key) = &args.minisign_public_key {
+        verifiers.push(Verifier::minisign(public_key)?);
+    } else if let Some(path) = &args.minisign_public_key_file {
+        verifiers.push(Verifier::minisign_from_file(path)?);
+    }
+    if args.gpg {
+        verifiers.push(Verifier::Gpg);
+    }
+    if verifiers.is_empty() {
+        return Err(ArchiveError::Signature(
+            "verifying needs a key, see `--minisign-public-key` and `--gpg`".to_owned(),
+        ));
+    }
+
+    for verifier in &verifiers {
+        verifier.verify(&args.versions_file)?;
+    }
+    info!("'{}' is signed", args.versions_file.display());
+
+    let versions = Versions::read_from(&args.versions_file)?;
+    let mirror = match &args.mirror {
+        Some(it) => it.as_path(),
+        None => args.versions_file.parent().unwrap_or(Path::new("")),
+    };
+
+    let mut failed = 0;
+    for version in &versions.versions {
+        let result = url_file_name(&version.url).and_then(|file_name| {
+            let path = mirror.join(file_name);
+            verifiers.iter().try_for_each(|it| it.verify(&path))
+        });
+        match result {
+            Ok(()) => info!("{} is signed", version.id),
+            Err(cause) => {
+                error!("{}: {cause}", version.id);
+                failed += 1;
+            }
+        }
+    }
+
+    let total = versions.versions.len();
+    if failed == 0 {
+        info!("All {total} archived versions are signed");
+        Ok(())
+    } else {
+        Err(ArchiveError::VerifyFailed { failed, total })
+    }
+}
+
+/// Creates the patch from one game JAR to the next, recording it in the patches data.
+pub fn diff_patch(global: &GlobalArgs, args: DiffPatchArgs) -> Result<(), ArchiveError> {
+    let from_id = match args.from_id {
+        Some(it) => it,
+        None => resolve_version_id(&args.from, jar_file_name(&args.from)?)?,
+    };
+    let to_id = match args.to_id {
+        Some(it) => it,
+        None => resolve_version_id(&args.to, jar_file_name(&args.to)?)?,
+    };
+
+    let patches_dir = args.patches.parent().unwrap_or(Path::new(""));
+    let output = args
+        .output
+        .unwrap_or_else(|| patches_dir.join(format!("{from_id}-{to_id}.{}.patch", args.format)));
+    let path = output
+        .strip_prefix(patches_dir)
+        .unwrap_or(&output)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    info!("Reading game JARs...");
+    let old = fs::read(&args.from)?;
+    let new = fs::read(&args.to)?;
+
+    info!(
+        "Creating {} patch from {from_id} to {to_id}...",
+        args.format
+    );
+    let patch = create_patch(&old, &new, args.format)?;
+    let entry = Patch::new(from_id, to_id, args.format, path, &patch, &new)?;
+    info!(
+        "Patch is {} bytes, {} bytes smaller than the game JAR",
+        entry.size,
+        (new.len() as u64).saturating_sub(entry.size)
+    );
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would write the patch to '{}' and add the following entry to '{}':",
+            output.display(),
+            args.patches.display()
+        );
+        println!("{}", serde_json::to_string_pretty(&entry)?);
+        return Ok(());
+    }
+
+    write_atomically(&output, |file| Ok(file.write_all(&patch)?))?;
+    let mut patches = Patches::read_or_default(&args.patches)?;
+    patches.add(entry);
+    patches.write_to(&args.patches)?;
+
+    println!("{}", output.display());
+    Ok(())
+}
+
+/// Applies a patch onto a game JAR.
+pub fn diff_apply(global: &GlobalArgs, args: DiffApplyArgs) -> Result<(), ArchiveError> {
+    let patch = fs::read(&args.patch)?;
+    let (sha256, _) = hash_reader(patch.as_slice())?;
+    let entry = Patches::read_or_default(&args.patches)?
+        .patches
+        .into_iter()
+        .find(|it| it.sha256 == sha256);
+
+    let format = match (args.format, &entry) {
+        (Some(format), _) => format,
+        (None, Some(entry)) => entry.format,
+        (None, None) => PatchFormat::Bsdiff,
+    };
+
+    info!("Applying {format} patch onto '{}'...", args.from.display());
+    let old = fs::read(&args.from)?;
+    let new = apply_patch(&old, &patch, format)?;
+
+    match &entry {
+        Some(entry) => {
+            let (actual, _) = hash_reader(new.as_slice())?;
+            if actual != entry.target_sha256 {
+                return Err(ArchiveError::HashMismatch {
+                    subject: args.output.display().to_string(),
+                    expected: entry.target_sha256,
+                    actual,
+                });
+            }
+            info!("Patched game JAR is {}", entry.to);
+        }
+        None => warn!("Patch is NOT in the patches data, the patched game JAR is NOT verified"),
+    }
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would write the patched game JAR to '{}'",
+            args.output.display()
+        );
+        return Ok(());
+    }
+
+    write_atomically(&args.output, |file| Ok(file.write_all(&new)?))?;
+    println!("{}", args.output.display());
+    Ok(())
+}
+
+/// Prints the classes and assets added, removed, or changed from one game JAR to the next.
+pub fn diff_jar(args: DiffJarArgs) -> Result<(), ArchiveError> {
+    let diff = diff_jars(&args.old, &args.new)?;
+    let describe = |entry: &JarEntry| match entry.name.strip_suffix(".class") {
+        Some(class) => format!("class {}", class.replace('/', ".")),
+        None => format!("asset {}", entry.name),
+    };
+    let is_shown = |entry: &JarEntry| !args.classes_only || entry.is_class();
+
+    for entry in diff.added.iter().filter(|it| is_shown(it)) {
+        println!("+ {} ({} bytes)", describe(entry), entry.size);
+    }
+    for entry in diff.removed.iter().filter(|it| is_shown(it)) {
+        println!("- {} ({} bytes)", describe(entry), entry.size);
+    }
+    for (old, new) in diff.changed.iter().filter(|(_, it)| is_shown(it)) {
+        println!("~ {} ({} -> {} bytes)", describe(new), old.size, new.size);
+    }
+
+    let changed = diff.changed.iter().map(|(_, it)| it).collect::<Vec<_>>();
+    info!(
+        "{} added, {} removed, and {} changed files, of which {}, {}, and {} are classes",
+        diff.added.len(),
+        diff.removed.len(),
+        changed.len(),
+        diff.added.iter().filter(|it| it.is_class()).count(),
+        diff.removed.iter().filter(|it| it.is_class()).count(),
+        changed.iter().filter(|it| it.is_class()).count(),
+    );
+    Ok(())
+}
@@ -0,0 +1,315 @@
+//! Persistent settings from a `cosmic-archive.toml` config file.
+//!
+//! Every setting is resolved in the following order of precedence, from highest to lowest:
+//!
+//! 1. command line flags,
+//! 2. environmental variables,
+//! 3. the config file,
+//! 4. built-in defaults.
+
+use crate::classify::TypeRule;
+use crate::download::{Mirror, SecondaryTarget};
+use crate::email::SmtpTls;
+use crate::pattern::Pattern;
+use crate::{ArchiveError, Artifact};
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
+
+/// Path of the config file used when none is given, relative to the current working directory.
+pub const DEFAULT_CONFIG_PATH: &str = "cosmic-archive.toml";
+
+/// Settings read from a config file, where absent ones fall back to their defaults.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Directory to cache responses and downloaded archives in between runs.
+    pub cache_dir: Option<PathBuf>,
+    /// Seconds a cached response is used without revalidating it.
+    pub cache_ttl: Option<u64>,
+    /// Directory to spill downloads and archive entries into instead of memory.
+    pub temp_dir: Option<PathBuf>,
+    /// URL to the archived versions data.
+    pub manifest_url: Option<String>,
+    /// Proxy of every request.
+    pub proxy: Option<String>,
+    /// User-Agent of every request.
+    pub user_agent: Option<String>,
+    /// Extra headers of every request, by name.
+    pub headers: BTreeMap<String, String>,
+    /// URL to the itch.io game page.
+    pub game_url: Option<String>,
+    /// Title of the download option containing the game JAR.
+    pub download_title: Option<Pattern>,
+    /// Title of the download option containing the dedicated server JAR.
+    pub server_download_title: Option<Pattern>,
+    /// Name of the game JAR within the archive.
+    pub jar_name: Option<Pattern>,
+    /// Name of the dedicated server JAR within the archive.
+    pub server_jar_name: Option<Pattern>,
+    /// Name of the game JAR to prefer when the archive contains several.
+    pub preferred_jar: Option<Pattern>,
+    /// Kinds of game JAR to fetch.
+    pub artifacts: Option<Vec<Artifact>>,
+    /// Maximum number of kinds of game JAR fetched at once.
+    pub concurrency: Option<NonZeroUsize>,
+    /// Number of ranged requests to download the archive from itch.io over at once.
+    pub connections: Option<NonZeroUsize>,
+    /// Directory to extract the game JAR into.
+    pub output_dir: Option<PathBuf>,
+    /// Template of the path of the extracted game JAR relative to the output directory.
+    pub output_template: Option<String>,
+    /// Staging directory to hold NOT yet archived game JARs in until promoted.
+    pub stage: Option<PathBuf>,
+    /// Local git repository to check for an already existing branch before downloading.
+    pub git_repo: Option<PathBuf>,
+    /// Remote whose remote-tracking branches are also checked.
+    pub git_remote: Option<String>,
+    /// Template of the branch name a version is archived into.
+    pub branch_template: Option<String>,
+    /// Template of the message of archiving commits.
+    pub commit_message: Option<String>,
+    /// Name of the author of archiving commits.
+    pub git_author_name: Option<String>,
+    /// Email of the author of archiving commits.
+    pub git_author_email: Option<String>,
+    /// Discord webhook to notify of NOT yet archived versions.
+    pub discord_webhook: Option<String>,
+    /// Template of the JSON payload posted to the Discord webhook.
+    pub discord_payload: Option<String>,
+    /// Policy of retrying failed network requests.
+    pub retry: RetryConfig,
+    /// Timeouts of network requests.
+    pub timeouts: TimeoutsConfig,
+    /// Sources of the zip archive tried in order when downloading from itch.io fails.
+    pub mirrors: Vec<Mirror>,
+    /// Rules classifying the type of newly archived versions, the first matching one applying.
+    pub version_types: Vec<TypeRule>,
+    /// Download options besides the game JARs also archived as is, e.g. the soundtrack.
+    pub secondary: Vec<SecondaryTarget>,
+    /// Settings of uploading to archive.org.
+    pub archive_org: ArchiveOrgConfig,
+    /// Settings of signing archived files.
+    pub signing: SigningConfig,
+    /// Settings of publishing GitHub Releases.
+    pub github: GithubConfig,
+    /// Settings of mirroring archived files to S3-compatible object storage.
+    pub s3: S3Config,
+    /// Settings of creating torrents of archived game JARs.
+    pub torrent: TorrentConfig,
+    /// Settings of pinning archived game JARs to IPFS.
+    pub ipfs: IpfsConfig,
+    /// Settings of authenticating to itch.io.
+    pub itch: ItchConfig,
+    /// Settings of email notifications.
+    pub email: EmailConfig,
+    /// Settings of Matrix room notifications.
+    pub matrix: MatrixConfig,
+    /// Settings of posting Mastodon statuses.
+    pub mastodon: MastodonConfig,
+}
+
+/// Settings of the `[retry]` table of a config file.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RetryConfig {
+    /// Maximum attempts of every network request.
+    pub attempts: Option<u32>,
+    /// Delay in milliseconds before the first retry.
+    pub delay: Option<u64>,
+    /// Upper bound in milliseconds of the random delay added to every retry.
+    pub jitter: Option<u64>,
+}
+
+/// Settings of the `[timeouts]` table of a config file, each in seconds where 0 disables it.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TimeoutsConfig {
+    /// Maximum duration of connecting to a server.
+    pub connect: Option<u64>,
+    /// Maximum duration of waiting for the next read from a server.
+    pub read: Option<u64>,
+    /// Maximum duration of every request scraping itch.io.
+    pub scrape: Option<u64>,
+    /// Maximum duration of fetching the archived versions data.
+    pub manifest: Option<u64>,
+    /// Maximum duration of every attempt of downloading a file.
+    pub download: Option<u64>,
+}
+
+/// Settings of the `[archive-org]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ArchiveOrgConfig {
+    /// Template of the identifier of the item every version is uploaded to.
+    pub identifier: Option<String>,
+    /// Collection every item is created in.
+    pub collection: Option<String>,
+    /// Access key of the S3-compatible API.
+    pub access_key: Option<String>,
+    /// Secret key of the S3-compatible API.
+    pub secret_key: Option<String>,
+    /// Templates of the metadata every item is created with, overriding the default ones.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Settings of the `[signing]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SigningConfig {
+    /// Minisign secret key file to sign with.
+    pub minisign_key: Option<PathBuf>,
+    /// Id of the GPG key to sign with.
+    pub gpg_key: Option<String>,
+}
+
+/// Settings of the `[github]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GithubConfig {
+    /// Repository to publish releases to, e.g. `CRModders/CosmicArchive`.
+    pub repository: Option<String>,
+    /// Token to authenticate pushing and publishing releases with.
+    pub token: Option<String>,
+    /// Branch that pull requests are opened against.
+    pub pr_base: Option<String>,
+    /// Template of the title of pull requests.
+    pub pr_title: Option<String>,
+    /// Template of the body of pull requests.
+    pub pr_body: Option<String>,
+}
+
+/// Settings of the `[s3]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct S3Config {
+    /// Endpoint of the S3-compatible API.
+    pub endpoint: Option<url::Url>,
+    /// Bucket to upload to.
+    pub bucket: Option<String>,
+    /// Prefix of the key of every uploaded object.
+    pub prefix: Option<String>,
+    /// Region the bucket is in.
+    pub region: Option<String>,
+    /// Access key id to sign requests with.
+    pub access_key: Option<String>,
+    /// Secret access key to sign requests with.
+    pub secret_key: Option<String>,
+    /// Public URL the bucket is served from, if other than the endpoint.
+    pub public_url: Option<url::Url>,
+}
+
+/// Settings of the `[torrent]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TorrentConfig {
+    /// Announce URLs of the trackers of every torrent, in order of preference.
+    pub trackers: Vec<url::Url>,
+    /// Web seeds of every torrent besides the URL of its version.
+    pub web_seeds: Vec<url::Url>,
+}
+
+/// Settings of the `[ipfs]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct IpfsConfig {
+    /// RPC API of the Kubo node to add to.
+    pub api: Option<url::Url>,
+    /// Token to authenticate requests to the RPC API with.
+    pub token: Option<String>,
+    /// Endpoint of the remote pinning service to also pin to.
+    pub pinning_service: Option<url::Url>,
+    /// Token to authenticate requests to the remote pinning service with.
+    pub pinning_token: Option<String>,
+}
+
+/// Settings of the `[itch]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ItchConfig {
+    /// API key of an itch.io account to request the download info with.
+    pub api_key: Option<String>,
+    /// Id of the download key of the game owned by the account.
+    pub download_key: Option<u64>,
+    /// Encrypted session written by `login`.
+    pub session: Option<PathBuf>,
+    /// Hosts other than itch.io that uploads hosted elsewhere are allowed to be downloaded from.
+    pub external_hosts: Vec<String>,
+    /// Average requests per minute sent to itch.io.
+    pub rate_limit: Option<NonZeroU32>,
+    /// Requests sent to itch.io at once before being rate limited.
+    pub rate_burst: Option<NonZeroU32>,
+}
+
+/// Settings of the `[email]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EmailConfig {
+    /// Host of the SMTP server to send email notifications with, enabling them.
+    pub host: Option<String>,
+    /// Port of the SMTP server, if other than the default one of `tls`.
+    pub port: Option<u16>,
+    /// How the connection to the SMTP server is secured.
+    pub tls: Option<SmtpTls>,
+    /// Username to authenticate to the SMTP server with.
+    pub username: Option<String>,
+    /// Password to authenticate to the SMTP server with.
+    pub password: Option<String>,
+    /// Sender of every email, e.g. `CosmicArchive <archive@example.com>`.
+    pub from: Option<String>,
+    /// Recipients of every email.
+    pub to: Vec<String>,
+    /// Template of the subject of notification emails.
+    pub subject: Option<String>,
+    /// Template of the body of notification emails.
+    pub body: Option<String>,
+    /// Consecutive failed polls or fetches after which watching sends a failure email, 3 by
+    /// default.
+    pub failure_threshold: Option<NonZeroU32>,
+}
+
+/// Settings of the `[matrix]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MatrixConfig {
+    /// Base URL of the homeserver, e.g. `https://matrix.org`.
+    pub homeserver: Option<url::Url>,
+    /// Access token of the account posting.
+    pub access_token: Option<String>,
+    /// Id of the room to post to.
+    pub room_id: Option<String>,
+    /// Template of the message posted.
+    pub message: Option<String>,
+}
+
+/// Settings of the `[mastodon]` table of a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MastodonConfig {
+    /// Base URL of the instance, e.g. `https://mastodon.social`.
+    pub instance: Option<url::Url>,
+    /// Access token of the account posting.
+    pub access_token: Option<String>,
+    /// Visibility of every status.
+    pub visibility: Option<String>,
+    /// Template of every status.
+    pub status: Option<String>,
+}
+
+impl Config {
+    /// Reads the config file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let text = fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Reads the config file at `path` if given, otherwise at [`DEFAULT_CONFIG_PATH`] if present.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, ArchiveError> {
+        match path {
+            Some(path) => Self::load(path),
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => Self::load(DEFAULT_CONFIG_PATH),
+            None => Ok(Self::default()),
+        }
+    }
+}
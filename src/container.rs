@@ -0,0 +1,170 @@
+//! Detection of the container format of a download, so that uploads other than zip archives are
+//! read just the same, by repacking them as one.
+//!
+//! Besides zip archives, uploads may be gzip or xz compressed tarballs, or else the game JAR
+//! itself, offered directly.
+
+use crate::jar::detect_version_id_in;
+use crate::{temp, ArchiveError};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use tracing::{info, warn};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Magic bytes starting a zip archive, or an empty one.
+const ZIP_MAGIC: [&[u8]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Magic bytes starting a gzip stream.
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+
+/// Magic bytes starting an xz stream.
+const XZ_MAGIC: &[u8] = b"\xFD7zXZ\x00";
+
+/// Container format of a download.
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArchiveFormat {
+    /// A zip archive, read as is.
+    Zip,
+    /// A gzip compressed tarball.
+    TarGz,
+    /// An xz compressed tarball.
+    TarXz,
+    /// The game JAR itself, which is a zip archive too but with a manifest and classes of its own.
+    Jar,
+}
+
+impl ArchiveFormat {
+    /// Returns the lowercase name of the format, e.g. `tar.gz`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+            Self::TarXz => "tar.xz",
+            Self::Jar => "jar",
+        }
+    }
+
+    /// Detects the format of the downloaded `file` from its magic bytes, and tells the game JAR
+    /// apart from a zip archive by whether it holds a JAR manifest at its top level along with
+    /// classes of its own, regardless of the library JARs bundled within.
+    ///
+    /// Fails with [`ArchiveError::UnknownArchiveFormat`] if it is none of them.
+    pub fn detect(file: &mut File) -> Result<Self, ArchiveError> {
+        file.rewind()?;
+        let mut header = Vec::with_capacity(XZ_MAGIC.len());
+        file.by_ref()
+            .take(XZ_MAGIC.len() as u64)
+            .read_to_end(&mut header)?;
+
+        let format = if ZIP_MAGIC.iter().any(|it| header.starts_with(it)) {
+            file.rewind()?;
+            let archive = ZipArchive::new(io::BufReader::new(&mut *file))?;
+            let has_manifest = archive
+                .file_names()
+                .any(|it| it.eq_ignore_ascii_case("META-INF/MANIFEST.MF"));
+            let has_classes = archive
+                .file_names()
+                .any(|it| it.ends_with(".class") && !it.starts_with("META-INF/"));
+            if has_manifest && has_classes {
+                Self::Jar
+            } else {
+                Self::Zip
+            }
+        } else if header.starts_with(GZIP_MAGIC) {
+            Self::TarGz
+        } else if header.starts_with(XZ_MAGIC) {
+            Self::TarXz
+        } else {
+            return Err(ArchiveError::UnknownArchiveFormat(hex::encode(&header)));
+        };
+        file.rewind()?;
+        Ok(format)
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Repacks the downloaded `file` of `format` as a zip archive within an anonymous temporary file
+/// within `temp_dir`, see [`temp::tempfile`], or returns it as is if already one. Returns it
+/// rewound to its start.
+///
+/// Every regular file and directory of a tarball is stored under the same path, while symbolic
+/// links and other special files are skipped. The game JAR itself is stored as the only entry,
+/// named `Cosmic Reach-<VERSION>.jar` after its version id as the upstream ones are, or else
+/// `Cosmic Reach.jar` if NOT detected.
+pub fn repack_as_zip(
+    mut file: File,
+    format: ArchiveFormat,
+    temp_dir: &Path,
+) -> Result<File, ArchiveError> {
+    file.rewind()?;
+    if format == ArchiveFormat::Zip {
+        return Ok(file);
+    }
+
+    info!("Repacking {format} download as zip archive...");
+    let mut repacked = temp::tempfile(temp_dir)?;
+    let writer = io::BufWriter::new(&mut repacked);
+    match format {
+        ArchiveFormat::Zip => unreachable!("zip archives should be returned as is"),
+        ArchiveFormat::TarGz => repack_tar(
+            flate2::read::GzDecoder::new(io::BufReader::new(file)),
+            writer,
+        )?,
+        ArchiveFormat::TarXz => {
+            repack_tar(xz2::read::XzDecoder::new(io::BufReader::new(file)), writer)?
+        }
+        ArchiveFormat::Jar => repack_jar(file, writer)?,
+    }
+    repacked.rewind()?;
+    Ok(repacked)
+}
+
+fn repack_tar<R: Read, W: Write + Seek>(reader: R, writer: W) -> Result<(), ArchiveError> {
+    let mut tarball = tar::Archive::new(reader);
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for entry in tarball.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().replace('\\', "/");
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            zip.add_directory(name.as_str(), options)?;
+        } else if kind.is_file() {
+            info!("    {name}");
+            let options = options.large_file(entry.size() >= u64::from(u32::MAX));
+            zip.start_file(name.as_str(), options)?;
+            io::copy(&mut entry, &mut zip)?;
+        } else {
+            warn!("Skipping '{name}' of tarball, which is NOT a regular file");
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+fn repack_jar<W: Write + Seek>(mut jar: File, writer: W) -> Result<(), ArchiveError> {
+    let name = match detect_version_id_in(io::BufReader::new(&mut jar))? {
+        Some(id) => format!("Cosmic Reach-{id}.jar"),
+        None => "Cosmic Reach.jar".to_owned(),
+    };
+    jar.rewind()?;
+    let size = jar.metadata()?.len();
+
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(size >= u64::from(u32::MAX));
+    zip.start_file(name.as_str(), options)?;
+    io::copy(&mut jar, &mut zip)?;
+    zip.finish()?.flush()?;
+    Ok(())
+}
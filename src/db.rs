@@ -0,0 +1,158 @@
+//! SQLite index of the archived versions data, for fast local queries without parsing it whole.
+//!
+//! Every sync replaces the indexed data as a whole within a single transaction, so the database
+//! always mirrors one archived versions data exactly.
+
+use crate::{ArchiveError, Versions};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use tracing::info;
+
+/// Version of the tables of the database, recorded as its `user_version`.
+pub const DB_SCHEMA_VERSION: u32 = 1;
+
+/// Statements creating the tables and indices of the database, if missing.
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS versions (
+    id TEXT NOT NULL,
+    artifact TEXT NOT NULL,
+    type TEXT NOT NULL,
+    release_time INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    zip_size INTEGER,
+    compression TEXT,
+    sha1 TEXT,
+    sha512 TEXT,
+    md5 TEXT,
+    blake3 TEXT,
+    ipfs_cid TEXT,
+    devlog TEXT,
+    java_version INTEGER,
+    save_version TEXT,
+    protocol_version TEXT,
+    build_number TEXT,
+    main_class TEXT,
+    slim_url TEXT,
+    slim_sha256 TEXT,
+    entry TEXT NOT NULL,
+    PRIMARY KEY (id, artifact)
+);
+CREATE INDEX IF NOT EXISTS versions_by_sha256 ON versions (sha256);
+CREATE INDEX IF NOT EXISTS versions_by_release_time ON versions (release_time);
+CREATE INDEX IF NOT EXISTS versions_by_type ON versions (type, release_time);
+CREATE TABLE IF NOT EXISTS latest (
+    type TEXT PRIMARY KEY NOT NULL,
+    id TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    target TEXT NOT NULL,
+    title TEXT NOT NULL,
+    release_time INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (target, sha256)
+);
+CREATE INDEX IF NOT EXISTS files_by_release_time ON files (release_time);
+";
+
+/// How many rows of each table a sync wrote.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SyncStats {
+    /// Rows of the `versions` table.
+    pub versions: usize,
+    /// Rows of the `latest` table.
+    pub latest: usize,
+    /// Rows of the `files` table.
+    pub files: usize,
+}
+
+/// A SQLite index of the archived versions data.
+#[derive(Debug)]
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it and its tables if missing.
+    pub fn open(path: &Path) -> Result<Self, ArchiveError> {
+        info!("Opening database '{}'...", path.display());
+        let connection = Connection::open(path)?;
+        connection.execute_batch(CREATE_TABLES)?;
+        connection.pragma_update(None, "user_version", DB_SCHEMA_VERSION)?;
+        Ok(Self { connection })
+    }
+
+    /// Replaces everything indexed by `versions`.
+    ///
+    /// Besides its columns, each version keeps its whole entry as JSON in `entry`, so fields
+    /// without a column of their own can still be queried with the JSON functions of SQLite.
+    pub fn sync(&mut self, versions: &Versions) -> Result<SyncStats, ArchiveError> {
+        let transaction = self.connection.transaction()?;
+        transaction
+            .execute_batch("DELETE FROM versions; DELETE FROM latest; DELETE FROM files;")?;
+
+        let mut stats = SyncStats::default();
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO versions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12,
+                 ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            )?;
+            for version in &versions.versions {
+                let build = version.build.as_ref();
+                let compatibility = version.compatibility.as_ref();
+                let slim = version.slim.as_ref();
+                insert.execute(params![
+                    version.id,
+                    version.artifact().name(),
+                    version.kind,
+                    version.release_time,
+                    version.url.as_str(),
+                    version.sha256.to_string(),
+                    version.size,
+                    version.zip_size,
+                    version.compression,
+                    version.sha1,
+                    version.sha512,
+                    version.md5,
+                    version.blake3,
+                    version.ipfs_cid,
+                    version.devlog.as_ref().map(url::Url::as_str),
+                    version.java_version,
+                    compatibility.and_then(|it| it.save_version.as_deref()),
+                    compatibility.and_then(|it| it.protocol_version.as_deref()),
+                    build.and_then(|it| it.build_number.as_deref()),
+                    build.and_then(|it| it.main_class.as_deref()),
+                    slim.map(|it| it.url.as_str()),
+                    slim.map(|it| it.sha256.to_string()),
+                    serde_json::to_string(version)?,
+                ])?;
+                stats.versions += 1;
+            }
+
+            let mut insert = transaction.prepare("INSERT INTO latest VALUES (?1, ?2)")?;
+            for (kind, id) in &versions.latest {
+                insert.execute(params![kind, id])?;
+                stats.latest += 1;
+            }
+
+            let mut insert = transaction
+                .prepare("INSERT OR REPLACE INTO files VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+            for file in &versions.files {
+                insert.execute(params![
+                    file.target,
+                    file.title,
+                    file.release_time,
+                    file.url.as_str(),
+                    file.sha256.to_string(),
+                    file.size,
+                ])?;
+                stats.files += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok(stats)
+    }
+}
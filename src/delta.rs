@@ -0,0 +1,180 @@
+//! Binary patches between consecutive versions of a game JAR, as found in a `patches.json`.
+
+use crate::atomic::write_atomically;
+use crate::hash::hash_reader;
+use crate::{ArchiveError, Sha256Hash};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::{fmt, str};
+
+/// Compression level of every patch, between zstd's default and maximum.
+const ZSTD_LEVEL: i32 = 19;
+
+/// Creates the patch of `format` turning `old` into `new`.
+pub fn create_patch(old: &[u8], new: &[u8], format: PatchFormat) -> Result<Vec<u8>, ArchiveError> {
+    match format {
+        PatchFormat::Bsdiff => {
+            let mut diff = Vec::new();
+            bsdiff::diff(old, new, &mut diff)?;
+            Ok(zstd::stream::encode_all(diff.as_slice(), ZSTD_LEVEL)?)
+        }
+        PatchFormat::Zstd => {
+            let mut encoder =
+                zstd::stream::write::Encoder::with_dictionary(Vec::new(), ZSTD_LEVEL, old)?;
+            encoder.write_all(new)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// Applies `patch` of `format` onto `old`, returning what it turns into.
+pub fn apply_patch(old: &[u8], patch: &[u8], format: PatchFormat) -> Result<Vec<u8>, ArchiveError> {
+    let mut new = Vec::new();
+    match format {
+        PatchFormat::Bsdiff => {
+            let diff = zstd::stream::decode_all(patch)?;
+            bsdiff::patch(old, &mut diff.as_slice(), &mut new)?;
+        }
+        PatchFormat::Zstd => {
+            zstd::stream::read::Decoder::with_dictionary(io::BufReader::new(patch), old)?
+                .read_to_end(&mut new)?;
+        }
+    }
+    Ok(new)
+}
+
+/// Format of a patch.
+#[derive(
+    Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchFormat {
+    /// A bsdiff patch, compressed with zstd.
+    Bsdiff,
+    /// The new version compressed with zstd, using the old version as its dictionary.
+    Zstd,
+}
+
+impl PatchFormat {
+    /// Every supported format.
+    pub const ALL: [Self; 2] = [Self::Bsdiff, Self::Zstd];
+
+    /// Returns the lowercase name of the format, e.g. `bsdiff`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bsdiff => "bsdiff",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+impl fmt::Display for PatchFormat {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for PatchFormat {
+    type Err = UnknownPatchFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownPatchFormat(s.to_owned()))
+    }
+}
+
+/// Error of parsing a [`PatchFormat`] from an unsupported name.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("unknown patch format '{0}', expected either bsdiff or zstd")]
+pub struct UnknownPatchFormat(pub String);
+
+/// The patches data, a sidecar of the archived versions data.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Patches {
+    /// Every patch, in order.
+    pub patches: Vec<Patch>,
+}
+
+/// A single patch from one version to another.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Patch {
+    /// Version id the patch is applied onto.
+    pub from: String,
+    /// Version id the patch turns into.
+    pub to: String,
+    /// Format of the patch.
+    pub format: PatchFormat,
+    /// Where the patch can be downloaded from, relative to the patches data.
+    pub path: String,
+    /// The sha256 hash of the patch.
+    pub sha256: Sha256Hash,
+    /// Size in bytes of the patch.
+    pub size: u64,
+    /// The sha256 hash of the game JAR the patch turns into.
+    #[serde(rename = "targetSha256")]
+    pub target_sha256: Sha256Hash,
+}
+
+impl Patches {
+    /// Reads the patches data from the local file at `path`.
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    /// Reads the patches data from the local file at `path` if present, otherwise starts empty.
+    pub fn read_or_default<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        match Self::read_from(&path) {
+            Err(ArchiveError::Io(cause)) if cause.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            result => result,
+        }
+    }
+
+    /// Writes the patches data as pretty JSON to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        write_atomically(path.as_ref(), |file| {
+            let mut file = io::BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut file, self)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+            Ok(())
+        })
+    }
+
+    /// Appends `patch`, replacing any patch between the same versions of the same format.
+    pub fn add(&mut self, patch: Patch) {
+        self.patches
+            .retain(|it| (&it.from, &it.to, it.format) != (&patch.from, &patch.to, patch.format));
+        self.patches.push(patch);
+    }
+}
+
+impl Patch {
+    /// Creates the entry of `patch` of `format` from version `from` to `to`, the game JAR `new`.
+    pub fn new(
+        from: String,
+        to: String,
+        format: PatchFormat,
+        path: String,
+        patch: &[u8],
+        new: &[u8],
+    ) -> Result<Self, ArchiveError> {
+        let (sha256, size) = hash_reader(patch)?;
+        let (target_sha256, _) = hash_reader(new)?;
+        Ok(Self {
+            from,
+            to,
+            format,
+            path,
+            sha256,
+            size,
+            target_sha256,
+        })
+    }
+}
@@ -0,0 +1,269 @@
+//! Devlog posts of the itch.io game page, archived as the changelogs of their versions.
+
+use crate::atomic::write_atomically;
+use crate::cache::{get_cached, ResponseCache};
+use crate::{ArchiveError, RetryPolicy};
+use regex::Regex;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::info;
+
+/// A devlog post of the game page.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Devlog {
+    /// Title of the post.
+    pub title: String,
+    /// Where the post is on itch.io.
+    pub url: url::Url,
+    /// Body of the post, converted to Markdown.
+    pub body: String,
+}
+
+impl Devlog {
+    /// Returns the post as a Markdown document headed by its title and where it was archived
+    /// from.
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "# {}\n\nArchived from <{}>\n\n{}\n",
+            self.title,
+            self.url,
+            self.body.trim_end()
+        )
+    }
+
+    /// Writes the post as a Markdown document to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        let markdown = self.to_markdown();
+        write_atomically(path.as_ref(), |file| {
+            Ok(file.write_all(markdown.as_bytes())?)
+        })
+    }
+}
+
+/// Returns the path of the archived devlog post of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.devlog.md`.
+pub fn devlog_path(jar: &Path) -> PathBuf {
+    jar.with_extension("devlog.md")
+}
+
+/// Finds the devlog post of version `id` on the itch.io game page at `game_url`, i.e. the newest
+/// one mentioning `id` in its title, and fetches it.
+pub async fn get_devlog(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    game_url: &str,
+    id: &str,
+    retry: &RetryPolicy,
+) -> Result<Option<Devlog>, ArchiveError> {
+    let devlog_url = format!("{}/devlog", game_url.trim_end_matches('/'));
+    info!("Sending GET request to devlog ({devlog_url})...");
+    let page = get_cached(client, cache, &devlog_url, "get devlog", retry).await?;
+    let page = String::from_utf8_lossy(&page);
+
+    static POST_LINK: OnceLock<Regex> = OnceLock::new();
+    let post_link = POST_LINK.get_or_init(|| {
+        Regex::new(r#"<a[^>]*href="([^"]*/devlog/\d+[^"]*)"[^>]*>([^<]+)</a>"#)
+            .expect("post link regex should be valid")
+    });
+    let post = post_link.captures_iter(&page).find_map(|it| {
+        let title = decode_entities(it[2].trim());
+        mentions_version(&title, id).then(|| (it[1].to_owned(), title))
+    });
+    let Some((url, title)) = post else {
+        info!("Devlog has NO post mentioning {id}");
+        return Ok(None);
+    };
+
+    let url = url::Url::parse(&devlog_url)?.join(&url)?;
+    info!("Sending GET request to devlog post ({url})...");
+    let page = get_cached(client, cache, url.as_str(), "get devlog post", retry).await?;
+    let page = String::from_utf8_lossy(&page);
+
+    let Some(body) = post_body(&page) else {
+        info!("Devlog post '{title}' has NO body");
+        return Ok(None);
+    };
+    Ok(Some(Devlog {
+        title,
+        url,
+        body: html_to_markdown(body),
+    }))
+}
+
+/// Checks whether `title` mentions the version `id` by itself, e.g. `0.1.4` but NOT `0.1.44`.
+fn mentions_version(title: &str, id: &str) -> bool {
+    title.match_indices(id).any(|(start, _)| {
+        let before = title[..start].chars().next_back();
+        let mut after = title[start + id.len()..].chars();
+        let is_continued = match after.next() {
+            Some('.') => after.next().is_some_and(|it| it.is_ascii_digit()),
+            Some(it) => it.is_ascii_alphanumeric(),
+            None => false,
+        };
+        !before.is_some_and(|it| it.is_ascii_digit() || it == '.') && !is_continued
+    })
+}
+
+/// Finds the HTML of the body within the HTML of a devlog post.
+fn post_body(page: &str) -> Option<&str> {
+    let body = &page[page.find("post_body")?..];
+    let body = &body[body.find('>')? + 1..];
+    Some(&body[..body.find("</section>")?])
+}
+
+/// Converts the HTML of a devlog post to Markdown, keeping its paragraphs, headings, emphasis,
+/// links, images, lists, and code while dropping any other markup.
+pub fn html_to_markdown(html: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| {
+        Regex::new(r"<(/?)([a-zA-Z0-9]+)([^>]*)>").expect("tag regex should be valid")
+    });
+
+    let mut markdown = String::new();
+    let mut links = Vec::new();
+    let mut lists = Vec::<Option<u32>>::new();
+    let mut in_pre = false;
+    let mut last = 0;
+    for captures in tag.captures_iter(html) {
+        let whole = captures.get(0).expect("whole match should be present");
+        push_text(&mut markdown, &html[last..whole.start()], in_pre);
+        last = whole.end();
+
+        let is_closing = &captures[1] == "/";
+        let attributes = &captures[3];
+        match (captures[2].to_ascii_lowercase().as_str(), is_closing) {
+            ("p" | "div", _) => markdown.push_str("\n\n"),
+            ("br", _) => markdown.push_str("\\\n"),
+            (heading @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6"), false) => {
+                let level = heading[1..].parse::<usize>().unwrap_or(1);
+                markdown.push_str(&format!("\n\n{} ", "#".repeat(level + 1)));
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => markdown.push_str("\n\n"),
+            ("strong" | "b", _) => markdown.push_str("**"),
+            ("em" | "i", _) => markdown.push('*'),
+            ("code", _) if !in_pre => markdown.push('`'),
+            ("pre", false) => {
+                in_pre = true;
+                markdown.push_str("\n\n```\n");
+            }
+            ("pre", true) => {
+                in_pre = false;
+                markdown.push_str("\n```\n\n");
+            }
+            ("blockquote", false) => markdown.push_str("\n\n> "),
+            ("blockquote", true) => markdown.push_str("\n\n"),
+            ("ul", false) => lists.push(None),
+            ("ol", false) => lists.push(Some(0)),
+            ("ul" | "ol", true) => {
+                lists.pop();
+                markdown.push('\n');
+            }
+            ("li", false) => {
+                let indent = "  ".repeat(lists.len().saturating_sub(1));
+                let marker = match lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{number}.")
+                    }
+                    _ => "-".to_owned(),
+                };
+                markdown.push_str(&format!("\n{indent}{marker} "));
+            }
+            ("a", false) => {
+                links.push(attribute(attributes, "href"));
+                markdown.push('[');
+            }
+            ("a", true) => match links.pop().flatten() {
+                Some(href) => markdown.push_str(&format!("]({href})")),
+                None => markdown.push(']'),
+            },
+            ("img", _) => {
+                if let Some(src) = attribute(attributes, "src") {
+                    let alt = attribute(attributes, "alt").unwrap_or_default();
+                    markdown.push_str(&format!("![{alt}]({src})"));
+                }
+            }
+            _ => {}
+        }
+    }
+    push_text(&mut markdown, &html[last..], in_pre);
+
+    collapse_blank_lines(&markdown)
+}
+
+/// Pushes the decoded `text` between tags, collapsing its whitespace unless `in_pre`.
+fn push_text(markdown: &mut String, text: &str, in_pre: bool) {
+    let text = decode_entities(text);
+    if in_pre {
+        markdown.push_str(&text);
+        return;
+    }
+    let mut words = text.split_whitespace().peekable();
+    let is_separated = markdown.is_empty() || markdown.ends_with(char::is_whitespace);
+    if text.starts_with(char::is_whitespace) && words.peek().is_some() && !is_separated {
+        markdown.push(' ');
+    }
+    let collapsed = words.collect::<Vec<_>>().join(" ");
+    markdown.push_str(&collapsed);
+    if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+        markdown.push(' ');
+    }
+}
+
+/// Returns the decoded value of the attribute `name` within the `attributes` of a tag.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let value = &attributes[attributes.find(&format!("{name}=\""))? + name.len() + 2..];
+    Some(decode_entities(&value[..value.find('"')?]))
+}
+
+/// Decodes the named entities common in HTML text and every numeric one.
+fn decode_entities(text: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let entity = ENTITY.get_or_init(|| {
+        Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").expect("entity regex should be valid")
+    });
+
+    entity
+        .replace_all(text, |captures: &regex::Captures| {
+            let name = &captures[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => name
+                        .strip_prefix('#')
+                        .and_then(|it| it.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            decoded.map_or_else(|| captures[0].to_owned(), String::from)
+        })
+        .into_owned()
+}
+
+/// Trims every line and collapses runs of blank lines into one, outside of code blocks.
+fn collapse_blank_lines(markdown: &str) -> String {
+    let mut collapsed = String::new();
+    let mut in_code = false;
+    let mut was_blank = true;
+    for line in markdown.lines() {
+        if line.trim() == "```" {
+            in_code = !in_code;
+        }
+        let line = if in_code { line } else { line.trim_end() };
+        let is_blank = line.trim().is_empty();
+        if is_blank && was_blank && !in_code {
+            continue;
+        }
+        collapsed.push_str(line);
+        collapsed.push('\n');
+        was_blank = is_blank;
+    }
+    collapsed.trim_end().to_owned()
+}
@@ -0,0 +1,1502 @@
+//! Fetching of the game JAR from its itch.io page, or from mirrors of its archive.
+
+use crate::atomic::write_atomically;
+use crate::auth::{get_anonymous_csrf_token, get_api_download_url, AuthMethod, ItchAuth};
+use crate::container::{repack_as_zip, ArchiveFormat};
+use crate::hash::{hash_reader, Digests, HashAlgorithm, HashingWriter};
+use crate::http::{is_on_hosts, ITCH_HOSTS};
+use crate::manifest::version_id_from_file_name;
+use crate::metrics::metrics;
+use crate::pattern::Pattern;
+use crate::{progress, release, temp, ArchiveError, RetryPolicy, Sha256Hash};
+use futures_util::StreamExt;
+use hex::FromHexError;
+use indicatif::ProgressBar;
+use itertools::Itertools;
+use reqwest::header::{
+    HeaderName, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use reqwest::StatusCode;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::str;
+use std::time::Instant;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Finds the title and id of the single download option of `game_url` with a title matching
+/// `download_title`.
+pub async fn get_jar_download_id(
+    client: &itch_io::Client,
+    game_url: &str,
+    download_title: &Pattern,
+    retry: &RetryPolicy,
+) -> Result<(String, u64), ArchiveError> {
+    let game_page = retry
+        .retry("get game page data", || async move {
+            warn!("Getting game page data of {game_url}...");
+            Ok::<_, ArchiveError>(client.get_game_page(game_url).await?)
+        })
+        .await?;
+
+    info!("Following are available downloads:");
+    for download in &game_page.downloads {
+        info!("        {}", download.title);
+    }
+
+    let jar_download = match game_page
+        .downloads
+        .into_iter()
+        .filter(|download| download_title.matches(&download.title))
+        .at_most_one()
+    {
+        Ok(None) => {
+            return Err(ArchiveError::DownloadNotFound {
+                title: download_title.to_string(),
+            })
+        }
+        Ok(Some(it)) => it,
+        Err(downloads) => {
+            return Err(ArchiveError::MultipleDownloads {
+                title: download_title.to_string(),
+                titles: downloads.map(|it| it.title).collect(),
+            })
+        }
+    };
+
+    match jar_download.id {
+        Some(id) => Ok((jar_download.title, id)),
+        None => Err(ArchiveError::MissingDownloadId {
+            title: jar_download.title,
+        }),
+    }
+}
+
+/// Lists the title and id of every download option of `game_url`, skipping the ones without an
+/// id.
+pub async fn get_download_ids(
+    client: &itch_io::Client,
+    game_url: &str,
+    retry: &RetryPolicy,
+) -> Result<Vec<(String, u64)>, ArchiveError> {
+    let game_page = retry
+        .retry("get game page data", || async move {
+            warn!("Getting game page data of {game_url}...");
+            Ok::<_, ArchiveError>(client.get_game_page(game_url).await?)
+        })
+        .await?;
+
+    Ok(game_page
+        .downloads
+        .into_iter()
+        .filter_map(|download| match download.id {
+            Some(id) => Some((download.title, id)),
+            None => {
+                warn!(
+                    "Skipping download option '{}', which has NO id",
+                    download.title
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+/// Zip archive of a download, backed by its downloaded file.
+pub type Archive = zip::ZipArchive<io::BufReader<File>>;
+
+/// A download read as a zip archive, along with the download as is if repacked as one.
+pub struct Download {
+    /// Zip archive of the download.
+    pub archive: Archive,
+    /// Container format of the download.
+    pub format: ArchiveFormat,
+    /// The download as is, if `archive` is repacked from it.
+    original: Option<File>,
+}
+
+impl Download {
+    /// Returns the download as is, along with the zip archive it was repacked as if any, see
+    /// [`repack_as_zip`].
+    pub fn into_files(self) -> (File, Option<File>) {
+        let zip = self.archive.into_inner().into_inner();
+        match self.original {
+            Some(original) => (original, Some(zip)),
+            None => (zip, None),
+        }
+    }
+}
+
+/// Downloads the archive of `download_id` and extracts the game JAR within.
+///
+/// The game JAR is extracted to `output` if present, otherwise to its name within the archive
+/// relative to `output_dir`. Returns the path to the extracted game JAR. Uploads hosted elsewhere
+/// are only downloaded from `external_hosts`, see [`resolve_external_download`], and the archive
+/// is downloaded into a temporary file within `temp_dir`.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_with_id(
+    client: &itch_io::Client,
+    game_url: &str,
+    download_id: u64,
+    auth: &ItchAuth,
+    external_hosts: &[String],
+    output: Option<&Path>,
+    output_dir: &Path,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<PathBuf, ArchiveError> {
+    let url = get_download_url(client, game_url, download_id, auth, external_hosts, retry).await?;
+    let mut archive = download_archive(client, &url, None, None, temp_dir, retry)
+        .await?
+        .archive;
+    let file_name = find_game_jar(&mut archive, None)?;
+    let destination = jar_destination(&mut archive, &file_name, output, output_dir, None)?;
+    extract_game_jar(&mut archive, &file_name, &destination, &[])?;
+    Ok(destination)
+}
+
+/// Downloads the zip archive from its itch.io download `url` if known, falling back to every one
+/// of `mirrors` in order if that fails.
+///
+/// Every source is downloaded into the partial download at `part` if present, see
+/// [`download_archive`], and the one from itch.io is expected to be `expected_size` bytes if
+/// known. Returns the error of the last source tried if every one of them fails. Temporary files
+/// are created within `temp_dir`.
+///
+/// The one from itch.io is downloaded over `connections` ranged requests at once instead if more
+/// than one, its size is known, and NO partial download is left to resume, see
+/// [`download_segmented`]. Mirrors are downloaded without the rate limit of `retry`, which is only
+/// meant for itch.io.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_archive_with_fallback(
+    client: &itch_io::Client,
+    url: Option<&str>,
+    expected_size: Option<u64>,
+    mirrors: &[Mirror],
+    part: Option<&Path>,
+    connections: usize,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<Download, ArchiveError> {
+    let mut last_error = None;
+
+    if let Some(url) = url {
+        let segmented = match expected_size {
+            Some(size) if connections > 1 && !part.is_some_and(Path::exists) => {
+                match download_segmented(client, url, size, connections, temp_dir, retry).await {
+                    Err(ArchiveError::RangesUnsupported(_)) => {
+                        warn!(
+                            "Download url does NOT support ranged requests, using one connection"
+                        );
+                        None
+                    }
+                    result => Some(result.and_then(|it| open_archive(it, temp_dir))),
+                }
+            }
+            _ => None,
+        };
+        let result = match segmented {
+            Some(result) => result,
+            None => download_archive(client, url, expected_size, part, temp_dir, retry).await,
+        };
+        match result {
+            Ok(archive) => return Ok(archive),
+            Err(cause) if mirrors.is_empty() => return Err(cause),
+            Err(cause) => {
+                warn!("Failed to download archive from itch.io: {cause}");
+                last_error = Some(cause);
+            }
+        }
+    }
+
+    let mirror_retry = retry.with_rate_limit(None);
+    for mirror in mirrors {
+        match download_from_mirror(client, mirror, part, temp_dir, &mirror_retry).await {
+            Ok(archive) => return Ok(archive),
+            Err(cause) => {
+                warn!(
+                    "Failed to download archive from mirror {}: {cause}",
+                    mirror.url
+                );
+                last_error = Some(cause);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or(ArchiveError::NoDownloadSource))
+}
+
+/// Requests the download info of `download_id`, returning the URL to download its archive from.
+///
+/// Every way of authenticating with `auth` is tried in order, see [`ItchAuth::methods`], falling
+/// back to the next one if it fails. Fails with the error of the last one otherwise. Uploads
+/// hosted elsewhere than itch.io are followed to where they redirect to, which must be one of
+/// `external_hosts`, see [`resolve_external_download`].
+pub async fn get_download_url(
+    client: &itch_io::Client,
+    game_url: &str,
+    download_id: u64,
+    auth: &ItchAuth,
+    external_hosts: &[String],
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    let methods = auth.methods();
+    let mut last_error = None;
+
+    for (index, &method) in methods.iter().enumerate() {
+        let result = match (method, &auth.api_key) {
+            (AuthMethod::ApiKey, Some(api_key)) => {
+                get_api_download_url(client, api_key, auth.download_key, download_id, retry).await
+            }
+            (AuthMethod::Anonymous, _) => {
+                get_anonymous_download_info_url(client, game_url, download_id, retry).await
+            }
+            _ => {
+                let csrf_token = auth.csrf_token.as_str();
+                get_download_info_url(client, game_url, download_id, csrf_token, retry).await
+            }
+        };
+
+        match result {
+            Ok(url) => {
+                match method {
+                    AuthMethod::Anonymous => info!(
+                        "Got download info with {method}, through the anonymous download path \
+                         of free uploads"
+                    ),
+                    _ => info!("Got download info authenticated with {method}"),
+                }
+                return resolve_external_download(client, &url, external_hosts, retry).await;
+            }
+            Err(cause) if index + 1 < methods.len() => {
+                warn!(
+                    "Failed to get download info with {method}, falling back to {}: {cause}",
+                    methods[index + 1]
+                );
+            }
+            Err(cause) => last_error = Some((method, cause)),
+        }
+    }
+
+    let (method, cause) = last_error.expect("at least one way of authenticating should be tried");
+    Err(ArchiveError::DownloadInfo {
+        method: method.to_string(),
+        cause: Box::new(cause),
+    })
+}
+
+/// Requests the download info of `download_id` through the game page with `csrf_token`,
+/// returning the URL to download its archive from.
+async fn get_download_info_url(
+    client: &itch_io::Client,
+    game_url: &str,
+    download_id: u64,
+    csrf_token: &str,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    retry
+        .retry("get download info", || async move {
+            info!("Getting download info");
+            Ok::<_, ArchiveError>(
+                client
+                    .get_download_info(game_url, download_id, csrf_token)
+                    .await?
+                    .url
+                    .into(),
+            )
+        })
+        .await
+}
+
+/// Requests the download info of `download_id` through the game page as an anonymous visitor,
+/// with the CSRF token it hands out, see [`get_anonymous_csrf_token`].
+async fn get_anonymous_download_info_url(
+    client: &itch_io::Client,
+    game_url: &str,
+    download_id: u64,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    info!("Getting the CSRF token of an anonymous visit of the game page");
+    let csrf_token = get_anonymous_csrf_token(client, game_url, retry).await?;
+    get_download_info_url(client, game_url, download_id, &csrf_token, retry).await
+}
+
+/// Checks the download `url` if hosted elsewhere than itch.io, returning where it redirects to.
+///
+/// `url` must be on one of `external_hosts` or any of their subdomains, failing with
+/// [`ArchiveError::ExternalHost`] otherwise, while every redirect after it is checked by the
+/// client itself, see [`build_client`](crate::http::build_client). Fails with
+/// [`ArchiveError::ExternalDownload`] if it responds to a HEAD request with a web page rather than
+/// a file, as hosts show before the actual download. Returns `url` as is if hosted by itch.io
+/// itself, or if the host does NOT answer HEAD requests.
+pub async fn resolve_external_download(
+    client: &itch_io::Client,
+    url: &str,
+    external_hosts: &[String],
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    let parsed = url::Url::parse(url)?;
+    if is_on_hosts(&parsed, &ITCH_HOSTS) {
+        return Ok(url.to_owned());
+    }
+    warn!(
+        "Download is hosted elsewhere than itch.io, at {}",
+        parsed.host_str().unwrap_or_default()
+    );
+    check_external_host(&parsed, external_hosts)?;
+
+    let response = retry
+        .retry("check external download", || async move {
+            info!("Sending HEAD request to external download url ({url})...");
+            Ok::<_, ArchiveError>(client.client.head(url).send().await?)
+        })
+        .await?;
+    if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+        warn!("External download does NOT answer HEAD requests, downloading it unchecked");
+        return Ok(url.to_owned());
+    }
+    let response = response.error_for_status()?;
+    let resolved = response.url().clone();
+    let is_page = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|it| it.to_str().ok())
+        .is_some_and(|it| it.starts_with("text/html"));
+    if is_page {
+        return Err(ArchiveError::ExternalDownload {
+            url: resolved.to_string(),
+            reason: "it responds with a web page rather than a file".to_owned(),
+        });
+    }
+
+    if resolved.as_str() != url {
+        info!("External download redirects to {resolved}");
+    }
+    Ok(resolved.into())
+}
+
+/// Fails with [`ArchiveError::ExternalHost`] unless `url` is on one of `external_hosts`.
+fn check_external_host(url: &url::Url, external_hosts: &[String]) -> Result<(), ArchiveError> {
+    if is_on_hosts(url, external_hosts) {
+        Ok(())
+    } else {
+        Err(ArchiveError::ExternalHost(
+            url.host_str().unwrap_or_default().to_owned(),
+        ))
+    }
+}
+
+/// Metadata of a download, as reported in response to a HEAD request.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DownloadHead {
+    /// Size in bytes of the download, from `Content-Length`.
+    pub content_length: Option<u64>,
+    /// Unix timestamp in seconds of when the download was last modified, from `Last-Modified`.
+    pub last_modified: Option<u64>,
+}
+
+/// Sends a HEAD request to `url`, returning the metadata of the download if reported.
+pub async fn head_download(
+    client: &itch_io::Client,
+    url: &str,
+    retry: &RetryPolicy,
+) -> Result<DownloadHead, ArchiveError> {
+    retry
+        .retry("get download metadata", || async move {
+            info!("Sending HEAD request to download url ({url})...");
+            let response = client.client.head(url).send().await?.error_for_status()?;
+            let header =
+                |name: HeaderName| response.headers().get(name).and_then(|it| it.to_str().ok());
+            Ok::<_, ArchiveError>(DownloadHead {
+                content_length: header(CONTENT_LENGTH).and_then(|it| it.parse().ok()),
+                last_modified: header(LAST_MODIFIED).and_then(release::parse_http_date),
+            })
+        })
+        .await
+}
+
+/// Downloads the zip archive at `url`, resuming the partial download at `part` if present.
+///
+/// The partial download is kept at `part` until removed by [`remove_partial_download`], so that
+/// both the next attempt and the next run resume it instead of restarting from zero. Downloads
+/// into an anonymous temporary file within `temp_dir` instead if `part` is absent, which only the
+/// next attempt resumes.
+///
+/// Fails before reading the archive if it is NOT `expected_size` bytes, see [`download_file`].
+pub async fn download_archive(
+    client: &itch_io::Client,
+    url: &str,
+    expected_size: Option<u64>,
+    part: Option<&Path>,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<Download, ArchiveError> {
+    let what = "download archive";
+    let archive_file =
+        download_file(client, url, expected_size, part, what, temp_dir, retry).await?;
+    open_archive(archive_file, temp_dir)
+}
+
+/// Downloads the zip archive from `mirror`, verifying its size and hash if known.
+///
+/// See [`download_archive`] for how the partial download at `part` is resumed. A partial download
+/// that fails verification is removed, so that it is never resumed.
+pub async fn download_from_mirror(
+    client: &itch_io::Client,
+    mirror: &Mirror,
+    part: Option<&Path>,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<Download, ArchiveError> {
+    let mut archive_file = download_file(
+        client,
+        &mirror.url,
+        mirror.size,
+        part,
+        "download archive from mirror",
+        temp_dir,
+        retry,
+    )
+    .await?;
+
+    if let Some(expected) = mirror.sha256 {
+        let (actual, _) = hash_reader(&mut archive_file)?;
+        if actual != expected {
+            metrics().record_hash_mismatch();
+            if let Some(part) = part {
+                remove_partial_download(part)?;
+            }
+            return Err(ArchiveError::HashMismatch {
+                subject: mirror.url.clone(),
+                expected,
+                actual,
+            });
+        }
+        info!("Archive from mirror matches its expected hash");
+    }
+
+    open_archive(archive_file, temp_dir)
+}
+
+/// Returns the path of the partial download of the download option with a title matching
+/// `download_title`, named after the pattern with every unusual character replaced by `_`.
+pub fn partial_download_path(output_dir: &Path, download_title: &Pattern) -> PathBuf {
+    let name = download_title.to_string().replace(
+        |it: char| !it.is_ascii_alphanumeric() && !"-._".contains(it),
+        "_",
+    );
+    output_dir.join(format!("{name}.part"))
+}
+
+/// Removes the partial download at `part`, along with its validator, if present.
+pub fn remove_partial_download(part: &Path) -> Result<(), ArchiveError> {
+    for path in [part.to_path_buf(), validator_path(part)] {
+        match fs::remove_file(&path) {
+            Err(cause) if cause.kind() != io::ErrorKind::NotFound => return Err(cause.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads the downloaded `archive_file` as a zip archive from its start.
+///
+/// Downloads of any other format are repacked as one first, see [`ArchiveFormat::detect`] and
+/// [`repack_as_zip`] into a temporary file within `temp_dir`, keeping the download as is along with
+/// it.
+pub fn open_archive(mut archive_file: File, temp_dir: &Path) -> Result<Download, ArchiveError> {
+    let format = ArchiveFormat::detect(&mut archive_file)?;
+    let (archive_file, original) = match format {
+        ArchiveFormat::Zip => (archive_file, None),
+        _ => {
+            let repacked = repack_as_zip(archive_file.try_clone()?, format, temp_dir)?;
+            (repacked, Some(archive_file))
+        }
+    };
+
+    info!("Reading temporary file as zip archive...");
+    let archive = zip::ZipArchive::new(io::BufReader::new(archive_file))?;
+
+    info!("Archive contains the following files:");
+    for file_name in archive.file_names() {
+        info!("    {file_name}");
+    }
+
+    Ok(Download {
+        archive,
+        format,
+        original,
+    })
+}
+
+/// Alternative source of the zip archive of the game JAR, tried when itch.io fails.
+///
+/// Parsed from its URL, optionally followed by `#sha256=<hex>` for the expected hash of the
+/// archive.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mirror {
+    /// URL to download the zip archive from.
+    pub url: String,
+    /// Expected sha256 hash of the zip archive, verified after downloading if present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<Sha256Hash>,
+    /// Expected size in bytes of the zip archive, verified after downloading if present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl str::FromStr for Mirror {
+    type Err = FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once("#sha256=") {
+            Some((url, sha256)) => Ok(Self {
+                url: url.to_owned(),
+                sha256: Some(sha256.parse()?),
+                size: None,
+            }),
+            None => Ok(Self {
+                url: s.to_owned(),
+                sha256: None,
+                size: None,
+            }),
+        }
+    }
+}
+
+/// A download option of the game page besides the game JARs archived as is, e.g. the soundtrack.
+///
+/// Parsed from `NAME=TITLE`, where `TITLE` is the [`Pattern`] of the title of the download option.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SecondaryTarget {
+    /// Name of the target, under which its files are listed in the archived versions data, e.g.
+    /// `soundtrack`.
+    pub name: String,
+    /// Title of the download option.
+    pub download_title: Pattern,
+}
+
+impl str::FromStr for SecondaryTarget {
+    type Err = InvalidSecondaryTarget;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidSecondaryTarget(s.to_owned());
+        let (name, title) = s.split_once('=').ok_or_else(invalid)?;
+        if name.is_empty() || title.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            download_title: title.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Error of parsing a [`SecondaryTarget`] NOT of the form `NAME=TITLE`.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("invalid secondary target '{0}', expected NAME=TITLE")]
+pub struct InvalidSecondaryTarget(pub String);
+
+/// Downloads the file at `url` as is to `destination`, resuming the partial download next to it
+/// if present.
+///
+/// Fails if it is NOT `expected_size` bytes, see [`download_file`].
+pub async fn download_secondary(
+    client: &itch_io::Client,
+    url: &str,
+    expected_size: Option<u64>,
+    destination: &Path,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let mut part = destination.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+
+    let file = download_file(
+        client,
+        url,
+        expected_size,
+        Some(&part),
+        "download file",
+        temp_dir,
+        retry,
+    )
+    .await?;
+    drop(file);
+    fs::rename(&part, destination)?;
+    remove_partial_download(&part)
+}
+
+/// Finds the name of the game JAR within `archive`, see [`find_game_jars`].
+pub fn find_game_jar(
+    archive: &mut Archive,
+    jar_name: Option<&Pattern>,
+) -> Result<String, ArchiveError> {
+    Ok(find_game_jars(archive, jar_name, None)?.0)
+}
+
+/// Finds the name of the game JAR within `archive`, along with the names of every other candidate
+/// game JAR.
+///
+/// The candidates are the ones with a name matching `jar_name` if present, otherwise the ones with
+/// a name either starting with `Cosmic Reach-` or ending with `.jar`. If there are several, the
+/// game JAR is the first one left after keeping only the ones named `Cosmic Reach-*.jar` if any,
+/// then only the largest ones, then only the ones matching `preferred` if present and any do.
+/// Fails with [`ArchiveError::MultipleJars`] if more than one is left.
+pub fn find_game_jars(
+    archive: &mut Archive,
+    jar_name: Option<&Pattern>,
+    preferred: Option<&Pattern>,
+) -> Result<(String, Vec<String>), ArchiveError> {
+    let candidates = archive
+        .file_names()
+        .filter(|file_name| match jar_name {
+            Some(jar_name) => jar_name.matches(file_name),
+            None => {
+                file_name.starts_with("Cosmic Reach-")
+                    || Path::extension(file_name.as_ref())
+                        .is_some_and(|it| it.eq_ignore_ascii_case("jar"))
+            }
+        })
+        .map(String::from)
+        .sorted()
+        .collect::<Vec<_>>();
+    if candidates.len() <= 1 {
+        let Some(it) = candidates.into_iter().next() else {
+            return Err(ArchiveError::JarNotFound);
+        };
+        info!("Found game JAR: {it}");
+        return Ok((it, Vec::new()));
+    }
+    info!(
+        "Found MULTIPLE candidate game JARs: {}",
+        candidates.join(", ")
+    );
+
+    let named = Pattern::Glob("Cosmic Reach-*.jar".to_owned());
+    let mut left = candidates
+        .iter()
+        .filter(|it| named.matches(entry_file_name(it)))
+        .cloned()
+        .collect::<Vec<_>>();
+    if left.is_empty() {
+        left.clone_from(&candidates);
+    }
+
+    let mut sizes = Vec::with_capacity(left.len());
+    for file_name in &left {
+        sizes.push(archive.by_name(file_name)?.size());
+    }
+    let largest = sizes.iter().copied().max().unwrap_or_default();
+    let mut left = left
+        .into_iter()
+        .zip(sizes)
+        .filter(|&(_, size)| size == largest)
+        .map(|(it, _)| it)
+        .collect::<Vec<_>>();
+
+    if let (Some(preferred), true) = (preferred, left.len() > 1) {
+        if left.iter().any(|it| preferred.matches(it)) {
+            left.retain(|it| preferred.matches(it));
+        } else {
+            warn!("NO candidate game JAR matches the preferred '{preferred}', ignoring it");
+        }
+    }
+    let selected = match left.into_iter().at_most_one() {
+        Ok(Some(it)) => it,
+        Ok(None) => unreachable!("at least the largest candidate game JAR should be left"),
+        Err(left) => return Err(ArchiveError::MultipleJars(left.collect())),
+    };
+
+    info!("Selected game JAR: {selected}");
+    let others = candidates
+        .into_iter()
+        .filter(|it| *it != selected)
+        .collect();
+    Ok((selected, others))
+}
+
+/// Returns the file name of the archived file named `name`, i.e. without its directories.
+fn entry_file_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// Returns the lowercase name of the compression method of the file named `file_name` within
+/// `archive`, e.g. `deflated`.
+pub fn compression_method(archive: &mut Archive, file_name: &str) -> Result<String, ArchiveError> {
+    let method = archive.by_name(file_name)?.compression();
+    Ok(format!("{method:?}").to_ascii_lowercase())
+}
+
+/// Returns where the game JAR named `file_name` within `archive` is to be extracted to.
+///
+/// This is `output` if present, otherwise `template` rendered relative to `output_dir` if present,
+/// or else its name within the archive relative to `output_dir`, see [`safe_entry_path`].
+///
+/// The `{version}` placeholder of `template` is replaced with the version id within its file
+/// name, and `{filename}` with its file name.
+pub fn jar_destination(
+    archive: &mut Archive,
+    file_name: &str,
+    output: Option<&Path>,
+    output_dir: &Path,
+    template: Option<&str>,
+) -> Result<PathBuf, ArchiveError> {
+    if let Some(output) = output {
+        return Ok(output.to_path_buf());
+    }
+
+    let file = archive.by_name(file_name)?;
+    let path = safe_entry_path(&file)?;
+    let Some(template) = template else {
+        return Ok(output_dir.join(path));
+    };
+
+    let file_name = path
+        .file_name()
+        .and_then(|it| it.to_str())
+        .unwrap_or(file_name);
+    let rendered = template
+        .replace("{version}", version_name(file_name))
+        .replace("{filename}", file_name);
+    let is_relative = Path::new(&rendered)
+        .components()
+        .all(|it| matches!(it, Component::Normal(_) | Component::CurDir));
+    if !is_relative || rendered.is_empty() {
+        return Err(ArchiveError::UnsafeEntry(rendered));
+    }
+    Ok(output_dir.join(rendered))
+}
+
+/// Checks that the file system of `directory`, or of its closest existing ancestor, has at least
+/// `required` bytes available.
+pub fn check_disk_space(directory: &Path, required: u64) -> Result<(), ArchiveError> {
+    let existing = directory
+        .ancestors()
+        .find(|it| it.is_dir())
+        .unwrap_or(Path::new("."));
+    let available = fs2::available_space(existing)?;
+    info!(
+        "{available} bytes available in '{}', needing {required} bytes",
+        existing.display()
+    );
+
+    if available < required {
+        return Err(ArchiveError::InsufficientDiskSpace {
+            path: directory.to_path_buf(),
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Returns the path that the archived `file` is extracted to, relative to the directory it is
+/// extracted into.
+///
+/// Fails with [`ArchiveError::UnsafeEntry`] if its name is absolute or contains `..`, or if it is
+/// a symbolic link, any of which could have extracting write outside of that directory.
+pub fn safe_entry_path(file: &zip::read::ZipFile<'_>) -> Result<PathBuf, ArchiveError> {
+    let unsafe_entry = || ArchiveError::UnsafeEntry(file.name().to_owned());
+    if file.is_symlink() {
+        return Err(unsafe_entry());
+    }
+
+    let path = file.enclosed_name().ok_or_else(unsafe_entry)?;
+    let is_relative = path
+        .components()
+        .all(|it| matches!(it, Component::Normal(_) | Component::CurDir));
+    if !is_relative || path.as_os_str().is_empty() {
+        return Err(unsafe_entry());
+    }
+    Ok(path)
+}
+
+/// Extracts the game JAR named `file_name` within `archive` to `destination`, returning its
+/// digests of every one of `algorithms`.
+///
+/// The game JAR is extracted to a temporary file first, which is only moved to `destination` once
+/// its size and CRC-32 match the ones recorded in `archive`. Both are calculated, along with the
+/// digests, from the bytes as they are written, so the extracted file is never read back.
+pub fn extract_game_jar(
+    archive: &mut Archive,
+    file_name: &str,
+    destination: &Path,
+    algorithms: &[HashAlgorithm],
+) -> Result<Digests, ArchiveError> {
+    info!("Reading archived game jar...");
+    let mut file = archive.by_name(file_name)?;
+    let (expected_size, expected_crc32) = (file.size(), file.crc32());
+
+    info!("Creating destination game jar file if absent...");
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    write_atomically(destination, |extracted| {
+        info!("Extracting extract game jar file...");
+        let bar = progress::bytes_bar(Some(expected_size), "Extracting");
+        let mut writer =
+            HashingWriter::new(io::BufWriter::new(extracted), algorithms.iter().copied());
+        io::copy(&mut bar.wrap_read(&mut file), &mut writer)?;
+        writer.flush()?;
+        bar.finish_and_clear();
+
+        info!("Verifying extracted game jar file...");
+        let (crc32, size) = (writer.crc32(), writer.size());
+        let subject = destination.display().to_string();
+        if size != expected_size {
+            return Err(ArchiveError::SizeMismatch {
+                subject,
+                expected: expected_size,
+                actual: size,
+            });
+        }
+        if crc32 != expected_crc32 {
+            return Err(ArchiveError::Crc32Mismatch {
+                subject,
+                expected: expected_crc32,
+                actual: crc32,
+            });
+        }
+        Ok(writer.finish().1)
+    })
+}
+
+/// Extracts the entry named `file_name` within `archive` into an anonymous temporary file within
+/// `temp_dir` rather than memory, see [`temp::tempfile`], returning it rewound to its start along
+/// with its digests of every one of `algorithms`.
+pub fn spill_entry(
+    archive: &mut Archive,
+    file_name: &str,
+    algorithms: &[HashAlgorithm],
+    temp_dir: &Path,
+) -> Result<(File, Digests), ArchiveError> {
+    let mut entry = archive.by_name(file_name)?;
+    let mut writer = HashingWriter::new(
+        io::BufWriter::new(temp::tempfile(temp_dir)?),
+        algorithms.iter().copied(),
+    );
+    io::copy(&mut entry, &mut writer)?;
+    let (file, digests) = writer.finish();
+    let mut file = file.into_inner().map_err(io::IntoInnerError::into_error)?;
+    file.rewind()?;
+    Ok((file, digests))
+}
+
+/// Validates every entry of `archive` before anything is extracted from it.
+///
+/// The entries listed by the central directory must NOT overlap one another, and every one must
+/// decompress to its recorded size and CRC-32. Fails with [`ArchiveError::BrokenArchive`] naming
+/// the first entry that does NOT.
+pub fn validate_archive(archive: &mut Archive) -> Result<(), ArchiveError> {
+    info!("Validating every entry of the archive...");
+    let unreadable = |index: usize, cause: zip::result::ZipError| {
+        ArchiveError::BrokenArchive(format!("entry {index} is unreadable: {cause}"))
+    };
+
+    let mut spans = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index_raw(index)
+            .map_err(|cause| unreadable(index, cause))?;
+        spans.push((
+            file.header_start(),
+            file.data_start() + file.compressed_size(),
+            file.name().to_owned(),
+        ));
+    }
+    spans.sort();
+    for pair in spans.windows(2) {
+        let ((_, end, name), (start, _, next)) = (&pair[0], &pair[1]);
+        if end > start {
+            return Err(ArchiveError::BrokenArchive(format!(
+                "'{name}' overlaps '{next}' within the central directory"
+            )));
+        }
+    }
+
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|cause| unreadable(index, cause))?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_owned();
+        let (expected_size, expected_crc32) = (file.size(), file.crc32());
+        let (crc32, size) = crc32_reader(file).map_err(|cause| {
+            ArchiveError::BrokenArchive(format!("'{name}' is unreadable: {cause}"))
+        })?;
+        if size != expected_size {
+            return Err(ArchiveError::BrokenArchive(format!(
+                "'{name}' is {size} bytes, but {expected_size} bytes are recorded"
+            )));
+        }
+        if crc32 != expected_crc32 {
+            return Err(ArchiveError::BrokenArchive(format!(
+                "'{name}' has CRC-32 {crc32:08x}, but {expected_crc32:08x} is recorded"
+            )));
+        }
+    }
+    info!("Every entry of the archive is intact");
+    Ok(())
+}
+
+/// Calculates the CRC-32 of everything read from `reader`, along with its size in bytes.
+fn crc32_reader<R: Read>(mut reader: R) -> Result<(u32, u64), ArchiveError> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = [0; 8192];
+    let mut size = 0;
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(cause) if cause.kind() == io::ErrorKind::Interrupted => continue,
+            Err(cause) => return Err(cause.into()),
+        };
+        hasher.update(&buffer[..len]);
+        size += len as u64;
+    }
+    Ok((hasher.finalize(), size))
+}
+
+/// Returns the directory within `directory` that every entry of the archive of the game JAR named
+/// `file_name` is unpacked into.
+///
+/// This is named after the version id within `file_name` if present, otherwise after `file_name`
+/// without its extension.
+pub fn version_directory(directory: &Path, file_name: &str) -> PathBuf {
+    let file_name = Path::new(file_name)
+        .file_name()
+        .and_then(|it| it.to_str())
+        .unwrap_or(file_name);
+    directory.join(version_name(file_name))
+}
+
+/// Returns the version id within the game JAR file name `file_name` if present, otherwise
+/// `file_name` without its extension.
+fn version_name(file_name: &str) -> &str {
+    version_id_from_file_name(file_name).unwrap_or_else(|| {
+        Path::new(file_name)
+            .file_stem()
+            .and_then(|it| it.to_str())
+            .unwrap_or(file_name)
+    })
+}
+
+/// Unpacks every entry of `archive` into `directory`, returning the number of unpacked files.
+///
+/// Entries that could escape `directory` are skipped, see [`safe_entry_path`].
+pub fn extract_all(archive: &mut Archive, directory: &Path) -> Result<usize, ArchiveError> {
+    info!(
+        "Unpacking every archived file into '{}'...",
+        directory.display()
+    );
+    fs::create_dir_all(directory)?;
+
+    let mut count = 0;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let name = match safe_entry_path(&file) {
+            Ok(it) => it,
+            Err(cause) => {
+                warn!("Skipping {cause}");
+                continue;
+            }
+        };
+        let path = directory.join(name);
+
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        info!("        {}", path.display());
+        io::copy(&mut file, &mut File::create(&path)?)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Downloads `url` into the partial download at `part` if present, otherwise into an anonymous
+/// temporary file within `temp_dir`, resuming it on every retry. Returns the complete file,
+/// rewound to its start.
+///
+/// Fails without retrying if the complete file is NOT `expected_size` bytes, which tells a
+/// truncated download apart from one that is merely a different file, e.g. a newer release.
+pub(crate) async fn download_file(
+    client: &itch_io::Client,
+    url: &str,
+    expected_size: Option<u64>,
+    part: Option<&Path>,
+    what: &str,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<File, ArchiveError> {
+    let started = Instant::now();
+    let partial = Mutex::new(PartialDownload::open(part, temp_dir)?);
+    let shared = &partial;
+    retry
+        .retry(what, || async move {
+            resume_download(client, url, &mut *shared.lock().await).await
+        })
+        .await?;
+    metrics().record_download(started.elapsed());
+
+    let mut file = partial.into_inner().file;
+    let size = file.seek(SeekFrom::End(0))?;
+    match expected_size {
+        Some(expected) if expected != size => {
+            return Err(ArchiveError::SizeMismatch {
+                subject: url.to_owned(),
+                expected,
+                actual: size,
+            })
+        }
+        Some(_) => info!("Download matches its expected size of {size} bytes"),
+        None => {}
+    }
+    file.rewind()?;
+    Ok(file)
+}
+
+/// Downloads `url` of `size` bytes into a temporary file within `temp_dir` over `connections`
+/// ranged requests at once, one for each segment of about equal size. Returns the complete file,
+/// rewound to its start.
+///
+/// Every segment is written in place and retried on its own, resuming from where it stopped.
+/// Probes `url` with a ranged request of its first byte before downloading anything, failing
+/// with [`ArchiveError::RangesUnsupported`] if it answers with the whole file, so that it can be
+/// downloaded over one connection instead, and with [`ArchiveError::SizeMismatch`] if the file is
+/// NOT `size` bytes after all.
+///
+/// Segments are NOT kept across runs, unlike the partial download of [`download_archive`].
+pub async fn download_segmented(
+    client: &itch_io::Client,
+    url: &str,
+    size: u64,
+    connections: usize,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<File, ArchiveError> {
+    probe_ranges(client, url, size, retry).await?;
+
+    let started = Instant::now();
+    let connections = u64::try_from(connections.max(1)).unwrap_or(u64::MAX);
+    let segment_size = size.div_ceil(connections).max(1);
+    info!("Downloading {size} bytes over {connections} connections...");
+
+    let temp = temp::named_tempfile(temp_dir)?;
+    temp.as_file().set_len(size)?;
+    let bar = progress::bytes_bar(Some(size), "Downloading");
+    let segments = (0..connections)
+        .map(|index| index * segment_size..((index + 1) * segment_size).min(size))
+        .filter(|range| !range.is_empty())
+        .map(|range| download_segment(client, url, temp.path(), range, size, &bar, retry));
+    futures_util::future::try_join_all(segments).await?;
+    bar.finish_and_clear();
+    metrics().record_download(started.elapsed());
+
+    let mut file = temp.into_file();
+    file.rewind()?;
+    Ok(file)
+}
+
+/// Checks whether `url` answers a ranged request with only the bytes requested of its file of
+/// `size` bytes, see [`check_content_range`].
+async fn probe_ranges(
+    client: &itch_io::Client,
+    url: &str,
+    size: u64,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    retry
+        .retry("probe ranged requests", || async move {
+            info!("Checking whether download url supports ranged requests...");
+            let response = client
+                .client
+                .get(url)
+                .header(RANGE, "bytes=0-0")
+                .send()
+                .await?
+                .error_for_status()?;
+            check_content_range(url, &response, 0, size)
+        })
+        .await
+}
+
+/// Checks whether `response` to a ranged request of `url` holds the bytes of its file of `size`
+/// bytes starting at `start`.
+///
+/// Fails with [`ArchiveError::RangesUnsupported`] if it holds the whole file or starts elsewhere,
+/// and with [`ArchiveError::SizeMismatch`] if the file is of another size, e.g. replaced by a
+/// newer release since its size was known.
+fn check_content_range(
+    url: &str,
+    response: &reqwest::Response,
+    start: u64,
+    size: u64,
+) -> Result<(), ArchiveError> {
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(ArchiveError::RangesUnsupported(url.to_owned()));
+    }
+    let content_range = response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|it| it.to_str().ok())
+        .and_then(parse_content_range);
+    match content_range {
+        Some((_, Some(total))) if total != size => Err(ArchiveError::SizeMismatch {
+            subject: url.to_owned(),
+            expected: size,
+            actual: total,
+        }),
+        Some((first, _)) if first == start => Ok(()),
+        _ => Err(ArchiveError::RangesUnsupported(url.to_owned())),
+    }
+}
+
+/// Parses the value of a `Content-Range` header into the first byte it holds and the total size
+/// of the file, if known.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (first, _) = range.split_once('-')?;
+    Some((first.parse().ok()?, total.parse().ok()))
+}
+
+/// Downloads the `range` of bytes of `url` into the same range of the file at `path`, retrying
+/// from where the last attempt stopped.
+///
+/// Fails like [`check_content_range`] as well if `url` answers with other bytes than those of its
+/// file of `size` bytes requested after all, once other segments may have been written already.
+async fn download_segment(
+    client: &itch_io::Client,
+    url: &str,
+    path: &Path,
+    range: Range<u64>,
+    size: u64,
+    bar: &ProgressBar,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+    let segment = Mutex::new((file, range.start));
+    let shared = &segment;
+    retry
+        .retry("download archive segment", || async move {
+            let mut segment = shared.lock().await;
+            let (file, offset) = &mut *segment;
+            if *offset >= range.end {
+                return Ok(());
+            }
+
+            info!(
+                "Requesting bytes {offset}-{} of download url...",
+                range.end - 1
+            );
+            let response = client
+                .client
+                .get(url)
+                .header(RANGE, format!("bytes={offset}-{}", range.end - 1))
+                .send()
+                .await?
+                .error_for_status()?;
+            check_content_range(url, &response, *offset, size)?;
+
+            file.seek(SeekFrom::Start(*offset)).await?;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                let len = (chunk.len() as u64).min(range.end - *offset);
+                file.write_all(&chunk[..len as usize]).await?;
+                *offset += len;
+                bar.inc(len);
+                metrics().record_bytes(len);
+            }
+            file.flush().await?;
+
+            if *offset < range.end {
+                return Err(ArchiveError::TruncatedSegment {
+                    subject: url.to_owned(),
+                    expected: range.end - range.start,
+                    received: *offset - range.start,
+                });
+            }
+            Ok::<_, ArchiveError>(())
+        })
+        .await
+}
+
+/// File being downloaded into, along with the validator of the response it was downloaded from.
+struct PartialDownload {
+    file: File,
+    /// Entity tag, or otherwise last modification date, sent in `If-Range` when resuming.
+    validator: Option<String>,
+    /// Where the validator is persisted across runs, if the file is.
+    validator_path: Option<PathBuf>,
+}
+
+impl PartialDownload {
+    fn open(part: Option<&Path>, temp_dir: &Path) -> Result<Self, ArchiveError> {
+        let Some(part) = part else {
+            return Ok(Self {
+                file: temp::tempfile(temp_dir)?,
+                validator: None,
+                validator_path: None,
+            });
+        };
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(part)?;
+        let validator_path = validator_path(part);
+        let validator = match fs::read_to_string(&validator_path) {
+            Ok(it) => Some(it),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => None,
+            Err(cause) => return Err(cause.into()),
+        };
+
+        Ok(Self {
+            file,
+            validator,
+            validator_path: Some(validator_path),
+        })
+    }
+
+    fn restart(&mut self, validator: Option<String>) -> Result<(), ArchiveError> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        if let Some(path) = &self.validator_path {
+            match &validator {
+                Some(validator) => fs::write(path, validator)?,
+                None => match fs::remove_file(path) {
+                    Err(cause) if cause.kind() != io::ErrorKind::NotFound => {
+                        return Err(cause.into())
+                    }
+                    _ => {}
+                },
+            }
+        }
+        self.validator = validator;
+        Ok(())
+    }
+}
+
+fn validator_path(part: &Path) -> PathBuf {
+    let mut path = part.as_os_str().to_owned();
+    path.push(".validator");
+    PathBuf::from(path)
+}
+
+async fn resume_download(
+    client: &itch_io::Client,
+    url: &str,
+    partial: &mut PartialDownload,
+) -> Result<(), ArchiveError> {
+    let mut offset = partial.file.seek(SeekFrom::End(0))?;
+    let response = loop {
+        let mut request = client.client.get(url);
+        if offset > 0 {
+            info!("Resuming download from byte {offset}...");
+            request = request.header(RANGE, format!("bytes={offset}-"));
+            if let Some(validator) = &partial.validator {
+                request = request.header(IF_RANGE, validator);
+            }
+        }
+
+        warn!("Sending GET request to download url ({url})...");
+        let response = request.send().await?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+            warn!("Partial download is NOT resumable, restarting from zero");
+            partial.restart(None)?;
+            offset = 0;
+            continue;
+        }
+        break response.error_for_status()?;
+    };
+
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        if offset > 0 {
+            warn!("Download url did NOT resume the partial download, restarting from zero");
+        }
+        let validator = response
+            .headers()
+            .get(ETAG)
+            .or_else(|| response.headers().get(LAST_MODIFIED))
+            .and_then(|it| it.to_str().ok())
+            .map(String::from);
+        partial.restart(validator)?;
+        offset = 0;
+    }
+
+    info!("Streaming GET response to download url into a file...");
+    let total = response.content_length().map(|it| it + offset);
+    let bar = progress::bytes_bar(total, "Downloading");
+    bar.set_position(offset);
+    let mut file = tokio::fs::File::from_std(partial.file.try_clone()?);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        bar.inc(chunk.len() as u64);
+        metrics().record_bytes(chunk.len() as u64);
+    }
+    file.flush().await?;
+    bar.finish_and_clear();
+
+    let size = partial.file.seek(SeekFrom::End(0))?;
+    match total {
+        Some(expected) if expected != size => Err(ArchiveError::SizeMismatch {
+            subject: url.to_owned(),
+            expected,
+            actual: size,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_archive(files: &[(&str, usize)]) -> Archive {
+        let mut writer = zip::ZipWriter::new(tempfile::tempfile().unwrap());
+        for &(name, size) in files {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&vec![0; size]).unwrap();
+        }
+        let mut file = writer.finish().unwrap();
+        file.rewind().unwrap();
+        zip::ZipArchive::new(io::BufReader::new(file)).unwrap()
+    }
+
+    #[test]
+    fn finds_only_game_jar() {
+        let mut archive = zip_archive(&[("Cosmic Reach-0.1.44.jar", 2), ("README.txt", 1)]);
+        assert_eq!(
+            find_game_jars(&mut archive, None, None).unwrap(),
+            ("Cosmic Reach-0.1.44.jar".to_owned(), Vec::new())
+        );
+
+        let mut archive = zip_archive(&[("README.txt", 1)]);
+        assert!(matches!(
+            find_game_jars(&mut archive, None, None),
+            Err(ArchiveError::JarNotFound)
+        ));
+    }
+
+    #[test]
+    fn prefers_named_then_largest_then_preferred_game_jars() {
+        let mut archive = zip_archive(&[
+            ("lib/lwjgl.jar", 8),
+            ("Cosmic Reach-0.1.44.jar", 2),
+            ("Cosmic Reach-0.1.44-small.jar", 1),
+        ]);
+        let (selected, others) = find_game_jars(&mut archive, None, None).unwrap();
+        assert_eq!(selected, "Cosmic Reach-0.1.44.jar");
+        assert_eq!(others, ["Cosmic Reach-0.1.44-small.jar", "lib/lwjgl.jar"]);
+
+        let mut archive = zip_archive(&[
+            ("Cosmic Reach-0.1.44.jar", 2),
+            ("Cosmic Reach-0.1.45.jar", 2),
+        ]);
+        assert!(matches!(
+            find_game_jars(&mut archive, None, None),
+            Err(ArchiveError::MultipleJars(_))
+        ));
+        let preferred = "*0.1.45*".parse().unwrap();
+        let (selected, _) = find_game_jars(&mut archive, None, Some(&preferred)).unwrap();
+        assert_eq!(selected, "Cosmic Reach-0.1.45.jar");
+    }
+
+    #[test]
+    fn finds_game_jar_by_name() {
+        let mut archive = zip_archive(&[("Cosmic Reach-0.1.44.jar", 2), ("game.bin", 1)]);
+        let jar_name = Pattern::from("game.bin");
+        assert_eq!(
+            find_game_jar(&mut archive, Some(&jar_name)).unwrap(),
+            "game.bin"
+        );
+    }
+
+    #[test]
+    fn rejects_unsafe_entry_paths() {
+        let mut writer = zip::ZipWriter::new(tempfile::tempfile().unwrap());
+        let options = SimpleFileOptions::default();
+        for name in ["assets/icon.png", "../escape.txt"] {
+            writer.start_file(name, options).unwrap();
+        }
+        writer.add_symlink("link", "/etc/passwd", options).unwrap();
+        let mut file = writer.finish().unwrap();
+        file.rewind().unwrap();
+        let mut archive = zip::ZipArchive::new(io::BufReader::new(file)).unwrap();
+
+        let path = safe_entry_path(&archive.by_name("assets/icon.png").unwrap()).unwrap();
+        assert_eq!(path, Path::new("assets/icon.png"));
+        for name in ["../escape.txt", "link"] {
+            assert!(matches!(
+                safe_entry_path(&archive.by_name(name).unwrap()),
+                Err(ArchiveError::UnsafeEntry(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parses_mirrors() {
+        let sha256 = "00".repeat(32);
+        let mirror = format!("https://example.com/a.zip#sha256={sha256}")
+            .parse::<Mirror>()
+            .unwrap();
+        assert_eq!(mirror.url, "https://example.com/a.zip");
+        assert_eq!(mirror.sha256, Some(Sha256Hash::from([0; 32])));
+
+        let mirror = "https://example.com/a.zip".parse::<Mirror>().unwrap();
+        assert_eq!(mirror.sha256, None);
+        assert!("https://example.com/a.zip#sha256=nothex"
+            .parse::<Mirror>()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_secondary_targets() {
+        let target = "soundtrack=*OST*.zip".parse::<SecondaryTarget>().unwrap();
+        assert_eq!(target.name, "soundtrack");
+        assert_eq!(target.download_title, Pattern::Glob("*OST*.zip".to_owned()));
+
+        for invalid in [
+            "soundtrack",
+            "=*OST*.zip",
+            "soundtrack=",
+            "soundtrack=regex:(",
+        ] {
+            assert_eq!(
+                invalid.parse::<SecondaryTarget>(),
+                Err(InvalidSecondaryTarget(invalid.to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(parse_content_range("bytes 0-0/1234"), Some((0, Some(1234))));
+        assert_eq!(parse_content_range("bytes 512-1023/*"), Some((512, None)));
+        assert_eq!(parse_content_range("bytes */1234"), None);
+        assert_eq!(parse_content_range("0-0/1234"), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Email notifications of newly detected or archived versions and of failures, sent over SMTP.
+
+use crate::config::EmailConfig;
+use crate::notify::Notification;
+use crate::{ArchiveError, RetryPolicy};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::fmt;
+use tracing::{info, warn};
+
+/// Default template of the body of notification emails.
+pub const DEFAULT_EMAIL_BODY: &str = "{title}
+
+Version: {id}
+Artifact: {artifact}
+Released: {release_date}
+Size: {size} bytes
+SHA-256: {sha256}
+";
+
+/// How the connection to an SMTP server is secured.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Implicit TLS from the start, on port 465 by default.
+    Tls,
+    /// Upgrading to TLS with `STARTTLS`, on port 587 by default.
+    #[default]
+    StartTls,
+    /// NO TLS at all, on port 25 by default, only meant for local servers.
+    None,
+}
+
+impl fmt::Display for SmtpTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Tls => "tls",
+            Self::StartTls => "starttls",
+            Self::None => "none",
+        })
+    }
+}
+
+/// SMTP server and addresses to send email notifications with.
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject: Option<String>,
+    body: Option<String>,
+}
+
+impl Mailer {
+    /// Creates the mailer of the `[email]` table of a config file, if its host is set.
+    pub fn from_config(config: &EmailConfig) -> Result<Option<Self>, ArchiveError> {
+        let Some(host) = config.host.as_deref() else {
+            return Ok(None);
+        };
+        let Some(from) = config.from.as_deref() else {
+            return Err(ArchiveError::Email(
+                "sending email needs a sender, see `email.from`".to_owned(),
+            ));
+        };
+        if config.to.is_empty() {
+            return Err(ArchiveError::Email(
+                "sending email needs recipients, see `email.to`".to_owned(),
+            ));
+        }
+
+        let mut builder = match config.tls.unwrap_or_default() {
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(email)?,
+            SmtpTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(email)?
+            }
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Some(Self {
+            transport: builder.build(),
+            from: from.parse().map_err(email)?,
+            to: config
+                .to
+                .iter()
+                .map(|it| it.parse().map_err(email))
+                .collect::<Result<_, _>>()?,
+            subject: config.subject.clone(),
+            body: config.body.clone(),
+        }))
+    }
+
+    /// Sends the plain text email `body` titled `subject` to every recipient.
+    pub async fn send(
+        &self,
+        subject: &str,
+        body: &str,
+        retry: &RetryPolicy,
+    ) -> Result<(), ArchiveError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = &message.body(body.to_owned()).map_err(email)?;
+
+        retry
+            .retry("send email", || async move {
+                info!("Sending email '{subject}' over SMTP...");
+                self.transport.send(message.clone()).await.map_err(email)?;
+                Ok::<_, ArchiveError>(())
+            })
+            .await?;
+        warn!("Sent email '{subject}' to {} recipients", self.to.len());
+        Ok(())
+    }
+
+    /// Sends the summary of `notification`, rendered from the subject and body templates if
+    /// configured, see [`Notification::render`].
+    pub async fn notify(
+        &self,
+        notification: &Notification,
+        retry: &RetryPolicy,
+    ) -> Result<(), ArchiveError> {
+        let subject = match &self.subject {
+            Some(template) => notification.render(template),
+            None => notification.title(),
+        };
+        let body = match &self.body {
+            Some(template) => notification.render(template),
+            None => notification.render(DEFAULT_EMAIL_BODY),
+        };
+        self.send(&subject, &body, retry).await
+    }
+
+    /// Sends the notice of `failures` consecutive failures, the last one being `cause`.
+    pub async fn notify_failures(
+        &self,
+        failures: u32,
+        cause: &str,
+        retry: &RetryPolicy,
+    ) -> Result<(), ArchiveError> {
+        let subject = format!("CosmicArchive updater failed {failures} times in a row");
+        let body = format!("{subject}, most recently with:\n\n{cause}\n");
+        self.send(&subject, &body, retry).await
+    }
+}
+
+fn email(cause: impl fmt::Display) -> ArchiveError {
+    ArchiveError::Email(cause.to_string())
+}
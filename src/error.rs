@@ -0,0 +1,329 @@
+//! Errors of the archiving pipeline.
+
+use crate::Sha256Hash;
+use reqwest::StatusCode;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Any failure while fetching, extracting, or checking a game JAR.
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    /// Sending a request or reading its response failed, or the response status was non-success.
+    #[error("network request failed: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// An extra request header has an invalid name or value.
+    #[error("invalid request header '{0}'")]
+    InvalidHeader(String),
+
+    /// A network request took longer than it is allowed to.
+    #[error("timed out after {} trying to {what}", humantime::format_duration(*.timeout))]
+    Timeout { what: String, timeout: Duration },
+
+    /// Scraping the itch.io game page or its download info failed.
+    #[error("itch.io request failed: {0}")]
+    Itch(#[from] itch_io::Error),
+
+    /// The itch.io API responded with errors.
+    #[error("itch.io API failed: {0}")]
+    ItchApi(String),
+
+    /// Requesting the download info failed with the last way of authenticating tried.
+    #[error("failed to get download info authenticated with {method}: {cause}")]
+    DownloadInfo {
+        method: String,
+        cause: Box<ArchiveError>,
+    },
+
+    /// Downloading without any credentials failed, as the game page handed out NO CSRF token to
+    /// anonymous visitors.
+    #[error("anonymous download failed: {0}")]
+    AnonymousDownload(String),
+
+    /// Logging into itch.io failed, e.g. with a wrong password.
+    #[error("itch.io login failed: {0}")]
+    Login(String),
+
+    /// Reading or writing a cached itch.io session failed.
+    #[error("itch.io session failed: {0}")]
+    Session(String),
+
+    /// Reading the downloaded zip archive failed.
+    #[error("failed to read zip archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    /// Reading or writing a local file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The archived versions data is not valid JSON of the expected shape.
+    #[error("failed to parse archived versions data: {0}")]
+    ManifestParse(#[from] serde_json::Error),
+
+    /// The archived versions data is of a schema this version does not support.
+    #[error(
+        "unsupported schema {0} of archived versions data, expected {} to {}",
+        crate::manifest::LEGACY_SCHEMA_VERSION,
+        crate::manifest::SCHEMA_VERSION
+    )]
+    UnsupportedSchema(String),
+
+    /// The config file is not valid TOML of the expected shape.
+    #[error("failed to parse config file: {0}")]
+    Config(#[from] toml::de::Error),
+
+    /// A URL could not be parsed or joined.
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    /// The hash of a file differs from the one it is expected to have.
+    #[error("hash mismatch for '{subject}': expected {expected}, but was {actual}")]
+    HashMismatch {
+        subject: String,
+        expected: Sha256Hash,
+        actual: Sha256Hash,
+    },
+
+    /// The size of a file differs from the one it is expected to have.
+    #[error("size mismatch for '{subject}': expected {expected} bytes, but was {actual} bytes")]
+    SizeMismatch {
+        subject: String,
+        expected: u64,
+        actual: u64,
+    },
+
+    /// The CRC-32 of an extracted file differs from the one recorded in its archive.
+    #[error("CRC-32 mismatch for '{subject}': expected {expected:08x}, but was {actual:08x}")]
+    Crc32Mismatch {
+        subject: String,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// None of the checksums published upstream are of a download, which may have been tampered
+    /// with or corrupted.
+    #[error("download matches none of the checksums published upstream: {}", .0.join(", "))]
+    UpstreamHashMismatch(Vec<String>),
+
+    /// The file system has too little space available to download and extract into.
+    #[error(
+        "'{}' has only {available} bytes available, but {required} bytes are needed",
+        .path.display()
+    )]
+    InsufficientDiskSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+
+    /// A cache directory is needed, but none is given.
+    #[error("NO cache directory, see `--cache-dir`")]
+    NoCacheDir,
+
+    /// A local mirror does not have the file an archived version refers to.
+    #[error("'{}' is missing", .0.display())]
+    MissingFile(PathBuf),
+
+    /// The URL of an archived version does not end with a file name.
+    #[error("'{0}' has NO file name")]
+    NoFileName(url::Url),
+
+    /// Some archived versions failed verification.
+    #[error("{failed} of {total} archived versions failed verification")]
+    VerifyFailed { failed: usize, total: usize },
+
+    /// The archived versions data and a directory of game JARs disagree.
+    #[error(
+        "{unreferenced} unreferenced, {missing} missing, and {mismatched} mismatched game JARs"
+    )]
+    CheckFailed {
+        unreferenced: usize,
+        missing: usize,
+        mismatched: usize,
+    },
+
+    /// No download option of the game page has the expected title.
+    #[error("NO download options matched '{title}'")]
+    DownloadNotFound { title: String },
+
+    /// More than one download option of the game page has the expected title.
+    #[error(
+        "there are {} download options matching '{title}': {}",
+        .titles.len(),
+        .titles.join(", ")
+    )]
+    MultipleDownloads { title: String, titles: Vec<String> },
+
+    /// The matched download option has no id to request its download info with.
+    #[error("download option '{title}' has NO id")]
+    MissingDownloadId { title: String },
+
+    /// A download is hosted elsewhere than itch.io, on a host NOT allowed to download from.
+    #[error(
+        "download is hosted at '{0}', which is NOT an allowed external host, see `--external-host`"
+    )]
+    ExternalHost(String),
+
+    /// A download hosted elsewhere than itch.io does NOT lead to a file.
+    #[error("external download {url} is unusable, as {reason}")]
+    ExternalDownload { url: String, reason: String },
+
+    /// A download is neither a zip archive, a tarball, nor a game JAR, by its starting bytes.
+    #[error("download is NOT of any supported archive format, starting with bytes {0}")]
+    UnknownArchiveFormat(String),
+
+    /// A download url answered a ranged request with the whole file.
+    #[error("download url '{0}' does NOT support ranged requests")]
+    RangesUnsupported(String),
+
+    /// A segment of a download ended before all of its bytes were received, which is retried.
+    #[error("segment of '{subject}' ended early after {received} of {expected} bytes")]
+    TruncatedSegment {
+        subject: String,
+        expected: u64,
+        received: u64,
+    },
+
+    /// Neither the itch.io upload nor any mirror is known to download the archive from.
+    #[error("NO itch.io upload nor mirror to download the archive from")]
+    NoDownloadSource,
+
+    /// The downloaded archive is broken just as its source ships it.
+    #[error("archive is broken as shipped by its source: {0}")]
+    BrokenArchive(String),
+
+    /// The downloaded archive got corrupted while downloading, e.g. by resuming a stale partial
+    /// download, so downloading it again from scratch may fix it.
+    #[error("archive got corrupted while downloading, downloading it again may help: {0}")]
+    CorruptDownload(String),
+
+    /// The downloaded archive does not contain the game JAR.
+    #[error("archive did NOT contain the game JAR")]
+    JarNotFound,
+
+    /// An entry of the downloaded archive could be extracted outside of its directory.
+    #[error("unsafe archived file '{0}', which could be extracted outside of its directory")]
+    UnsafeEntry(String),
+
+    /// The downloaded archive contains more than one candidate game JAR.
+    #[error("archive contained MULTIPLE game JARs: {}", .0.join(", "))]
+    MultipleJars(Vec<String>),
+
+    /// Accessing the git repository failed.
+    #[error("git failed: {0}")]
+    Git(String),
+
+    /// A libgit2 operation on the git repository failed.
+    #[error("git failed: {0}")]
+    Repository(#[from] git2::Error),
+
+    /// The branch a version would be archived into already exists.
+    #[error("branch '{0}' already exists, the version is likely already archived")]
+    BranchExists(String),
+
+    /// Sending an email notification could NOT be done.
+    #[error("email failed: {0}")]
+    Email(String),
+
+    /// Updating the updater itself could NOT be done.
+    #[error("self-update failed: {0}")]
+    SelfUpdate(String),
+
+    /// Uploading an archived version elsewhere could NOT be done.
+    #[error("upload failed: {0}")]
+    Upload(String),
+
+    /// Loading a signing key or signing a file failed.
+    #[error("signing failed: {0}")]
+    Signature(String),
+
+    /// The detached signature of a file does NOT match it.
+    #[error("bad signature of '{subject}': {reason}")]
+    BadSignature { subject: String, reason: String },
+
+    /// The game JAR is already listed in the archived versions data.
+    #[error("'{}' is already archived", .0.display())]
+    AlreadyArchived(PathBuf),
+
+    /// The version id could not be derived from the game JAR file name.
+    #[error("could NOT derive a version id from '{}'", .0.display())]
+    UnknownVersionId(PathBuf),
+
+    /// The archived versions data already has an entry with the same version id.
+    #[error("version '{0}' is already in the archived versions data")]
+    DuplicateVersionId(String),
+
+    /// No version with the given id is in the archived versions data.
+    #[error("version '{0}' is NOT in the archived versions data")]
+    UnknownVersion(String),
+
+    /// Reading or writing the SQLite index of the archived versions data failed.
+    #[cfg(feature = "sqlite")]
+    #[error("database failed: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    /// The archived versions data could not be serialized in the export format.
+    #[error("failed to export archived versions data: {0}")]
+    Export(String),
+
+    /// The archived versions data does NOT match its JSON Schema.
+    #[error("archived versions data does NOT match its schema, with {0} problems")]
+    InvalidManifest(usize),
+
+    /// The local archived versions data is NOT written canonically.
+    #[error("'{}' is NOT written canonically, see `manifest fmt`", .0.display())]
+    NotCanonical(PathBuf),
+
+    /// An archived version is NOT pinned to IPFS by its recorded CID.
+    #[error("{0} is NOT pinned to IPFS")]
+    NotPinned(String),
+
+    /// Staging or promoting a version failed.
+    #[error("staging failed: {0}")]
+    Stage(String),
+
+    /// The game JAR is NOT listed in the archived versions data.
+    #[error("'{}' is NOT yet archived", .0.display())]
+    Unarchived(PathBuf),
+}
+
+impl ArchiveError {
+    /// Checks whether the error may go away by retrying, e.g. an unstable connection.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Network(cause) if cause.is_redirect() => false,
+            Self::Network(cause) => cause.status().is_none_or(|status| {
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+            }),
+            Self::Itch(_) | Self::Timeout { .. } | Self::TruncatedSegment { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_only_transient_errors() {
+        assert!(ArchiveError::Timeout {
+            what: "download".to_owned(),
+            timeout: Duration::from_secs(1),
+        }
+        .is_transient());
+        assert!(ArchiveError::TruncatedSegment {
+            subject: "download".to_owned(),
+            expected: 2,
+            received: 1,
+        }
+        .is_transient());
+
+        assert!(!ArchiveError::JarNotFound.is_transient());
+        assert!(
+            !ArchiveError::AlreadyArchived(PathBuf::from("Cosmic Reach-0.1.44.jar")).is_transient()
+        );
+        assert!(!ArchiveError::Io(io::ErrorKind::NotFound.into()).is_transient());
+    }
+}
@@ -0,0 +1,249 @@
+//! Conversion of the archived versions data into the version manifests that launchers expect, or
+//! into other formats than JSON.
+
+use crate::manifest::{compare_version_ids, SCHEMA_VERSION};
+use crate::release::format_timestamp;
+use crate::{ArchiveError, Artifact, Manifest, Version, Versions};
+use serde_json::{json, Map, Value};
+use std::{fmt, str};
+
+/// Format of a version manifest of a launcher, or of the archived versions data itself.
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ExportFormat {
+    /// The one of CRLauncher, listing the client and server JARs of every version together.
+    CrLauncher,
+    /// The `version_manifest_v2.json` format of Minecraft, which many launchers derive from.
+    ///
+    /// Every type is mapped to the closest Minecraft one, and the `url` of each version is the
+    /// one of its client JAR itself rather than of a version JSON.
+    Mojang,
+    /// A table of the archived versions, one row each, as CSV for spreadsheets.
+    Csv,
+    /// The archived versions data itself, of the newest schema, as TOML.
+    Toml,
+    /// The archived versions data itself, of the newest schema, as YAML.
+    Yaml,
+}
+
+impl ExportFormat {
+    /// Every format.
+    pub const ALL: [Self; 5] = [
+        Self::CrLauncher,
+        Self::Mojang,
+        Self::Csv,
+        Self::Toml,
+        Self::Yaml,
+    ];
+
+    /// Returns the lowercase name of the format, e.g. `crlauncher`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CrLauncher => "crlauncher",
+            Self::Mojang => "mojang",
+            Self::Csv => "csv",
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for ExportFormat {
+    type Err = UnknownExportFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownExportFormat(s.to_owned()))
+    }
+}
+
+/// Error of parsing an [`ExportFormat`] from an unsupported name.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("unknown export format '{0}', expected either crlauncher, mojang, csv, toml, or yaml")]
+pub struct UnknownExportFormat(pub String);
+
+/// Converts `versions` into the version manifest of `format`, or into the archived versions data
+/// of the newest schema as is.
+pub fn export(versions: &Versions, format: ExportFormat) -> Result<Value, ArchiveError> {
+    match format {
+        ExportFormat::CrLauncher => Ok(export_crlauncher(versions)),
+        ExportFormat::Mojang => Ok(export_mojang(versions)),
+        ExportFormat::Csv => Ok(export_rows(versions)),
+        ExportFormat::Toml | ExportFormat::Yaml => Manifest {
+            schema_version: SCHEMA_VERSION,
+            versions: versions.clone(),
+        }
+        .to_value(),
+    }
+}
+
+/// Serializes the `exported` value in `format`, with a trailing newline.
+///
+/// TOML and YAML keep the structure of the JSON as is, so converting them back to JSON yields the
+/// same value.
+pub fn serialize_export(exported: &Value, format: ExportFormat) -> Result<String, ArchiveError> {
+    let mut text = match format {
+        ExportFormat::CrLauncher | ExportFormat::Mojang => serde_json::to_string_pretty(exported)?,
+        ExportFormat::Csv => to_csv(exported),
+        ExportFormat::Toml => toml::to_string_pretty(exported).map_err(export_error)?,
+        ExportFormat::Yaml => serde_yaml::to_string(exported).map_err(export_error)?,
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Columns of the CSV table of the archived versions, in order.
+const CSV_COLUMNS: [&str; 7] = [
+    "id",
+    "type",
+    "artifact",
+    "releaseTime",
+    "sha256",
+    "size",
+    "url",
+];
+
+/// Flattens every archived version into a row of [`CSV_COLUMNS`], with its release time in
+/// ISO 8601.
+fn export_rows(versions: &Versions) -> Value {
+    let rows = versions.versions.iter().map(|version| {
+        json!({
+            "id": version.id,
+            "type": version.kind,
+            "artifact": version.artifact().name(),
+            "releaseTime": format_timestamp(version.release_time),
+            "sha256": version.sha256,
+            "size": version.size,
+            "url": version.url,
+        })
+    });
+    Value::Array(rows.collect())
+}
+
+/// Writes the `rows` of [`export_rows`] as CSV with a header, quoting fields as by RFC 4180.
+fn to_csv(rows: &Value) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    for row in rows.as_array().into_iter().flatten() {
+        let fields = CSV_COLUMNS.map(|column| match &row[column] {
+            Value::String(it) => csv_field(it),
+            Value::Null => String::new(),
+            it => it.to_string(),
+        });
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn export_error(cause: impl fmt::Display) -> ArchiveError {
+    ArchiveError::Export(cause.to_string())
+}
+
+fn export_crlauncher(versions: &Versions) -> Value {
+    let mut entries = Vec::<Map<String, Value>>::new();
+    for version in &versions.versions {
+        let index = match entries.iter().position(|it| it["id"] == version.id) {
+            Some(index) => index,
+            None => {
+                let mut entry = Map::new();
+                entry.insert("id".to_owned(), json!(version.id));
+                entry.insert("type".to_owned(), json!(version.kind));
+                entry.insert("releaseTime".to_owned(), json!(version.release_time));
+                entries.push(entry);
+                entries.len() - 1
+            }
+        };
+
+        let mut file = json!({
+            "url": version.url,
+            "sha256": version.sha256,
+            "size": version.size,
+        });
+        if let Some(sha1) = &version.sha1 {
+            file["sha1"] = json!(sha1);
+        }
+        if let Some(java_version) = version.java_version {
+            file["javaVersion"] = json!(java_version);
+        }
+        entries[index].insert(version.artifact().name().to_owned(), file);
+    }
+
+    json!({
+        "latest": versions.latest,
+        "versions": entries,
+    })
+}
+
+fn export_mojang(versions: &Versions) -> Value {
+    let clients = versions
+        .versions
+        .iter()
+        .filter(|it| it.artifact() == Artifact::Client)
+        .collect::<Vec<_>>();
+    let newest = |is_release: bool| {
+        clients
+            .iter()
+            .filter(|it| (mojang_type(&it.kind) == "release") == is_release)
+            .max_by(|a, b| {
+                compare_version_ids(&a.id, &b.id).then(a.release_time.cmp(&b.release_time))
+            })
+            .map(|it| it.id.clone())
+    };
+
+    let entries = clients.iter().rev().copied().map(mojang_entry);
+    json!({
+        "latest": {
+            "release": newest(true),
+            "snapshot": newest(false),
+        },
+        "versions": entries.collect::<Vec<_>>(),
+    })
+}
+
+fn mojang_entry(version: &Version) -> Value {
+    let time = format_timestamp(version.release_time);
+    let mut entry = json!({
+        "id": version.id,
+        "type": mojang_type(&version.kind),
+        "url": version.url,
+        "time": time,
+        "releaseTime": time,
+        "complianceLevel": 0,
+    });
+    if let Some(sha1) = &version.sha1 {
+        entry["sha1"] = json!(sha1);
+    }
+    if let Some(java_version) = version.java_version {
+        entry["javaVersion"] = json!({ "majorVersion": java_version });
+    }
+    entry
+}
+
+/// Returns the closest Minecraft version type of the version type `kind`.
+fn mojang_type(kind: &str) -> &'static str {
+    match kind {
+        "pre_alpha" | "alpha" => "old_alpha",
+        "beta" => "old_beta",
+        "snapshot" => "snapshot",
+        _ => "release",
+    }
+}
@@ -0,0 +1,182 @@
+//! Git repository that versions are archived into, one branch per version.
+
+use crate::ArchiveError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Default template of the branch name a version is archived into.
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "upload-{download_id}";
+
+/// Default name and email of the author of archiving commits.
+pub const DEFAULT_AUTHOR: (&str, &str) = (
+    "github-actions[bot]",
+    "41898282+github-actions[bot]@users.noreply.github.com",
+);
+
+/// Renders the branch name of the itch.io upload `download_id` from `template`.
+///
+/// Every occurrence of `{download_id}` in `template` is replaced by `download_id`, and of
+/// `{version}` by `version`, if known.
+pub fn branch_name(template: &str, download_id: Option<u64>, version: Option<&str>) -> String {
+    let mut name = template.to_owned();
+    if let Some(download_id) = download_id {
+        name = name.replace("{download_id}", &download_id.to_string());
+    }
+    if let Some(version) = version {
+        name = name.replace("{version}", version);
+    }
+    name
+}
+
+/// Checks whether the branch name rendered from `template` needs the version id.
+pub fn needs_version(template: &str) -> bool {
+    template.contains("{version}")
+}
+
+/// Checks whether the branch name rendered from `template` needs the itch.io upload id, which is
+/// unknown when downloading from a mirror.
+pub fn needs_download_id(template: &str) -> bool {
+    template.contains("{download_id}")
+}
+
+/// Checks whether the git repository at `repo` has either a local branch named `branch`, or a
+/// remote-tracking branch of the same name from `remote`.
+pub fn branch_exists(repo: &Path, remote: &str, branch: &str) -> Result<bool, ArchiveError> {
+    let repo = git2::Repository::open(repo)?;
+
+    for reference in [
+        format!("refs/heads/{branch}"),
+        format!("refs/remotes/{remote}/{branch}"),
+    ] {
+        info!("Checking whether '{reference}' exists...");
+        match repo.find_reference(&reference) {
+            Ok(_) => return Ok(true),
+            Err(cause) if cause.code() == git2::ErrorCode::NotFound => continue,
+            Err(cause) => return Err(cause.into()),
+        }
+    }
+    Ok(false)
+}
+
+/// Deletes the local branch `branch` of the git repository at `repo`, or renames it to `rename_to`
+/// if set, keeping its commits around.
+///
+/// The branch must NOT be the one checked out.
+pub fn remove_branch(
+    repo: &Path,
+    branch: &str,
+    rename_to: Option<&str>,
+) -> Result<(), ArchiveError> {
+    let repo = git2::Repository::open(repo)?;
+    let mut local = repo.find_branch(branch, git2::BranchType::Local)?;
+    if local.is_head() {
+        return Err(ArchiveError::Git(format!(
+            "branch '{branch}' is checked out"
+        )));
+    }
+
+    match rename_to {
+        Some(rename_to) => {
+            info!("Renaming branch '{branch}' to '{rename_to}'...");
+            local.rename(rename_to, false)?;
+        }
+        None => {
+            info!("Deleting branch '{branch}'...");
+            local.delete()?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates the branch `branch` at `HEAD` of the git repository at `repo`, switches to it, and
+/// commits `files` onto it with `message` authored by `author`.
+///
+/// Every one of `files` must be within the working directory of the repository. The working
+/// directory is left untouched otherwise, as the new branch starts at the same commit.
+pub fn commit_to_new_branch(
+    repo: &Path,
+    branch: &str,
+    files: &[PathBuf],
+    message: &str,
+    author: (&str, &str),
+) -> Result<git2::Oid, ArchiveError> {
+    let repo = git2::Repository::open(repo)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| ArchiveError::Git("repository has NO working directory".to_owned()))?;
+    let workdir = fs::canonicalize(workdir)?;
+
+    let head = repo.head()?.peel_to_commit()?;
+
+    info!("Creating and switching to branch '{branch}'...");
+    let reference = format!("refs/heads/{branch}");
+    repo.branch(branch, &head, false)?;
+    repo.set_head(&reference)?;
+
+    let mut index = repo.index()?;
+    for file in files {
+        let file = fs::canonicalize(file)?;
+        let relative = file.strip_prefix(&workdir).map_err(|_| {
+            ArchiveError::Git(format!(
+                "'{}' is outside of the working directory '{}'",
+                file.display(),
+                workdir.display()
+            ))
+        })?;
+
+        info!("Staging '{}'...", relative.display());
+        index.add_path(relative)?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    info!("Committing onto branch '{branch}'...");
+    let signature = git2::Signature::now(author.0, author.1)?;
+    let commit = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &[&head],
+    )?;
+    info!("Committed {commit}");
+
+    Ok(commit)
+}
+
+/// Pushes the local branch `branch` of the git repository at `repo` to `remote`, authenticating
+/// over HTTPS with `token`.
+pub fn push_branch(
+    repo: &Path,
+    remote: &str,
+    branch: &str,
+    token: &str,
+) -> Result<(), ArchiveError> {
+    let repo = git2::Repository::open(repo)?;
+    let mut remote = repo.find_remote(remote)?;
+
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(|_url, _username, _allowed| {
+        git2::Cred::userpass_plaintext("x-access-token", token)
+    });
+    callbacks.push_update_reference(|reference, status| match status {
+        Some(message) => Err(git2::Error::from_str(&format!(
+            "remote rejected '{reference}': {message}"
+        ))),
+        None => Ok(()),
+    });
+
+    let mut options = git2::PushOptions::new();
+    options.remote_callbacks(callbacks);
+
+    info!(
+        "Pushing branch '{branch}' to '{}'...",
+        remote.name().unwrap_or("remote")
+    );
+    let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+    remote.push(&[refspec], Some(&mut options))?;
+
+    Ok(())
+}
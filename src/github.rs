@@ -0,0 +1,314 @@
+//! Publishing of archived versions as GitHub Releases and pull requests, and listing the branches
+//! and tags they are archived into, through the REST API.
+
+use crate::checksum::checksum_line;
+use crate::http::file_body;
+use crate::{ArchiveError, RetryPolicy, Version};
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::StatusCode;
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Endpoint of the GitHub REST API.
+pub const API_URL: &str = "https://api.github.com";
+
+/// Default template of the title of the pull request of an archived version.
+pub const DEFAULT_PR_TITLE: &str = "Archive {id}";
+
+/// Default template of the body of the pull request of an archived version.
+pub const DEFAULT_PR_BODY: &str = "Archives Cosmic Reach {id} ({type}), released on {release_date}.
+
+| Artifact | Size | SHA-256 |
+| --- | --- | --- |
+| {artifact} | {size} bytes | `{sha256}` |
+";
+
+/// A GitHub Release, as returned by the REST API.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+pub struct Release {
+    /// Id of the release.
+    pub id: u64,
+    /// Name of the tag of the release.
+    pub tag_name: String,
+    /// URL to the page of the release.
+    pub html_url: String,
+    /// Templated URL to upload assets of the release to.
+    pub upload_url: String,
+}
+
+/// A pull request to open.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct NewPullRequest {
+    /// Title of the pull request.
+    pub title: String,
+    /// Body of the pull request, in Markdown.
+    pub body: String,
+    /// Branch of the changes to merge.
+    pub head: String,
+    /// Branch to merge the changes into, e.g. `main`.
+    pub base: String,
+}
+
+/// A pull request, as returned by the REST API.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+pub struct PullRequest {
+    /// Number of the pull request.
+    pub number: u64,
+    /// URL to the page of the pull request.
+    pub html_url: String,
+}
+
+/// A git reference, as returned by the REST API.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+struct Reference {
+    #[serde(rename = "ref")]
+    name: String,
+}
+
+/// A file to attach to a release.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Asset {
+    /// File name of the asset.
+    pub name: String,
+    /// Media type of the asset.
+    pub content_type: &'static str,
+    /// Contents of the asset.
+    pub contents: AssetContents,
+}
+
+/// Contents of a file to attach to a release.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AssetContents {
+    /// Contents held in memory.
+    Bytes(Vec<u8>),
+    /// Local file at the path, streamed while uploading rather than read into memory.
+    File(PathBuf),
+}
+
+impl Asset {
+    /// Creates the asset of the local file at `path`.
+    pub fn from_file(path: &Path, content_type: &'static str) -> Result<Self, ArchiveError> {
+        let name = path
+            .file_name()
+            .and_then(|it| it.to_str())
+            .filter(|_| path.is_file())
+            .ok_or_else(|| ArchiveError::MissingFile(path.to_path_buf()))?;
+        Ok(Self {
+            name: name.to_owned(),
+            content_type,
+            contents: AssetContents::File(path.to_path_buf()),
+        })
+    }
+
+    /// Creates the checksum file of the game JAR of `version` named `jar_name`, in the same
+    /// format that `sha256sum` prints.
+    pub fn checksum(version: &Version, jar_name: &str) -> Self {
+        Self {
+            name: format!("{jar_name}.sha256"),
+            content_type: "text/plain",
+            contents: AssetContents::Bytes(checksum_line(version.sha256, jar_name).into_bytes()),
+        }
+    }
+}
+
+/// Attaches `assets` to the release of `repository` tagged with the version id of `version`,
+/// creating the release if absent. Returns the release.
+pub async fn publish_release(
+    client: &itch_io::Client,
+    repository: &str,
+    token: &str,
+    version: &Version,
+    assets: &[Asset],
+    retry: &RetryPolicy,
+) -> Result<Release, ArchiveError> {
+    let tag = &version.id;
+    let release = match get_release(client, repository, token, tag, retry).await? {
+        Some(it) => {
+            info!("Found existing release {tag} ({})", it.html_url);
+            it
+        }
+        None => create_release(client, repository, token, version, retry).await?,
+    };
+
+    for asset in assets {
+        upload_asset(client, &release, token, asset, retry).await?;
+    }
+
+    warn!("Published {tag} to {}", release.html_url);
+    Ok(release)
+}
+
+/// Opens `pull_request` against `repository`, returning the opened pull request.
+pub async fn create_pull_request(
+    client: &itch_io::Client,
+    repository: &str,
+    token: &str,
+    pull_request: &NewPullRequest,
+    retry: &RetryPolicy,
+) -> Result<PullRequest, ArchiveError> {
+    let url = format!("{API_URL}/repos/{repository}/pulls");
+    let url = &url;
+
+    let opened = retry
+        .retry("create GitHub pull request", || async move {
+            info!("Sending POST request to GitHub ({url})...");
+            let response = client
+                .client
+                .post(url)
+                .bearer_auth(token)
+                .header("accept", "application/vnd.github+json")
+                .json(pull_request)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>(response.json::<PullRequest>().await?)
+        })
+        .await?;
+    warn!(
+        "Opened pull request #{} ({})",
+        opened.number, opened.html_url
+    );
+    Ok(opened)
+}
+
+async fn get_release(
+    client: &itch_io::Client,
+    repository: &str,
+    token: &str,
+    tag: &str,
+    retry: &RetryPolicy,
+) -> Result<Option<Release>, ArchiveError> {
+    let url = format!("{API_URL}/repos/{repository}/releases/tags/{tag}");
+    let url = &url;
+    retry
+        .retry("get GitHub release", || async move {
+            info!("Sending GET request to GitHub ({url})...");
+            let response = client
+                .client
+                .get(url)
+                .bearer_auth(token)
+                .header("accept", "application/vnd.github+json")
+                .send()
+                .await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            Ok::<_, ArchiveError>(Some(response.error_for_status()?.json().await?))
+        })
+        .await
+}
+
+async fn create_release(
+    client: &itch_io::Client,
+    repository: &str,
+    token: &str,
+    version: &Version,
+    retry: &RetryPolicy,
+) -> Result<Release, ArchiveError> {
+    let url = format!("{API_URL}/repos/{repository}/releases");
+    let url = &url;
+    let body = serde_json::json!({
+        "tag_name": version.id,
+        "name": format!("Cosmic Reach {}", version.id),
+        "body": format!("Archived Cosmic Reach {} ({}).", version.id, version.kind),
+    });
+    let body = &body;
+
+    retry
+        .retry("create GitHub release", || async move {
+            info!("Sending POST request to GitHub ({url})...");
+            let response = client
+                .client
+                .post(url)
+                .bearer_auth(token)
+                .header("accept", "application/vnd.github+json")
+                .json(body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>(response.json().await?)
+        })
+        .await
+}
+
+async fn upload_asset(
+    client: &itch_io::Client,
+    release: &Release,
+    token: &str,
+    asset: &Asset,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    // NOTE: the upload URL ends with a `{?name,label}` URI template
+    let base = release
+        .upload_url
+        .split_once('{')
+        .map_or(release.upload_url.as_str(), |(base, _)| base);
+    let mut url = url::Url::parse(base)?;
+    url.query_pairs_mut().append_pair("name", &asset.name);
+    let url = &url;
+
+    retry
+        .retry("upload GitHub release asset", || async move {
+            let (body, size) = match &asset.contents {
+                AssetContents::Bytes(bytes) => (bytes.clone().into(), bytes.len() as u64),
+                AssetContents::File(path) => file_body(&File::open(path)?)?,
+            };
+            info!(
+                "Uploading '{}' to GitHub release {}...",
+                asset.name, release.tag_name
+            );
+            client
+                .client
+                .post(url.clone())
+                .bearer_auth(token)
+                .header("accept", "application/vnd.github+json")
+                .header("content-type", asset.content_type)
+                .header(CONTENT_LENGTH, size)
+                .body(body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>(())
+        })
+        .await
+}
+
+/// Lists the names of every branch and tag of `repository`, e.g. `upload-9309862`, authenticating
+/// with `token` if present.
+pub async fn list_refs(
+    client: &itch_io::Client,
+    repository: &str,
+    token: Option<&str>,
+    retry: &RetryPolicy,
+) -> Result<HashSet<String>, ArchiveError> {
+    let mut names = HashSet::new();
+    for prefix in ["heads", "tags"] {
+        let url = format!("{API_URL}/repos/{repository}/git/matching-refs/{prefix}/");
+        let url = &url;
+        let references = retry
+            .retry("list GitHub refs", || async move {
+                info!("Sending GET request to GitHub ({url})...");
+                let mut request = client
+                    .client
+                    .get(url)
+                    .header("accept", "application/vnd.github+json");
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                let response = request.send().await?.error_for_status()?;
+                Ok::<_, ArchiveError>(response.json::<Vec<Reference>>().await?)
+            })
+            .await?;
+
+        let full_prefix = format!("refs/{prefix}/");
+        names.extend(
+            references
+                .into_iter()
+                .filter_map(|it| it.name.strip_prefix(&full_prefix).map(String::from)),
+        );
+    }
+    info!("{repository} has {} branches and tags", names.len());
+    Ok(names)
+}
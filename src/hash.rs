@@ -0,0 +1,420 @@
+//! Calculation of game JAR hashes.
+
+use crate::ArchiveError;
+use hex::FromHexError;
+use sha2::Digest;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::path::Path;
+use std::{fmt, io, ops, str};
+use tracing::info;
+
+/// Checks whether the hash of the file at `path` is absent from `archived_hashes`.
+pub fn is_version_unarchived<P: AsRef<Path>>(
+    archived_hashes: &HashSet<Sha256Hash>,
+    path: P,
+) -> Result<bool, ArchiveError> {
+    let hash = hash_file(path)?;
+    Ok(!archived_hashes.contains(&hash))
+}
+
+/// Calculates the sha256 hash of the file at `path`.
+pub fn hash_file<P: AsRef<Path>>(path: P) -> Result<Sha256Hash, ArchiveError> {
+    let path = path.as_ref();
+
+    info!("Opening file before hash calculation...");
+    let file = File::open(path)?;
+
+    let (hash, _) = hash_reader(file)?;
+    info!("Hash of '{}': {hash}", path.display());
+
+    Ok(hash)
+}
+
+/// Calculates the sha256 hash of everything read from `reader`, along with its size in bytes.
+pub fn hash_reader<R: io::Read>(mut reader: R) -> Result<(Sha256Hash, u64), ArchiveError> {
+    let mut hasher = sha2::Sha256::new();
+
+    info!("Calculating sha256 hash...");
+    let size = io::copy(&mut reader, &mut hasher)?;
+
+    info!("Generating sha256 hash...");
+    Ok((Sha256Hash::new(hasher.finalize().into()), size))
+}
+
+/// Calculates the digests of every one of `algorithms` of everything read from `reader` in a
+/// single pass, along with its size in bytes.
+pub fn hash_reader_with<R, I>(mut reader: R, algorithms: I) -> Result<(Digests, u64), ArchiveError>
+where
+    R: io::Read,
+    I: IntoIterator<Item = HashAlgorithm>,
+{
+    let mut hasher = MultiHasher::new(algorithms);
+
+    info!("Calculating digests...");
+    let size = io::copy(&mut reader, &mut hasher)?;
+
+    Ok((hasher.finalize(), size))
+}
+
+/// A sha256 hash, (de)serialized as a lowercase hex string.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    derive_new::new,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[repr(transparent)]
+#[serde(try_from = "String", into = "String")]
+pub struct Sha256Hash {
+    inner: [u8; 32],
+}
+
+impl AsRef<[u8]> for Sha256Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl AsRef<[u8; 32]> for Sha256Hash {
+    fn as_ref(&self) -> &[u8; 32] {
+        &self.inner
+    }
+}
+
+impl AsMut<[u8; 32]> for Sha256Hash {
+    fn as_mut(&mut self) -> &mut [u8; 32] {
+        &mut self.inner
+    }
+}
+
+impl AsMut<[u8]> for Sha256Hash {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.inner
+    }
+}
+
+impl From<[u8; 32]> for Sha256Hash {
+    #[inline]
+    fn from(value: [u8; 32]) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Sha256Hash> for [u8; 32] {
+    #[inline]
+    fn from(value: Sha256Hash) -> Self {
+        value.inner
+    }
+}
+
+impl From<Sha256Hash> for String {
+    #[inline]
+    fn from(hash: Sha256Hash) -> Self {
+        hex::encode(hash)
+    }
+}
+
+impl TryFrom<String> for Sha256Hash {
+    type Error = FromHexError;
+
+    #[inline]
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let mut array = [0; 32];
+        hex::decode_to_slice(s, &mut array)?;
+        Ok(Self::new(array))
+    }
+}
+
+impl fmt::Display for Sha256Hash {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self))
+    }
+}
+
+impl ops::Deref for Sha256Hash {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl ops::DerefMut for Sha256Hash {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl str::FromStr for Sha256Hash {
+    type Err = FromHexError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut array = [0; 32];
+        hex::decode_to_slice(s, &mut array)?;
+        Ok(Self::new(array))
+    }
+}
+
+/// Algorithm of a digest calculated by [`MultiHasher`].
+#[derive(
+    Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Every supported algorithm.
+    pub const ALL: [Self; 5] = [
+        Self::Md5,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Sha512,
+        Self::Blake3,
+    ];
+
+    /// Returns the lowercase name of the algorithm, e.g. `sha256`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    /// Returns the tag of the algorithm in BSD-style checksum lines, e.g. `SHA256`.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+            Self::Blake3 => "BLAKE3",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for HashAlgorithm {
+    type Err = UnknownHashAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownHashAlgorithm(s.to_owned()))
+    }
+}
+
+/// Error of parsing a [`HashAlgorithm`] from an unsupported name.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("unknown hash algorithm '{0}', expected one of md5, sha1, sha256, sha512, or blake3")]
+pub struct UnknownHashAlgorithm(pub String);
+
+/// Digests of the same bytes, at most one per algorithm.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Digests {
+    inner: BTreeMap<HashAlgorithm, Vec<u8>>,
+}
+
+impl Digests {
+    /// Returns the digest of `algorithm`, if calculated.
+    pub fn get(&self, algorithm: HashAlgorithm) -> Option<&[u8]> {
+        self.inner.get(&algorithm).map(Vec::as_slice)
+    }
+
+    /// Returns the digest of `algorithm` as a lowercase hex string, if calculated.
+    pub fn hex(&self, algorithm: HashAlgorithm) -> Option<String> {
+        self.get(algorithm).map(hex::encode)
+    }
+
+    /// Returns the sha256 digest, if calculated.
+    pub fn sha256(&self) -> Option<Sha256Hash> {
+        self.get(HashAlgorithm::Sha256)
+            .and_then(|it| <[u8; 32]>::try_from(it).ok())
+            .map(Sha256Hash::new)
+    }
+
+    /// Iterates over every calculated digest, ordered by algorithm.
+    pub fn iter(&self) -> impl Iterator<Item = (HashAlgorithm, &[u8])> {
+        self.inner.iter().map(|(k, v)| (*k, v.as_slice()))
+    }
+}
+
+/// Hasher calculating the digests of several algorithms in a single pass over the bytes written
+/// to it.
+pub struct MultiHasher {
+    hashers: Vec<AnyHasher>,
+}
+
+impl MultiHasher {
+    /// Creates a hasher of every one of `algorithms`, ignoring duplicates.
+    pub fn new<I: IntoIterator<Item = HashAlgorithm>>(algorithms: I) -> Self {
+        let algorithms = algorithms.into_iter().collect::<BTreeSet<_>>();
+        Self {
+            hashers: algorithms.into_iter().map(AnyHasher::new).collect(),
+        }
+    }
+
+    /// Feeds `data` to the hasher of every algorithm.
+    pub fn update(&mut self, data: &[u8]) {
+        for hasher in &mut self.hashers {
+            hasher.update(data);
+        }
+    }
+
+    /// Consumes the hasher, returning the digests of every algorithm.
+    pub fn finalize(self) -> Digests {
+        Digests {
+            inner: self
+                .hashers
+                .into_iter()
+                .map(|it| (it.algorithm(), it.finalize()))
+                .collect(),
+        }
+    }
+}
+
+impl io::Write for MultiHasher {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer calculating the digests of several algorithms, along with the CRC-32 that zip archives
+/// record, of everything written through it to the inner writer, so that nothing needs to be
+/// read back to be hashed.
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: MultiHasher,
+    crc32: crc32fast::Hasher,
+    size: u64,
+}
+
+impl<W: io::Write> HashingWriter<W> {
+    /// Creates a writer into `inner` hashing with every one of `algorithms`, see
+    /// [`MultiHasher::new`].
+    pub fn new<I: IntoIterator<Item = HashAlgorithm>>(inner: W, algorithms: I) -> Self {
+        Self {
+            inner,
+            hasher: MultiHasher::new(algorithms),
+            crc32: crc32fast::Hasher::new(),
+            size: 0,
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the CRC-32 of the bytes written so far.
+    pub fn crc32(&self) -> u32 {
+        self.crc32.clone().finalize()
+    }
+
+    /// Consumes the writer, returning the inner writer and the digests of every algorithm.
+    pub fn finish(self) -> (W, Digests) {
+        (self.inner, self.hasher.finalize())
+    }
+}
+
+impl<W: io::Write> io::Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.hasher.update(&buf[..len]);
+        self.crc32.update(&buf[..len]);
+        self.size += len as u64;
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+enum AnyHasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl AnyHasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Md5 => Self::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Self::Sha1(sha1::Sha1::new()),
+            HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Sha512 => Self::Sha512(sha2::Sha512::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+        }
+    }
+
+    fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            Self::Md5(_) => HashAlgorithm::Md5,
+            Self::Sha1(_) => HashAlgorithm::Sha1,
+            Self::Sha256(_) => HashAlgorithm::Sha256,
+            Self::Sha512(_) => HashAlgorithm::Sha512,
+            Self::Blake3(_) => HashAlgorithm::Blake3,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(it) => it.update(data),
+            Self::Sha1(it) => it.update(data),
+            Self::Sha256(it) => it.update(data),
+            Self::Sha512(it) => it.update(data),
+            Self::Blake3(it) => {
+                it.update(data);
+            }
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Md5(it) => it.finalize().to_vec(),
+            Self::Sha1(it) => it.finalize().to_vec(),
+            Self::Sha256(it) => it.finalize().to_vec(),
+            Self::Sha512(it) => it.finalize().to_vec(),
+            Self::Blake3(it) => it.finalize().as_bytes().to_vec(),
+        }
+    }
+}
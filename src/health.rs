@@ -0,0 +1,58 @@
+//! Health of the updater while watching, reported at `/healthz` and in heartbeat files so that a
+//! supervisor can restart a wedged updater.
+
+use crate::atomic::write_atomically;
+use crate::metrics::{metrics, unix_now};
+use crate::ArchiveError;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// How the health of the updater is judged.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HealthCheck {
+    /// Unix timestamp, in seconds, of when watching started.
+    pub started_at: u64,
+    /// How long after the last successful poll, or the start if none, the updater is unhealthy.
+    pub stale_after: Duration,
+}
+
+/// Health of the updater at some point in time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    /// Whether the updater polled successfully recently enough.
+    pub healthy: bool,
+    /// Unix timestamp, in seconds, of when the health was checked.
+    pub checked_at: u64,
+    /// Unix timestamp, in seconds, of the last successful poll, if any.
+    pub last_poll: Option<u64>,
+    /// Unix timestamp, in seconds, of the last successful archive, if any.
+    pub last_archive: Option<u64>,
+}
+
+impl HealthCheck {
+    /// Checks the health of the updater now, from its recorded metrics.
+    pub fn check(&self) -> Health {
+        let metrics = metrics();
+        let now = unix_now();
+        let last_poll = metrics.last_poll();
+        let since = last_poll.unwrap_or(self.started_at);
+        Health {
+            healthy: now.saturating_sub(since) <= self.stale_after.as_secs(),
+            checked_at: now,
+            last_poll,
+            last_archive: metrics.last_archive(),
+        }
+    }
+}
+
+impl Health {
+    /// Writes the health as a JSON heartbeat file at `path`.
+    pub fn write_to(&self, path: &Path) -> Result<(), ArchiveError> {
+        write_atomically(path, |file| {
+            serde_json::to_writer_pretty(&mut *file, self)?;
+            Ok(file.write_all(b"\n")?)
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! The HTTP client every request is sent with.
+
+use crate::auth::ITCH_URL;
+use crate::ArchiveError;
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::{Attempt, Policy};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Seek;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// User-Agent of every request unless overridden, identifying the archiver and where to reach
+/// its maintainers.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/CRModders/CosmicArchive)"
+);
+
+/// Hosts that itch.io serves uploads from, including its CDN.
+pub const ITCH_HOSTS: [&str; 2] = ["itch.io", "itch.zone"];
+
+/// Maximum number of redirects followed by every request, as by default.
+const MAX_REDIRECTS: usize = 10;
+
+/// Options of the HTTP client.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClientOptions {
+    /// Proxy of every request, e.g. `http://proxy:8080` or `socks5h://127.0.0.1:9050`.
+    pub proxy: Option<String>,
+    /// Whether to ignore the proxies of the `HTTP_PROXY`, `HTTPS_PROXY`, and `ALL_PROXY`
+    /// environmental variables.
+    pub no_proxy: bool,
+    /// Maximum duration of connecting to a server, if any.
+    pub connect_timeout: Option<Duration>,
+    /// Maximum duration of waiting for the next read from a server, if any, so stalled
+    /// responses fail without bounding how long the whole of them may take.
+    pub read_timeout: Option<Duration>,
+    /// User-Agent of every request, defaulting to [`DEFAULT_USER_AGENT`].
+    pub user_agent: Option<String>,
+    /// Extra headers of every request, by name.
+    pub headers: BTreeMap<String, String>,
+    /// Cookies of an itch.io session as the value of a `Cookie` header, sent with every request
+    /// to itch.io and its subdomains only.
+    pub cookies: Option<String>,
+    /// Hosts other than itch.io, along with their subdomains, that requests to itch.io or to them
+    /// may be redirected to.
+    pub external_hosts: Vec<String>,
+}
+
+/// Builds the client with `options`, which is used for every request to itch.io and elsewhere.
+///
+/// The client keeps every cookie it is sent, e.g. the session cookie of an anonymous visit that a
+/// CSRF token is tied to, besides the ones of `options`.
+pub fn build_client(options: &ClientOptions) -> Result<itch_io::Client, ArchiveError> {
+    let jar = Jar::default();
+    if let Some(cookies) = &options.cookies {
+        let url = url::Url::parse(ITCH_URL)?;
+        for cookie in cookies
+            .split(';')
+            .map(str::trim)
+            .filter(|it| !it.is_empty())
+        {
+            jar.add_cookie_str(&format!("{cookie}; Domain=itch.io; Path=/"), &url);
+        }
+    }
+
+    let mut client = itch_io::Client::new();
+    client.client = client_builder(options)?
+        .cookie_provider(Arc::new(jar))
+        .build()?;
+    Ok(client)
+}
+
+/// Configures the builder of a client with `options`, except for its cookies.
+pub fn client_builder(options: &ClientOptions) -> Result<reqwest::ClientBuilder, ArchiveError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &options.headers {
+        let invalid = || ArchiveError::InvalidHeader(name.clone());
+        headers.insert(
+            HeaderName::try_from(name.as_str()).map_err(|_| invalid())?,
+            HeaderValue::try_from(value.as_str()).map_err(|_| invalid())?,
+        );
+    }
+
+    let external_hosts = options.external_hosts.clone();
+    let mut builder = reqwest::Client::builder()
+        .user_agent(options.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+        .default_headers(headers)
+        .redirect(Policy::custom(move |attempt| {
+            follow_redirect(attempt, &external_hosts)
+        }));
+    if options.no_proxy {
+        builder = builder.no_proxy();
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = options.read_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+    if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder)
+}
+
+/// Follows the redirect `attempt` unless too many were followed already, or the request started on
+/// itch.io or one of `external_hosts` and is redirected off all of them, as every hop of a
+/// download is only allowed on those.
+fn follow_redirect(attempt: Attempt, external_hosts: &[String]) -> reqwest::redirect::Action {
+    let is_allowed =
+        |url: &url::Url| is_on_hosts(url, &ITCH_HOSTS) || is_on_hosts(url, external_hosts);
+    if attempt.previous().len() >= MAX_REDIRECTS {
+        attempt.error("too many redirects")
+    } else if attempt.previous().first().is_some_and(is_allowed) && !is_allowed(attempt.url()) {
+        let host = attempt.url().host_str().unwrap_or_default().to_owned();
+        attempt.error(ArchiveError::ExternalHost(host).to_string())
+    } else {
+        attempt.follow()
+    }
+}
+
+/// Checks whether `url` is on one of `hosts` or any of their subdomains.
+pub fn is_on_hosts<S: AsRef<str>>(url: &url::Url, hosts: &[S]) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    hosts.iter().map(AsRef::as_ref).any(|it| {
+        host.eq_ignore_ascii_case(it)
+            || host
+                .to_ascii_lowercase()
+                .ends_with(&format!(".{}", it.to_ascii_lowercase()))
+    })
+}
+
+/// Returns the whole `file` as the body of a request along with its size in bytes, streamed from
+/// its start rather than read into memory. Every attempt of a retried request needs its own body.
+pub fn file_body(file: &File) -> Result<(reqwest::Body, u64), ArchiveError> {
+    let mut file = file.try_clone()?;
+    file.rewind()?;
+    let size = file.metadata()?.len();
+    Ok((tokio::fs::File::from_std(file).into(), size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_hosts_and_their_subdomains() {
+        let url = |it: &str| url::Url::parse(it).unwrap();
+        assert!(is_on_hosts(&url("https://itch.io/login"), &ITCH_HOSTS));
+        assert!(is_on_hosts(
+            &url("https://W3G3A5V6.SSL.HWCDN.NET/"),
+            &["hwcdn.net"]
+        ));
+        assert!(is_on_hosts(
+            &url("https://img.itch.zone/a.png"),
+            &ITCH_HOSTS
+        ));
+        assert!(!is_on_hosts(&url("https://notitch.io/"), &ITCH_HOSTS));
+        assert!(!is_on_hosts(
+            &url("https://itch.io.example.com/"),
+            &ITCH_HOSTS
+        ));
+        assert!(!is_on_hosts(&url("file:///itch.io"), &ITCH_HOSTS));
+    }
+}
@@ -0,0 +1,192 @@
+//! Pinning of archived game JARs to IPFS, through the RPC API of a Kubo node and optionally a
+//! remote pinning service.
+
+use crate::http::file_body;
+use crate::{ArchiveError, RetryPolicy};
+use reqwest::multipart::{Form, Part};
+use reqwest::StatusCode;
+use std::fs::File;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// RPC API of the local Kubo node used when none is given.
+pub const DEFAULT_IPFS_API: &str = "http://127.0.0.1:5001";
+
+/// An IPFS node, along with a remote pinning service that every added file is also pinned to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Ipfs {
+    /// Base URL of the RPC API of the node, e.g. `http://127.0.0.1:5001`.
+    pub api: url::Url,
+    /// Token to authenticate requests to the RPC API with, if needed.
+    pub token: Option<String>,
+    /// Remote pinning service, if any.
+    pub pinning_service: Option<PinningService>,
+}
+
+/// A remote pinning service implementing the IPFS Pinning Service API.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PinningService {
+    /// Endpoint of the API, e.g. `https://api.pinata.cloud/psa`.
+    pub endpoint: url::Url,
+    /// Token to authenticate requests with.
+    pub token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct AddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+#[derive(serde::Deserialize)]
+struct PinResults {
+    count: u64,
+}
+
+impl Ipfs {
+    /// Adds the local file at `path` as the file named `name`, streaming it rather than reading it
+    /// into memory, and pins it to the node and the pinning service if any. Returns its CID.
+    pub async fn add(
+        &self,
+        client: &itch_io::Client,
+        name: &str,
+        path: &Path,
+        retry: &RetryPolicy,
+    ) -> Result<String, ArchiveError> {
+        let url = self.rpc_url("add")?;
+        let file = File::open(path)?;
+        let (url, file) = (&url, &file);
+
+        let response = retry
+            .retry("add to IPFS", || async move {
+                info!("Adding '{name}' to IPFS ({url})...");
+                let (body, size) = file_body(file)?;
+                let part = Part::stream_with_length(body, size).file_name(name.to_owned());
+                let mut request = client
+                    .client
+                    .post(url.clone())
+                    .query(&[("pin", "true"), ("cid-version", "1")])
+                    .multipart(Form::new().part("file", part));
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                let response = request.send().await?.error_for_status()?;
+                Ok::<_, ArchiveError>(response.json::<AddResponse>().await?)
+            })
+            .await?;
+        let cid = response.hash;
+        warn!("Added '{name}' to IPFS as {cid}");
+
+        if let Some(service) = &self.pinning_service {
+            service.pin(client, &cid, name, retry).await?;
+        }
+        Ok(cid)
+    }
+
+    /// Checks whether `cid` is pinned to the pinning service if any, otherwise to the node.
+    pub async fn is_pinned(
+        &self,
+        client: &itch_io::Client,
+        cid: &str,
+        retry: &RetryPolicy,
+    ) -> Result<bool, ArchiveError> {
+        if let Some(service) = &self.pinning_service {
+            return service.is_pinned(client, cid, retry).await;
+        }
+
+        let url = self.rpc_url("pin/ls")?;
+        let url = &url;
+        retry
+            .retry("list IPFS pins", || async move {
+                info!("Checking whether {cid} is pinned to IPFS ({url})...");
+                let mut request = client
+                    .client
+                    .post(url.clone())
+                    .query(&[("arg", cid), ("type", "recursive")]);
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                let response = request.send().await?;
+
+                // NOTE: Kubo responds with an error when listing a CID that is NOT pinned
+                if response.status() == StatusCode::INTERNAL_SERVER_ERROR {
+                    let message = response.text().await?;
+                    return if message.contains("not pinned") {
+                        Ok(false)
+                    } else {
+                        Err(ArchiveError::Upload(format!("IPFS failed: {message}")))
+                    };
+                }
+                response.error_for_status()?;
+                Ok::<_, ArchiveError>(true)
+            })
+            .await
+    }
+
+    fn rpc_url(&self, command: &str) -> Result<url::Url, ArchiveError> {
+        let base = self.api.as_str().trim_end_matches('/');
+        Ok(url::Url::parse(&format!("{base}/api/v0/{command}"))?)
+    }
+}
+
+impl PinningService {
+    async fn pin(
+        &self,
+        client: &itch_io::Client,
+        cid: &str,
+        name: &str,
+        retry: &RetryPolicy,
+    ) -> Result<(), ArchiveError> {
+        let url = self.pins_url()?;
+        let body = serde_json::json!({ "cid": cid, "name": name });
+        let (url, body) = (&url, &body);
+
+        retry
+            .retry("pin to pinning service", || async move {
+                info!("Pinning {cid} to pinning service ({url})...");
+                client
+                    .client
+                    .post(url.clone())
+                    .bearer_auth(&self.token)
+                    .json(body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<_, ArchiveError>(())
+            })
+            .await?;
+        warn!("Requested pinning service to pin {cid}");
+        Ok(())
+    }
+
+    async fn is_pinned(
+        &self,
+        client: &itch_io::Client,
+        cid: &str,
+        retry: &RetryPolicy,
+    ) -> Result<bool, ArchiveError> {
+        let url = self.pins_url()?;
+        let url = &url;
+
+        let results = retry
+            .retry("list pins of pinning service", || async move {
+                info!("Checking whether {cid} is pinned to pinning service ({url})...");
+                let response = client
+                    .client
+                    .get(url.clone())
+                    .query(&[("cid", cid), ("status", "pinned")])
+                    .bearer_auth(&self.token)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<_, ArchiveError>(response.json::<PinResults>().await?)
+            })
+            .await?;
+        Ok(results.count > 0)
+    }
+
+    fn pins_url(&self) -> Result<url::Url, ArchiveError> {
+        let base = self.endpoint.as_str().trim_end_matches('/');
+        Ok(url::Url::parse(&format!("{base}/pins"))?)
+    }
+}
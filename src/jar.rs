@@ -0,0 +1,526 @@
+//! Inspection of the contents of a game JAR.
+
+use crate::atomic::write_atomically;
+use crate::classfile::{java_version, major_version, ClassFile, ConstantValue};
+use crate::natives::is_native_library;
+use crate::normalize::normalize_zip;
+use crate::ArchiveError;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Files within the game JAR that hold nothing but its version id.
+const VERSION_FILES: [&str; 2] = ["build_assets/version.txt", "version.txt"];
+
+/// Classes within the game JAR whose constants may contain its version id and build number.
+const VERSION_CLASSES: [&str; 1] = ["finalforeach/cosmicreach/RuntimeInfo.class"];
+
+/// Prefix of the classes of the game itself, as opposed to those of its bundled libraries.
+const GAME_CLASSES: &str = "finalforeach/";
+
+/// Names of the constants holding the version of the world save format, in lowercase and without
+/// underscores.
+const SAVE_VERSION_FIELDS: [&str; 4] = [
+    "saveversion",
+    "saveformatversion",
+    "worldversion",
+    "worldformatversion",
+];
+
+/// Names of the constants holding the version of the network protocol, in lowercase and without
+/// underscores.
+const PROTOCOL_VERSION_FIELDS: [&str; 3] = ["protocolversion", "networkversion", "netversion"];
+
+/// Path of the JAR manifest within the game JAR.
+const MANIFEST_FILE: &str = "META-INF/MANIFEST.MF";
+
+/// Attributes of the JAR manifest that may hold when the game JAR was built, by preference.
+const BUILD_TIME_ATTRIBUTES: [&str; 4] = [
+    "Build-Timestamp",
+    "Build-Time",
+    "Build-Date",
+    "Bnd-LastModified",
+];
+
+/// Build metadata of a game JAR, as recorded in its JAR manifest and compiled into its version
+/// class.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// The `Implementation-Version`, e.g. `0.1.44`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implementation_version: Option<String>,
+    /// The `Main-Class`, e.g. `finalforeach.cosmicreach.lwjgl3.Lwjgl3Launcher`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    /// The `Created-By`, i.e. the tool and JDK the game JAR was built with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// The `Build-Jdk-Spec` or `Build-Jdk`, i.e. the version of the JDK it was built with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_jdk: Option<String>,
+    /// When the game JAR was built, e.g. from its `Build-Timestamp` or `Bnd-LastModified`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_time: Option<String>,
+    /// The build number constant of the version class, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_number: Option<String>,
+}
+
+/// Versions of the formats a game JAR reads and writes, whose changes break compatibility with
+/// older worlds, servers, or clients.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Compatibility {
+    /// Version of the world save format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_version: Option<String>,
+    /// Version of the network protocol between clients and servers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<String>,
+}
+
+/// Constants compiled into the version class of a game JAR.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct VersionConstants {
+    /// The version id, e.g. `0.3.2a`.
+    pub version: Option<String>,
+    /// The build number, e.g. `1042`.
+    pub build_number: Option<String>,
+}
+
+impl VersionConstants {
+    /// Reads the constants of the version class file `class`, or returns [`None`] if it is
+    /// malformed.
+    ///
+    /// The version id is the constant value of a field named like `version`, or else the first
+    /// string literal, or else UTF-8 constant, that looks like one. The build number is the
+    /// constant value of a field named like `build`.
+    pub fn from_class(class: &[u8]) -> Option<Self> {
+        let class = ClassFile::parse(class)?;
+        let field_value = |needle: &str| {
+            class
+                .fields
+                .iter()
+                .filter(move |it| it.name.to_ascii_lowercase().contains(needle))
+                .filter_map(|it| it.constant_value.as_ref())
+                .map(ConstantValue::to_string)
+        };
+
+        let version = field_value("version")
+            .find(|it| is_version_id(it))
+            .or_else(|| {
+                class
+                    .string_literals()
+                    .chain(class.utf8_constants())
+                    .find(|it| is_version_id(it))
+                    .map(str::to_owned)
+            });
+        let build_number = field_value("build").find(|it| !it.is_empty());
+        Some(Self {
+            version,
+            build_number,
+        })
+    }
+}
+
+impl BuildInfo {
+    /// Collects the build metadata within the `attributes` of the main section of a JAR manifest,
+    /// none if it has none.
+    pub fn from_attributes(attributes: &BTreeMap<String, String>) -> Option<Self> {
+        let attribute = |name: &str| attributes.get(name).cloned();
+        let info = Self {
+            implementation_version: attribute("Implementation-Version"),
+            main_class: attribute("Main-Class"),
+            created_by: attribute("Created-By"),
+            build_jdk: attribute("Build-Jdk-Spec").or_else(|| attribute("Build-Jdk")),
+            build_time: BUILD_TIME_ATTRIBUTES.into_iter().find_map(attribute),
+            build_number: None,
+        };
+        (info != Self::default()).then_some(info)
+    }
+}
+
+/// Parses the attributes of the main section of the JAR `manifest`, joining continued lines.
+pub fn parse_manifest(manifest: &str) -> BTreeMap<String, String> {
+    let mut attributes = BTreeMap::<String, String>::new();
+    let mut last = None::<String>;
+    for line in manifest.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if let Some(continued) = line.strip_prefix(' ') {
+            if let Some(value) = last.as_ref().and_then(|it| attributes.get_mut(it)) {
+                value.push_str(continued);
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_owned();
+            attributes.insert(name.clone(), value.trim_start().to_owned());
+            last = Some(name);
+        }
+    }
+    attributes
+}
+
+/// Reads the build metadata of the game JAR at `path` from its JAR manifest and version class,
+/// none if it has none.
+pub fn read_build_info<P: AsRef<Path>>(path: P) -> Result<Option<BuildInfo>, ArchiveError> {
+    let file = File::open(path)?;
+    let mut jar = zip::ZipArchive::new(io::BufReader::new(file))?;
+    let mut info = match read_entry(&mut jar, MANIFEST_FILE)? {
+        Some(manifest) => {
+            let attributes = parse_manifest(&String::from_utf8_lossy(&manifest));
+            BuildInfo::from_attributes(&attributes).unwrap_or_default()
+        }
+        None => BuildInfo::default(),
+    };
+    if let Some((_, constants)) = read_version_constants(&mut jar)? {
+        info.build_number = constants.build_number;
+    }
+    Ok((info != BuildInfo::default()).then_some(info))
+}
+
+/// Reads the constants of the first version class within `jar`, along with its name.
+fn read_version_constants<R: Read + Seek>(
+    jar: &mut zip::ZipArchive<R>,
+) -> Result<Option<(&'static str, VersionConstants)>, ArchiveError> {
+    for name in VERSION_CLASSES {
+        if let Some(class) = read_entry(jar, name)? {
+            match VersionConstants::from_class(&class) {
+                Some(constants) => return Ok(Some((name, constants))),
+                None => warn!("Failed to parse '{name}' as a class file"),
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Detects the versions of the world save format and network protocol of the game JAR at `path`,
+/// none if it has neither.
+///
+/// They are the constant values of the fields of the game classes named like `SAVE_VERSION` or
+/// `PROTOCOL_VERSION`, or named `VERSION` within classes named like `SaveFormat` or `Protocol`.
+pub fn detect_compatibility<P: AsRef<Path>>(
+    path: P,
+) -> Result<Option<Compatibility>, ArchiveError> {
+    let file = File::open(path)?;
+    let mut jar = zip::ZipArchive::new(io::BufReader::new(file))?;
+
+    let names = jar
+        .file_names()
+        .filter(|it| it.starts_with(GAME_CLASSES) && it.ends_with(".class"))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    let mut compatibility = Compatibility::default();
+    for name in names {
+        let Some(class) = read_entry(&mut jar, &name)?.and_then(|it| ClassFile::parse(&it)) else {
+            warn!("Failed to parse '{name}' as a class file");
+            continue;
+        };
+        let class_name = name
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        for field in &class.fields {
+            let Some(value) = &field.constant_value else {
+                continue;
+            };
+            let field_name = field.name.to_ascii_lowercase().replace('_', "");
+            let is_version = field_name == "version";
+            let found = if SAVE_VERSION_FIELDS.contains(&field_name.as_str())
+                || (is_version && class_name.contains("save"))
+            {
+                &mut compatibility.save_version
+            } else if PROTOCOL_VERSION_FIELDS.contains(&field_name.as_str())
+                || (is_version && class_name.contains("protocol"))
+            {
+                &mut compatibility.protocol_version
+            } else {
+                continue;
+            };
+            if found.is_none() {
+                info!("Found {} = {value} in '{name}'", field.name);
+                *found = Some(value.to_string());
+            }
+        }
+    }
+
+    Ok((compatibility != Compatibility::default()).then_some(compatibility))
+}
+
+/// Detects the minimum version of Java to run the game JAR at `path` with, e.g. 17.
+///
+/// It is the newest class file format of the game classes, or else the `X-Compile-Target-JDK` or
+/// `Build-Jdk-Spec` of its JAR manifest.
+pub fn detect_java_version<P: AsRef<Path>>(path: P) -> Result<Option<u16>, ArchiveError> {
+    let file = File::open(path)?;
+    let mut jar = zip::ZipArchive::new(io::BufReader::new(file))?;
+
+    let mut newest = None;
+    for index in 0..jar.len() {
+        let mut file = jar.by_index(index)?;
+        if !file.name().starts_with(GAME_CLASSES) || !file.name().ends_with(".class") {
+            continue;
+        }
+        let mut header = [0; 8];
+        file.read_exact(&mut header)?;
+        match major_version(&header).and_then(java_version) {
+            Some(version) => newest = newest.max(Some(version)),
+            None => warn!("Failed to parse '{}' as a class file", file.name()),
+        }
+    }
+    if let Some(version) = newest {
+        info!("Found classes of Java {version}");
+        return Ok(Some(version));
+    }
+
+    let Some(manifest) = read_entry(&mut jar, MANIFEST_FILE)? else {
+        return Ok(None);
+    };
+    let attributes = parse_manifest(&String::from_utf8_lossy(&manifest));
+    let version = ["X-Compile-Target-JDK", "Build-Jdk-Spec"]
+        .into_iter()
+        .filter_map(|it| attributes.get(it))
+        .find_map(|it| {
+            // NOTE: older versions are numbered like `1.8`
+            let it = it.trim();
+            it.strip_prefix("1.").unwrap_or(it).parse::<u16>().ok()
+        });
+    if let Some(version) = version {
+        info!("Found Java {version} in the JAR manifest");
+    }
+    Ok(version)
+}
+
+/// Detects the version id of the game JAR at `path` from its contents.
+///
+/// See [`detect_version_id_in`] for where the version id is looked for.
+pub fn detect_version_id<P: AsRef<Path>>(path: P) -> Result<Option<String>, ArchiveError> {
+    let file = File::open(path)?;
+    detect_version_id_in(io::BufReader::new(file))
+}
+
+/// Detects the version id of the game JAR read from `reader` from its contents.
+///
+/// The version id is looked for in the dedicated version files, then in the constants of the
+/// runtime info class, see [`VersionConstants::from_class`], and then in the
+/// `Implementation-Version` of the JAR manifest.
+pub fn detect_version_id_in<R: Read + Seek>(reader: R) -> Result<Option<String>, ArchiveError> {
+    let mut jar = zip::ZipArchive::new(reader)?;
+
+    for name in VERSION_FILES {
+        if let Some(text) = read_entry(&mut jar, name)? {
+            let id = String::from_utf8_lossy(&text).trim().to_owned();
+            if is_version_id(&id) {
+                info!("Found version id {id} in '{name}'");
+                return Ok(Some(id));
+            }
+        }
+    }
+
+    if let Some((name, constants)) = read_version_constants(&mut jar)? {
+        if let Some(build_number) = &constants.build_number {
+            info!("Found build number {build_number} in '{name}'");
+        }
+        if let Some(id) = constants.version {
+            info!("Found version id {id} in '{name}'");
+            return Ok(Some(id));
+        }
+    }
+
+    if let Some(manifest) = read_entry(&mut jar, MANIFEST_FILE)? {
+        let mut attributes = parse_manifest(&String::from_utf8_lossy(&manifest));
+        let id = attributes
+            .remove("Implementation-Version")
+            .map(|it| it.trim().to_owned())
+            .filter(|it| is_version_id(it));
+        if let Some(id) = id {
+            info!("Found version id {id} in the JAR manifest");
+            return Ok(Some(id));
+        }
+    }
+
+    Ok(None)
+}
+
+/// A file within a game JAR, as listed in its central directory.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct JarEntry {
+    /// Path of the file within the game JAR, e.g. `finalforeach/cosmicreach/GameAssetLoader.class`.
+    pub name: String,
+    /// Uncompressed size in bytes of the file.
+    pub size: u64,
+    /// The CRC-32 checksum of the file.
+    pub crc32: u32,
+}
+
+impl JarEntry {
+    /// Checks whether the file is a class file.
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+}
+
+/// Differences between the files of two game JARs, each sorted by name.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct JarDiff {
+    /// Files only in the new game JAR.
+    pub added: Vec<JarEntry>,
+    /// Files only in the old game JAR.
+    pub removed: Vec<JarEntry>,
+    /// Files in both game JARs but with different contents, as the old and the new one.
+    pub changed: Vec<(JarEntry, JarEntry)>,
+}
+
+/// Index of every class file within the game JAR of a version, e.g. for finding the first
+/// version with a class without downloading every game JAR.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct ClassIndex {
+    /// Id of the version.
+    pub version: String,
+    /// Every class file, sorted by name.
+    pub classes: Vec<JarEntry>,
+}
+
+impl ClassIndex {
+    /// Writes the index as pretty JSON to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        write_atomically(path.as_ref(), |file| {
+            let mut file = io::BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut file, self)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Returns the path of the class index of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.classes.json`.
+pub fn class_index_path(jar: &Path) -> PathBuf {
+    jar.with_extension("classes.json")
+}
+
+/// Creates the class index of the game JAR at `jar` of version `id`.
+pub fn create_class_index(jar: &Path, id: &str) -> Result<ClassIndex, ArchiveError> {
+    info!("Indexing classes of '{}'...", jar.display());
+    let entries = list_entries(io::BufReader::new(File::open(jar)?))?;
+    Ok(ClassIndex {
+        version: id.to_owned(),
+        classes: entries.into_iter().filter(JarEntry::is_class).collect(),
+    })
+}
+
+/// Returns the path of the slim JAR of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.slim.jar`.
+pub fn slim_jar_path(jar: &Path) -> PathBuf {
+    jar.with_extension("slim.jar")
+}
+
+/// Writes the slim JAR of the game JAR at `jar` to `destination`, holding only the classes and
+/// assets of the game itself, and returns how many entries were stripped.
+///
+/// Bundled libraries are stripped by the top-level directories of their classes, along with
+/// embedded JARs, native libraries, and everything within `META-INF` but the JAR manifest. The
+/// slim JAR is re-zipped reproducibly, see [`normalize_zip`].
+pub fn create_slim_jar(jar: &Path, destination: &Path) -> Result<usize, ArchiveError> {
+    info!("Stripping bundled libraries of '{}'...", jar.display());
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(File::open(jar)?))?;
+    let library_roots = archive
+        .file_names()
+        .filter(|it| it.ends_with(".class") && !it.starts_with(GAME_CLASSES))
+        .filter_map(|it| it.split_once('/').map(|(root, _)| format!("{root}/")))
+        .collect::<BTreeSet<_>>();
+    let is_game = |name: &str| {
+        if name.starts_with("META-INF/") {
+            return name == MANIFEST_FILE;
+        }
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        !file_name.ends_with(".jar")
+            && !is_native_library(file_name)
+            && !library_roots.iter().any(|it| name.starts_with(it.as_str()))
+    };
+
+    let kept = write_atomically(destination, |file| {
+        normalize_zip(&mut archive, io::BufWriter::new(file), is_game)
+    })?;
+    Ok(archive.len() - kept)
+}
+
+/// Lists every file within the game JAR read from `reader`, sorted by name.
+///
+/// Directories are NOT listed.
+pub fn list_entries<R: Read + Seek>(reader: R) -> Result<Vec<JarEntry>, ArchiveError> {
+    let mut jar = zip::ZipArchive::new(reader)?;
+
+    let mut entries = Vec::with_capacity(jar.len());
+    for index in 0..jar.len() {
+        let file = jar.by_index_raw(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        entries.push(JarEntry {
+            name: file.name().to_owned(),
+            size: file.size(),
+            crc32: file.crc32(),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Compares the files of the game JAR `old` against `new` by their names, sizes, and CRC-32
+/// checksums.
+pub fn diff_jars<P: AsRef<Path>>(old: P, new: P) -> Result<JarDiff, ArchiveError> {
+    let old = list_entries(io::BufReader::new(File::open(old)?))?;
+    let new = list_entries(io::BufReader::new(File::open(new)?))?;
+
+    let mut old = old
+        .into_iter()
+        .map(|it| (it.name.clone(), it))
+        .collect::<BTreeMap<_, _>>();
+    let mut diff = JarDiff::default();
+    for entry in new {
+        match old.remove(&entry.name) {
+            Some(old) if old.size != entry.size || old.crc32 != entry.crc32 => {
+                diff.changed.push((old, entry));
+            }
+            Some(_) => {}
+            None => diff.added.push(entry),
+        }
+    }
+    diff.removed.extend(old.into_values());
+    Ok(diff)
+}
+
+fn read_entry<R: Read + Seek>(
+    jar: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, ArchiveError> {
+    let mut file = match jar.by_name(name) {
+        Ok(it) => it,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(cause) => return Err(cause.into()),
+    };
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Checks whether `s` looks like a version id, e.g. `0.1.44` or `0.3.2a`.
+fn is_version_id(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+    first.is_ascii_digit()
+        && s.contains('.')
+        && s.len() <= 32
+        && s.chars()
+            .all(|it| it.is_ascii_alphanumeric() || matches!(it, '.' | '-' | '_' | '+'))
+}
@@ -0,0 +1,81 @@
+//! Library behind the CosmicArchive updater.
+//!
+//! Fetches the Cosmic Reach game JAR from its itch.io page, calculates its hash, and checks it
+//! against the archived versions data of CosmicArchive.
+
+pub mod archive_org;
+pub mod atomic;
+pub mod auth;
+pub mod cache;
+pub mod checksum;
+pub mod classfile;
+pub mod classify;
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod container;
+#[cfg(feature = "sqlite")]
+pub mod db;
+pub mod delta;
+pub mod devlog;
+pub mod download;
+pub mod email;
+pub mod error;
+pub mod export;
+pub mod git;
+pub mod github;
+pub mod hash;
+pub mod health;
+pub mod http;
+pub mod ipfs;
+pub mod jar;
+pub mod manifest;
+pub mod metrics;
+pub mod natives;
+pub mod normalize;
+pub mod notify;
+pub mod pattern;
+pub mod pipeline;
+pub mod progress;
+pub mod ratelimit;
+pub mod rehost;
+pub mod release;
+pub mod retry;
+pub mod sbom;
+pub mod schema;
+pub mod server;
+pub mod sign;
+pub mod stage;
+pub mod stats;
+pub mod storage;
+pub mod temp;
+pub mod template;
+pub mod torrent;
+pub mod update;
+pub mod upstream;
+pub mod verify;
+
+pub use config::Config;
+pub use error::ArchiveError;
+pub use hash::Sha256Hash;
+pub use manifest::{Artifact, Manifest, Version, Versions};
+pub use retry::RetryPolicy;
+
+/// URL to the archived versions data of CosmicArchive.
+pub const ARCHIVED_VERSIONS_URL: &str =
+    "https://raw.githubusercontent.com/CRModders/CosmicArchive/main/versions.json";
+
+/// URL to the itch.io page of Cosmic Reach.
+pub const ITCH_GAME_URL: &str = "https://finalforeach.itch.io/cosmic-reach";
+
+/// Title of the download option containing the game JAR.
+pub const TARGET_DOWNLOAD_TITLE: &str = "cosmic-reach-jar.zip";
+
+/// Title of the download option containing the dedicated server JAR.
+pub const SERVER_DOWNLOAD_TITLE: &str = "cosmic-reach-server.zip";
+
+/// Base URL that archived game JARs are downloadable from, joined with their file names.
+pub const ARCHIVED_JARS_URL: &str = "https://github.com/CRModders/CosmicArchive/raw/main/";
+
+/// Version type of newly archived versions when none is given.
+pub const DEFAULT_VERSION_TYPE: &str = "pre_alpha";
@@ -1,420 +1,381 @@
-use hex::FromHexError;
-use itertools::Itertools;
-use log::{error, info, warn};
-use once_cell::sync::Lazy;
-use sha2::Digest;
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{stdout, Write};
-use std::path::{Path, PathBuf};
+use clap::{CommandFactory, Parser};
+#[cfg(feature = "sqlite")]
+use cosmicarchive_updater::cli::DbSyncArgs;
+use cosmicarchive_updater::cli::{
+    BackfillArgs, CacheGcArgs, ClassIndexArgs, DiffApplyArgs, DiffJarArgs, DiffPatchArgs,
+    FetchArgs, FsckArgs, GlobalArgs, HashArgs, ListArgs, LogFormat, LoginArgs, ManifestAddArgs,
+    ManifestExportArgs, ManifestFmtArgs, ManifestLintArgs, ManifestMigrateArgs, ManifestRehostArgs,
+    NativesArgs, NormalizeArgs, PromoteArgs, RollbackArgs, SbomArgs, SelfUpdateArgs, ShowArgs,
+    SlimArgs, StatsArgs, TorrentArgs, VerifyArgs, VerifySignaturesArgs, WatchArgs,
+};
+#[cfg(feature = "sqlite")]
+use cosmicarchive_updater::commands::db_sync;
+use cosmicarchive_updater::commands::{
+    cache_gc, class_index, diff_apply, diff_jar, diff_patch, fsck, hash, list, login, manifest_add,
+    manifest_export, manifest_fmt, manifest_lint, manifest_migrate, manifest_rehost, natives,
+    normalize, sbom, self_update, show, slim, stats, torrent, verify_signatures,
+};
+use cosmicarchive_updater::pipeline::{backfill, fetch, promote, rollback, verify, watch};
+use cosmicarchive_updater::{progress, ArchiveError, Config};
+use std::io::{self, IsTerminal};
 use std::process::ExitCode;
-use std::{fmt, io, ops, str};
-
-const ARCHIVED_VERSIONS_URL: &str =
-    "https://raw.githubusercontent.com/CRModders/CosmicArchive/main/versions.json";
-
-const ITCH_GAME_URL: &str = "https://finalforeach.itch.io/cosmic-reach";
-
-const TARGET_DOWNLOAD_TITLE: &str = "cosmic-reach-jar.zip";
-
-static CSRF_TOKEN: Lazy<String> = Lazy::new(|| std::env::var("CSRF_TOKEN").unwrap_or_default());
-
-#[tokio::main]
-async fn main() -> ExitCode {
-    match run().await {
-        Ok(()) => ExitCode::SUCCESS,
-        Err(()) => ExitCode::FAILURE,
-    }
+use tracing::{error, warn};
+use tracing_subscriber::EnvFilter;
+
+/// Exit codes of every command, see [`Outcome`].
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Succeeded, e.g. fetched a NOT yet archived version
+  1  Failed for any other reason
+  2  Already archived
+  3  Network request failed
+  4  Archived versions data is invalid
+  5  Extracting the game JAR failed";
+
+/// Archiver of every Cosmic Reach version
+#[derive(Debug, clap::Parser)]
+#[command(version, about, after_help = EXIT_CODES)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Command,
 }
 
-async fn run() -> Result<(), ()> {
-    env_logger::init();
-
-    if CSRF_TOKEN.is_empty() {
-        warn!("Environmental variable 'CSRF_TOKEN' is empty");
-    }
-
-    let client = itch_io::Client::new();
-    let download_id = get_jar_download_id(&client);
-    let archived_hashes = get_version_hashes(&client);
-
-    let download_id = download_id.await?;
-    // TODO: only download and check hash if git branch does not yet exist
-    let path = download_with_id(&client, download_id);
-
-    let (path, archived_hashes) = tokio::try_join!(path, archived_hashes)?;
-
-    if is_version_unarchived(&archived_hashes, &path) {
-        warn!("Printing to STDOUT the JAR path that is NOT yet archived.");
-        println!("{}", path.display());
-        Ok(())
-    } else {
-        error!("'{}' is already archived", path.display());
-        Err(())
-    }
+/// Outcome of running a command, each ending with its own exit code, see [`EXIT_CODES`].
+#[derive(Debug)]
+enum Outcome {
+    /// The command succeeded.
+    Success,
+    /// The game JAR is already archived.
+    AlreadyArchived(ArchiveError),
+    /// A network request failed, even after retrying.
+    Network(ArchiveError),
+    /// The archived versions data is invalid.
+    Manifest(ArchiveError),
+    /// Reading the downloaded archive or extracting the game JAR within failed.
+    Extraction(ArchiveError),
+    /// The command failed for any other reason.
+    Failure(ArchiveError),
 }
 
-async fn get_version_hashes(client: &itch_io::Client) -> Result<HashSet<Sha256Hash>, ()> {
-    warn!("Sending GET request to archived versions data ({ARCHIVED_VERSIONS_URL})...");
-    let versions_response = match client.client.get(ARCHIVED_VERSIONS_URL).send().await {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to send GET request for archived versions data: {cause}");
-            return Err(());
-        }
-    };
-
-    if !versions_response.status().is_success() {
-        error!(
-            "Non-success GET response status: {}",
-            versions_response.status()
-        );
-        return Err(());
-    }
-
-    info!("Reading bytes from GET response to archived versions data...");
-    let versions_bytes = match versions_response.bytes().await {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to read bytes from GET response to archived versions data: {cause}");
-            error!("This usually happens with unstable connection from either end");
-            return Err(());
-        }
-    };
-
-    info!("Deserialize received bytes as valid JSON...");
-    let versions: Versions = match serde_json::from_slice(&versions_bytes) {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to deserialize received bytes as valid JSON: {cause})");
-
-            error!("Dumping bytes to STDOUT...");
-            if let Err(cause) = stdout().write_all(&versions_bytes) {
-                error!("Failed to bump the bytes: {cause}");
-            }
-
-            return Err(());
-        }
-    };
-
-    let hashes = versions.versions.into_iter().map(|it| it.sha256).collect();
-    info!("Collected known game jar sha256 hashes");
-    for hash in &hashes {
-        info!("        {hash}");
-    }
-
-    Ok(hashes)
-}
-
-async fn get_jar_download_id(client: &itch_io::Client) -> Result<u64, ()> {
-    warn!("Getting game page data of {ITCH_GAME_URL}...");
-    let game_page = match client.get_game_page(ITCH_GAME_URL).await {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed getting game page data: {cause}");
-            return Err(());
-        }
-    };
-
-    info!("Following are available downloads:");
-    for download in &game_page.downloads {
-        info!("        {}", download.title);
-    }
-
-    let jar_download = match game_page
-        .downloads
-        .into_iter()
-        .filter(|download| matches!(download.title.as_str(), TARGET_DOWNLOAD_TITLE))
-        .at_most_one()
-    {
-        Ok(None) => {
-            error!("NO download options matched `{TARGET_DOWNLOAD_TITLE}`");
-            return Err(());
-        }
-        Ok(Some(it)) => it,
-        Err(downloads) => {
-            error!("There is more than one '{TARGET_DOWNLOAD_TITLE}':");
-            for download in downloads {
-                error!("        {download:?}");
-            }
-            return Err(());
-        }
-    };
-
-    jar_download.id.map_or_else(
-        || {
-            error!("Jar download option has NO id");
-            Err(())
-        },
-        Ok,
-    )
-}
-
-async fn download_with_id(client: &itch_io::Client, download_id: u64) -> Result<PathBuf, ()> {
-    info!("Getting download info");
-    let url = match client
-        .get_download_info(ITCH_GAME_URL, download_id, &CSRF_TOKEN)
-        .await
-    {
-        Ok(it) => it.url,
-        Err(cause) => {
-            error!("Failed getting download info: {cause}");
-            return Err(());
-        }
-    };
-
-    warn!("Sending GET request to download url ({ARCHIVED_VERSIONS_URL})...");
-    let response = match client.client.get(url).send().await {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to send GET request to download url: {cause}");
-            return Err(());
-        }
-    };
-
-    if !response.status().is_success() {
-        error!("Non-success GET response status: {}", response.status());
-        return Err(());
-    }
-
-    info!("Reading bytes from GET response to download url...");
-    let bytes = match response.bytes().await {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to read bytes from GET response to download url: {cause}");
-            error!("This usually happens with unstable connection from either end");
-            return Err(());
-        }
-    };
-
-    info!("Reading bytes as zip archive...");
-    let mut archive = match zip::ZipArchive::new(io::Cursor::new(bytes)) {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to read bytes as zip archive: {cause}");
-            return Err(());
-        }
-    };
-
-    info!("Archive contains the following files:");
-    for file_name in archive.file_names() {
-        info!("    {file_name}");
-    }
-
-    let file_name = match archive
-        .file_names()
-        .filter(|file_name| {
-            file_name.starts_with("Cosmic Reach-")
-                || Path::extension(file_name.as_ref())
-                    .map_or(false, |it| it.eq_ignore_ascii_case("jar"))
+impl Outcome {
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Self::Success => 0,
+            Self::Failure(_) => 1,
+            Self::AlreadyArchived(_) => 2,
+            Self::Network(_) => 3,
+            Self::Manifest(_) => 4,
+            Self::Extraction(_) => 5,
         })
-        .at_most_one()
-    {
-        Ok(None) => {
-            error!("Archive did NOT contain the game JAR");
-            return Err(());
-        }
-        Ok(Some(it)) => {
-            info!("Found game JAR: {it}");
-            String::from(it)
-        }
-        Err(file_names) => {
-            error!("Archived contained MULTIPLE game JARs:");
-            for file_name in file_names {
-                error!("        {file_name}");
-            }
-            return Err(());
-        }
-    };
-
-    info!("Reading archived game jar...");
-    let mut file = match archive.by_name(&file_name) {
-        Ok(it) => it,
-        Err(cause) => {
-            error!(
-                "Previously accessed archived file is no longer accessible '{file_name}': {cause}"
-            );
-            return Err(());
-        }
-    };
-
-    // NOTE: might as well stay in the safety of ZipFile::mangled_name
-    let relative_path = file.mangled_name();
-
-    info!("Creating destination game jar file if absent...");
-    let mut extracted = match File::create(&relative_path) {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to create destination game jar file: {cause}");
-            return Err(());
-        }
-    };
-
-    info!("Extracting extract game jar file...");
-    if let Err(cause) = std::io::copy(&mut file, &mut extracted) {
-        error!("Failed copy archived game jar contents to destination file: {cause}");
-        Err(())
-    } else {
-        Ok(relative_path)
     }
 }
 
-fn is_version_unarchived<P: AsRef<Path>>(archived_hashes: &HashSet<Sha256Hash>, path: P) -> bool {
-    let path = path.as_ref();
-
-    let mut hasher = sha2::Sha256::new();
+impl From<Result<(), ArchiveError>> for Outcome {
+    fn from(result: Result<(), ArchiveError>) -> Self {
+        let cause = match result {
+            Ok(()) => return Self::Success,
+            Err(it) => it,
+        };
 
-    info!("Opening file before hash calculation...");
-    let mut file = match File::open(path) {
-        Ok(it) => it,
-        Err(cause) => {
-            error!("Failed to open file for hash calculations: {cause}");
-            return false;
+        match cause {
+            ArchiveError::AlreadyArchived(_) | ArchiveError::BranchExists(_) => {
+                Self::AlreadyArchived(cause)
+            }
+            ArchiveError::Network(_)
+            | ArchiveError::Timeout { .. }
+            | ArchiveError::Itch(_)
+            | ArchiveError::ItchApi(_)
+            | ArchiveError::CorruptDownload(_)
+            | ArchiveError::UpstreamHashMismatch(_)
+            | ArchiveError::DownloadInfo { .. }
+            | ArchiveError::RangesUnsupported(_)
+            | ArchiveError::TruncatedSegment { .. }
+            | ArchiveError::NoDownloadSource => Self::Network(cause),
+            ArchiveError::ManifestParse(_)
+            | ArchiveError::UnsupportedSchema(_)
+            | ArchiveError::DuplicateVersionId(_)
+            | ArchiveError::InvalidManifest(_)
+            | ArchiveError::NotCanonical(_) => Self::Manifest(cause),
+            ArchiveError::Zip(_)
+            | ArchiveError::BrokenArchive(_)
+            | ArchiveError::SizeMismatch { .. }
+            | ArchiveError::Crc32Mismatch { .. }
+            | ArchiveError::JarNotFound
+            | ArchiveError::MultipleJars(_)
+            | ArchiveError::UnknownArchiveFormat(_)
+            | ArchiveError::UnsafeEntry(_) => Self::Extraction(cause),
+            _ => Self::Failure(cause),
         }
-    };
-
-    info!("Calculating sha256 hash...");
-    if let Err(cause) = std::io::copy(&mut file, &mut hasher) {
-        error!("Failed to calculate sha256 hash: {cause}");
-        return false;
     }
-
-    info!("Generating sha256 hash...");
-    let mut hash = Sha256Hash::default();
-    if let Err(cause) = sha2::digest::DynDigest::finalize_into(hasher, &mut hash) {
-        error!("Failed to generate sha256 hash: {cause}");
-        error!("[FATAL] This is likely a logic bug concerning incorrect byte buffer size");
-        return false;
-    };
-    info!("Game JAR hash: {hash}");
-
-    !archived_hashes.contains(&hash)
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-struct Versions {
-    pub latest: HashMap<String, String>,
-    pub versions: Vec<Version>,
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Download the latest game JAR and print its path if NOT yet archived
+    Fetch(FetchArgs),
+    /// Keep polling the itch.io game page, fetching whenever the available uploads change
+    Watch(WatchArgs),
+    /// Download every available itch.io upload, printing draft entries of the NOT yet archived
+    Backfill(BackfillArgs),
+    /// Archive staged game JARs that passed their checks, printing their paths
+    Promote(PromoteArgs),
+    /// Remove a bad entry from a local archived versions data, printing it
+    Rollback(RollbackArgs),
+    /// Audit every archived version, or check whether local game JARs are already archived
+    Verify(VerifyArgs),
+    /// Compare a local archived versions data against a local directory of game JARs
+    Fsck(FsckArgs),
+    /// Print the sha256 hashes of local files
+    Hash(HashArgs),
+    /// List archived versions as a table
+    #[command(visible_alias = "history")]
+    List(ListArgs),
+    /// Print a summary of archived versions by their id, hash, or release date
+    #[command(visible_alias = "query")]
+    Show(ShowArgs),
+    /// Print statistics of the archived versions, e.g. their release cadence and size growth
+    Stats(StatsArgs),
+    /// Log into itch.io, caching the session encrypted for `--itch-session` of later fetches
+    Login(LoginArgs),
+    /// Edit a local archived versions data file
+    Manifest {
+        #[command(subcommand)]
+        command: ManifestCommand,
+    },
+    /// Create or apply binary patches between versions
+    Diff {
+        #[command(subcommand)]
+        command: DiffCommand,
+    },
+    /// Verify the detached signatures of a local archived versions data and its game JARs
+    VerifySignatures(VerifySignaturesArgs),
+    /// Write a CycloneDX SBOM of what local game JARs bundle next to each of them
+    Sbom(SbomArgs),
+    /// Write an index of the class files of local game JARs next to each of them
+    ClassIndex(ClassIndexArgs),
+    /// Write an index of the native libraries and LWJGL modules local game JARs bundle next to
+    /// each of them
+    Natives(NativesArgs),
+    /// Write a slim JAR of local game JARs next to each of them, stripped of their bundled
+    /// libraries, printing their sha256 hashes
+    Slim(SlimArgs),
+    /// Re-zip local zip archives and JARs reproducibly in place, printing their sha256 hashes
+    Normalize(NormalizeArgs),
+    /// Write a torrent of archived game JARs, printing their magnet links
+    Torrent(TorrentArgs),
+    /// Manage the cache directory
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Manage the SQLite index of the archived versions data
+    #[cfg(feature = "sqlite")]
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// Replace this executable by the binary of the newest release of the updater if newer
+    SelfUpdate(SelfUpdateArgs),
+    /// Print the completions of SHELL
+    Completions {
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page
+    Mangen,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-struct Version {
-    pub id: String,
-    #[serde(rename = "type")]
-    pub kind: String,
-    #[serde(rename = "releaseTime")]
-    pub release_time: u64,
-    pub url: url::Url,
-    pub sha256: Sha256Hash,
-    pub size: u64,
+#[derive(Debug, clap::Subcommand)]
+enum CacheCommand {
+    /// Remove cached archives and responses that were NOT used for a while
+    Gc(CacheGcArgs),
 }
 
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    Default,
-    Eq,
-    Hash,
-    Ord,
-    PartialEq,
-    PartialOrd,
-    derive_new::new,
-    serde::Deserialize,
-    serde::Serialize,
-)]
-#[repr(transparent)]
-#[serde(try_from = "String", into = "String")]
-struct Sha256Hash {
-    inner: [u8; 32],
+#[cfg(feature = "sqlite")]
+#[derive(Debug, clap::Subcommand)]
+enum DbCommand {
+    /// Mirror a local or the remote archived versions data into a SQLite database
+    Sync(DbSyncArgs),
 }
 
-impl AsRef<[u8]> for Sha256Hash {
-    fn as_ref(&self) -> &[u8] {
-        &self.inner
-    }
+#[derive(Debug, clap::Subcommand)]
+enum ManifestCommand {
+    /// Append the entry of a game JAR, making it the latest of its type
+    Add(ManifestAddArgs),
+    /// Rewrite a local archived versions data in another schema
+    Migrate(ManifestMigrateArgs),
+    /// Rewrite a local archived versions data canonically, with its versions sorted by their
+    /// release times
+    Fmt(ManifestFmtArgs),
+    /// Validate a local or the remote archived versions data against its JSON Schema, printing
+    /// every problem found
+    Lint(ManifestLintArgs),
+    /// Print the archived versions data as the version manifest of a launcher
+    Export(ManifestExportArgs),
+    /// Print the archived versions data with every URL rewritten to a mirror
+    Rehost(ManifestRehostArgs),
 }
 
-impl AsRef<[u8; 32]> for Sha256Hash {
-    fn as_ref(&self) -> &[u8; 32] {
-        &self.inner
-    }
+#[derive(Debug, clap::Subcommand)]
+enum DiffCommand {
+    /// Create the patch from one game JAR to the next, recording it in the patches data
+    Patch(DiffPatchArgs),
+    /// Apply a patch onto a game JAR
+    Apply(DiffApplyArgs),
+    /// Print the classes and assets added, removed, or changed from one game JAR to the next
+    Jar(DiffJarArgs),
 }
 
-impl AsMut<[u8; 32]> for Sha256Hash {
-    fn as_mut(&mut self) -> &mut [u8; 32] {
-        &mut self.inner
-    }
-}
-
-impl AsMut<[u8]> for Sha256Hash {
-    fn as_mut(&mut self) -> &mut [u8] {
-        &mut self.inner
-    }
-}
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-impl From<[u8; 32]> for Sha256Hash {
-    #[inline]
-    fn from(value: [u8; 32]) -> Self {
-        Self::new(value)
+    let filter = match cli.global.log_level {
+        Some(level) => EnvFilter::default().add_directive(level.into()),
+        None => EnvFilter::from_default_env(),
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr);
+    match cli.global.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
     }
-}
 
-impl From<Sha256Hash> for [u8; 32] {
-    #[inline]
-    fn from(value: Sha256Hash) -> Self {
-        value.inner
-    }
-}
+    progress::set_enabled(!cli.global.no_progress && io::stdout().is_terminal());
 
-impl From<Sha256Hash> for String {
-    #[inline]
-    fn from(hash: Sha256Hash) -> Self {
-        hex::encode(hash)
+    let outcome = Outcome::from(run(cli).await);
+    match &outcome {
+        Outcome::Success => {}
+        Outcome::AlreadyArchived(cause) => warn!("{cause}"),
+        Outcome::Network(cause)
+        | Outcome::Manifest(cause)
+        | Outcome::Extraction(cause)
+        | Outcome::Failure(cause) => error!("{cause}"),
     }
+    outcome.exit_code()
 }
 
-impl TryFrom<String> for Sha256Hash {
-    type Error = FromHexError;
+async fn run(mut cli: Cli) -> Result<(), ArchiveError> {
+    let config = Config::load_or_default(cli.global.config.as_deref())?;
+    cli.global.merge(&config);
 
-    #[inline]
-    fn try_from(s: String) -> Result<Self, Self::Error> {
-        let mut array = [0; 32];
-        hex::decode_to_slice(s, &mut array)?;
-        Ok(Self::new(array))
-    }
-}
-
-impl fmt::Display for Sha256Hash {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&hex::encode(self))
+    match cli.command {
+        Command::Fetch(mut args) => {
+            args.merge(&config);
+            args.itch.load_session()?;
+            fetch(&cli.global, &args).await
+        }
+        Command::Watch(mut args) => {
+            args.fetch.merge(&config);
+            args.fetch.itch.load_session()?;
+            watch(&cli.global, args).await
+        }
+        Command::Backfill(mut args) => {
+            args.merge(&config);
+            args.itch.load_session()?;
+            backfill(&cli.global, args).await
+        }
+        Command::Promote(mut args) => {
+            args.fetch.merge(&config);
+            promote(&cli.global, args).await
+        }
+        Command::Rollback(mut args) => {
+            args.sign.merge(&config);
+            rollback(&cli.global, args)
+        }
+        Command::Verify(mut args) => {
+            args.ipfs_node.merge(&config);
+            verify(&cli.global, args).await
+        }
+        Command::Fsck(args) => fsck(args),
+        Command::Hash(args) => hash(args),
+        Command::List(args) => list(&cli.global, args).await,
+        Command::Show(args) => show(&cli.global, args).await,
+        Command::Stats(args) => stats(&cli.global, args).await,
+        Command::Login(args) => login(&cli.global, args).await,
+        Command::SelfUpdate(args) => self_update(&cli.global, &args).await,
+        Command::Completions { shell } => completions(shell),
+        Command::Mangen => mangen(),
+        Command::Manifest { command } => match command {
+            ManifestCommand::Add(mut args) => {
+                args.sign.merge(&config);
+                args.entry.version_types.clone_from(&config.version_types);
+                manifest_add(&cli.global, args)
+            }
+            ManifestCommand::Migrate(args) => manifest_migrate(&cli.global, args),
+            ManifestCommand::Fmt(args) => manifest_fmt(&cli.global, args),
+            ManifestCommand::Lint(args) => manifest_lint(&cli.global, args).await,
+            ManifestCommand::Export(args) => manifest_export(&cli.global, args).await,
+            ManifestCommand::Rehost(args) => manifest_rehost(&cli.global, args).await,
+        },
+        Command::Diff { command } => match command {
+            DiffCommand::Patch(args) => diff_patch(&cli.global, args),
+            DiffCommand::Apply(args) => diff_apply(&cli.global, args),
+            DiffCommand::Jar(args) => diff_jar(args),
+        },
+        Command::VerifySignatures(args) => verify_signatures(args),
+        Command::Sbom(args) => sbom(&cli.global, args),
+        Command::ClassIndex(args) => class_index(&cli.global, args),
+        Command::Natives(args) => natives(&cli.global, args),
+        Command::Slim(args) => slim(&cli.global, args),
+        Command::Normalize(args) => normalize(&cli.global, args),
+        Command::Torrent(mut args) => {
+            args.merge(&config);
+            torrent(&cli.global, args).await
+        }
+        Command::Cache { command } => match command {
+            CacheCommand::Gc(args) => cache_gc(&cli.global, args),
+        },
+        #[cfg(feature = "sqlite")]
+        Command::Db { command } => match command {
+            DbCommand::Sync(args) => db_sync(&cli.global, args).await,
+        },
     }
 }
 
-impl ops::Deref for Sha256Hash {
-    type Target = [u8];
-
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
+fn completions(shell: clap_complete::Shell) -> Result<(), ArchiveError> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_owned();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+    Ok(())
 }
 
-impl ops::DerefMut for Sha256Hash {
-    #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
-    }
+fn mangen() -> Result<(), ArchiveError> {
+    clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+    Ok(())
 }
 
-impl str::FromStr for Sha256Hash {
-    type Err = FromHexError;
-
-    #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut array = [0; 32];
-        hex::decode_to_slice(s, &mut array)?;
-        Ok(Self::new(array))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn classifies_outcomes_by_their_errors() {
+        assert!(matches!(Outcome::from(Ok(())), Outcome::Success));
+        assert!(matches!(
+            Outcome::from(Err(ArchiveError::AlreadyArchived(PathBuf::from("a.jar")))),
+            Outcome::AlreadyArchived(_)
+        ));
+        assert!(matches!(
+            Outcome::from(Err(ArchiveError::NoDownloadSource)),
+            Outcome::Network(_)
+        ));
+        assert!(matches!(
+            Outcome::from(Err(ArchiveError::UnsupportedSchema("3".to_owned()))),
+            Outcome::Manifest(_)
+        ));
+        assert!(matches!(
+            Outcome::from(Err(ArchiveError::JarNotFound)),
+            Outcome::Extraction(_)
+        ));
+        assert!(matches!(
+            Outcome::from(Err(io::Error::from(io::ErrorKind::NotFound).into())),
+            Outcome::Failure(_)
+        ));
     }
 }
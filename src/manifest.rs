@@ -0,0 +1,787 @@
+//! Archived versions data, as found in the `versions.json` of CosmicArchive.
+
+use crate::atomic::write_atomically;
+use crate::cache::{get_cached, ResponseCache};
+use crate::hash::{hash_reader_with, HashAlgorithm};
+use crate::jar::{
+    detect_compatibility, detect_java_version, read_build_info, BuildInfo, Compatibility,
+};
+use crate::release::format_date;
+use crate::{template, ArchiveError, RetryPolicy, Sha256Hash};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::{fmt, iter, str};
+use tracing::{info, warn};
+
+/// Newest schema of the archived versions data, see [`Manifest`].
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Schema of the archived versions data without a `schemaVersion`, see [`Manifest`].
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// Hashes of a version besides its sha256 hash, grouped within its `hashes` since schema 2.
+const EXTRA_HASHES: [&str; 4] = ["sha1", "sha512", "md5", "blake3"];
+
+/// Fetches and deserializes the archived versions data at `manifest_url`, revalidating the one in
+/// `cache` if present instead of downloading it again.
+pub async fn get_versions(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    manifest_url: &str,
+    retry: &RetryPolicy,
+) -> Result<Versions, ArchiveError> {
+    warn!("Sending GET request to archived versions data ({manifest_url})...");
+    let versions_bytes = get_cached(
+        client,
+        cache,
+        manifest_url,
+        "fetch archived versions data",
+        retry,
+    )
+    .await?;
+
+    info!("Deserialize received bytes as valid JSON...");
+    let manifest = Manifest::from_slice(&versions_bytes)?;
+
+    Ok(manifest.versions)
+}
+
+/// Fetches the sha256 hashes of every archived game JAR listed at `manifest_url`.
+pub async fn get_version_hashes(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    manifest_url: &str,
+    retry: &RetryPolicy,
+) -> Result<HashSet<Sha256Hash>, ArchiveError> {
+    let versions = get_versions(client, cache, manifest_url, retry).await?;
+
+    let hashes = versions.versions.into_iter().map(|it| it.sha256).collect();
+    info!("Collected known game jar sha256 hashes");
+    for hash in &hashes {
+        info!("        {hash}");
+    }
+
+    Ok(hashes)
+}
+
+/// The archived versions data along with the schema it is read and written in.
+///
+/// Schema 1 lists the extra hashes of each version next to its sha256 hash. Schema 2 records its
+/// `schemaVersion` and groups the extra hashes of each version within its `hashes`, keeping the
+/// sha256 hash and size where older consumers look for them.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Manifest {
+    /// Schema of the archived versions data, from [`LEGACY_SCHEMA_VERSION`] to [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The archived versions data itself.
+    pub versions: Versions,
+}
+
+impl Manifest {
+    /// Deserializes the archived versions data of any supported schema from `bytes`.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ArchiveError> {
+        let mut value = serde_json::from_slice::<Value>(bytes)?;
+        let schema_version = match value.get("schemaVersion") {
+            Some(it) => it
+                .as_u64()
+                .and_then(|it| u32::try_from(it).ok())
+                .ok_or_else(|| ArchiveError::UnsupportedSchema(it.to_string()))?,
+            None => LEGACY_SCHEMA_VERSION,
+        };
+        check_schema_version(schema_version)?;
+
+        for from in (LEGACY_SCHEMA_VERSION + 1..=schema_version).rev() {
+            downgrade(&mut value, from);
+        }
+        Ok(Self {
+            schema_version,
+            versions: serde_json::from_value(value)?,
+        })
+    }
+
+    /// Reads the archived versions data of any supported schema from the local file at `path`.
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        Self::from_slice(&fs::read(path)?)
+    }
+
+    /// Serializes the archived versions data in its schema.
+    pub fn to_value(&self) -> Result<Value, ArchiveError> {
+        let mut value = serde_json::to_value(&self.versions)?;
+        for to in LEGACY_SCHEMA_VERSION + 1..=self.schema_version {
+            upgrade(&mut value, to);
+        }
+        Ok(value)
+    }
+
+    /// Serializes the archived versions data in its schema as canonical JSON.
+    ///
+    /// Canonical JSON has its versions sorted, see [`Versions::sort`], its keys in the order of
+    /// the schema, or sorted where the schema has none, an indentation of two spaces, and a
+    /// trailing newline, so rewriting the same data always yields the same bytes.
+    pub fn to_canonical_bytes(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut canonical = self.clone();
+        canonical.versions.sort();
+        let mut bytes = serde_json::to_vec_pretty(&canonical.to_value()?)?;
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+
+    /// Writes the archived versions data in its schema as canonical JSON to the local file at
+    /// `path`, see [`Manifest::to_canonical_bytes`].
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        let bytes = self.to_canonical_bytes()?;
+        write_atomically(path.as_ref(), |file| Ok(file.write_all(&bytes)?))
+    }
+
+    /// Migrates the archived versions data to `schema_version`, which may be older than its
+    /// current one for the sake of older consumers.
+    pub fn migrate(&mut self, schema_version: u32) -> Result<(), ArchiveError> {
+        check_schema_version(schema_version)?;
+        if schema_version == self.schema_version {
+            info!("Archived versions data is already of schema {schema_version}");
+        } else {
+            info!(
+                "Migrating archived versions data from schema {} to {schema_version}...",
+                self.schema_version
+            );
+            self.schema_version = schema_version;
+        }
+        Ok(())
+    }
+}
+
+fn check_schema_version(schema_version: u32) -> Result<(), ArchiveError> {
+    if (LEGACY_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&schema_version) {
+        Ok(())
+    } else {
+        Err(ArchiveError::UnsupportedSchema(schema_version.to_string()))
+    }
+}
+
+/// Upgrades the serialized archived versions data of the schema before `to` to `to`.
+fn upgrade(value: &mut Value, to: u32) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    if to == 2 {
+        for version in versions_of(object) {
+            let hashes = EXTRA_HASHES
+                .into_iter()
+                .filter_map(|name| Some((name.to_owned(), version.remove(name)?)))
+                .collect::<Map<_, _>>();
+            if !hashes.is_empty() {
+                version.insert("hashes".to_owned(), Value::Object(hashes));
+            }
+        }
+    }
+
+    let mut upgraded = Map::new();
+    upgraded.insert("schemaVersion".to_owned(), Value::from(to));
+    object.remove("schemaVersion");
+    upgraded.append(object);
+    *object = upgraded;
+}
+
+/// Downgrades the serialized archived versions data of schema `from` to the one before it.
+fn downgrade(value: &mut Value, from: u32) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    if from == 2 {
+        for version in versions_of(object) {
+            if let Some(Value::Object(hashes)) = version.remove("hashes") {
+                version.extend(hashes);
+            }
+        }
+    }
+
+    if from - 1 == LEGACY_SCHEMA_VERSION {
+        object.remove("schemaVersion");
+    } else {
+        object.insert("schemaVersion".to_owned(), Value::from(from - 1));
+    }
+}
+
+fn versions_of(object: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    object
+        .get_mut("versions")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// The archived versions data.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Versions {
+    /// Latest version id of each version type.
+    pub latest: BTreeMap<String, String>,
+    /// Every archived version, in order.
+    pub versions: Vec<Version>,
+    /// Every archived file of a download option besides the game JARs, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<ArchivedFile>,
+}
+
+/// A single archived file of a download option besides the game JARs, e.g. the soundtrack.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ArchivedFile {
+    /// Name of the secondary target the file was archived as, e.g. `soundtrack`.
+    pub target: String,
+    /// Title of the download option at the time, e.g. `Cosmic Reach OST.zip`.
+    pub title: String,
+    /// Unix timestamp, in seconds, of when the file was released.
+    #[serde(rename = "releaseTime")]
+    pub release_time: u64,
+    /// Where the archived file can be downloaded from.
+    pub url: url::Url,
+    /// The sha256 hash of the archived file.
+    pub sha256: Sha256Hash,
+    /// Size in bytes of the archived file.
+    pub size: u64,
+}
+
+/// A single archived version of the game.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Version {
+    /// Identifier of the version, e.g. `0.1.44`.
+    pub id: String,
+    /// Type of the version, e.g. `pre_alpha`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Kind of the archived game JAR, a client one if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Artifact>,
+    /// Unix timestamp, in seconds, of when the version was released.
+    #[serde(rename = "releaseTime")]
+    pub release_time: u64,
+    /// Unix timestamp, in seconds, from the `Last-Modified` of the download, if recorded.
+    #[serde(
+        rename = "lastModified",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_modified: Option<u64>,
+    /// Unix timestamp, in seconds, of when the upload was last updated on itch.io, if recorded.
+    #[serde(rename = "updatedAt", default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+    /// Where the archived game JAR can be downloaded from.
+    pub url: url::Url,
+    /// The sha256 hash of the archived game JAR.
+    pub sha256: Sha256Hash,
+    /// Size in bytes of the archived game JAR, i.e. uncompressed.
+    pub size: u64,
+    /// Size in bytes of the download the game JAR was downloaded within, usually a zip archive,
+    /// if recorded.
+    #[serde(rename = "zipSize", default, skip_serializing_if = "Option::is_none")]
+    pub zip_size: Option<u64>,
+    /// Compression method of the game JAR within its zip archive, e.g. `deflated`, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Container format of the download the game JAR was downloaded within, e.g. `tar.gz`, if
+    /// NOT a zip archive.
+    #[serde(
+        rename = "archiveFormat",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub archive_format: Option<String>,
+    /// The sha1 hash of the archived game JAR, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    /// The sha512 hash of the archived game JAR, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    /// The md5 hash of the archived game JAR, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// The blake3 hash of the archived game JAR, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<String>,
+    /// CID of the archived game JAR on IPFS, if pinned there.
+    #[serde(rename = "ipfsCid", default, skip_serializing_if = "Option::is_none")]
+    pub ipfs_cid: Option<String>,
+    /// Where the archived devlog post of the version can be downloaded from, if archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub devlog: Option<url::Url>,
+    /// Build metadata from the JAR manifest of the archived game JAR, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+    /// Minimum version of Java to run the archived game JAR with, e.g. 17, if detected.
+    #[serde(
+        rename = "javaVersion",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub java_version: Option<u16>,
+    /// Versions of the world save format and network protocol of the version, if detected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<Compatibility>,
+    /// The archived slim JAR of the version, without its bundled libraries, if archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slim: Option<SlimJar>,
+}
+
+/// An archived slim JAR, holding only the classes and assets of the game itself.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SlimJar {
+    /// Where the archived slim JAR can be downloaded from.
+    pub url: url::Url,
+    /// The sha256 hash of the archived slim JAR.
+    pub sha256: Sha256Hash,
+    /// Size in bytes of the archived slim JAR.
+    pub size: u64,
+}
+
+impl Versions {
+    /// Reads the archived versions data of any supported schema from the local file at `path`.
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        Ok(Manifest::read_from(path)?.versions)
+    }
+
+    /// Appends `version`, making it the latest of its type if it is a client one newer than the
+    /// current latest of its type.
+    ///
+    /// Versions are ordered by their ids, see [`compare_version_ids`], and then by their release
+    /// times.
+    pub fn add(&mut self, version: Version) -> Result<(), ArchiveError> {
+        if self
+            .versions
+            .iter()
+            .any(|it| it.id == version.id && it.artifact() == version.artifact())
+        {
+            return Err(ArchiveError::DuplicateVersionId(version.id));
+        }
+
+        if version.artifact() == Artifact::Client {
+            self.update_latest(&version);
+        }
+        self.versions.push(version);
+        Ok(())
+    }
+
+    /// Removes version `id` of `artifact`, returning its entry.
+    ///
+    /// If it was the latest of its type, the newest remaining client version of the type becomes
+    /// the latest instead, ordered as by [`Versions::add`], or the type has none if none remain.
+    pub fn remove(&mut self, id: &str, artifact: Artifact) -> Result<Version, ArchiveError> {
+        let index = self
+            .versions
+            .iter()
+            .position(|it| it.id == id && it.artifact() == artifact)
+            .ok_or_else(|| ArchiveError::UnknownVersion(id.to_owned()))?;
+        let version = self.versions.remove(index);
+
+        if self.latest.get(&version.kind) == Some(&version.id) && artifact == Artifact::Client {
+            let newest = self
+                .versions
+                .iter()
+                .filter(|it| it.kind == version.kind && it.artifact() == Artifact::Client)
+                .max_by(|a, b| {
+                    compare_version_ids(&a.id, &b.id).then(a.release_time.cmp(&b.release_time))
+                });
+            match newest {
+                Some(newest) => {
+                    info!("Making {} the latest {} version", newest.id, newest.kind);
+                    self.latest.insert(version.kind.clone(), newest.id.clone());
+                }
+                None => {
+                    warn!("NO {} versions remain to be the latest", version.kind);
+                    self.latest.remove(&version.kind);
+                }
+            }
+        }
+        Ok(version)
+    }
+
+    /// Sorts the versions by their release times, and then by their ids, see
+    /// [`compare_version_ids`], with client versions before server ones.
+    pub fn sort(&mut self) {
+        self.versions.sort_by(|a, b| {
+            a.release_time
+                .cmp(&b.release_time)
+                .then_with(|| compare_version_ids(&a.id, &b.id))
+                .then_with(|| a.artifact().cmp(&b.artifact()))
+        });
+    }
+
+    fn update_latest(&mut self, version: &Version) {
+        let latest = self.latest.get(&version.kind).and_then(|id| {
+            self.versions
+                .iter()
+                .find(|it| &it.id == id && it.artifact() == Artifact::Client)
+        });
+        let Some(latest) = latest else {
+            self.latest.insert(version.kind.clone(), version.id.clone());
+            return;
+        };
+
+        let by_id = compare_version_ids(&version.id, &latest.id);
+        let by_release_time = version.release_time.cmp(&latest.release_time);
+        if by_id.is_ne() && by_release_time.is_ne() && by_id != by_release_time {
+            warn!(
+                "{} and the latest {} version {} are ordered differently by their ids than by their release times",
+                version.id, version.kind, latest.id
+            );
+        }
+
+        if by_id.then(by_release_time).is_gt() {
+            info!("Making {} the latest {} version", version.id, version.kind);
+            self.latest.insert(version.kind.clone(), version.id.clone());
+        } else {
+            warn!(
+                "{} is older than the latest {} version {}, which stays the latest",
+                version.id, version.kind, latest.id
+            );
+        }
+    }
+
+    /// Collects the sha256 hashes of every archived game JAR of `artifact`.
+    pub fn hashes(&self, artifact: Artifact) -> HashSet<Sha256Hash> {
+        self.versions
+            .iter()
+            .filter(|it| it.artifact() == artifact)
+            .map(|it| it.sha256)
+            .collect()
+    }
+
+    /// Appends `file`, unless a file with the same hash is already archived as its target.
+    pub fn add_file(&mut self, file: ArchivedFile) -> Result<(), ArchiveError> {
+        if self.file_hashes(&file.target).contains(&file.sha256) {
+            return Err(ArchiveError::AlreadyArchived(file.title.into()));
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// Collects the sha256 hashes of every archived file of the secondary target `target`.
+    pub fn file_hashes(&self, target: &str) -> HashSet<Sha256Hash> {
+        self.files
+            .iter()
+            .filter(|it| it.target == target)
+            .map(|it| it.sha256)
+            .collect()
+    }
+
+    /// Returns the size in bytes of the largest archived game JAR of `artifact`, if any.
+    pub fn largest_size(&self, artifact: Artifact) -> Option<u64> {
+        self.versions
+            .iter()
+            .filter(|it| it.artifact() == artifact)
+            .map(|it| it.size)
+            .max()
+    }
+}
+
+impl Version {
+    /// Creates the entry of the game JAR at `path`, calculating its size, sha256 hash, and the
+    /// `extra_hashes` to record alongside it in a single pass, and reading its build metadata,
+    /// required Java version, and format versions.
+    pub fn from_jar<P: AsRef<Path>>(
+        path: P,
+        id: String,
+        kind: String,
+        artifact: Artifact,
+        release_time: u64,
+        url: url::Url,
+        extra_hashes: &[HashAlgorithm],
+    ) -> Result<Self, ArchiveError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let algorithms = iter::once(HashAlgorithm::Sha256).chain(extra_hashes.iter().copied());
+        let (digests, size) = hash_reader_with(io::BufReader::new(file), algorithms)?;
+        let build = read_build_info(path).unwrap_or_else(|cause| {
+            warn!(
+                "Failed to read the JAR manifest of '{}': {cause}",
+                path.display()
+            );
+            None
+        });
+        let java_version = detect_java_version(path).unwrap_or_else(|cause| {
+            warn!(
+                "Failed to detect the Java version of '{}': {cause}",
+                path.display()
+            );
+            None
+        });
+        let compatibility = detect_compatibility(path).unwrap_or_else(|cause| {
+            warn!(
+                "Failed to detect the format versions of '{}': {cause}",
+                path.display()
+            );
+            None
+        });
+
+        Ok(Self {
+            id,
+            kind,
+            artifact: Some(artifact),
+            release_time,
+            last_modified: None,
+            updated_at: None,
+            url,
+            sha256: digests
+                .sha256()
+                .expect("sha256 hash should always be calculated"),
+            size,
+            zip_size: None,
+            compression: None,
+            archive_format: None,
+            sha1: digests.hex(HashAlgorithm::Sha1),
+            sha512: digests.hex(HashAlgorithm::Sha512),
+            md5: digests.hex(HashAlgorithm::Md5),
+            blake3: digests.hex(HashAlgorithm::Blake3),
+            ipfs_cid: None,
+            devlog: None,
+            build,
+            java_version,
+            compatibility,
+            slim: None,
+        })
+    }
+
+    /// Returns the kind of the archived game JAR.
+    pub fn artifact(&self) -> Artifact {
+        self.artifact.unwrap_or_default()
+    }
+
+    /// Renders `template`, replacing every `{id}`, `{type}`, `{artifact}`, `{release_time}`,
+    /// `{release_date}`, `{sha256}`, `{size}`, and `{url}` by that of the version.
+    pub fn render(&self, template: &str) -> String {
+        template::render(template, &self.variables())
+    }
+
+    /// Returns the variables of the version rendered by [`Version::render`].
+    pub fn variables(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("id", self.id.clone()),
+            ("type", self.kind.clone()),
+            ("artifact", self.artifact().name().to_owned()),
+            ("release_time", self.release_time.to_string()),
+            ("release_date", format_date(self.release_time)),
+            ("sha256", self.sha256.to_string()),
+            ("size", self.size.to_string()),
+            ("url", self.url.to_string()),
+        ]
+    }
+}
+
+/// Kind of game JAR, each archived and looked up separately.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Artifact {
+    /// The game itself.
+    #[default]
+    Client,
+    /// The dedicated server.
+    Server,
+}
+
+impl Artifact {
+    /// Every kind of game JAR.
+    pub const ALL: [Self; 2] = [Self::Client, Self::Server];
+
+    /// Returns the lowercase name of the kind, e.g. `client`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Client => "client",
+            Self::Server => "server",
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for Artifact {
+    type Err = UnknownArtifact;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownArtifact(s.to_owned()))
+    }
+}
+
+/// Error of parsing an [`Artifact`] from an unsupported name.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("unknown artifact '{0}', expected either client or server")]
+pub struct UnknownArtifact(pub String);
+
+/// Compares the version ids `a` and `b`, e.g. `0.1.9` < `0.1.31` < `0.1.31b` < `0.1.44`.
+///
+/// Ids are compared by every one of their dot-separated segments in order, each by its leading
+/// number and then by whatever follows it.
+pub fn compare_version_ids(a: &str, b: &str) -> Ordering {
+    fn segment(s: &str) -> (u64, &str) {
+        let end = s.find(|it: char| !it.is_ascii_digit()).unwrap_or(s.len());
+        (s[..end].parse().unwrap_or(0), &s[end..])
+    }
+
+    a.split('.').map(segment).cmp(b.split('.').map(segment))
+}
+
+/// Extracts the version id from a game JAR file name, e.g. `0.1.44` from `Cosmic Reach-0.1.44.jar`.
+pub fn version_id_from_file_name(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_suffix(".jar")?
+        .strip_prefix("Cosmic Reach-")
+        .filter(|it| !it.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(id: &str, release_time: u64, artifact: Artifact) -> Version {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": "pre_alpha",
+            "artifact": artifact,
+            "releaseTime": release_time,
+            "url": format!("https://example.com/Cosmic Reach-{id}.jar"),
+            "sha256": Sha256Hash::from([0; 32]),
+            "size": 1,
+        }))
+        .unwrap()
+    }
+
+    fn versions(added: &[(&str, u64, Artifact)]) -> Versions {
+        let mut versions = Versions {
+            latest: BTreeMap::new(),
+            versions: Vec::new(),
+            files: Vec::new(),
+        };
+        for &(id, release_time, artifact) in added {
+            versions.add(version(id, release_time, artifact)).unwrap();
+        }
+        versions
+    }
+
+    #[test]
+    fn compares_version_ids_by_segment() {
+        assert_eq!(compare_version_ids("0.1.9", "0.1.31"), Ordering::Less);
+        assert_eq!(compare_version_ids("0.1.31", "0.1.31b"), Ordering::Less);
+        assert_eq!(compare_version_ids("0.1.31b", "0.1.44"), Ordering::Less);
+        assert_eq!(compare_version_ids("0.2", "0.1.44"), Ordering::Greater);
+        assert_eq!(compare_version_ids("0.1", "0.1.0"), Ordering::Less);
+        assert_eq!(compare_version_ids("0.1.44", "0.1.44"), Ordering::Equal);
+    }
+
+    #[test]
+    fn reads_legacy_schema_without_schema_version() {
+        let bytes = br#"{
+            "latest": {"pre_alpha": "0.1.44"},
+            "versions": [{
+                "id": "0.1.44",
+                "type": "pre_alpha",
+                "releaseTime": 1,
+                "url": "https://example.com/Cosmic Reach-0.1.44.jar",
+                "sha256": "0000000000000000000000000000000000000000000000000000000000000000",
+                "size": 1,
+                "sha1": "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+            }]
+        }"#;
+        let manifest = Manifest::from_slice(bytes).unwrap();
+        assert_eq!(manifest.schema_version, LEGACY_SCHEMA_VERSION);
+        assert_eq!(
+            manifest.versions.versions[0].sha1.as_deref(),
+            Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+        );
+        assert_eq!(manifest.to_value().unwrap().get("schemaVersion"), None);
+    }
+
+    #[test]
+    fn groups_extra_hashes_since_schema_2() {
+        let mut version = version("0.1.44", 1, Artifact::Client);
+        version.sha1 = Some("da39a3ee5e6b4b0d3255bfef95601890afd80709".to_owned());
+        let mut versions = versions(&[]);
+        versions.add(version).unwrap();
+        let manifest = Manifest {
+            schema_version: 2,
+            versions,
+        };
+
+        let value = manifest.to_value().unwrap();
+        assert_eq!(value["schemaVersion"], 2);
+        assert_eq!(
+            value["versions"][0]["hashes"],
+            json!({"sha1": "da39a3ee5e6b4b0d3255bfef95601890afd80709"})
+        );
+        assert_eq!(value["versions"][0].get("sha1"), None);
+        assert!(value["versions"][0].get("sha256").is_some());
+
+        let bytes = serde_json::to_vec(&value).unwrap();
+        assert_eq!(Manifest::from_slice(&bytes).unwrap(), manifest);
+    }
+
+    #[test]
+    fn rejects_unsupported_schema() {
+        let bytes = br#"{"schemaVersion": 3, "latest": {}, "versions": []}"#;
+        assert!(matches!(
+            Manifest::from_slice(bytes),
+            Err(ArchiveError::UnsupportedSchema(_))
+        ));
+    }
+
+    #[test]
+    fn adds_newer_client_versions_as_latest() {
+        let mut versions = versions(&[
+            ("0.1.31", 1, Artifact::Client),
+            ("0.1.44", 2, Artifact::Client),
+            ("0.1.9", 3, Artifact::Client),
+            ("0.1.45", 4, Artifact::Server),
+        ]);
+        assert_eq!(versions.latest["pre_alpha"], "0.1.44");
+
+        assert!(matches!(
+            versions.add(version("0.1.44", 5, Artifact::Client)),
+            Err(ArchiveError::DuplicateVersionId(_))
+        ));
+        versions
+            .add(version("0.1.44", 5, Artifact::Server))
+            .unwrap();
+    }
+
+    #[test]
+    fn removes_latest_version_for_newest_remaining() {
+        let mut versions = versions(&[
+            ("0.1.31", 1, Artifact::Client),
+            ("0.1.44", 2, Artifact::Client),
+            ("0.1.45", 3, Artifact::Server),
+        ]);
+
+        versions.remove("0.1.44", Artifact::Client).unwrap();
+        assert_eq!(versions.latest["pre_alpha"], "0.1.31");
+
+        versions.remove("0.1.31", Artifact::Client).unwrap();
+        assert_eq!(versions.latest.get("pre_alpha"), None);
+
+        assert!(matches!(
+            versions.remove("0.1.45", Artifact::Client),
+            Err(ArchiveError::UnknownVersion(_))
+        ));
+    }
+}
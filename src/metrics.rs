@@ -0,0 +1,221 @@
+//! Metrics of the updater, rendered in the Prometheus text exposition format.
+//!
+//! Every metric is process-wide, recorded wherever the measured thing happens.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static METRICS: Metrics = Metrics::new();
+
+/// Returns the metrics of the process.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Counters and histograms of what the updater did.
+#[derive(Debug)]
+pub struct Metrics {
+    polls: AtomicU64,
+    failed_polls: AtomicU64,
+    last_poll: AtomicU64,
+    downloads: AtomicU64,
+    downloaded_bytes: AtomicU64,
+    hash_mismatches: AtomicU64,
+    archives: AtomicU64,
+    last_archive: AtomicU64,
+    poll_seconds: Histogram<6>,
+    download_seconds: Histogram<7>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            polls: AtomicU64::new(0),
+            failed_polls: AtomicU64::new(0),
+            last_poll: AtomicU64::new(0),
+            downloads: AtomicU64::new(0),
+            downloaded_bytes: AtomicU64::new(0),
+            hash_mismatches: AtomicU64::new(0),
+            archives: AtomicU64::new(0),
+            last_archive: AtomicU64::new(0),
+            poll_seconds: Histogram::new([0.1, 0.5, 1.0, 5.0, 15.0, 60.0]),
+            download_seconds: Histogram::new([1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 1800.0]),
+        }
+    }
+
+    /// Records a poll of the game page that took `elapsed`, failed unless `is_ok`.
+    pub fn record_poll(&self, elapsed: Duration, is_ok: bool) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        if is_ok {
+            self.last_poll.store(unix_now(), Ordering::Relaxed);
+        } else {
+            self.failed_polls.fetch_add(1, Ordering::Relaxed);
+        }
+        self.poll_seconds.observe(elapsed);
+    }
+
+    /// Records a completed download that took `elapsed`.
+    pub fn record_download(&self, elapsed: Duration) {
+        self.downloads.fetch_add(1, Ordering::Relaxed);
+        self.download_seconds.observe(elapsed);
+    }
+
+    /// Records `len` more downloaded bytes.
+    pub fn record_bytes(&self, len: u64) {
+        self.downloaded_bytes.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Records a download whose hash differs from the expected one.
+    pub fn record_hash_mismatch(&self) {
+        self.hash_mismatches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a successful archive of the available uploads, at the current time.
+    pub fn record_archive(&self) {
+        self.archives.fetch_add(1, Ordering::Relaxed);
+        self.last_archive.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// Returns the Unix timestamp, in seconds, of the last successful poll, if any.
+    pub fn last_poll(&self) -> Option<u64> {
+        Some(self.last_poll.load(Ordering::Relaxed)).filter(|&it| it > 0)
+    }
+
+    /// Returns the Unix timestamp, in seconds, of the last successful archive, if any.
+    pub fn last_archive(&self) -> Option<u64> {
+        Some(self.last_archive.load(Ordering::Relaxed)).filter(|&it| it > 0)
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        counter(
+            &mut text,
+            "cosmic_archive_polls_total",
+            "Polls of the itch.io game page.",
+            &self.polls,
+        );
+        counter(
+            &mut text,
+            "cosmic_archive_failed_polls_total",
+            "Polls of the itch.io game page that failed.",
+            &self.failed_polls,
+        );
+        counter(
+            &mut text,
+            "cosmic_archive_downloads_total",
+            "Completed downloads.",
+            &self.downloads,
+        );
+        counter(
+            &mut text,
+            "cosmic_archive_downloaded_bytes_total",
+            "Bytes transferred by downloads.",
+            &self.downloaded_bytes,
+        );
+        counter(
+            &mut text,
+            "cosmic_archive_hash_mismatches_total",
+            "Downloads whose hash differs from the expected one.",
+            &self.hash_mismatches,
+        );
+        counter(
+            &mut text,
+            "cosmic_archive_archives_total",
+            "Successful archives of the available uploads.",
+            &self.archives,
+        );
+        gauge(
+            &mut text,
+            "cosmic_archive_last_poll_timestamp_seconds",
+            "Unix timestamp of the last successful poll, zero if none.",
+            self.last_poll.load(Ordering::Relaxed),
+        );
+        gauge(
+            &mut text,
+            "cosmic_archive_last_archive_timestamp_seconds",
+            "Unix timestamp of the last successful archive, zero if none.",
+            self.last_archive.load(Ordering::Relaxed),
+        );
+        self.poll_seconds.render(
+            &mut text,
+            "cosmic_archive_poll_duration_seconds",
+            "Time taken by polls of the itch.io game page.",
+        );
+        self.download_seconds.render(
+            &mut text,
+            "cosmic_archive_download_duration_seconds",
+            "Time taken by completed downloads.",
+        );
+        text
+    }
+}
+
+/// Returns the current Unix timestamp, in seconds.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |it| it.as_secs())
+}
+
+fn counter(text: &mut String, name: &str, help: &str, value: &AtomicU64) {
+    let value = value.load(Ordering::Relaxed);
+    let _ = writeln!(
+        text,
+        "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}"
+    );
+}
+
+fn gauge(text: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(
+        text,
+        "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+    );
+}
+
+/// Histogram of durations, counted into `N` buckets of upper bounds in seconds.
+#[derive(Debug)]
+struct Histogram<const N: usize> {
+    bounds: [f64; N],
+    buckets: [AtomicU64; N],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl<const N: usize> Histogram<N> {
+    const fn new(bounds: [f64; N]) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        Self {
+            bounds,
+            buckets: [ZERO; N],
+            count: ZERO,
+            sum_micros: ZERO,
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn render(&self, text: &mut String, name: &str, help: &str) {
+        let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} histogram");
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            let value = bucket.load(Ordering::Relaxed);
+            let _ = writeln!(text, "{name}_bucket{{le=\"{bound}\"}} {value}");
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(text, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(text, "{name}_sum {sum}\n{name}_count {count}");
+    }
+}
@@ -0,0 +1,247 @@
+//! Indices of the native libraries and LWJGL modules bundled inside a game JAR, tracking when its
+//! platform support or LWJGL version changes.
+
+use crate::atomic::write_atomically;
+use crate::classfile::{ClassFile, ConstantValue};
+use crate::hash::hash_reader;
+use crate::{ArchiveError, Sha256Hash};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Class of LWJGL holding its version as constants.
+const LWJGL_VERSION_CLASS: &str = "org/lwjgl/Version.class";
+
+/// Index of what a game JAR bundles to run on each platform.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NativesIndex {
+    /// Version id of the game JAR.
+    pub version: String,
+    /// Version of the bundled LWJGL, e.g. `3.3.3`, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lwjgl_version: Option<String>,
+    /// Every bundled LWJGL module, sorted by name.
+    pub lwjgl_modules: Vec<LwjglModule>,
+    /// Every bundled native library, sorted by path.
+    pub libraries: Vec<NativeLibrary>,
+}
+
+/// A single LWJGL module bundled inside a game JAR, e.g. `lwjgl-glfw`.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct LwjglModule {
+    /// Name of the module, e.g. `lwjgl-glfw`.
+    pub name: String,
+    /// Version of the module, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Where inside the game JAR the module was found.
+    pub location: String,
+}
+
+/// A single native library bundled inside a game JAR.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct NativeLibrary {
+    /// Path of the library within the game JAR, e.g. `linux/x64/org/lwjgl/liblwjgl.so`.
+    pub path: String,
+    /// Operating system the library is built for, e.g. `linux`, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<&'static str>,
+    /// CPU architecture the library is built for, e.g. `x64`, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<&'static str>,
+    /// Size in bytes of the library.
+    pub size: u64,
+    /// The sha256 hash of the library.
+    pub sha256: Sha256Hash,
+}
+
+impl NativesIndex {
+    /// Writes the index as pretty JSON to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        write_atomically(path.as_ref(), |file| {
+            let mut file = io::BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut file, self)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Returns the path of the natives index of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.natives.json`.
+pub fn natives_path(jar: &Path) -> PathBuf {
+    jar.with_extension("natives.json")
+}
+
+/// Creates the natives index of the game JAR at `jar` of version `id`.
+///
+/// See [`scan_natives`] for what is listed as bundled.
+pub fn create_natives_index(jar: &Path, id: &str) -> Result<NativesIndex, ArchiveError> {
+    info!("Scanning '{}' for native libraries...", jar.display());
+    let mut index = scan_natives(io::BufReader::new(File::open(jar)?))?;
+    index.version = id.to_owned();
+    Ok(index)
+}
+
+/// Lists the native libraries and LWJGL modules bundled inside the game JAR read from `reader`,
+/// leaving its version id empty.
+///
+/// Native libraries are the `.dll`, `.so`, `.dylib`, and `.jnilib` files. LWJGL modules are listed
+/// from their `pom.properties` within `META-INF/maven/org.lwjgl`, their embedded JARs, or else the
+/// directories of their natives. The LWJGL version is read from the constants of its `Version`
+/// class.
+pub fn scan_natives<R: Read + Seek>(reader: R) -> Result<NativesIndex, ArchiveError> {
+    let mut jar = zip::ZipArchive::new(reader)?;
+
+    let mut lwjgl_version = None;
+    let mut modules = BTreeMap::<String, LwjglModule>::new();
+    let mut libraries = Vec::new();
+    for index in 0..jar.len() {
+        let mut file = jar.by_index(index)?;
+        let name = file.name().to_owned();
+        let file_name = name.rsplit('/').next().unwrap_or(&name);
+
+        if name == LWJGL_VERSION_CLASS {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            lwjgl_version = ClassFile::parse(&bytes).and_then(|it| class_lwjgl_version(&it));
+        } else if name.starts_with("META-INF/maven/org.lwjgl/") && name.ends_with("/pom.properties")
+        {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+            let property = |key: &str| {
+                text.lines()
+                    .filter_map(|it| it.split_once('='))
+                    .find(|(it, _)| it.trim() == key)
+                    .map(|(_, value)| value.trim().to_owned())
+            };
+            if let Some(artifact) = property("artifactId") {
+                let module = LwjglModule {
+                    name: artifact.clone(),
+                    version: property("version"),
+                    location: name.clone(),
+                };
+                modules.insert(artifact, module);
+            }
+        } else if file_name.starts_with("lwjgl") && file_name.ends_with(".jar") {
+            let (module, version) = split_jar_name(file_name);
+            modules.entry(module.clone()).or_insert(LwjglModule {
+                name: module,
+                version,
+                location: name.clone(),
+            });
+        } else if is_native_library(file_name) {
+            let size = file.size();
+            let (sha256, _) = hash_reader(&mut file)?;
+            if let Some(module) = lwjgl_native_module(&name, file_name) {
+                modules.entry(module.clone()).or_insert(LwjglModule {
+                    name: module,
+                    version: None,
+                    location: name.clone(),
+                });
+            }
+            libraries.push(NativeLibrary {
+                os: native_os(&name),
+                arch: native_arch(&name),
+                path: name,
+                size,
+                sha256,
+            });
+        }
+    }
+    libraries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(NativesIndex {
+        version: String::new(),
+        lwjgl_version,
+        lwjgl_modules: modules.into_values().collect(),
+        libraries,
+    })
+}
+
+/// Returns the version of LWJGL from the constants of its `Version` class, e.g. `3.3.3`.
+fn class_lwjgl_version(class: &ClassFile) -> Option<String> {
+    let constant = |name: &str| {
+        class
+            .fields
+            .iter()
+            .find(|it| it.name == name)
+            .and_then(|it| it.constant_value.as_ref())
+            .and_then(|it| match it {
+                ConstantValue::Integer(it) => Some(*it),
+                _ => None,
+            })
+    };
+    let major = constant("VERSION_MAJOR")?;
+    let minor = constant("VERSION_MINOR")?;
+    let revision = constant("VERSION_REVISION")?;
+    Some(format!("{major}.{minor}.{revision}"))
+}
+
+/// Splits the file name of an embedded JAR, e.g. `lwjgl-glfw-3.3.3-natives-linux.jar`, into its
+/// module name and version, if any.
+fn split_jar_name(file_name: &str) -> (String, Option<String>) {
+    let stem = file_name.strip_suffix(".jar").unwrap_or(file_name);
+    let stem = stem.split("-natives").next().unwrap_or(stem);
+    let version_start = stem
+        .match_indices('-')
+        .find(|(index, _)| stem[index + 1..].starts_with(|it: char| it.is_ascii_digit()))
+        .map(|(index, _)| index);
+    match version_start {
+        Some(index) => (stem[..index].to_owned(), Some(stem[index + 1..].to_owned())),
+        None => (stem.to_owned(), None),
+    }
+}
+
+/// Returns the LWJGL module of the native library at `path`, e.g. `lwjgl-glfw` of
+/// `linux/x64/org/lwjgl/glfw/libglfw.so`.
+fn lwjgl_native_module(path: &str, file_name: &str) -> Option<String> {
+    let directory = path.strip_suffix(file_name)?.trim_end_matches('/');
+    let (_, module) = directory.split_once("org/lwjgl")?;
+    match module.trim_start_matches('/') {
+        "" => Some("lwjgl".to_owned()),
+        module => Some(format!("lwjgl-{}", module.replace('/', "-"))),
+    }
+}
+
+/// Returns whether the file `file_name` is a native library, by its extension.
+pub fn is_native_library(file_name: &str) -> bool {
+    [".dll", ".so", ".dylib", ".jnilib"]
+        .iter()
+        .any(|it| file_name.ends_with(it))
+}
+
+/// Returns the operating system of the native library at `path`, from its extension.
+fn native_os(path: &str) -> Option<&'static str> {
+    if path.ends_with(".dll") {
+        Some("windows")
+    } else if path.ends_with(".dylib") || path.ends_with(".jnilib") {
+        Some("macos")
+    } else if path.ends_with(".so") {
+        Some("linux")
+    } else {
+        None
+    }
+}
+
+/// Returns the CPU architecture of the native library at `path`, from its directories or name,
+/// e.g. `x64` of `linux/x64/org/lwjgl/liblwjgl.so` or of `libgdx64.so`.
+fn native_arch(path: &str) -> Option<&'static str> {
+    let path = path.to_ascii_lowercase();
+    if path.contains("arm64") || path.contains("aarch64") {
+        Some("arm64")
+    } else if path.contains("arm32") || path.contains("arm") {
+        Some("arm32")
+    } else if path.contains("x64") || path.contains("x86_64") || path.contains("amd64") {
+        Some("x64")
+    } else if path.contains("x86") {
+        Some("x86")
+    } else {
+        let stem = path.rsplit('/').next()?.split('.').next()?;
+        Some(if stem.ends_with("64") { "x64" } else { "x86" })
+    }
+}
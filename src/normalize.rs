@@ -0,0 +1,107 @@
+//! Reproducible re-zipping of archives, so that re-running the updater on the same upstream bytes
+//! yields byte-identical outputs.
+//!
+//! Normalized archives have their entries sorted by name, with the JAR manifest first as Java
+//! expects, every timestamp set to the earliest one zip archives support, and fixed permissions.
+
+use crate::atomic::write_atomically;
+use crate::{temp, ArchiveError};
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use tracing::info;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+/// Unix permissions of every normalized file.
+const FILE_PERMISSIONS: u32 = 0o644;
+
+/// Unix permissions of every normalized directory.
+const DIRECTORY_PERMISSIONS: u32 = 0o755;
+
+/// Re-zips the entries of `archive` for which `keep` holds into `writer`, and returns how many
+/// were kept.
+///
+/// Stored entries are stored again, while every other one is deflated anew.
+pub fn normalize_zip<R, W, F>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    keep: F,
+) -> Result<usize, ArchiveError>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    F: Fn(&str) -> bool,
+{
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let name = archive.by_index_raw(index)?.name().to_owned();
+        if keep(&name) {
+            entries.push((index, name));
+        }
+    }
+    entries.sort_by(|(_, a), (_, b)| entry_order(a).cmp(&entry_order(b)));
+
+    let mut writer = ZipWriter::new(writer);
+    for (index, name) in &entries {
+        let mut entry = archive.by_index(*index)?;
+        let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+        if entry.is_dir() {
+            writer.add_directory(
+                name.as_str(),
+                options.unix_permissions(DIRECTORY_PERMISSIONS),
+            )?;
+            continue;
+        }
+        let method = match entry.compression() {
+            CompressionMethod::Stored => CompressionMethod::Stored,
+            _ => CompressionMethod::Deflated,
+        };
+        let options = options
+            .compression_method(method)
+            .unix_permissions(FILE_PERMISSIONS)
+            .large_file(entry.size() >= u64::from(u32::MAX));
+        writer.start_file(name.as_str(), options)?;
+        io::copy(&mut entry, &mut writer)?;
+    }
+    writer.finish()?.flush()?;
+    Ok(entries.len())
+}
+
+/// Re-zips the archive `bytes` in memory, see [`normalize_zip`].
+pub fn normalize_zip_bytes(bytes: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = ZipArchive::new(io::Cursor::new(bytes))?;
+    let mut normalized = io::Cursor::new(Vec::with_capacity(bytes.len()));
+    normalize_zip(&mut archive, &mut normalized, |_| true)?;
+    Ok(normalized.into_inner())
+}
+
+/// Re-zips the archive `file` into an anonymous temporary file within `temp_dir` rather than
+/// memory, see [`temp::tempfile`], returning it rewound to its start.
+pub fn normalize_zip_file(file: &mut File, temp_dir: &Path) -> Result<File, ArchiveError> {
+    file.rewind()?;
+    let mut archive = ZipArchive::new(io::BufReader::new(file))?;
+    let mut normalized = temp::tempfile(temp_dir)?;
+    normalize_zip(&mut archive, io::BufWriter::new(&mut normalized), |_| true)?;
+    normalized.rewind()?;
+    Ok(normalized)
+}
+
+/// Re-zips the local archive at `path` in place, see [`normalize_zip`], and returns how many
+/// entries it has.
+pub fn normalize_file(path: &Path) -> Result<usize, ArchiveError> {
+    info!("Normalizing '{}'...", path.display());
+    let mut archive = ZipArchive::new(io::BufReader::new(File::open(path)?))?;
+    write_atomically(path, |file| {
+        normalize_zip(&mut archive, io::BufWriter::new(file), |_| true)
+    })
+}
+
+/// Returns the sort key of the entry `name`, placing `META-INF/` and its JAR manifest first.
+fn entry_order(name: &str) -> (u8, &str) {
+    match name {
+        "META-INF/" => (0, name),
+        "META-INF/MANIFEST.MF" => (1, name),
+        _ => (2, name),
+    }
+}
@@ -0,0 +1,270 @@
+//! Notifications of newly detected or archived versions.
+
+use crate::release::format_timestamp;
+use crate::{template, ArchiveError, Artifact, RetryPolicy, Sha256Hash};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::Rng;
+use tracing::{info, warn};
+
+/// A game JAR that is NOT yet archived, or was just archived.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Notification {
+    /// Kind of the game JAR.
+    pub artifact: Artifact,
+    /// Version id of the game JAR, if known.
+    pub id: Option<String>,
+    /// The sha256 hash of the game JAR.
+    pub sha256: Sha256Hash,
+    /// Size in bytes of the game JAR.
+    pub size: u64,
+    /// Unix timestamp, in seconds, of when the version was released, if known.
+    pub release_time: Option<u64>,
+    /// Whether the game JAR was archived, rather than only detected.
+    pub archived: bool,
+    /// Where the archived game JAR can be downloaded from, if archived.
+    pub url: Option<url::Url>,
+}
+
+/// A Matrix room to post notifications to, through the client-server API.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MatrixRoom {
+    /// Base URL of the homeserver, e.g. `https://matrix.org`.
+    pub homeserver: url::Url,
+    /// Access token of the account posting.
+    pub access_token: String,
+    /// Id of the room, e.g. `!abcdefghijklmnop:matrix.org`.
+    pub room_id: String,
+    /// Template of the plain text message, see [`Notification::render`], if other than the
+    /// default one.
+    pub template: Option<String>,
+}
+
+/// A Mastodon account to post statuses with, through the API of its instance.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MastodonAccount {
+    /// Base URL of the instance, e.g. `https://mastodon.social`.
+    pub instance: url::Url,
+    /// Access token of the account, with the `write:statuses` scope.
+    pub access_token: String,
+    /// Visibility of every status, e.g. `public` or `unlisted`.
+    pub visibility: String,
+    /// Template of every status, see [`Notification::render`], if other than the default one.
+    pub template: Option<String>,
+}
+
+impl Notification {
+    /// Returns the title of the notification, e.g. `Archived Cosmic Reach 0.1.44`.
+    pub fn title(&self) -> String {
+        let id = self.id.as_deref().unwrap_or("unknown");
+        match (self.artifact, self.archived) {
+            (Artifact::Client, true) => format!("Archived Cosmic Reach {id}"),
+            (Artifact::Client, false) => format!("New Cosmic Reach {id}"),
+            (Artifact::Server, true) => format!("Archived Cosmic Reach server {id}"),
+            (Artifact::Server, false) => format!("New Cosmic Reach server {id}"),
+        }
+    }
+
+    /// Returns the date of the release of the version, e.g. `2024-08-10T12:00:00Z`, if known.
+    pub fn release_date(&self) -> Option<String> {
+        self.release_time.map(format_timestamp)
+    }
+
+    /// Renders `template`, replacing every `{title}`, `{id}`, `{artifact}`, `{release_time}`,
+    /// `{release_date}`, `{sha256}`, `{size}`, and `{url}` by that of the notification, the
+    /// unknown ones being `unknown`.
+    pub fn render(&self, template: &str) -> String {
+        template::render(template, &self.variables())
+    }
+
+    fn variables(&self) -> Vec<(&'static str, String)> {
+        let unknown = || "unknown".to_owned();
+        vec![
+            ("title", self.title()),
+            ("id", self.id.clone().unwrap_or_else(unknown)),
+            ("artifact", self.artifact.name().to_owned()),
+            (
+                "release_time",
+                self.release_time.map_or_else(unknown, |it| it.to_string()),
+            ),
+            ("release_date", self.release_date().unwrap_or_else(unknown)),
+            ("sha256", self.sha256.to_string()),
+            ("size", self.size.to_string()),
+            (
+                "url",
+                self.url.as_ref().map_or_else(unknown, |it| it.to_string()),
+            ),
+        ]
+    }
+}
+
+/// Posts `notification` as an embed to the Discord webhook at `webhook_url`, or as the JSON
+/// payload rendered from `template` if given, see [`Notification::render`].
+pub async fn notify_discord(
+    client: &itch_io::Client,
+    webhook_url: &str,
+    template: Option<&str>,
+    notification: &Notification,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let body = match template {
+        Some(template) => {
+            let payload = template::render_json(template, &notification.variables());
+            serde_json::from_str(&payload).map_err(|cause| {
+                ArchiveError::Upload(format!("invalid Discord payload template: {cause}"))
+            })?
+        }
+        None => discord_message(notification),
+    };
+    retry
+        .retry("notify Discord webhook", || {
+            let body = &body;
+            async move {
+                info!("Sending POST request to Discord webhook...");
+                client
+                    .client
+                    .post(webhook_url)
+                    .json(body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<_, ArchiveError>(())
+            }
+        })
+        .await?;
+
+    warn!("Notified Discord webhook");
+    Ok(())
+}
+
+fn discord_message(notification: &Notification) -> serde_json::Value {
+    let id = notification.id.as_deref().unwrap_or("unknown");
+    let title = notification.title();
+    let release_time = match notification.release_time {
+        Some(it) => format!("<t:{it}:F>"),
+        None => "unknown".to_owned(),
+    };
+
+    serde_json::json!({
+        "embeds": [{
+            "title": title,
+            "fields": [
+                { "name": "Version", "value": id, "inline": true },
+                { "name": "Size", "value": format!("{} bytes", notification.size), "inline": true },
+                { "name": "Released", "value": release_time, "inline": true },
+                { "name": "SHA-256", "value": format!("`{}`", notification.sha256) },
+            ],
+        }],
+    })
+}
+
+/// Posts `notification` as a message to the Matrix `room`.
+pub async fn notify_matrix(
+    client: &itch_io::Client,
+    room: &MatrixRoom,
+    notification: &Notification,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    // NOTE: the same transaction id across retries keeps the homeserver from posting twice
+    let transaction_id = format!("cosmic-archive-{:016x}", rand::thread_rng().gen::<u64>());
+    let base = room.homeserver.as_str().trim_end_matches('/');
+    let room_id = utf8_percent_encode(&room.room_id, NON_ALPHANUMERIC);
+    let url =
+        format!("{base}/_matrix/client/v3/rooms/{room_id}/send/m.room.message/{transaction_id}");
+    let body = match &room.template {
+        Some(template) => serde_json::json!({
+            "msgtype": "m.notice",
+            "body": notification.render(template),
+        }),
+        None => matrix_message(notification),
+    };
+    let (url, body) = (&url, &body);
+
+    retry
+        .retry("notify Matrix room", || async move {
+            info!("Sending PUT request to Matrix homeserver ({base})...");
+            client
+                .client
+                .put(url)
+                .bearer_auth(&room.access_token)
+                .json(body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>(())
+        })
+        .await?;
+
+    warn!("Notified Matrix room {}", room.room_id);
+    Ok(())
+}
+
+fn matrix_message(notification: &Notification) -> serde_json::Value {
+    let id = notification.id.as_deref().unwrap_or("unknown");
+    let title = notification.title();
+    let released = notification
+        .release_date()
+        .unwrap_or_else(|| "unknown".to_owned());
+    let (size, sha256) = (notification.size, notification.sha256);
+
+    serde_json::json!({
+        "msgtype": "m.notice",
+        "body": format!(
+            "{title}\nVersion: {id}\nReleased: {released}\nSize: {size} bytes\nSHA-256: {sha256}"
+        ),
+        "format": "org.matrix.custom.html",
+        "formatted_body": format!(
+            "<strong>{title}</strong><br>Version: {id}<br>Released: {released}<br>Size: {size} bytes<br>SHA-256: <code>{sha256}</code>"
+        ),
+    })
+}
+
+/// Posts `notification` as a status of the Mastodon `account`.
+pub async fn notify_mastodon(
+    client: &itch_io::Client,
+    account: &MastodonAccount,
+    notification: &Notification,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    // NOTE: the same idempotency key across retries keeps the instance from posting twice
+    let idempotency_key = format!("cosmic-archive-{:016x}", rand::thread_rng().gen::<u64>());
+    let base = account.instance.as_str().trim_end_matches('/');
+    let url = format!("{base}/api/v1/statuses");
+    let body = serde_json::json!({
+        "status": match &account.template {
+            Some(template) => notification.render(template),
+            None => mastodon_status(notification),
+        },
+        "visibility": account.visibility,
+    });
+    let (url, body, idempotency_key) = (&url, &body, &idempotency_key);
+
+    retry
+        .retry("post Mastodon status", || async move {
+            info!("Sending POST request to Mastodon instance ({base})...");
+            client
+                .client
+                .post(url)
+                .bearer_auth(&account.access_token)
+                .header("idempotency-key", idempotency_key)
+                .json(body)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok::<_, ArchiveError>(())
+        })
+        .await?;
+
+    warn!("Posted Mastodon status");
+    Ok(())
+}
+
+fn mastodon_status(notification: &Notification) -> String {
+    let mut status = notification.title();
+    if let Some(date) = notification.release_date() {
+        status.push_str(&format!(", released on {}", &date[..10]));
+    }
+    if let Some(url) = &notification.url {
+        status.push_str(&format!("\n\n{url}"));
+    }
+    status
+}
@@ -0,0 +1,139 @@
+//! Patterns matching download option titles and file names within archives.
+
+use std::{fmt, str};
+
+/// Prefix of a pattern that is a regex.
+const REGEX_PREFIX: &str = "regex:";
+
+/// A pattern matching names, (de)serialized as its string.
+///
+/// A pattern prefixed with `regex:` is a regex matching anywhere within a name, one with `*` or `?`
+/// is a glob matching whole names, and any other one matches its exact name.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Pattern {
+    /// Matches exactly this name.
+    Exact(String),
+    /// Matches every name where `*` matches any characters and `?` any single one.
+    Glob(String),
+    /// Matches every name this regex matches within.
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    /// Checks whether `name` matches the pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(it) => it == name,
+            Self::Glob(it) => {
+                let pattern = it.chars().collect::<Vec<_>>();
+                let name = name.chars().collect::<Vec<_>>();
+                glob_matches(&pattern, &name)
+            }
+            Self::Regex(it) => it.is_match(name),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(a), Self::Exact(b)) | (Self::Glob(a), Self::Glob(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Pattern {}
+
+impl From<&str> for Pattern {
+    fn from(s: &str) -> Self {
+        Self::Exact(s.to_owned())
+    }
+}
+
+impl From<Pattern> for String {
+    #[inline]
+    fn from(value: Pattern) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(it) | Self::Glob(it) => f.write_str(it),
+            Self::Regex(it) => write!(f, "{REGEX_PREFIX}{}", it.as_str()),
+        }
+    }
+}
+
+impl str::FromStr for Pattern {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(regex) = s.strip_prefix(REGEX_PREFIX) {
+            Ok(Self::Regex(regex::Regex::new(regex)?))
+        } else if s.contains(['*', '?']) {
+            Ok(Self::Glob(s.to_owned()))
+        } else {
+            Ok(Self::Exact(s.to_owned()))
+        }
+    }
+}
+
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|start| glob_matches(rest, &name[start..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches(rest, &name[1..]),
+        Some((it, rest)) => name.first() == Some(it) && glob_matches(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(s: &str) -> Pattern {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn matches_exact_names() {
+        assert_eq!(
+            pattern("cosmic-reach-jar.zip"),
+            Pattern::from("cosmic-reach-jar.zip")
+        );
+        assert!(pattern("cosmic-reach-jar.zip").matches("cosmic-reach-jar.zip"));
+        assert!(!pattern("cosmic-reach-jar.zip").matches("cosmic-reach-jar.zip.bak"));
+    }
+
+    #[test]
+    fn matches_globs_against_whole_names() {
+        let glob = pattern("Cosmic Reach-?.*.jar");
+        assert!(glob.matches("Cosmic Reach-0.1.44.jar"));
+        assert!(!glob.matches("Cosmic Reach-.1.44.jar"));
+        assert!(!glob.matches("lib/Cosmic Reach-0.1.44.jar"));
+        assert!(pattern("*").matches(""));
+    }
+
+    #[test]
+    fn matches_regexes_anywhere() {
+        let regex = pattern(r"regex:\d+\.\d+");
+        assert!(regex.matches("Cosmic Reach-0.1.44.jar"));
+        assert!(!regex.matches("cosmic-reach-jar.zip"));
+        assert_eq!(regex.to_string(), r"regex:\d+\.\d+");
+        assert!("regex:(".parse::<Pattern>().is_err());
+    }
+}
@@ -0,0 +1,2180 @@
+//! Fetching and publishing of game JARs, from downloading them off itch.io to archiving them
+//! everywhere enabled, along with promoting and rolling back archived versions and auditing them.
+
+use crate::archive_org::upload_version;
+use crate::atomic::write_atomically;
+use crate::auth::AuthMethod;
+use crate::cache::{ArchiveCache, ResponseCache};
+use crate::checksum::{add_to_sums, checksum_path, write_checksum, SHA256SUMS};
+use crate::classify::{classify_version, TypeRule};
+use crate::cli::{
+    BackfillArgs, EntryArgs, FetchArgs, GlobalArgs, PromoteArgs, RollbackArgs, VerifyArgs,
+    WatchArgs,
+};
+use crate::container::ArchiveFormat;
+use crate::devlog::{devlog_path, get_devlog};
+use crate::download::{
+    check_disk_space, compression_method, download_archive, download_archive_with_fallback,
+    download_secondary, extract_all, extract_game_jar, find_game_jar, find_game_jars,
+    get_download_ids, get_download_url, get_jar_download_id, head_download, jar_destination,
+    open_archive, partial_download_path, remove_partial_download, spill_entry, validate_archive,
+    version_directory, DownloadHead, Mirror, SecondaryTarget,
+};
+use crate::email::Mailer;
+use crate::git::{
+    branch_exists, branch_name, commit_to_new_branch, needs_download_id, needs_version,
+    push_branch, remove_branch,
+};
+use crate::github::{
+    create_pull_request, list_refs, publish_release, Asset, NewPullRequest, DEFAULT_PR_BODY,
+    DEFAULT_PR_TITLE,
+};
+use crate::hash::{hash_file, hash_reader, hash_reader_with, Digests, HashAlgorithm};
+use crate::health::HealthCheck;
+use crate::jar::{
+    class_index_path, create_class_index, create_slim_jar, detect_version_id, detect_version_id_in,
+    slim_jar_path,
+};
+use crate::manifest::{get_versions, version_id_from_file_name, ArchivedFile, Manifest, SlimJar};
+use crate::metrics::metrics;
+use crate::natives::{create_natives_index, natives_path};
+use crate::normalize::{normalize_file, normalize_zip_file};
+use crate::notify::{notify_discord, notify_mastodon, notify_matrix, Notification};
+use crate::release::{get_game_page, get_upload_date, ReleaseTimes};
+use crate::sbom::{create_sbom, sbom_path};
+use crate::server::serve;
+use crate::sign::Signer;
+use crate::stage::{
+    restage_file, restage_version, stage_file, stage_version, unstage_file, unstage_version,
+    Pending,
+};
+use crate::storage::Storage;
+use crate::upstream::{check_published_hashes, find_published_hashes, PUBLISHED_ALGORITHMS};
+use crate::verify::{check_link, url_file_name, verify_version, version_links, LinkStatus};
+use crate::{
+    temp, ArchiveError, Artifact, RetryPolicy, Sha256Hash, Version, Versions, ARCHIVED_JARS_URL,
+    DEFAULT_VERSION_TYPE,
+};
+use futures_util::{stream, FutureExt, StreamExt};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Seek};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// Fetches the game JAR of every artifact of `args` and the file of every secondary target,
+/// archiving the NOT yet archived ones everywhere enabled by `args`.
+///
+/// Fails with the first failure if any, otherwise with why they were skipped if every one was,
+/// e.g. [`ArchiveError::AlreadyArchived`].
+pub async fn fetch(global: &GlobalArgs, args: &FetchArgs) -> Result<(), ArchiveError> {
+    match args.itch.auth().methods().first() {
+        Some(AuthMethod::ApiKey) => info!("Requesting download info with the itch.io API key"),
+        Some(AuthMethod::CsrfToken) => info!("Requesting download info with the CSRF token"),
+        Some(AuthMethod::Anonymous) | None => warn!(
+            "CSRF token is empty, downloading anonymously which only free uploads allow, consider \
+             setting either the 'ITCH_API_KEY' or 'CSRF_TOKEN' environmental variable"
+        ),
+    }
+
+    let retry = global.retry_policy();
+    let client = global.itch_client(&args.itch)?;
+    let versions = get_versions(
+        &client,
+        global.response_cache().as_ref(),
+        global.manifest_url(),
+        &global.manifest_policy(),
+    )
+    .await?;
+
+    // NOTE: every fetch runs within this task, so committing and the like never interleave
+    let fetched = stream::iter(args.artifacts().iter().copied())
+        .map(|artifact| {
+            info!("Fetching {artifact} game JAR...");
+            fetch_artifact(global, args, &client, &retry, artifact, &versions)
+                .instrument(info_span!("fetch", %artifact))
+                .map(move |it| (artifact, it))
+        })
+        .buffer_unordered(args.concurrency())
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut fetched = fetched
+        .into_iter()
+        .map(|(artifact, it)| (format!("{artifact} game JAR"), it))
+        .collect::<Vec<_>>();
+    for target in &args.secondaries {
+        info!("Fetching {} file...", target.name);
+        let result = fetch_secondary(global, args, &client, target, &versions)
+            .instrument(info_span!("fetch", target = %target.name))
+            .await;
+        fetched.push((format!("{} file", target.name), result));
+    }
+
+    let mut archived = 0;
+    let mut skipped = None;
+    let mut failed = None;
+    for (subject, result) in fetched {
+        match result {
+            Ok(()) => archived += 1,
+            Err(cause @ (ArchiveError::AlreadyArchived(_) | ArchiveError::BranchExists(_))) => {
+                warn!("Skipping {subject}: {cause}");
+                skipped = Some(cause);
+            }
+            Err(cause) if failed.is_some() => {
+                error!("Failed to fetch {subject}: {cause}")
+            }
+            Err(cause) => failed = Some(cause),
+        }
+    }
+
+    match (failed, skipped) {
+        (Some(cause), _) => Err(cause),
+        (None, Some(cause)) if archived == 0 => Err(cause),
+        _ => Ok(()),
+    }
+}
+
+/// Fetches the game JAR of `artifact`, printing its path if it is NOT yet archived.
+pub async fn fetch_artifact(
+    global: &GlobalArgs,
+    args: &FetchArgs,
+    client: &itch_io::Client,
+    retry: &RetryPolicy,
+    artifact: Artifact,
+    versions: &Versions,
+) -> Result<(), ArchiveError> {
+    let mut archived_hashes = versions.hashes(artifact);
+    if let Some(stage) = &args.stage {
+        archived_hashes.extend(Pending::read_from(stage)?.hashes(artifact));
+    }
+    let mirrors = args.mirrors(artifact);
+    let download_title = args.download_title(artifact);
+    let template = args.branch_template(artifact);
+    let scrape = global.scrape_policy();
+    let download = global.itch_download_policy();
+
+    let upload = match args.download_id(artifact) {
+        Some(id) => {
+            info!("Using the given itch.io upload {id} instead of finding it on the game page");
+            None
+        }
+        None => {
+            let upload = get_jar_download_id(client, args.game_url(), &download_title, &scrape)
+                .instrument(info_span!("scrape"))
+                .await;
+            fallible(upload, mirrors, "find the itch.io upload")?
+        }
+    };
+    let download_id = args
+        .download_id(artifact)
+        .or(upload.as_ref().map(|&(_, id)| id));
+    if args.git_repo.is_some() || args.check_github {
+        if needs_version(&template) {
+            info!("Branch name needs the version id, checking it after downloading instead");
+        } else if download_id.is_none() && needs_download_id(&template) {
+            warn!("Branch name needs the itch.io upload id, which is unknown");
+        } else {
+            let branch = branch_name(&template, download_id, None);
+            if let Some(repo) = &args.git_repo {
+                if branch_exists(repo, args.git_remote(), &branch)? {
+                    return Err(ArchiveError::BranchExists(branch));
+                }
+            }
+            if args.check_github {
+                let Some(repository) = args.github_repository.as_deref() else {
+                    return Err(ArchiveError::Git(
+                        "checking GitHub needs a repository, see `--github-repository`".to_owned(),
+                    ));
+                };
+                let refs = list_refs(client, repository, args.git_token.as_deref(), retry).await?;
+                if refs.contains(&branch) {
+                    return Err(ArchiveError::BranchExists(branch));
+                }
+            }
+            info!("Branch '{branch}' does NOT yet exist");
+        }
+    }
+
+    let url = match download_id {
+        Some(download_id) => fallible(
+            get_download_url(
+                client,
+                args.game_url(),
+                download_id,
+                &args.itch.auth(),
+                &args.itch.external_hosts,
+                &scrape,
+            )
+            .instrument(info_span!("scrape"))
+            .await,
+            mirrors,
+            "get the itch.io download url",
+        )?,
+        None => None,
+    };
+
+    let head = match url.as_deref() {
+        Some(url) => head_download(client, url, &download)
+            .await
+            .unwrap_or_else(|cause| {
+                warn!("Failed to get download metadata: {cause}");
+                DownloadHead::default()
+            }),
+        None => DownloadHead::default(),
+    };
+    let updated_at = match &upload {
+        Some((title, _)) => get_upload_date(
+            client,
+            global.response_cache().as_ref(),
+            args.game_url(),
+            title,
+            &scrape,
+        )
+        .instrument(info_span!("scrape"))
+        .await
+        .unwrap_or_else(|cause| {
+            warn!("Failed to get upload date: {cause}");
+            None
+        }),
+        None => None,
+    };
+    let release = ReleaseTimes {
+        last_modified: head.last_modified,
+        updated_at,
+    };
+    info!(
+        "Download was last modified at {:?}, and its upload updated at {:?}",
+        release.last_modified, release.updated_at
+    );
+
+    // NOTE: the game JAR is about as large as its archive, which is already compressed
+    let jar_size = versions.largest_size(artifact);
+    match head.content_length.or(jar_size) {
+        Some(archive_size) => {
+            let required = archive_size + jar_size.unwrap_or(archive_size);
+            check_disk_space(args.output_dir(), required)?;
+        }
+        None => info!("Download size is unknown, skipping the disk space check"),
+    }
+
+    if global.dry_run {
+        let (path, hash) = dry_run_download(
+            client,
+            global,
+            args,
+            artifact,
+            url.as_deref(),
+            &head,
+            &download,
+        )
+        .await?;
+
+        return if archived_hashes.contains(&hash) {
+            Err(ArchiveError::AlreadyArchived(path))
+        } else {
+            warn!("[DRY RUN] '{}' would NOT yet be archived", path.display());
+            if args.archive_org {
+                warn!("[DRY RUN] Would upload to archive.org");
+            }
+            if args.github_release {
+                warn!("[DRY RUN] Would publish a GitHub Release");
+            }
+            if args.pull_request {
+                warn!("[DRY RUN] Would open a pull request");
+            }
+            if args.s3.enabled {
+                warn!("[DRY RUN] Would upload to S3");
+            }
+            if args.devlog {
+                warn!("[DRY RUN] Would archive the devlog post");
+            }
+            if args.check_upstream_hashes {
+                warn!("[DRY RUN] Would cross-check the checksums published upstream");
+            }
+            if let Some(stage) = &args.stage {
+                warn!("[DRY RUN] Would stage in '{}' instead", stage.display());
+            }
+            if args.ipfs {
+                warn!("[DRY RUN] Would add to IPFS");
+            }
+            if args.discord_webhook.is_some() {
+                warn!("[DRY RUN] Would notify Discord webhook");
+            }
+            if args.email.host.is_some() {
+                warn!("[DRY RUN] Would send email notification");
+            }
+            if args.matrix.matrix_homeserver.is_some() {
+                warn!("[DRY RUN] Would notify Matrix room");
+            }
+            if args.mastodon.mastodon_instance.is_some() {
+                warn!("[DRY RUN] Would post Mastodon status if archived");
+            }
+            Ok(())
+        };
+    }
+
+    let archive_cache = global.archive_cache();
+    let cache_key = download_id
+        .and_then(|id| ArchiveCache::itch_key(id, head.last_modified, head.content_length));
+    let cached = match &archive_cache {
+        Some(cache) if url.is_some() => cache_key
+            .as_deref()
+            .map_or(Ok(None), |key| cache.lookup(key))?,
+        Some(cache) => cached_mirror(cache, mirrors)?,
+        None => None,
+    };
+
+    let part = partial_download_path(args.output_dir(), &download_title);
+    let temp_dir = global.temp_dir();
+    let is_cached = cached.is_some();
+    let is_resumed = !is_cached && part.exists();
+    let mut downloaded = match cached {
+        Some(file) => {
+            warn!("Reusing cached archive instead of downloading it");
+            open_archive(file, &temp_dir)?
+        }
+        None => {
+            download_archive_with_fallback(
+                client,
+                url.as_deref(),
+                head.content_length,
+                mirrors,
+                Some(&part),
+                args.connections(),
+                &temp_dir,
+                &download,
+            )
+            .instrument(info_span!("download"))
+            .await?
+        }
+    };
+    let archive = &mut downloaded.archive;
+    if let Err(cause) = info_span!("validate").in_scope(|| validate_archive(archive)) {
+        // NOTE: only a resumed download mixes bytes of different downloads
+        return Err(match cause {
+            ArchiveError::BrokenArchive(reason) if is_resumed => {
+                remove_partial_download(&part)?;
+                ArchiveError::CorruptDownload(reason)
+            }
+            cause => cause,
+        });
+    }
+    let (mut path, jar_digests, extras, compression) = info_span!("extract").in_scope(|| {
+        let (file_name, others) = find_game_jars(
+            archive,
+            args.jar_name(artifact),
+            args.preferred_jar.as_ref(),
+        )?;
+        let path = jar_destination(
+            archive,
+            &file_name,
+            args.output(artifact),
+            args.output_dir(),
+            args.output_template(artifact).as_deref(),
+        )?;
+        let algorithms: &[HashAlgorithm] = if args.check_upstream_hashes {
+            &PUBLISHED_ALGORITHMS
+        } else {
+            &[HashAlgorithm::Sha256]
+        };
+        let digests = extract_game_jar(archive, &file_name, &path, algorithms)?;
+        let compression = compression_method(archive, &file_name)?;
+        let mut extras = Vec::with_capacity(others.len());
+        for other in others {
+            let Some(name) = Path::new(&other).file_name() else {
+                return Err(ArchiveError::UnsafeEntry(other));
+            };
+            let extra = args.output_dir().join(name);
+            extract_game_jar(archive, &other, &extra, &[])?;
+            warn!("Extracted other JAR '{other}' to '{}'", extra.display());
+            extras.push(extra);
+        }
+        if let Some(directory) = &args.extract_all {
+            let directory = version_directory(directory, &file_name);
+            let count = extract_all(archive, &directory)?;
+            info!("Unpacked {count} files into '{}'", directory.display());
+        }
+        Ok::<_, ArchiveError>((path, digests, extras, compression))
+    })?;
+
+    let storage = args.s3.storage()?;
+    let format = downloaded.format;
+    let (mut file, repacked) = downloaded.into_files();
+    let zip_size = file.metadata()?.len();
+    if let Some(directory) = &args.keep_zip {
+        let zip = directory.join(download_file_name(&path, format)?);
+        keep_zip(&mut file, &zip, format, args)?;
+    }
+    let zip_digests = if args.check_upstream_hashes {
+        file.rewind()?;
+        Some(hash_reader_with(io::BufReader::new(&mut file), PUBLISHED_ALGORITHMS)?.0)
+    } else {
+        None
+    };
+    if let (Some(cache), false) = (&archive_cache, is_cached) {
+        cache.store(cache_key.as_deref(), &mut file)?;
+    }
+    // NOTE: mirrors are uploaded the zip archive even if repacked from another format
+    let mut file = repacked.unwrap_or(file);
+    let zip = if storage.is_some() || args.stage.is_some() {
+        if args.normalize_zip {
+            Some(normalize_zip_file(&mut file, &temp_dir)?)
+        } else {
+            // NOTE: copied so that the partial download is removed before it is mirrored
+            file.rewind()?;
+            let mut zip = temp::tempfile(&temp_dir)?;
+            io::copy(&mut file, &mut zip)?;
+            Some(zip)
+        }
+    } else {
+        None
+    };
+    drop(file);
+    remove_partial_download(&part)?;
+
+    let sha256 = jar_digests
+        .sha256()
+        .expect("sha256 digest should be calculated while extracting");
+    info!("Hash of '{}': {sha256}", path.display());
+    write_checksum(&path, sha256)?;
+    if args.sha256sums {
+        add_to_sums(&args.output_dir().join(SHA256SUMS), &path, sha256)?;
+    }
+    if archived_hashes.contains(&sha256) {
+        return Err(ArchiveError::AlreadyArchived(path));
+    }
+    if let Some(zip_digests) = zip_digests {
+        check_upstream_hashes(client, global, args, zip_digests, jar_digests, &path).await?;
+    }
+
+    let needs_entry = args.commit
+        || args.archive_org
+        || args.github_release
+        || args.sbom
+        || args.class_index
+        || args.natives
+        || args.slim
+        || args.devlog
+        || args.s3.enabled
+        || args.ipfs
+        || args.stage.is_some();
+    let notification = if needs_entry {
+        let base_url = archive_base_url(storage.as_ref())?;
+        let page = optional_game_page(client, global, args.game_url()).await;
+        let mut version = fetched_entry(
+            artifact,
+            &release,
+            &path,
+            base_url.clone(),
+            &args.version_types,
+            page,
+        )?;
+        version.zip_size = Some(zip_size);
+        if format == ArchiveFormat::Zip {
+            version.compression = Some(compression);
+        } else {
+            version.archive_format = Some(format.name().to_owned());
+        }
+        if args.sbom {
+            let sbom = sbom_path(&path);
+            create_sbom(&path, &version.id)?.write_to(&sbom)?;
+            info!("Wrote SBOM to '{}'", sbom.display());
+        }
+        if args.class_index {
+            let index = class_index_path(&path);
+            create_class_index(&path, &version.id)?.write_to(&index)?;
+            info!("Wrote class index to '{}'", index.display());
+        }
+        if args.natives {
+            let index = natives_path(&path);
+            create_natives_index(&path, &version.id)?.write_to(&index)?;
+            info!("Wrote natives index to '{}'", index.display());
+        }
+        if args.slim {
+            version.slim = Some(write_slim_jar(&path, &base_url)?);
+        }
+        if args.devlog {
+            archive_devlog(client, global, args, &mut version, &path, &base_url).await?;
+        }
+        let archived = match &args.stage {
+            Some(stage) => {
+                let mut files = sidecar_files(&path);
+                if let Some(mut zip) = zip {
+                    let zip_path = path.with_file_name(zip_file_name(&path)?);
+                    zip.rewind()?;
+                    write_atomically(&zip_path, |file| {
+                        io::copy(&mut zip, file)?;
+                        Ok(())
+                    })?;
+                    files.push(zip_path);
+                }
+                path = stage_version(stage, version.clone(), download_id, &path, &files, &extras)?;
+                warn!(
+                    "Staged {} in '{}' until promoted",
+                    version.id,
+                    stage.display()
+                );
+                false
+            }
+            None => {
+                let files = FetchedFiles {
+                    jar: path.clone(),
+                    extras,
+                    zip,
+                };
+                publish_version(
+                    client,
+                    args,
+                    retry,
+                    versions,
+                    &mut version,
+                    download_id,
+                    files,
+                )
+                .await?
+            }
+        };
+        Notification {
+            artifact,
+            id: Some(version.id),
+            sha256,
+            size: version.size,
+            release_time: Some(version.release_time),
+            archived,
+            url: archived.then_some(version.url),
+        }
+    } else {
+        let id = detect_version_id(&path)?;
+        match &id {
+            Some(id) => warn!("Detected version id {id}"),
+            None => warn!("Could NOT detect the version id"),
+        }
+        Notification {
+            artifact,
+            id,
+            sha256,
+            size: fs::metadata(&path)?.len(),
+            release_time: release.release_time(),
+            archived: false,
+            url: None,
+        }
+    };
+    send_notifications(client, args, &notification, retry).await?;
+
+    warn!("Printing to STDOUT the JAR path that is NOT yet archived.");
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Identity of the upload of a kind of game JAR, which changes whenever a new version is uploaded.
+type UploadSignature = (u64, Option<u64>);
+
+/// Keeps polling the itch.io game page until shut down, fetching whenever the upload of any
+/// artifact of `args` changes, see [`fetch`].
+pub async fn watch(global: &GlobalArgs, args: WatchArgs) -> Result<(), ArchiveError> {
+    let retry = global.scrape_policy();
+    let client = global.client()?;
+    let cache = global.response_cache();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    let health = HealthCheck {
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |it| it.as_secs()),
+        stale_after: args.stale_after.unwrap_or(args.interval * 3),
+    };
+    if let Some(address) = args.metrics_address {
+        let listener = TcpListener::bind(address).await?;
+        tokio::spawn(async move {
+            if let Err(cause) = serve(listener, health).await {
+                error!("Metrics server stopped: {cause}");
+            }
+        });
+    }
+
+    let mailer = Mailer::from_config(&args.fetch.email)?;
+    let failure_threshold = args
+        .fetch
+        .email
+        .failure_threshold
+        .map_or(3, NonZeroU32::get);
+    let mut failures = 0;
+
+    let mut seen = HashMap::new();
+    loop {
+        let mut failure = None;
+        let mut polled = HashMap::new();
+        for &artifact in args.fetch.artifacts() {
+            let started = Instant::now();
+            let signature =
+                upload_signature(&client, cache.as_ref(), &args.fetch, artifact, &retry).await;
+            metrics().record_poll(started.elapsed(), signature.is_ok());
+            match signature {
+                Ok(signature) => {
+                    polled.insert(artifact, signature);
+                }
+                Err(cause) => {
+                    warn!("Failed to poll {artifact} upload: {cause}");
+                    failure = Some(format!("failed to poll {artifact} upload: {cause}"));
+                }
+            }
+        }
+
+        if polled
+            .iter()
+            .any(|(it, signature)| seen.get(it) != Some(signature))
+        {
+            info!("Available uploads changed, fetching...");
+            match fetch(global, &args.fetch).await {
+                Ok(()) => {
+                    metrics().record_archive();
+                    seen.extend(polled);
+                }
+                Err(cause @ (ArchiveError::AlreadyArchived(_) | ArchiveError::BranchExists(_))) => {
+                    info!("{cause}");
+                    seen.extend(polled);
+                }
+                Err(cause) => {
+                    error!("{cause}");
+                    failure = Some(cause.to_string());
+                }
+            }
+        } else {
+            info!("Available uploads are unchanged");
+        }
+
+        match failure {
+            Some(cause) => {
+                failures += 1;
+                if let (Some(mailer), true) = (&mailer, failures == failure_threshold) {
+                    if let Err(cause) = mailer.notify_failures(failures, &cause, &retry).await {
+                        warn!("Failed to send failure email: {cause}");
+                    }
+                }
+            }
+            None => failures = 0,
+        }
+
+        if let Some(path) = &args.heartbeat_file {
+            if let Err(cause) = health.check().write_to(path) {
+                warn!("Failed to write heartbeat file: {cause}");
+            }
+        }
+
+        let jitter = rand::thread_rng().gen_range(0..=args.interval.as_secs() / 10);
+        let delay = args.interval + Duration::from_secs(jitter);
+        info!("Polling again in {}...", humantime::format_duration(delay));
+        tokio::select! {
+            result = &mut shutdown => {
+                result?;
+                warn!("Shutting down...");
+                return Ok(());
+            }
+            () = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Polls the itch.io game page for the signature of the upload of `artifact`.
+async fn upload_signature(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    args: &FetchArgs,
+    artifact: Artifact,
+    retry: &RetryPolicy,
+) -> Result<UploadSignature, ArchiveError> {
+    let download_title = args.download_title(artifact);
+    let (title, download_id) =
+        get_jar_download_id(client, args.game_url(), &download_title, retry).await?;
+    let updated_at = get_upload_date(client, cache, args.game_url(), &title, retry).await?;
+    Ok((download_id, updated_at))
+}
+
+/// Waits for either an interrupt or, on Unix, a termination signal.
+async fn shutdown_signal() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+/// Downloads every available itch.io upload, printing the draft entries of the NOT yet archived
+/// game JARs within as JSON.
+pub async fn backfill(global: &GlobalArgs, args: BackfillArgs) -> Result<(), ArchiveError> {
+    let client = global.itch_client(&args.itch)?;
+    let versions = get_versions(
+        &client,
+        global.response_cache().as_ref(),
+        global.manifest_url(),
+        &global.manifest_policy(),
+    )
+    .await?;
+    let mut hashes = versions
+        .versions
+        .iter()
+        .map(|it| it.sha256)
+        .collect::<HashSet<_>>();
+
+    let mut uploads = get_download_ids(&client, args.game_url(), &global.scrape_policy())
+        .await?
+        .into_iter()
+        .map(|(title, id)| (Some(title), id))
+        .collect::<Vec<_>>();
+    for &id in &args.upload_ids {
+        if !uploads.iter().any(|&(_, it)| it == id) {
+            uploads.push((None, id));
+        }
+    }
+
+    let mut drafts = Vec::new();
+    for (title, id) in &uploads {
+        let backfilled =
+            backfill_upload(global, &args, &client, title.as_deref(), *id, &mut hashes)
+                .instrument(info_span!("backfill", upload = id))
+                .await;
+        match backfilled {
+            Ok(Some(version)) => drafts.push(version),
+            Ok(None) => {}
+            Err(cause) => warn!("Skipping upload {id}: {cause}"),
+        }
+    }
+
+    warn!(
+        "{} of {} uploads are NOT yet archived",
+        drafts.len(),
+        uploads.len()
+    );
+    if !global.dry_run {
+        println!("{}", serde_json::to_string_pretty(&drafts)?);
+    }
+    Ok(())
+}
+
+/// Downloads the itch.io upload `id` titled `title` if known, extracting the game JAR within if
+/// its hash is NOT yet in `hashes`. Returns the draft entry of the extracted game JAR.
+async fn backfill_upload(
+    global: &GlobalArgs,
+    args: &BackfillArgs,
+    client: &itch_io::Client,
+    title: Option<&str>,
+    id: u64,
+    hashes: &mut HashSet<Sha256Hash>,
+) -> Result<Option<Version>, ArchiveError> {
+    let scrape = global.scrape_policy();
+    let download = global.itch_download_policy();
+    info!(
+        "Backfilling upload {id} ({})...",
+        title.unwrap_or("NOT listed")
+    );
+
+    let url = get_download_url(
+        client,
+        args.game_url(),
+        id,
+        &args.itch.auth(),
+        &args.itch.external_hosts,
+        &scrape,
+    )
+    .await?;
+    let head = head_download(client, &url, &download)
+        .await
+        .unwrap_or_else(|cause| {
+            warn!("Failed to get download metadata: {cause}");
+            DownloadHead::default()
+        });
+    let temp_dir = global.temp_dir();
+    let mut downloaded = download_archive(
+        client,
+        &url,
+        head.content_length,
+        None,
+        &temp_dir,
+        &download,
+    )
+    .instrument(info_span!("download"))
+    .await?;
+    let archive = &mut downloaded.archive;
+    let file_name = find_game_jar(archive, None)?;
+
+    let (sha256, _) = hash_reader(archive.by_name(&file_name)?)?;
+    if !hashes.insert(sha256) {
+        info!("'{file_name}' of upload {id} is already archived");
+        return Ok(None);
+    }
+
+    let path = jar_destination(archive, &file_name, None, args.output_dir(), None)?;
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would extract NOT yet archived '{file_name}' to '{}'",
+            path.display()
+        );
+        return Ok(None);
+    }
+    info_span!("extract").in_scope(|| extract_game_jar(archive, &file_name, &path, &[]))?;
+    let compression = compression_method(archive, &file_name)?;
+    let format = downloaded.format;
+    let zip_size = downloaded.into_files().0.metadata()?.len();
+
+    let updated_at = match title {
+        Some(title) => get_upload_date(
+            client,
+            global.response_cache().as_ref(),
+            args.game_url(),
+            title,
+            &scrape,
+        )
+        .await
+        .unwrap_or_else(|cause| {
+            warn!("Failed to get upload date: {cause}");
+            None
+        }),
+        None => None,
+    };
+    let release = ReleaseTimes {
+        last_modified: head.last_modified,
+        updated_at,
+    };
+    let page = optional_game_page(client, global, args.game_url()).await;
+    let mut version = fetched_entry(
+        args.artifact(title),
+        &release,
+        &path,
+        args.base_url.clone(),
+        &args.version_types,
+        page,
+    )?;
+    version.zip_size = Some(zip_size);
+    if format == ArchiveFormat::Zip {
+        version.compression = Some(compression);
+    } else {
+        version.archive_format = Some(format.name().to_owned());
+    }
+    warn!(
+        "Upload {id} is {} ('{}'), which is NOT yet archived",
+        version.id,
+        path.display()
+    );
+    Ok(Some(version))
+}
+
+/// Opens the cached archive of the first of `mirrors` with a known hash that is cached, if any.
+fn cached_mirror(cache: &ArchiveCache, mirrors: &[Mirror]) -> Result<Option<File>, ArchiveError> {
+    for sha256 in mirrors.iter().filter_map(|it| it.sha256) {
+        if let Some(file) = cache.lookup_hash(sha256)? {
+            return Ok(Some(file));
+        }
+    }
+    Ok(None)
+}
+
+/// Turns the failure of an itch.io step into [`None`] if there are `mirrors` to fall back to.
+fn fallible<T>(
+    result: Result<T, ArchiveError>,
+    mirrors: &[Mirror],
+    what: &str,
+) -> Result<Option<T>, ArchiveError> {
+    match result {
+        Ok(it) => Ok(Some(it)),
+        Err(cause) if !mirrors.is_empty() => {
+            warn!("Failed to {what}, falling back to mirrors: {cause}");
+            Ok(None)
+        }
+        Err(cause) => Err(cause),
+    }
+}
+
+/// Fetches the download option of the secondary `target` as is, printing its path if it is NOT yet
+/// archived.
+pub async fn fetch_secondary(
+    global: &GlobalArgs,
+    args: &FetchArgs,
+    client: &itch_io::Client,
+    target: &SecondaryTarget,
+    versions: &Versions,
+) -> Result<(), ArchiveError> {
+    let scrape = global.scrape_policy();
+    let download = global.itch_download_policy();
+
+    let (title, download_id) =
+        get_jar_download_id(client, args.game_url(), &target.download_title, &scrape)
+            .instrument(info_span!("scrape"))
+            .await?;
+    let url = get_download_url(
+        client,
+        args.game_url(),
+        download_id,
+        &args.itch.auth(),
+        &args.itch.external_hosts,
+        &scrape,
+    )
+    .instrument(info_span!("scrape"))
+    .await?;
+    let head = head_download(client, &url, &download)
+        .await
+        .unwrap_or_else(|cause| {
+            warn!("Failed to get download metadata: {cause}");
+            DownloadHead::default()
+        });
+
+    let Some(file_name) = Path::new(&title).file_name() else {
+        return Err(ArchiveError::UnsafeEntry(title));
+    };
+    let path = args.output_dir().join(file_name);
+    if global.dry_run {
+        warn!("[DRY RUN] Would download '{title}' to '{}'", path.display());
+        return Ok(());
+    }
+
+    if let Some(size) = head.content_length {
+        check_disk_space(args.output_dir(), size)?;
+    }
+    let temp_dir = global.temp_dir();
+    download_secondary(
+        client,
+        &url,
+        head.content_length,
+        &path,
+        &temp_dir,
+        &download,
+    )
+    .instrument(info_span!("download"))
+    .await?;
+    let (sha256, size) = hash_reader(io::BufReader::new(File::open(&path)?))?;
+    if versions.file_hashes(&target.name).contains(&sha256) {
+        return Err(ArchiveError::AlreadyArchived(path));
+    }
+
+    let storage = args.s3.storage()?;
+    let file = ArchivedFile {
+        target: target.name.clone(),
+        title,
+        release_time: head.last_modified.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |it| it.as_secs())
+        }),
+        url: archive_base_url(storage.as_ref())?.join(&file_name.to_string_lossy())?,
+        sha256,
+        size,
+    };
+    let path = match &args.stage {
+        Some(stage) => {
+            let path = stage_file(stage, file, &path)?;
+            warn!(
+                "Staged {} file in '{}' until promoted",
+                target.name,
+                stage.display()
+            );
+            path
+        }
+        None => {
+            publish_secondary(client, args, &global.retry_policy(), file, &path).await?;
+            path
+        }
+    };
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Mirrors the secondary `file` at `path` to the storage if any, and commits it if enabled by
+/// `args`.
+async fn publish_secondary(
+    client: &itch_io::Client,
+    args: &FetchArgs,
+    retry: &RetryPolicy,
+    file: ArchivedFile,
+    path: &Path,
+) -> Result<(), ArchiveError> {
+    if let Some(storage) = args.s3.storage()? {
+        let name = jar_file_name(path)?;
+        let content_type = "application/octet-stream";
+        storage
+            .put(client, name, File::open(path)?, content_type, retry)
+            .await?;
+    }
+    if args.commit {
+        commit_secondary(args, file, path)?;
+    }
+    Ok(())
+}
+
+/// Commits the archived `file` at `path` and its entry in the local archived versions data onto a
+/// new branch named after its target and hash.
+fn commit_secondary(args: &FetchArgs, file: ArchivedFile, path: &Path) -> Result<(), ArchiveError> {
+    let Some(repo) = args.git_repo.as_deref() else {
+        return Err(ArchiveError::Git(
+            "committing needs a repository, see `--git-repo`".to_owned(),
+        ));
+    };
+    let versions_file = args
+        .versions_file
+        .clone()
+        .unwrap_or_else(|| repo.join("versions.json"));
+
+    let branch = format!("{}-{}", file.target, &file.sha256.to_string()[..12]);
+    if branch_exists(repo, args.git_remote(), &branch)? {
+        return Err(ArchiveError::BranchExists(branch));
+    }
+
+    let signers = args.sign.signers()?;
+    info!("Reading local archived versions data...");
+    let mut manifest = Manifest::read_from(&versions_file)?;
+    let message = format!("Archive {} {}", file.target, file.title);
+    info!("Adding '{}' to local archived versions data...", file.title);
+    manifest.versions.add_file(file)?;
+    manifest.write_to(&versions_file)?;
+
+    commit_files(
+        args,
+        repo,
+        &branch,
+        &signers,
+        vec![path.to_path_buf(), versions_file],
+        &message,
+    )
+}
+
+/// Signs `files` and commits them along with their signatures onto the new `branch` of `repo`,
+/// pushing it if requested.
+fn commit_files(
+    args: &FetchArgs,
+    repo: &Path,
+    branch: &str,
+    signers: &[Signer],
+    mut files: Vec<PathBuf>,
+    message: &str,
+) -> Result<(), ArchiveError> {
+    let signatures = sign_files(
+        signers,
+        &files.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+    )?;
+    files.extend(signatures);
+    commit_to_new_branch(repo, branch, &files, message, args.git_author())?;
+
+    if args.push {
+        let token = args.git_token.as_deref().unwrap_or_default();
+        if token.is_empty() {
+            return Err(ArchiveError::Git(
+                "pushing needs a token, see `--git-token`".to_owned(),
+            ));
+        }
+        push_branch(repo, args.git_remote(), branch, token)?;
+    }
+
+    Ok(())
+}
+
+/// Describes the other game JARs at `paths` that were extracted along with `version` as secondary
+/// files, skipping the ones already archived in `versions`.
+fn extra_files(
+    artifact: Artifact,
+    paths: &[PathBuf],
+    version: &Version,
+    base_url: &url::Url,
+    versions: &Versions,
+) -> Result<Vec<(PathBuf, ArchivedFile)>, ArchiveError> {
+    let target = format!("{artifact}-extra");
+    let archived_hashes = versions.file_hashes(&target);
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let title = jar_file_name(path)?;
+        let (sha256, size) = hash_reader(io::BufReader::new(File::open(path)?))?;
+        if archived_hashes.contains(&sha256) {
+            info!("Other JAR '{title}' is already archived");
+            continue;
+        }
+        let file = ArchivedFile {
+            target: target.clone(),
+            title: title.to_owned(),
+            release_time: version.release_time,
+            url: base_url.join(title)?,
+            sha256,
+            size,
+        };
+        files.push((path.clone(), file));
+    }
+    Ok(files)
+}
+
+/// Commits the game JAR at `jar` and its entry `version` in the local archived versions data onto
+/// a new branch, pushing it afterwards if requested.
+pub fn commit_version(
+    args: &FetchArgs,
+    template: &str,
+    download_id: Option<u64>,
+    version: &Version,
+    jar: &Path,
+    extras: &[(PathBuf, ArchivedFile)],
+) -> Result<String, ArchiveError> {
+    let Some(repo) = args.git_repo.as_deref() else {
+        return Err(ArchiveError::Git(
+            "committing needs a repository, see `--git-repo`".to_owned(),
+        ));
+    };
+    if download_id.is_none() && needs_download_id(template) {
+        return Err(ArchiveError::Git(
+            "branch name needs the itch.io upload id, which is unknown after downloading from a mirror"
+                .to_owned(),
+        ));
+    }
+    let versions_file = args
+        .versions_file
+        .clone()
+        .unwrap_or_else(|| repo.join("versions.json"));
+
+    let id = &version.id;
+
+    let branch = branch_name(template, download_id, Some(id));
+    if needs_version(template) && branch_exists(repo, args.git_remote(), &branch)? {
+        return Err(ArchiveError::BranchExists(branch));
+    }
+
+    let signers = args.sign.signers()?;
+    add_entry(&versions_file, jar, version.clone())?;
+    if !extras.is_empty() {
+        let mut manifest = Manifest::read_from(&versions_file)?;
+        for (_, file) in extras {
+            info!("Adding '{}' to local archived versions data...", file.title);
+            manifest.versions.add_file(file.clone())?;
+        }
+        manifest.write_to(&versions_file)?;
+    }
+    let mut files = vec![jar.to_path_buf(), versions_file];
+    files.extend(extras.iter().map(|(path, _)| path.clone()));
+    if args.sbom {
+        files.push(sbom_path(jar));
+    }
+    if args.class_index {
+        files.push(class_index_path(jar));
+    }
+    if args.natives {
+        files.push(natives_path(jar));
+    }
+    if version.slim.is_some() {
+        files.push(slim_jar_path(jar));
+    }
+    if version.devlog.is_some() {
+        files.push(devlog_path(jar));
+    }
+    let message = match (&args.commit_message, version.artifact()) {
+        (Some(template), _) => version.render(template),
+        (None, Artifact::Client) => format!("Archive {id}"),
+        (None, Artifact::Server) => format!("Archive {id} server"),
+    };
+    commit_files(args, repo, &branch, &signers, files, &message)?;
+    Ok(branch)
+}
+
+/// Opens the pull request of the pushed `branch` archiving `version`.
+async fn open_pull_request(
+    client: &itch_io::Client,
+    args: &FetchArgs,
+    version: &Version,
+    branch: String,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let Some(repository) = args.github_repository.as_deref() else {
+        return Err(ArchiveError::Upload(
+            "opening a pull request needs a repository, see `--github-repository`".to_owned(),
+        ));
+    };
+    let token = args.git_token.as_deref().unwrap_or_default();
+
+    let pull_request = NewPullRequest {
+        title: version.render(args.pr_title.as_deref().unwrap_or(DEFAULT_PR_TITLE)),
+        body: version.render(args.pr_body.as_deref().unwrap_or(DEFAULT_PR_BODY)),
+        head: branch,
+        base: args.pr_base.clone().unwrap_or_else(|| "main".to_owned()),
+    };
+    create_pull_request(client, repository, token, &pull_request, retry).await?;
+    Ok(())
+}
+
+/// Publishes the game JAR at `jar` of `version` along with its checksum file, and its SBOM if
+/// requested, as a GitHub Release.
+async fn publish_github_release(
+    client: &itch_io::Client,
+    args: &FetchArgs,
+    version: &Version,
+    jar: &Path,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let Some(repository) = args.github_repository.as_deref() else {
+        return Err(ArchiveError::Upload(
+            "publishing a GitHub Release needs a repository, see `--github-repository`".to_owned(),
+        ));
+    };
+    let token = args.git_token.as_deref().unwrap_or_default();
+    if token.is_empty() {
+        return Err(ArchiveError::Upload(
+            "publishing a GitHub Release needs a token, see `--git-token`".to_owned(),
+        ));
+    }
+
+    let jar_asset = Asset::from_file(jar, "application/java-archive")?;
+    let checksum = Asset::checksum(version, &jar_asset.name);
+    let mut assets = vec![jar_asset, checksum];
+    if args.sbom {
+        assets.push(Asset::from_file(
+            &sbom_path(jar),
+            "application/vnd.cyclonedx+json",
+        )?);
+    }
+    if args.class_index {
+        assets.push(Asset::from_file(
+            &class_index_path(jar),
+            "application/json",
+        )?);
+    }
+    if args.natives {
+        assets.push(Asset::from_file(&natives_path(jar), "application/json")?);
+    }
+    if version.slim.is_some() {
+        assets.push(Asset::from_file(
+            &slim_jar_path(jar),
+            "application/java-archive",
+        )?);
+    }
+
+    publish_release(client, repository, token, version, &assets, retry).await?;
+    Ok(())
+}
+
+/// Copies the downloaded `file` of `format` to `zip`, as is unless normalizing it as a zip archive,
+/// writing its checksum file.
+fn keep_zip(
+    file: &mut File,
+    zip: &Path,
+    format: ArchiveFormat,
+    args: &FetchArgs,
+) -> Result<(), ArchiveError> {
+    if let Some(directory) = zip.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    file.rewind()?;
+    write_atomically(zip, |out| {
+        io::copy(file, out)?;
+        Ok(())
+    })?;
+    if args.normalize_zip && format == ArchiveFormat::Zip {
+        normalize_file(zip)?;
+    }
+    let (sha256, _) = hash_reader(io::BufReader::new(File::open(zip)?))?;
+    write_checksum(zip, sha256)?;
+    if args.sha256sums {
+        add_to_sums(&args.output_dir().join(SHA256SUMS), zip, sha256)?;
+    }
+    warn!("Kept downloaded archive at '{}'", zip.display());
+    Ok(())
+}
+
+/// Uploads the game JAR at `jar` and the zip archive `zip` it was extracted from to `storage`.
+async fn mirror_version(
+    client: &itch_io::Client,
+    storage: &Storage,
+    jar: &Path,
+    zip: File,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let jar_name = jar_file_name(jar)?;
+    let zip_name = zip_file_name(jar)?;
+
+    let file = File::open(jar)?;
+    storage
+        .put(client, jar_name, file, "application/java-archive", retry)
+        .await?;
+    storage
+        .put(client, &zip_name, zip, "application/zip", retry)
+        .await?;
+
+    let devlog = devlog_path(jar);
+    if devlog.is_file() {
+        let file = File::open(&devlog)?;
+        let name = jar_file_name(&devlog)?;
+        storage
+            .put(client, name, file, "text/markdown", retry)
+            .await?;
+    }
+    let slim = slim_jar_path(jar);
+    if slim.is_file() {
+        let file = File::open(&slim)?;
+        let name = jar_file_name(&slim)?;
+        storage
+            .put(client, name, file, "application/java-archive", retry)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Writes the slim JAR of the game JAR at `jar` next to it, returning its entry relative to
+/// `base_url`.
+fn write_slim_jar(jar: &Path, base_url: &url::Url) -> Result<SlimJar, ArchiveError> {
+    let path = slim_jar_path(jar);
+    let stripped = create_slim_jar(jar, &path)?;
+    let (sha256, size) = hash_reader(io::BufReader::new(File::open(&path)?))?;
+    info!(
+        "Wrote slim JAR stripped of {stripped} entries to '{}'",
+        path.display()
+    );
+    Ok(SlimJar {
+        url: base_url.join(jar_file_name(&path)?)?,
+        sha256,
+        size,
+    })
+}
+
+/// Archives the devlog post of `version` next to its game JAR at `jar`, linking it from its entry
+/// relative to `base_url`, or warns if it has none.
+async fn archive_devlog(
+    client: &itch_io::Client,
+    global: &GlobalArgs,
+    args: &FetchArgs,
+    version: &mut Version,
+    jar: &Path,
+    base_url: &url::Url,
+) -> Result<(), ArchiveError> {
+    let cache = global.response_cache();
+    let devlog = get_devlog(
+        client,
+        cache.as_ref(),
+        args.game_url(),
+        &version.id,
+        &global.scrape_policy(),
+    )
+    .await?;
+    let Some(devlog) = devlog else {
+        warn!("Could NOT find the devlog post of {}", version.id);
+        return Ok(());
+    };
+
+    let path = devlog_path(jar);
+    devlog.write_to(&path)?;
+    info!(
+        "Archived devlog post '{}' to '{}'",
+        devlog.title,
+        path.display()
+    );
+    version.devlog = Some(base_url.join(jar_file_name(&path)?)?);
+    Ok(())
+}
+
+/// Sends `notification` everywhere enabled by `args`, only warning of failures to send it.
+pub async fn send_notifications(
+    client: &itch_io::Client,
+    args: &FetchArgs,
+    notification: &Notification,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    if let Some(webhook) = &args.discord_webhook {
+        if let Err(cause) = notify_discord(
+            client,
+            webhook,
+            args.discord_payload.as_deref(),
+            notification,
+            retry,
+        )
+        .await
+        {
+            warn!("Failed to notify Discord webhook: {cause}");
+        }
+    }
+    if let Some(mailer) = Mailer::from_config(&args.email)? {
+        if let Err(cause) = mailer.notify(notification, retry).await {
+            warn!("Failed to send email notification: {cause}");
+        }
+    }
+    if let Some(room) = args.matrix.room()? {
+        if let Err(cause) = notify_matrix(client, &room, notification, retry).await {
+            warn!("Failed to notify Matrix room: {cause}");
+        }
+    }
+    if let (Some(account), true) = (args.mastodon.account()?, notification.archived) {
+        if let Err(cause) = notify_mastodon(client, &account, notification, retry).await {
+            warn!("Failed to post Mastodon status: {cause}");
+        }
+    }
+    Ok(())
+}
+
+/// Files of a fetched game JAR to archive.
+pub struct FetchedFiles {
+    /// The game JAR itself.
+    pub jar: PathBuf,
+    /// The other game JARs extracted along with it.
+    pub extras: Vec<PathBuf>,
+    /// The zip archive it was downloaded within, if kept to be mirrored.
+    pub zip: Option<File>,
+}
+
+/// Returns where archived game JARs are downloadable from, i.e. the mirror in `storage` if any.
+fn archive_base_url(storage: Option<&Storage>) -> Result<url::Url, ArchiveError> {
+    match storage {
+        Some(storage) => storage.base_url(),
+        None => Ok(url::Url::parse(ARCHIVED_JARS_URL)?),
+    }
+}
+
+/// Returns the paths of the files written next to the game JAR at `jar`, whether they exist or
+/// not, e.g. its checksum file.
+fn sidecar_files(jar: &Path) -> Vec<PathBuf> {
+    vec![
+        checksum_path(jar),
+        sbom_path(jar),
+        class_index_path(jar),
+        natives_path(jar),
+        slim_jar_path(jar),
+        devlog_path(jar),
+    ]
+}
+
+/// Archives `version` of the fetched game JAR `files`, downloaded from the itch.io upload
+/// `download_id` if known, everywhere enabled by `args`, and returns whether it was archived
+/// anywhere.
+pub async fn publish_version(
+    client: &itch_io::Client,
+    args: &FetchArgs,
+    retry: &RetryPolicy,
+    versions: &Versions,
+    version: &mut Version,
+    download_id: Option<u64>,
+    files: FetchedFiles,
+) -> Result<bool, ArchiveError> {
+    let artifact = version.artifact();
+    let template = args.branch_template(artifact);
+    let storage = args.s3.storage()?;
+    let base_url = archive_base_url(storage.as_ref())?;
+    let ipfs = args.ipfs.then(|| args.ipfs_node.ipfs()).transpose()?;
+    let path = &files.jar;
+
+    if let (Some(storage), Some(zip)) = (&storage, files.zip) {
+        mirror_version(client, storage, path, zip, retry).await?;
+    }
+    if let Some(ipfs) = &ipfs {
+        let cid = ipfs.add(client, jar_file_name(path)?, path, retry).await?;
+        version.ipfs_cid = Some(cid);
+    }
+    if args.commit {
+        let extras = extra_files(artifact, &files.extras, version, &base_url, versions)?;
+        let branch = commit_version(args, &template, download_id, version, path, &extras)?;
+        if args.pull_request {
+            open_pull_request(client, args, version, branch, retry).await?;
+        }
+    }
+    if args.archive_org {
+        let item = args.archive_org_item(version)?;
+        upload_version(client, &item, version, path, retry).await?;
+    }
+    if args.github_release {
+        publish_github_release(client, args, version, path, retry).await?;
+    }
+    Ok(args.commit
+        || args.archive_org
+        || args.github_release
+        || storage.is_some()
+        || ipfs.is_some())
+}
+
+/// Cross-checks the zip archive, of `zip_digests`, and the game `jar` within, of `jar_digests`,
+/// against the checksums published on the game page and in the devlog post of its version.
+async fn check_upstream_hashes(
+    client: &itch_io::Client,
+    global: &GlobalArgs,
+    args: &FetchArgs,
+    zip_digests: Digests,
+    jar_digests: Digests,
+    jar: &Path,
+) -> Result<(), ArchiveError> {
+    let mut published = Vec::new();
+    if let Some(page) = optional_game_page(client, global, args.game_url()).await {
+        published.extend(find_published_hashes(&page));
+    }
+    if let Some(id) = detect_version_id(jar)? {
+        let cache = global.response_cache();
+        let devlog = get_devlog(
+            client,
+            cache.as_ref(),
+            args.game_url(),
+            &id,
+            &global.scrape_policy(),
+        )
+        .await
+        .unwrap_or_else(|cause| {
+            warn!("Failed to get devlog post: {cause}");
+            None
+        });
+        if let Some(devlog) = devlog {
+            published.extend(find_published_hashes(&devlog.body));
+        }
+    }
+
+    check_published_hashes(&published, &[zip_digests, jar_digests]).inspect_err(|_| {
+        metrics().record_hash_mismatch();
+        error!("Download may have been tampered with or corrupted!");
+    })
+}
+
+/// Gets the HTML of the game page at `game_url`, which is only used to classify version types.
+async fn optional_game_page(
+    client: &itch_io::Client,
+    global: &GlobalArgs,
+    game_url: &str,
+) -> Option<String> {
+    let cache = global.response_cache();
+    get_game_page(client, cache.as_ref(), game_url, &global.scrape_policy())
+        .await
+        .map_err(|cause| warn!("Failed to get game page: {cause}"))
+        .ok()
+}
+
+/// Creates the entry of the fetched game JAR of `artifact` at `jar`, downloaded relative to
+/// `base_url`.
+///
+/// Its type is classified by `version_types`, or otherwise from the HTML of the game `page` if
+/// known, see [`classify_version`].
+fn fetched_entry(
+    artifact: Artifact,
+    release: &ReleaseTimes,
+    jar: &Path,
+    base_url: url::Url,
+    version_types: &[TypeRule],
+    page: Option<String>,
+) -> Result<Version, ArchiveError> {
+    let mut version = new_entry(&EntryArgs {
+        jar: jar.to_path_buf(),
+        id: None,
+        kind: None,
+        artifact,
+        release_time: release.release_time(),
+        url: None,
+        base_url,
+        extra_hashes: Vec::new(),
+        version_types: version_types.to_vec(),
+        game_page: page,
+    })?;
+    version.last_modified = release.last_modified;
+    version.updated_at = release.updated_at;
+    Ok(version)
+}
+
+/// Downloads the archive into a temporary file and hashes the game JAR within without extracting
+/// it, returning where it would have been extracted to along with its hash.
+async fn dry_run_download(
+    client: &itch_io::Client,
+    global: &GlobalArgs,
+    args: &FetchArgs,
+    artifact: Artifact,
+    url: Option<&str>,
+    head: &DownloadHead,
+    retry: &RetryPolicy,
+) -> Result<(PathBuf, Sha256Hash), ArchiveError> {
+    match head.content_length {
+        Some(size) => info!("[DRY RUN] Download is {size} bytes"),
+        None => info!("[DRY RUN] Download size is unknown"),
+    }
+
+    let mut downloaded = download_archive_with_fallback(
+        client,
+        url,
+        head.content_length,
+        args.mirrors(artifact),
+        None,
+        args.connections(),
+        &global.temp_dir(),
+        retry,
+    )
+    .await?;
+    let archive = &mut downloaded.archive;
+    let (file_name, others) = find_game_jars(
+        archive,
+        args.jar_name(artifact),
+        args.preferred_jar.as_ref(),
+    )?;
+    for other in &others {
+        warn!("[DRY RUN] Would archive other JAR '{other}' as a secondary file");
+    }
+    let destination = jar_destination(
+        archive,
+        &file_name,
+        args.output(artifact),
+        args.output_dir(),
+        args.output_template(artifact).as_deref(),
+    )?;
+    info!(
+        "[DRY RUN] Would extract '{file_name}' to '{}'",
+        destination.display()
+    );
+    if let Some(directory) = &args.extract_all {
+        info!(
+            "[DRY RUN] Would unpack {} archived files into '{}'",
+            archive.len(),
+            version_directory(directory, &file_name).display()
+        );
+    }
+    if let Some(directory) = &args.keep_zip {
+        info!(
+            "[DRY RUN] Would keep downloaded archive at '{}'",
+            directory
+                .join(download_file_name(&destination, downloaded.format)?)
+                .display()
+        );
+    }
+    info!(
+        "[DRY RUN] Would write checksum file '{}'",
+        checksum_path(&destination).display()
+    );
+    if args.sha256sums {
+        info!(
+            "[DRY RUN] Would add it to '{}'",
+            args.output_dir().join(SHA256SUMS).display()
+        );
+    }
+    if args.sbom {
+        info!(
+            "[DRY RUN] Would write SBOM to '{}'",
+            sbom_path(&destination).display()
+        );
+    }
+    if args.class_index {
+        info!(
+            "[DRY RUN] Would write class index to '{}'",
+            class_index_path(&destination).display()
+        );
+    }
+    if args.natives {
+        info!(
+            "[DRY RUN] Would write natives index to '{}'",
+            natives_path(&destination).display()
+        );
+    }
+    if args.slim {
+        info!(
+            "[DRY RUN] Would write slim JAR to '{}'",
+            slim_jar_path(&destination).display()
+        );
+    }
+
+    let (jar, digests) = spill_entry(
+        archive,
+        &file_name,
+        &[HashAlgorithm::Sha256],
+        &global.temp_dir(),
+    )?;
+    let hash = digests
+        .sha256()
+        .expect("sha256 digest should be calculated while spilling");
+    info!("Game JAR hash: {hash}");
+    match detect_version_id_in(io::BufReader::new(jar))? {
+        Some(id) => warn!("[DRY RUN] Detected version id {id}"),
+        None => warn!("[DRY RUN] Could NOT detect the version id"),
+    }
+    Ok((destination, hash))
+}
+
+/// Audits every archived version, or checks whether the local game JARs of `args` are already
+/// archived.
+pub async fn verify(global: &GlobalArgs, args: VerifyArgs) -> Result<(), ArchiveError> {
+    let client = global.client()?;
+    let retry = global.download_policy();
+    let versions = match &args.versions_file {
+        Some(path) => Versions::read_from(path)?,
+        None => {
+            get_versions(
+                &client,
+                global.response_cache().as_ref(),
+                global.manifest_url(),
+                &global.manifest_policy(),
+            )
+            .await?
+        }
+    };
+
+    if args.links_only {
+        return verify_links(global, &client, &args, versions, &retry).await;
+    }
+    if args.paths.is_empty() {
+        let ipfs = args.ipfs.then(|| args.ipfs_node.ipfs()).transpose()?;
+        let temp_dir = global.temp_dir();
+        let mut failed = 0;
+        for version in &versions.versions {
+            let mut result =
+                verify_version(&client, version, args.mirror.as_deref(), &temp_dir, &retry).await;
+            if let (Ok(()), Some(ipfs), Some(cid)) = (&result, &ipfs, &version.ipfs_cid) {
+                result = match ipfs.is_pinned(&client, cid, &retry).await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(ArchiveError::NotPinned(cid.clone())),
+                    Err(cause) => Err(cause),
+                };
+            }
+            match result {
+                Ok(()) => info!("{} is intact", version.id),
+                Err(cause) => {
+                    error!("{}: {cause}", version.id);
+                    failed += 1;
+                }
+            }
+        }
+
+        let total = versions.versions.len();
+        return if failed == 0 {
+            info!("All {total} archived versions are intact");
+            Ok(())
+        } else {
+            Err(ArchiveError::VerifyFailed { failed, total })
+        };
+    }
+
+    for path in args.paths {
+        let hash = hash_file(&path)?;
+        match versions.versions.iter().find(|it| it.sha256 == hash) {
+            Some(version) => info!("'{}' is archived as {}", path.display(), version.id),
+            None => return Err(ArchiveError::Unarchived(path)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks every archived URL of `versions`, rewriting the failing ones of game JARs as by
+/// `--rewrite-to`.
+pub async fn verify_links(
+    global: &GlobalArgs,
+    client: &itch_io::Client,
+    args: &VerifyArgs,
+    versions: Versions,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let mut rewrites = Vec::new();
+    let (mut failed, mut total) = (0, 0);
+    for version in &versions.versions {
+        for url in version_links(version) {
+            total += 1;
+            let status = check_link(client, url, retry).await;
+            if let Some(to) = &status.redirected_to {
+                println!("redirected\t{}\t{url}\t{to}", version.id);
+            }
+            if status.is_healthy() {
+                continue;
+            }
+
+            failed += 1;
+            println!("broken\t{}\t{url}\t{}", version.id, link_failure(&status));
+            if let (Some(base_url), true) = (&args.rewrite_to, url == &version.url) {
+                let mirrored = base_url.join(&url_file_name(url)?)?;
+                if check_link(client, &mirrored, retry).await.is_healthy() {
+                    rewrites.push((version.id.clone(), version.artifact(), mirrored));
+                } else {
+                    warn!("{} is NOT mirrored at {mirrored} either", version.id);
+                }
+            }
+        }
+    }
+
+    for file in &versions.files {
+        total += 1;
+        let status = check_link(client, &file.url, retry).await;
+        if let Some(to) = &status.redirected_to {
+            println!("redirected\t{}\t{}\t{to}", file.title, file.url);
+        }
+        if !status.is_healthy() {
+            failed += 1;
+            println!(
+                "broken\t{}\t{}\t{}",
+                file.title,
+                file.url,
+                link_failure(&status)
+            );
+        }
+    }
+
+    if let (Some(path), false) = (&args.versions_file, rewrites.is_empty()) {
+        let mut manifest = Manifest::read_from(path)?;
+        for (id, artifact, mirrored) in rewrites {
+            let Some(version) = manifest
+                .versions
+                .versions
+                .iter_mut()
+                .find(|it| it.id == id && it.artifact() == artifact)
+            else {
+                continue;
+            };
+            if global.dry_run {
+                warn!("[DRY RUN] Would rewrite the URL of {id} to {mirrored}");
+            } else {
+                warn!("Rewriting the URL of {id} to {mirrored}");
+                version.url = mirrored;
+            }
+        }
+        if !global.dry_run {
+            manifest.write_to(path)?;
+        }
+    }
+
+    if failed == 0 {
+        info!("All {total} archived URLs respond successfully");
+        Ok(())
+    } else {
+        Err(ArchiveError::VerifyFailed { failed, total })
+    }
+}
+
+/// Returns why the archived URL of `status` failed, i.e. its HTTP status or error.
+fn link_failure(status: &LinkStatus) -> String {
+    match (status.status, &status.error) {
+        (Some(code), _) => code.to_string(),
+        (None, Some(error)) => error.clone(),
+        (None, None) => "unknown".to_owned(),
+    }
+}
+
+/// Archives the staged game JARs and secondary files of `args` everywhere enabled, printing their
+/// paths, and removes them from the staging directory.
+pub async fn promote(global: &GlobalArgs, args: PromoteArgs) -> Result<(), ArchiveError> {
+    let fetch = &args.fetch;
+    let Some(stage) = fetch.stage.as_deref() else {
+        return Err(ArchiveError::Stage(
+            "promoting needs a staging directory, see `--stage`".to_owned(),
+        ));
+    };
+    let mut pending = Pending::read_from(stage)?;
+    for id in &args.ids {
+        let is_staged = pending.versions.iter().any(|it| &it.version.id == id)
+            || pending.files.iter().any(|it| &it.file.target == id);
+        if !is_staged {
+            return Err(ArchiveError::Stage(format!("{id} is NOT staged")));
+        }
+    }
+    let promoted = pending
+        .versions
+        .iter()
+        .filter(|it| args.ids.is_empty() || args.ids.contains(&it.version.id))
+        .cloned()
+        .collect::<Vec<_>>();
+    let promoted_files = pending
+        .files
+        .iter()
+        .filter(|it| args.ids.is_empty() || args.ids.contains(&it.file.target))
+        .cloned()
+        .collect::<Vec<_>>();
+    if promoted.is_empty() && promoted_files.is_empty() {
+        warn!("NOTHING is staged in '{}'", stage.display());
+        return Ok(());
+    }
+
+    if global.dry_run {
+        for staged in &promoted {
+            warn!(
+                "[DRY RUN] Would promote {} from '{}'",
+                staged.version.id,
+                stage.display()
+            );
+        }
+        for staged in &promoted_files {
+            warn!(
+                "[DRY RUN] Would promote {} file '{}' from '{}'",
+                staged.file.target,
+                staged.file.title,
+                stage.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let retry = global.retry_policy();
+    let client = global.client()?;
+    let versions = get_versions(
+        &client,
+        global.response_cache().as_ref(),
+        global.manifest_url(),
+        &global.manifest_policy(),
+    )
+    .await?;
+
+    for staged in promoted {
+        info!("Promoting {}...", staged.version.id);
+        let (jar, extras) = unstage_version(stage, &staged, fetch.output_dir())?;
+        let zip_path = jar.with_file_name(zip_file_name(&jar)?);
+        let zip = if fetch.s3.enabled && zip_path.is_file() {
+            Some(File::open(&zip_path)?)
+        } else {
+            None
+        };
+        let mut version = staged.version.clone();
+        let files = FetchedFiles {
+            jar: jar.clone(),
+            extras,
+            zip,
+        };
+        let published = publish_version(
+            &client,
+            fetch,
+            &retry,
+            &versions,
+            &mut version,
+            staged.download_id,
+            files,
+        )
+        .await;
+        let archived = match published {
+            Ok(archived) => archived,
+            Err(cause) => {
+                restage_version(stage, &staged, fetch.output_dir())?;
+                return Err(cause);
+            }
+        };
+
+        pending.versions.retain(|it| it.jar != staged.jar);
+        pending.write_to(stage)?;
+        if zip_path.is_file() {
+            fs::remove_file(&zip_path)?;
+        }
+        warn!("Promoted {}", version.id);
+        let notification = Notification {
+            artifact: version.artifact(),
+            id: Some(version.id.clone()),
+            sha256: version.sha256,
+            size: version.size,
+            release_time: Some(version.release_time),
+            archived,
+            url: archived.then_some(version.url),
+        };
+        send_notifications(&client, fetch, &notification, &retry).await?;
+        println!("{}", jar.display());
+    }
+
+    for staged in promoted_files {
+        info!(
+            "Promoting {} file '{}'...",
+            staged.file.target, staged.file.title
+        );
+        let path = unstage_file(stage, &staged, fetch.output_dir())?;
+        let published = publish_secondary(&client, fetch, &retry, staged.file.clone(), &path);
+        if let Err(cause) = published.await {
+            restage_file(stage, &staged, fetch.output_dir())?;
+            return Err(cause);
+        }
+
+        pending.files.retain(|it| it.name != staged.name);
+        pending.write_to(stage)?;
+        warn!(
+            "Promoted {} file '{}'",
+            staged.file.target, staged.file.title
+        );
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Removes the bad entry of `args` from the local archived versions data, printing it, and deletes
+/// its branch and files if requested.
+pub fn rollback(global: &GlobalArgs, args: RollbackArgs) -> Result<(), ArchiveError> {
+    let mut manifest = Manifest::read_from(&args.versions_file)?;
+    let version = manifest.versions.remove(&args.id, args.artifact)?;
+    let files = match &args.files_dir {
+        Some(directory) => {
+            let jar = directory.join(url_file_name(&version.url)?);
+            let mut files = sidecar_files(&jar);
+            files.insert(0, jar);
+            files
+        }
+        None => Vec::new(),
+    };
+    let kept_branch = args
+        .branch
+        .as_ref()
+        .filter(|_| args.keep_branch)
+        .map(|branch| format!("rolled-back/{branch}"));
+
+    if global.dry_run {
+        warn!(
+            "[DRY RUN] Would remove the following entry from '{}':",
+            args.versions_file.display()
+        );
+        if let Some(branch) = &args.branch {
+            match &kept_branch {
+                Some(kept) => warn!("[DRY RUN] Would rename branch '{branch}' to '{kept}'"),
+                None => warn!("[DRY RUN] Would delete branch '{branch}'"),
+            }
+        }
+        for file in files.iter().filter(|it| it.exists()) {
+            match &args.move_files_to {
+                Some(directory) => warn!(
+                    "[DRY RUN] Would move '{}' into '{}'",
+                    file.display(),
+                    directory.display()
+                ),
+                None => warn!("[DRY RUN] Would delete '{}'", file.display()),
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&version)?);
+        return Ok(());
+    }
+
+    let signers = args.sign.signers()?;
+    info!(
+        "Removing {} ({}) from local archived versions data...",
+        version.id, version.kind
+    );
+    manifest.write_to(&args.versions_file)?;
+    sign_files(&signers, &[&args.versions_file])?;
+
+    if let (Some(repo), Some(branch)) = (&args.git_repo, &args.branch) {
+        remove_branch(repo, branch, kept_branch.as_deref())?;
+    }
+    for file in files.iter().filter(|it| it.exists()) {
+        match &args.move_files_to {
+            Some(directory) => {
+                fs::create_dir_all(directory)?;
+                let destination = directory.join(jar_file_name(file)?);
+                fs::rename(file, &destination)?;
+                info!("Moved '{}' to '{}'", file.display(), destination.display());
+            }
+            None => {
+                fs::remove_file(file)?;
+                info!("Deleted '{}'", file.display());
+            }
+        }
+    }
+
+    warn!("Rolled back {}", version.id);
+    println!("{}", serde_json::to_string_pretty(&version)?);
+    Ok(())
+}
+
+/// Signs every file of `files` with every signer of `signers`, returning the signatures.
+pub(crate) fn sign_files(
+    signers: &[Signer],
+    files: &[&Path],
+) -> Result<Vec<PathBuf>, ArchiveError> {
+    let mut signatures = Vec::new();
+    for file in files {
+        for signer in signers {
+            signatures.push(signer.sign(file)?);
+        }
+    }
+    Ok(signatures)
+}
+
+/// Returns the file name of the zip archive of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.zip`.
+fn zip_file_name(jar: &Path) -> Result<String, ArchiveError> {
+    download_file_name(jar, ArchiveFormat::Zip)
+}
+
+/// Returns the file name of the download of `format` of the game JAR at `jar`, e.g.
+/// `Cosmic Reach-0.1.44.tar.gz`.
+fn download_file_name(jar: &Path, format: ArchiveFormat) -> Result<String, ArchiveError> {
+    let jar_name = jar_file_name(jar)?;
+    Ok(match jar_name.strip_suffix(".jar") {
+        Some(stem) => format!("{stem}.{format}"),
+        None => format!("{jar_name}.{format}"),
+    })
+}
+
+/// Returns the file name of the game JAR at `jar`.
+pub(crate) fn jar_file_name(jar: &Path) -> Result<&str, ArchiveError> {
+    jar.file_name()
+        .and_then(|it| it.to_str())
+        .ok_or_else(|| ArchiveError::UnknownVersionId(jar.to_path_buf()))
+}
+
+/// Creates the entry of the game JAR of `args`, deriving whatever is absent.
+pub(crate) fn new_entry(args: &EntryArgs) -> Result<Version, ArchiveError> {
+    let jar = &args.jar;
+    let file_name = jar_file_name(jar)?;
+
+    let id = match &args.id {
+        Some(it) => it.clone(),
+        None => resolve_version_id(jar, file_name)?,
+    };
+    let release_time = args.release_time.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |it| it.as_secs())
+    });
+    let url = match &args.url {
+        Some(it) => it.clone(),
+        None => args.base_url.join(file_name)?,
+    };
+
+    let kind = match &args.kind {
+        Some(it) => it.clone(),
+        None => classify_version(&id, args.game_page.as_deref(), &args.version_types)
+            .unwrap_or_else(|| {
+                info!("Could NOT classify version {id}, defaulting to {DEFAULT_VERSION_TYPE}");
+                DEFAULT_VERSION_TYPE.to_owned()
+            }),
+    };
+
+    Version::from_jar(
+        jar,
+        id,
+        kind,
+        args.artifact,
+        release_time,
+        url,
+        &args.extra_hashes,
+    )
+}
+
+/// Detects the version id of the game JAR at `jar` from its contents, falling back to the one in
+/// its `file_name`.
+pub(crate) fn resolve_version_id(jar: &Path, file_name: &str) -> Result<String, ArchiveError> {
+    let from_file_name = version_id_from_file_name(file_name);
+    match detect_version_id(jar)? {
+        Some(id) => {
+            if from_file_name.is_some_and(|it| it != id) {
+                warn!("Detected version id {id} differs from the one in '{file_name}'");
+            }
+            warn!("Detected version id {id}");
+            Ok(id)
+        }
+        None => {
+            let id =
+                from_file_name.ok_or_else(|| ArchiveError::UnknownVersionId(jar.to_path_buf()))?;
+            warn!("Could NOT detect the version id, using {id} from '{file_name}' instead");
+            Ok(id.to_owned())
+        }
+    }
+}
+
+/// Appends `version` of the game JAR at `jar` to the local archived versions data at
+/// `versions_file`.
+pub(crate) fn add_entry(
+    versions_file: &Path,
+    jar: &Path,
+    version: Version,
+) -> Result<(), ArchiveError> {
+    info!("Reading local archived versions data...");
+    let mut manifest = Manifest::read_from(versions_file)?;
+    let versions = &mut manifest.versions;
+
+    if versions
+        .versions
+        .iter()
+        .any(|it| it.sha256 == version.sha256)
+    {
+        return Err(ArchiveError::AlreadyArchived(jar.to_path_buf()));
+    }
+
+    info!(
+        "Adding {} ({}) to local archived versions data...",
+        version.id, version.kind
+    );
+    versions.add(version)?;
+    manifest.write_to(versions_file)
+}
@@ -0,0 +1,40 @@
+//! Progress bars of long running transfers, drawn to STDERR once enabled.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables drawing every progress bar created afterwards, disabled by default.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks whether progress bars are drawn.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Creates a progress bar of transferring `len` bytes, or a spinner if `len` is unknown.
+///
+/// The progress bar is hidden if progress bars are disabled.
+pub fn bytes_bar(len: Option<u64>, message: &'static str) -> ProgressBar {
+    if !is_enabled() {
+        return ProgressBar::hidden();
+    }
+
+    let bar = match len {
+        Some(len) => ProgressBar::new(len).with_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+            )
+            .expect("progress bar template should be valid")
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {msg} {bytes} ({bytes_per_sec})")
+                .expect("spinner template should be valid"),
+        ),
+    };
+    bar.with_message(message)
+}
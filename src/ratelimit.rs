@@ -0,0 +1,70 @@
+//! Client-side rate limiting of requests to itch.io, shared by every one of them.
+
+use std::num::NonZeroU32;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::info;
+
+/// A token bucket allowing `requests_per_minute` requests on average, and up to `burst` of them at
+/// once.
+///
+/// Limiters are compared by identity, every request sharing a limiter counting towards the same
+/// limit.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_minute: NonZeroU32,
+    burst: NonZeroU32,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter with a full bucket.
+    pub fn new(requests_per_minute: NonZeroU32, burst: NonZeroU32) -> Self {
+        Self {
+            requests_per_minute,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(burst.get()),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request is allowed, taking its token from the bucket.
+    pub async fn acquire(&self) {
+        let per_token = Duration::from_secs(60) / self.requests_per_minute.get();
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let refilled =
+                    now.duration_since(bucket.refilled_at).as_secs_f64() / per_token.as_secs_f64();
+                bucket.tokens = (bucket.tokens + refilled).min(f64::from(self.burst.get()));
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                per_token.mul_f64(1.0 - bucket.tokens)
+            };
+            info!("Rate limited, waiting {wait:?} before the next request...");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl PartialEq for RateLimiter {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for RateLimiter {}
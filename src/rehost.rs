@@ -0,0 +1,124 @@
+//! Rewriting of the archived versions data to point at a mirror, so third parties can publish a
+//! self-consistent copy of the archive under their own base URL.
+
+use crate::verify::url_file_name;
+use crate::{ArchiveError, Sha256Hash, Versions};
+use std::{fmt, str};
+
+/// Where each archived file is placed below the base URL of a mirror.
+#[derive(Debug, Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MirrorLayout {
+    /// Every file directly below the base URL by its file name, e.g.
+    /// `Cosmic Reach-0.1.44.jar`, as archived upstream.
+    #[default]
+    Flat,
+    /// Every file below a directory of the id of its version, e.g.
+    /// `0.1.44/Cosmic Reach-0.1.44.jar`, or of the target of a secondary file.
+    Id,
+    /// Every file below a directory of its sha256 hash, e.g. `3f9a…/Cosmic Reach-0.1.44.jar`,
+    /// so that mirrors never overwrite one file with another. Devlog posts are placed along the
+    /// game JAR of their version.
+    Hash,
+}
+
+impl MirrorLayout {
+    /// Every layout.
+    pub const ALL: [Self; 3] = [Self::Flat, Self::Id, Self::Hash];
+
+    /// Returns the lowercase name of the layout, e.g. `flat`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Id => "id",
+            Self::Hash => "hash",
+        }
+    }
+}
+
+impl fmt::Display for MirrorLayout {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for MirrorLayout {
+    type Err = UnknownMirrorLayout;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownMirrorLayout(s.to_owned()))
+    }
+}
+
+/// Error of parsing a [`MirrorLayout`] from an unsupported name.
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("unknown mirror layout '{0}', expected either flat, id, or hash")]
+pub struct UnknownMirrorLayout(pub String);
+
+/// A single archived file moved to a mirror by [`rehost`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rehosted {
+    /// Where the file was archived before.
+    pub from: url::Url,
+    /// Where the file is archived on the mirror.
+    pub to: url::Url,
+}
+
+/// Rewrites every URL of `versions`, i.e. of their game JARs, slim JARs, devlog posts, and
+/// secondary files, to the same file below `base_url` in `layout`, and returns every file moved.
+///
+/// Files land below `base_url` even if it lacks a trailing `/`.
+pub fn rehost(
+    versions: &mut Versions,
+    base_url: &url::Url,
+    layout: MirrorLayout,
+) -> Result<Vec<Rehosted>, ArchiveError> {
+    let mut moved = Vec::new();
+    let mut rewrite = |url: &mut url::Url, directory: Option<&str>| {
+        let file_name = url_file_name(url)?;
+        let mut to = base_url.clone();
+        to.path_segments_mut()
+            .map_err(|()| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+            .pop_if_empty()
+            .extend(directory)
+            .push(&file_name);
+        if *url != to {
+            moved.push(Rehosted {
+                from: url.clone(),
+                to: to.clone(),
+            });
+            *url = to;
+        }
+        Ok::<_, ArchiveError>(())
+    };
+
+    for version in &mut versions.versions {
+        let directory = |sha256: &Sha256Hash| match layout {
+            MirrorLayout::Flat => None,
+            MirrorLayout::Id => Some(version.id.clone()),
+            MirrorLayout::Hash => Some(sha256.to_string()),
+        };
+        let jar_directory = directory(&version.sha256);
+        let slim_directory = version.slim.as_ref().map(|it| directory(&it.sha256));
+
+        rewrite(&mut version.url, jar_directory.as_deref())?;
+        if let Some(devlog) = &mut version.devlog {
+            rewrite(devlog, jar_directory.as_deref())?;
+        }
+        if let (Some(slim), Some(directory)) = (&mut version.slim, slim_directory) {
+            rewrite(&mut slim.url, directory.as_deref())?;
+        }
+    }
+    for file in &mut versions.files {
+        let directory = match layout {
+            MirrorLayout::Flat => None,
+            MirrorLayout::Id => Some(file.target.clone()),
+            MirrorLayout::Hash => Some(file.sha256.to_string()),
+        };
+        rewrite(&mut file.url, directory.as_deref())?;
+    }
+    Ok(moved)
+}
@@ -0,0 +1,174 @@
+//! Upstream timestamps of when a version was released.
+
+use crate::cache::{get_cached, ResponseCache};
+use crate::{ArchiveError, RetryPolicy};
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+/// Timestamps of when the download of a version was released, as reported upstream.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ReleaseTimes {
+    /// Unix timestamp in seconds from the `Last-Modified` of the download.
+    pub last_modified: Option<u64>,
+    /// Unix timestamp in seconds of when the upload was last updated on its itch.io game page.
+    pub updated_at: Option<u64>,
+}
+
+impl ReleaseTimes {
+    /// Returns the release time of the version, preferring the `Last-Modified` of the download.
+    pub fn release_time(&self) -> Option<u64> {
+        self.last_modified.or(self.updated_at)
+    }
+}
+
+/// Scrapes the itch.io game page at `game_url` for when its download option titled
+/// `download_title` was last updated, revalidating the game page in `cache` if present instead of
+/// downloading it again.
+pub async fn get_upload_date(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    game_url: &str,
+    download_title: &str,
+    retry: &RetryPolicy,
+) -> Result<Option<u64>, ArchiveError> {
+    let page = get_game_page(client, cache, game_url, retry).await?;
+
+    let date = upload_date_text(&page, download_title);
+    match date {
+        Some(date) => {
+            let timestamp = parse_itch_date(date);
+            if timestamp.is_none() {
+                warn!("Failed to parse upload date '{date}'");
+            }
+            Ok(timestamp)
+        }
+        None => {
+            warn!("Game page has NO upload date of '{download_title}'");
+            Ok(None)
+        }
+    }
+}
+
+/// Gets the HTML of the itch.io game page at `game_url`, revalidating the one in `cache` if present
+/// instead of downloading it again.
+pub async fn get_game_page(
+    client: &itch_io::Client,
+    cache: Option<&ResponseCache>,
+    game_url: &str,
+    retry: &RetryPolicy,
+) -> Result<String, ArchiveError> {
+    info!("Sending GET request to game page ({game_url})...");
+    let page = get_cached(client, cache, game_url, "get game page", retry).await?;
+    Ok(String::from_utf8_lossy(&page).into_owned())
+}
+
+/// Finds the text of the upload date of the download option titled `download_title` within the
+/// HTML of a game page, e.g. `15 August 2024 @ 00:23 UTC`.
+fn upload_date_text<'a>(page: &'a str, download_title: &str) -> Option<&'a str> {
+    let upload = &page[page.find(&format!("title=\"{download_title}\""))?..];
+    let upload = match upload[1..].find("class=\"upload\"") {
+        Some(end) => &upload[..=end],
+        None => upload,
+    };
+    let date = &upload[upload.find("class=\"upload_date\"")?..];
+    let date = &date[date.find("<abbr title=\"")? + "<abbr title=\"".len()..];
+    Some(&date[..date.find('"')?])
+}
+
+/// Parses an HTTP date, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`, as a Unix timestamp in seconds.
+pub fn parse_http_date(s: &str) -> Option<u64> {
+    let time = httpdate::parse_http_date(s).ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Parses an itch.io date, e.g. `15 August 2024 @ 00:23 UTC`, as a Unix timestamp in seconds.
+pub fn parse_itch_date(s: &str) -> Option<u64> {
+    let mut parts = s.split_whitespace();
+    let day = parts.next()?.parse::<u32>().ok()?;
+    let month = parse_month(parts.next()?)?;
+    let year = parts.next()?.parse::<i64>().ok()?;
+    if parts.next()? != "@" {
+        return None;
+    }
+    let (hour, minute) = parts.next()?.split_once(':')?;
+    let (hour, minute) = (hour.parse::<u64>().ok()?, minute.parse::<u64>().ok()?);
+    if parts.next().is_some_and(|it| it != "UTC") || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86400 + hour * 3600 + minute * 60)
+}
+
+/// Parses a date in UTC, e.g. `2024-08-15`, as the Unix timestamp in seconds of its start.
+pub fn parse_date(s: &str) -> Option<u64> {
+    let mut parts = s.splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86400)
+}
+
+/// Formats a Unix timestamp in seconds as its date in UTC, e.g. `2024-08-15`.
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats a Unix timestamp in seconds as its RFC 3339 date and time in UTC, e.g.
+/// `2024-08-15T17:03:00Z`.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let seconds = timestamp % 86400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(timestamp),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn parse_month(s: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = s.get(..3)?.to_ascii_lowercase();
+    let index = MONTHS.iter().position(|it| *it == prefix)?;
+    Some(index as u32 + 1)
+}
+
+/// Returns the number of days since the Unix epoch of the date in the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the date in the proleptic Gregorian calendar of the number of days since the Unix
+/// epoch.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    }) as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
@@ -0,0 +1,104 @@
+//! Retrying of failed network requests with exponential backoff.
+
+use crate::ratelimit::RateLimiter;
+use crate::ArchiveError;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often and how long to wait before retrying a failed network request.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for every retry after it.
+    pub base_delay: Duration,
+    /// Upper bound of the random delay added to every backoff.
+    pub jitter: Duration,
+    /// Maximum duration of every attempt, after which it fails as timed out.
+    pub timeout: Option<Duration>,
+    /// Limiter every attempt waits for before it is sent, if any.
+    pub rate_limit: Option<&'static RateLimiter>,
+}
+
+impl RetryPolicy {
+    /// Policy of never retrying.
+    pub const NONE: Self = Self {
+        attempts: 1,
+        base_delay: Duration::ZERO,
+        jitter: Duration::ZERO,
+        timeout: None,
+        rate_limit: None,
+    };
+
+    /// Returns the policy with every attempt timing out after `timeout`.
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Returns the policy with every attempt waiting for `rate_limit` before it is sent.
+    pub fn with_rate_limit(self, rate_limit: Option<&'static RateLimiter>) -> Self {
+        Self { rate_limit, ..self }
+    }
+
+    /// Returns the delay before the `retry`-th retry, counting from zero.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(retry));
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+        exponential.saturating_add(jitter)
+    }
+
+    /// Runs `request` until it succeeds, fails with a non-transient error, or runs out of attempts.
+    ///
+    /// `what` describes the request in the logged warnings.
+    pub async fn retry<T, F, Fut>(&self, what: &str, mut request: F) -> Result<T, ArchiveError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ArchiveError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            if let Some(limiter) = self.rate_limit {
+                limiter.acquire().await;
+            }
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, request())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(ArchiveError::Timeout {
+                            what: what.to_owned(),
+                            timeout,
+                        })
+                    }),
+                None => request().await,
+            };
+            match result {
+                Ok(it) => return Ok(it),
+                Err(cause) if attempt < self.attempts && cause.is_transient() => {
+                    let delay = self.backoff(attempt - 1);
+                    warn!(
+                        "Attempt {attempt}/{} to {what} failed: {cause}",
+                        self.attempts
+                    );
+                    warn!("Retrying in {delay:?}...");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(cause) => return Err(cause),
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+            jitter: Duration::from_millis(500),
+            timeout: None,
+            rate_limit: None,
+        }
+    }
+}
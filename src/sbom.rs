@@ -0,0 +1,360 @@
+//! CycloneDX software bills of materials of what is bundled inside a game JAR.
+
+use crate::atomic::write_atomically;
+use crate::hash::hash_reader;
+use crate::{ArchiveError, Sha256Hash};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Version of the CycloneDX specification every SBOM follows.
+pub const SPEC_VERSION: &str = "1.5";
+
+/// Packages of the game itself, which are NOT listed as bundled dependencies.
+const GAME_PACKAGES: [&str; 1] = ["finalforeach"];
+
+/// Well-known libraries by their package, Maven group id, and artifact id, used to guess the
+/// coordinates of shaded classes and embedded JARs without a `pom.properties`.
+const KNOWN_LIBRARIES: [(&str, &str, &str); 9] = [
+    ("com.badlogic", "com.badlogicgames.gdx", "gdx"),
+    ("org.lwjgl", "org.lwjgl", "lwjgl"),
+    ("org.joml", "org.joml", "joml"),
+    ("org.slf4j", "org.slf4j", "slf4j-api"),
+    ("org.json", "org.json", "json"),
+    ("org.luaj", "org.luaj", "luaj-jse"),
+    ("it.unimi", "it.unimi.dsi", "fastutil"),
+    ("io.netty", "io.netty", "netty-all"),
+    ("kotlin", "org.jetbrains.kotlin", "kotlin-stdlib"),
+];
+
+/// A CycloneDX SBOM, serialized as its JSON format.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bom {
+    /// Always `CycloneDX`.
+    pub bom_format: &'static str,
+    /// Version of the CycloneDX specification followed.
+    pub spec_version: &'static str,
+    /// Revision of the SBOM, always 1 as it is generated once per version.
+    pub version: u32,
+    /// What the SBOM describes.
+    pub metadata: Metadata,
+    /// Everything found bundled inside the game JAR.
+    pub components: Vec<Component>,
+}
+
+/// The metadata of a [`Bom`].
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct Metadata {
+    /// The game JAR itself.
+    pub component: Component,
+}
+
+/// A single component of a [`Bom`].
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct Component {
+    /// CycloneDX type of the component, e.g. `library`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Maven group id of the component, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Name of the component.
+    pub name: String,
+    /// Version of the component, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Package URL of the component, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    /// Hashes of the component, if it is a file of its own.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hashes: Vec<ComponentHash>,
+    /// Where inside the game JAR the component was found.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+/// A hash of a [`Component`].
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct ComponentHash {
+    /// CycloneDX name of the hash algorithm, e.g. `SHA-256`.
+    pub alg: &'static str,
+    /// Lowercase hex digest.
+    pub content: String,
+}
+
+/// A name-value property of a [`Component`].
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+pub struct Property {
+    /// Name of the property.
+    pub name: &'static str,
+    /// Value of the property.
+    pub value: String,
+}
+
+impl Bom {
+    /// Writes the SBOM as pretty JSON to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        write_atomically(path.as_ref(), |file| {
+            let mut file = io::BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut file, self)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+            Ok(())
+        })
+    }
+}
+
+impl Component {
+    fn new(kind: &'static str, name: String) -> Self {
+        Self {
+            kind,
+            group: None,
+            name,
+            version: None,
+            purl: None,
+            hashes: Vec::new(),
+            properties: Vec::new(),
+        }
+    }
+
+    fn with_sha256(mut self, sha256: Sha256Hash) -> Self {
+        self.hashes.push(ComponentHash {
+            alg: "SHA-256",
+            content: sha256.to_string(),
+        });
+        self
+    }
+
+    fn with_location(mut self, location: &str) -> Self {
+        self.properties.push(Property {
+            name: "cosmicarchive:location",
+            value: location.to_owned(),
+        });
+        self
+    }
+
+    /// Sets the Maven coordinates of the component, marking them as guessed from `source`, e.g.
+    /// `file-name`, if NOT read from a `pom.properties`.
+    fn with_coordinates(
+        mut self,
+        group: &str,
+        artifact: &str,
+        version: Option<&str>,
+        source: Option<&'static str>,
+    ) -> Self {
+        self.group = Some(group.to_owned());
+        self.name = artifact.to_owned();
+        self.version = version.map(str::to_owned);
+        self.purl = Some(match version {
+            Some(version) => format!("pkg:maven/{group}/{artifact}@{version}"),
+            None => format!("pkg:maven/{group}/{artifact}"),
+        });
+        if let Some(source) = source {
+            self.properties.push(Property {
+                name: "cosmicarchive:guessed",
+                value: source.to_owned(),
+            });
+        }
+        self
+    }
+
+    /// Returns the Maven coordinates of the component, e.g. `org.lwjgl:lwjgl:3.3.3`, or just its
+    /// name if unknown.
+    pub fn coordinates(&self) -> String {
+        let mut coordinates = self.name.clone();
+        if let Some(group) = &self.group {
+            coordinates = format!("{group}:{coordinates}");
+        }
+        if let Some(version) = &self.version {
+            coordinates = format!("{coordinates}:{version}");
+        }
+        coordinates
+    }
+
+    /// Returns whether the coordinates of the component were guessed rather than read from a
+    /// `pom.properties`.
+    pub fn is_guessed(&self) -> bool {
+        self.properties
+            .iter()
+            .any(|it| it.name == "cosmicarchive:guessed")
+    }
+}
+
+/// Returns the path of the SBOM of the game JAR at `jar`, e.g. `Cosmic Reach-0.1.44.cdx.json`.
+pub fn sbom_path(jar: &Path) -> PathBuf {
+    jar.with_extension("cdx.json")
+}
+
+/// Creates the SBOM of the game JAR at `jar` of version `id`.
+///
+/// See [`scan_jar`] for what is listed as bundled.
+pub fn create_sbom(jar: &Path, id: &str) -> Result<Bom, ArchiveError> {
+    info!("Scanning '{}' for bundled dependencies...", jar.display());
+    let (sha256, _) = hash_reader(io::BufReader::new(File::open(jar)?))?;
+    let components = scan_jar(io::BufReader::new(File::open(jar)?))?;
+
+    let mut game = Component::new("application", "cosmic-reach".to_owned()).with_sha256(sha256);
+    game.group = Some("finalforeach".to_owned());
+    game.version = Some(id.to_owned());
+
+    Ok(Bom {
+        bom_format: "CycloneDX",
+        spec_version: SPEC_VERSION,
+        version: 1,
+        metadata: Metadata { component: game },
+        components,
+    })
+}
+
+/// Lists everything bundled inside the game JAR read from `reader`.
+///
+/// Maven artifacts are listed from their `pom.properties` within `META-INF/maven`, embedded
+/// JARs as files of their own, and any other shaded classes by their top-level packages.
+///
+/// Embedded JARs take the coordinates of their own `pom.properties`, or else guess them from
+/// their file names, e.g. `gdx-1.12.1.jar`. Shaded classes guess them from their packages if of
+/// [`KNOWN_LIBRARIES`]. Guessed coordinates are marked with the `cosmicarchive:guessed` property.
+pub fn scan_jar<R: Read + Seek>(reader: R) -> Result<Vec<Component>, ArchiveError> {
+    let mut jar = zip::ZipArchive::new(reader)?;
+
+    let mut components = Vec::new();
+    let mut groups = BTreeSet::new();
+    let mut packages = BTreeSet::new();
+    for index in 0..jar.len() {
+        let mut file = jar.by_index(index)?;
+        let name = file.name().to_owned();
+
+        if name.starts_with("META-INF/maven/") && name.ends_with("/pom.properties") {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+            if let Some(component) = maven_component(&text) {
+                groups.extend(component.group.clone());
+                components.push(component.with_location(&name));
+            }
+        } else if name.ends_with(".jar") {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            let (sha256, _) = hash_reader(bytes.as_slice())?;
+            let file_name = name.rsplit('/').next().unwrap_or(&name);
+            let mut component = Component::new("library", file_name.to_owned())
+                .with_sha256(sha256)
+                .with_location(&name);
+            if let Some(maven) = embedded_maven_component(&bytes) {
+                component = component.with_coordinates(
+                    maven.group.as_deref().unwrap_or_default(),
+                    &maven.name,
+                    maven.version.as_deref(),
+                    None,
+                );
+            } else {
+                let (artifact, version) = split_jar_name(file_name);
+                if let Some((_, group, _)) = known_library_of_artifact(artifact) {
+                    component =
+                        component.with_coordinates(group, artifact, version, Some("file-name"));
+                } else {
+                    component.name = artifact.to_owned();
+                    component.version = version.map(str::to_owned);
+                }
+            }
+            components.push(component);
+        } else if let Some(package) = top_level_package(&name) {
+            packages.insert(package);
+        }
+    }
+
+    for package in packages {
+        let is_maven = groups
+            .iter()
+            .any(|group: &String| group.starts_with(&package) || package.starts_with(group));
+        let root = package.split('.').next().unwrap_or_default();
+        if !is_maven && !GAME_PACKAGES.contains(&root) {
+            let location = format!("{}/", package.replace('.', "/"));
+            let mut component = Component::new("library", package.clone()).with_location(&location);
+            if let Some((_, group, artifact)) = known_library_of_package(&package) {
+                component = component.with_coordinates(group, artifact, None, Some("package"));
+            }
+            components.push(component);
+        }
+    }
+
+    Ok(components)
+}
+
+/// Creates the component of a `pom.properties` of a Maven artifact.
+fn maven_component(text: &str) -> Option<Component> {
+    let properties = text
+        .lines()
+        .map(str::trim)
+        .filter(|it| !it.starts_with('#'))
+        .filter_map(|it| it.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect::<BTreeMap<_, _>>();
+    let group = *properties.get("groupId")?;
+    let artifact = *properties.get("artifactId")?;
+    let version = properties.get("version").copied();
+
+    let mut component = Component::new("library", artifact.to_owned());
+    component.group = Some(group.to_owned());
+    component.version = version.map(str::to_owned);
+    component.purl = Some(match version {
+        Some(version) => format!("pkg:maven/{group}/{artifact}@{version}"),
+        None => format!("pkg:maven/{group}/{artifact}"),
+    });
+    Some(component)
+}
+
+/// Reads the component of the first `pom.properties` within the embedded JAR `bytes`, if any.
+fn embedded_maven_component(bytes: &[u8]) -> Option<Component> {
+    let mut jar = zip::ZipArchive::new(io::Cursor::new(bytes)).ok()?;
+    for index in 0..jar.len() {
+        let mut file = jar.by_index(index).ok()?;
+        let name = file.name();
+        if name.starts_with("META-INF/maven/") && name.ends_with("/pom.properties") {
+            let mut text = String::new();
+            file.read_to_string(&mut text).ok()?;
+            return maven_component(&text);
+        }
+    }
+    None
+}
+
+/// Splits the file name of an embedded JAR, e.g. `gdx-backend-lwjgl3-1.12.1.jar`, into its
+/// artifact id and version, if any.
+fn split_jar_name(file_name: &str) -> (&str, Option<&str>) {
+    let stem = file_name.strip_suffix(".jar").unwrap_or(file_name);
+    let version_start = stem
+        .match_indices('-')
+        .map(|(index, _)| index)
+        .find(|index| stem[index + 1..].starts_with(|it: char| it.is_ascii_digit()));
+    match version_start {
+        Some(index) => (&stem[..index], Some(&stem[index + 1..])),
+        None => (stem, None),
+    }
+}
+
+fn known_library_of_package(package: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    KNOWN_LIBRARIES
+        .into_iter()
+        .find(|(known, _, _)| package == *known || package.starts_with(&format!("{known}.")))
+}
+
+fn known_library_of_artifact(artifact: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    KNOWN_LIBRARIES
+        .into_iter()
+        .find(|(_, _, known)| artifact == *known || artifact.starts_with(&format!("{known}-")))
+}
+
+/// Returns the dotted name of the package of at most two segments the class file `name` is in,
+/// e.g. `com.badlogic` of `com/badlogic/gdx/Gdx.class`.
+fn top_level_package(name: &str) -> Option<String> {
+    let directory = name.strip_suffix(".class")?.rsplit_once('/')?.0;
+    if directory.starts_with("META-INF") {
+        return None;
+    }
+    let package = directory.split('/').take(2).collect::<Vec<_>>();
+    Some(package.join("."))
+}
@@ -0,0 +1,48 @@
+//! JSON Schema of the archived versions data, validating it before it breaks consumers.
+
+use crate::ArchiveError;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// JSON Schema of the archived versions data, of every supported schema.
+///
+/// Schema 1 lists the extra hashes of each version next to its sha256 hash, while later ones
+/// record their `schemaVersion` and group them within its `hashes`.
+pub const VERSIONS_SCHEMA: &str = include_str!("versions.schema.json");
+
+/// A single problem found while validating the archived versions data against
+/// [`VERSIONS_SCHEMA`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Problem {
+    /// JSON pointer to the offending value, e.g. `/versions/3/sha256`, or empty if the whole.
+    pub path: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+/// Validates the archived versions data `bytes` against [`VERSIONS_SCHEMA`], returning every
+/// problem found, or none if valid.
+///
+/// Fails if `bytes` is NOT JSON at all.
+pub fn lint_versions(bytes: &[u8]) -> Result<Vec<Problem>, ArchiveError> {
+    static SCHEMA: OnceLock<JSONSchema> = OnceLock::new();
+    let schema = SCHEMA.get_or_init(|| {
+        let schema = serde_json::from_str(VERSIONS_SCHEMA).expect("versions schema should be JSON");
+        JSONSchema::compile(&schema).expect("versions schema should be valid")
+    });
+
+    info!("Validating archived versions data against its schema...");
+    let value = serde_json::from_slice::<Value>(bytes)?;
+    let problems = match schema.validate(&value) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|it| Problem {
+                path: it.instance_path.to_string(),
+                message: it.to_string(),
+            })
+            .collect(),
+    };
+    Ok(problems)
+}
@@ -0,0 +1,77 @@
+//! HTTP endpoints exposed while watching the itch.io game page, for monitoring the updater.
+
+use crate::health::HealthCheck;
+use crate::metrics::metrics;
+use crate::ArchiveError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// Size in bytes of the largest request head that is read.
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// Serves the endpoints to every connection accepted by `listener`, until dropped:
+///
+/// - `/metrics`, the metrics of the process in the Prometheus text exposition format.
+/// - `/healthz`, the [`Health`](crate::health::Health) judged by `health` as JSON, responding
+///   with `503 Service Unavailable` if unhealthy.
+pub async fn serve(listener: TcpListener, health: HealthCheck) -> Result<(), ArchiveError> {
+    let address = listener.local_addr()?;
+    info!("Serving metrics on http://{address}/metrics and health on http://{address}/healthz");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(cause) = respond(stream, health).await {
+                warn!("Failed to respond to {peer}: {cause}");
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, health: HealthCheck) -> Result<(), ArchiveError> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|it| it == b"\r\n\r\n") && head.len() < MAX_HEAD_SIZE {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics().render(),
+        ),
+        ("GET", "/healthz") => {
+            let health = health.check();
+            let status = if health.healthy {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (
+                status,
+                "application/json",
+                serde_json::to_string(&health).expect("health should be serializable"),
+            )
+        }
+        (_, "/metrics" | "/healthz") => ("405 Method Not Allowed", "text/plain", String::new()),
+        _ => ("404 Not Found", "text/plain", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
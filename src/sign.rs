@@ -0,0 +1,166 @@
+//! Detached signatures of archived game JARs and archived versions data.
+
+use crate::ArchiveError;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::info;
+
+/// Signer of local files, writing a detached signature next to every signed file.
+pub enum Signer {
+    /// Signs with a minisign secret key, writing `.minisig` signatures.
+    Minisign(Box<minisign::SecretKey>),
+    /// Signs with the GPG key of the given id through the `gpg` executable, writing `.asc`
+    /// signatures.
+    Gpg(String),
+}
+
+/// Verifier of the detached signatures next to local files.
+pub enum Verifier {
+    /// Verifies `.minisig` signatures against a minisign public key.
+    Minisign(Box<minisign::PublicKey>),
+    /// Verifies `.asc` signatures against the local keyring through the `gpg` executable.
+    Gpg,
+}
+
+impl Signer {
+    /// Creates a minisign signer from the contents of a secret key file, decrypting it with
+    /// `password` if encrypted.
+    pub fn minisign(secret_key: &str, password: Option<String>) -> Result<Self, ArchiveError> {
+        let secret_key = minisign::SecretKeyBox::from_string(secret_key)
+            .and_then(|it| it.into_secret_key(password))
+            .map_err(|cause| {
+                ArchiveError::Signature(format!("bad minisign secret key: {cause}"))
+            })?;
+        Ok(Self::Minisign(Box::new(secret_key)))
+    }
+
+    /// Creates a minisign signer from the secret key file at `path`, decrypting it with
+    /// `password` if encrypted.
+    pub fn minisign_from_file<P: AsRef<Path>>(
+        path: P,
+        password: Option<String>,
+    ) -> Result<Self, ArchiveError> {
+        Self::minisign(&fs::read_to_string(path)?, password)
+    }
+
+    /// Returns the extension of the signatures written by the signer.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Minisign(_) => "minisig",
+            Self::Gpg(_) => "asc",
+        }
+    }
+
+    /// Signs the local file at `path`, returning the path of the detached signature next to it.
+    pub fn sign(&self, path: &Path) -> Result<PathBuf, ArchiveError> {
+        let signature_path = signature_path(path, self.extension());
+        info!("Signing '{}'...", path.display());
+
+        match self {
+            Self::Minisign(secret_key) => {
+                let file = io::BufReader::new(File::open(path)?);
+                let signature = minisign::sign(None, secret_key, file, None, None)
+                    .map_err(|cause| ArchiveError::Signature(cause.to_string()))?;
+                fs::write(&signature_path, signature.to_string())?;
+            }
+            Self::Gpg(key_id) => {
+                gpg([
+                    "--yes".into(),
+                    "--armor".into(),
+                    "--local-user".into(),
+                    key_id.into(),
+                    "--output".into(),
+                    signature_path.clone().into(),
+                    "--detach-sign".into(),
+                    path.into(),
+                ])
+                .map_err(ArchiveError::Signature)?;
+            }
+        }
+
+        Ok(signature_path)
+    }
+}
+
+impl Verifier {
+    /// Creates a minisign verifier from either the contents of a public key file or the bare
+    /// base64 public key.
+    pub fn minisign(public_key: &str) -> Result<Self, ArchiveError> {
+        let public_key = public_key.trim();
+        let public_key = if public_key.contains('\n') {
+            minisign::PublicKeyBox::from_string(public_key).and_then(|it| it.into_public_key())
+        } else {
+            minisign::PublicKey::from_base64(public_key)
+        }
+        .map_err(|cause| ArchiveError::Signature(format!("bad minisign public key: {cause}")))?;
+        Ok(Self::Minisign(Box::new(public_key)))
+    }
+
+    /// Creates a minisign verifier from the public key file at `path`.
+    pub fn minisign_from_file<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        Self::minisign(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the extension of the signatures verified by the verifier.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Minisign(_) => "minisig",
+            Self::Gpg => "asc",
+        }
+    }
+
+    /// Verifies the local file at `path` against the detached signature next to it.
+    pub fn verify(&self, path: &Path) -> Result<(), ArchiveError> {
+        let signature_path = signature_path(path, self.extension());
+        if !signature_path.is_file() {
+            return Err(ArchiveError::MissingFile(signature_path));
+        }
+        let bad_signature = |reason: String| ArchiveError::BadSignature {
+            subject: path.display().to_string(),
+            reason,
+        };
+
+        match self {
+            Self::Minisign(public_key) => {
+                let signature =
+                    minisign::SignatureBox::from_string(&fs::read_to_string(&signature_path)?)
+                        .map_err(|cause| bad_signature(cause.to_string()))?;
+                let file = io::BufReader::new(File::open(path)?);
+                minisign::verify(public_key, &signature, file, true, false, false)
+                    .map_err(|cause| bad_signature(cause.to_string()))?;
+            }
+            Self::Gpg => {
+                gpg(["--verify".into(), signature_path.into(), path.into()])
+                    .map_err(bad_signature)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path of the detached signature with `extension` of the file at `path`, e.g.
+/// `versions.json.minisig`.
+pub fn signature_path(path: &Path, extension: &str) -> PathBuf {
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".");
+    signature_path.push(extension);
+    PathBuf::from(signature_path)
+}
+
+/// Runs `gpg` non-interactively with `args`, returning what it reported if it failed.
+fn gpg<const N: usize>(args: [OsString; N]) -> Result<(), String> {
+    let output = Command::new("gpg")
+        .arg("--batch")
+        .args(args)
+        .output()
+        .map_err(|cause| format!("failed to run gpg: {cause}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+    }
+}
@@ -0,0 +1,245 @@
+//! Staging area of fetched game JARs, holding them back from being archived until promoted after a
+//! human or automated check passes.
+//!
+//! Staged game JARs are moved into the staging directory along with the files written next to
+//! them, and their pending entries are recorded in its [`PENDING_FILE`]. Staged secondary files
+//! are moved into it the same way.
+
+use crate::atomic::write_atomically;
+use crate::manifest::{ArchivedFile, Artifact, Version};
+use crate::{ArchiveError, Sha256Hash};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// File name of the pending entries within a staging directory.
+pub const PENDING_FILE: &str = "pending.json";
+
+/// The pending entries of a staging directory.
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Pending {
+    /// Every staged version, in order.
+    pub versions: Vec<StagedVersion>,
+    /// Every staged secondary file, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<StagedFile>,
+}
+
+/// A single staged version of the game.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedVersion {
+    /// Pending entry of the version, as it will be archived.
+    pub version: Version,
+    /// Id of the itch.io upload the game JAR was downloaded from, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_id: Option<u64>,
+    /// File name of the game JAR within the staging directory.
+    pub jar: String,
+    /// File names of the files written next to the game JAR, e.g. its checksum file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// File names of the other game JARs extracted along with the game JAR.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+}
+
+/// A single staged file of a secondary target, e.g. the soundtrack.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedFile {
+    /// Pending entry of the file, as it will be archived.
+    pub file: ArchivedFile,
+    /// File name of the file within the staging directory.
+    pub name: String,
+}
+
+impl Pending {
+    /// Reads the pending entries of the staging directory `stage`, none if it has none yet.
+    pub fn read_from(stage: &Path) -> Result<Self, ArchiveError> {
+        match fs::read(stage.join(PENDING_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(cause) => Err(cause.into()),
+        }
+    }
+
+    /// Writes the pending entries as pretty JSON into the staging directory `stage`.
+    pub fn write_to(&self, stage: &Path) -> Result<(), ArchiveError> {
+        write_atomically(&stage.join(PENDING_FILE), |file| {
+            serde_json::to_writer_pretty(&mut *file, self)?;
+            Ok(file.write_all(b"\n")?)
+        })
+    }
+
+    /// Collects the sha256 hashes of every staged game JAR of `artifact`.
+    pub fn hashes(&self, artifact: Artifact) -> HashSet<Sha256Hash> {
+        self.versions
+            .iter()
+            .filter(|it| it.version.artifact() == artifact)
+            .map(|it| it.version.sha256)
+            .collect()
+    }
+}
+
+/// Moves the game JAR at `jar`, the existing `files` next to it, and the other game JARs `extras`
+/// into the staging directory `stage`, recording the pending entry `version`, and returns where
+/// the game JAR was moved to.
+///
+/// Files are moved by renaming them, so `stage` has to be on the same file system.
+pub fn stage_version(
+    stage: &Path,
+    version: Version,
+    download_id: Option<u64>,
+    jar: &Path,
+    files: &[PathBuf],
+    extras: &[PathBuf],
+) -> Result<PathBuf, ArchiveError> {
+    let mut pending = Pending::read_from(stage)?;
+    if pending
+        .versions
+        .iter()
+        .any(|it| it.version.sha256 == version.sha256)
+    {
+        return Err(ArchiveError::AlreadyArchived(jar.to_path_buf()));
+    }
+
+    fs::create_dir_all(stage)?;
+    let staged_jar = move_into(jar, stage)?;
+    let mut staged_files = Vec::with_capacity(files.len());
+    for file in files.iter().filter(|it| it.exists()) {
+        move_into(file, stage)?;
+        staged_files.push(file_name(file)?);
+    }
+    let mut staged_extras = Vec::with_capacity(extras.len());
+    for extra in extras {
+        move_into(extra, stage)?;
+        staged_extras.push(file_name(extra)?);
+    }
+
+    info!("Staging {} in '{}'...", version.id, stage.display());
+    pending.versions.push(StagedVersion {
+        version,
+        download_id,
+        jar: file_name(jar)?,
+        files: staged_files,
+        extras: staged_extras,
+    });
+    pending.write_to(stage)?;
+    Ok(staged_jar)
+}
+
+/// Moves the secondary file at `path` into the staging directory `stage`, recording the pending
+/// entry `file`, and returns where it was moved to.
+///
+/// Files are moved by renaming them, so `stage` has to be on the same file system.
+pub fn stage_file(stage: &Path, file: ArchivedFile, path: &Path) -> Result<PathBuf, ArchiveError> {
+    let mut pending = Pending::read_from(stage)?;
+    if pending.files.iter().any(|it| it.file.sha256 == file.sha256) {
+        return Err(ArchiveError::AlreadyArchived(path.to_path_buf()));
+    }
+
+    fs::create_dir_all(stage)?;
+    let staged = move_into(path, stage)?;
+    info!(
+        "Staging {} '{}' in '{}'...",
+        file.target,
+        file.title,
+        stage.display()
+    );
+    pending.files.push(StagedFile {
+        file,
+        name: file_name(path)?,
+    });
+    pending.write_to(stage)?;
+    Ok(staged)
+}
+
+/// Moves the game JAR of `staged`, the files next to it, and the other game JARs out of the
+/// staging directory `stage` into `directory`, and returns where the game JAR and the other game
+/// JARs were moved to.
+///
+/// Its pending entry is left for the caller to remove once archived.
+pub fn unstage_version(
+    stage: &Path,
+    staged: &StagedVersion,
+    directory: &Path,
+) -> Result<(PathBuf, Vec<PathBuf>), ArchiveError> {
+    fs::create_dir_all(directory)?;
+    let jar = move_into(&stage.join(&staged.jar), directory)?;
+    for file in &staged.files {
+        move_into(&stage.join(file), directory)?;
+    }
+    let extras = staged
+        .extras
+        .iter()
+        .map(|extra| move_into(&stage.join(extra), directory))
+        .collect::<Result<_, _>>()?;
+    Ok((jar, extras))
+}
+
+/// Moves the files of `staged` back from `directory` into the staging directory `stage`, undoing
+/// [`unstage_version`].
+pub fn restage_version(
+    stage: &Path,
+    staged: &StagedVersion,
+    directory: &Path,
+) -> Result<(), ArchiveError> {
+    let names = [&staged.jar]
+        .into_iter()
+        .chain(&staged.files)
+        .chain(&staged.extras);
+    for name in names {
+        let path = directory.join(name);
+        if path.exists() {
+            move_into(&path, stage)?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves the secondary file of `staged` out of the staging directory `stage` into `directory`, and
+/// returns where it was moved to.
+///
+/// Its pending entry is left for the caller to remove once archived.
+pub fn unstage_file(
+    stage: &Path,
+    staged: &StagedFile,
+    directory: &Path,
+) -> Result<PathBuf, ArchiveError> {
+    fs::create_dir_all(directory)?;
+    move_into(&stage.join(&staged.name), directory)
+}
+
+/// Moves the secondary file of `staged` back from `directory` into the staging directory `stage`,
+/// undoing [`unstage_file`].
+pub fn restage_file(
+    stage: &Path,
+    staged: &StagedFile,
+    directory: &Path,
+) -> Result<(), ArchiveError> {
+    let path = directory.join(&staged.name);
+    if path.exists() {
+        move_into(&path, stage)?;
+    }
+    Ok(())
+}
+
+fn move_into(path: &Path, directory: &Path) -> Result<PathBuf, ArchiveError> {
+    if !path.exists() {
+        return Err(ArchiveError::MissingFile(path.to_path_buf()));
+    }
+    let destination = directory.join(file_name(path)?);
+    fs::rename(path, &destination)?;
+    info!("Moved '{}' to '{}'", path.display(), destination.display());
+    Ok(destination)
+}
+
+fn file_name(path: &Path) -> Result<String, ArchiveError> {
+    path.file_name()
+        .and_then(|it| it.to_str())
+        .map(str::to_owned)
+        .ok_or_else(|| ArchiveError::MissingFile(path.to_path_buf()))
+}
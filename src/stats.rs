@@ -0,0 +1,159 @@
+//! Statistics summarizing the archived versions data, e.g. how often versions are released and
+//! how much the archive grows with each.
+
+use crate::manifest::compare_version_ids;
+use crate::{Artifact, Version, Versions};
+use std::collections::BTreeMap;
+
+/// Seconds in a day.
+const DAY: f64 = 86400.0;
+
+/// Statistics of the archived versions data.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStats {
+    /// How many versions are archived, of either artifact.
+    pub versions: usize,
+    /// Total size in bytes of every archived game JAR.
+    pub total_size: u64,
+    /// Count and size of the archived game JARs of each version type.
+    pub types: BTreeMap<String, TypeStats>,
+    /// Count of the archived game JARs of each artifact.
+    pub artifacts: BTreeMap<Artifact, usize>,
+    /// Days between the releases of consecutive client versions, if at least two.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cadence: Option<Cadence>,
+    /// Size of every archived game JAR in order of release, along with its growth.
+    pub growth: Vec<Growth>,
+}
+
+/// Statistics of the archived game JARs of one version type.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeStats {
+    /// How many game JARs are of the type.
+    pub count: usize,
+    /// Total size in bytes of the game JARs of the type.
+    pub total_size: u64,
+}
+
+/// Days between the releases of consecutive versions.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Cadence {
+    /// Mean days between releases.
+    pub mean: f64,
+    /// Median days between releases.
+    pub median: f64,
+    /// Fewest days between releases.
+    pub min: f64,
+    /// Most days between releases.
+    pub max: f64,
+}
+
+/// Size of an archived game JAR relative to the one released before it.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Growth {
+    /// Id of the version.
+    pub id: String,
+    /// Kind of the game JAR.
+    pub artifact: Artifact,
+    /// Unix timestamp, in seconds, of when the version was released.
+    pub release_time: u64,
+    /// Size in bytes of the game JAR.
+    pub size: u64,
+    /// Difference in bytes from the size of the previous game JAR of the same artifact, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_change: Option<i64>,
+    /// Total size in bytes of every game JAR released up to and including this one.
+    pub cumulative_size: u64,
+}
+
+impl ArchiveStats {
+    /// Calculates the statistics of every version of `versions`.
+    pub fn of(versions: &Versions) -> Self {
+        let mut released = versions.versions.iter().collect::<Vec<_>>();
+        released.sort_by(|a, b| {
+            a.release_time
+                .cmp(&b.release_time)
+                .then_with(|| compare_version_ids(&a.id, &b.id))
+                .then_with(|| a.artifact().cmp(&b.artifact()))
+        });
+
+        let mut types = BTreeMap::<String, TypeStats>::new();
+        let mut artifacts = BTreeMap::new();
+        let mut growth = Vec::with_capacity(released.len());
+        let mut previous_sizes = BTreeMap::new();
+        let mut cumulative_size = 0;
+        for version in &released {
+            let stats = types.entry(version.kind.clone()).or_default();
+            stats.count += 1;
+            stats.total_size += version.size;
+            *artifacts.entry(version.artifact()).or_default() += 1;
+
+            cumulative_size += version.size;
+            let previous_size = previous_sizes.insert(version.artifact(), version.size);
+            growth.push(Growth {
+                id: version.id.clone(),
+                artifact: version.artifact(),
+                release_time: version.release_time,
+                size: version.size,
+                size_change: previous_size.map(|it| size_change(it, version.size)),
+                cumulative_size,
+            });
+        }
+
+        let clients = released
+            .iter()
+            .copied()
+            .filter(|it| it.artifact() == Artifact::Client);
+        Self {
+            versions: released.len(),
+            total_size: cumulative_size,
+            types,
+            artifacts,
+            cadence: Cadence::of(clients),
+            growth,
+        }
+    }
+}
+
+impl Cadence {
+    /// Calculates the days between the releases of `versions`, in order of release, or returns
+    /// [`None`] if fewer than two.
+    pub fn of<'a>(versions: impl IntoIterator<Item = &'a Version>) -> Option<Self> {
+        let times = versions
+            .into_iter()
+            .map(|it| it.release_time)
+            .collect::<Vec<_>>();
+        let mut gaps = times
+            .windows(2)
+            .map(|pair| pair[1].saturating_sub(pair[0]) as f64 / DAY)
+            .collect::<Vec<_>>();
+        if gaps.is_empty() {
+            return None;
+        }
+        gaps.sort_by(f64::total_cmp);
+
+        let middle = gaps.len() / 2;
+        let median = if gaps.len() % 2 == 0 {
+            (gaps[middle - 1] + gaps[middle]) / 2.0
+        } else {
+            gaps[middle]
+        };
+        Some(Self {
+            mean: gaps.iter().sum::<f64>() / gaps.len() as f64,
+            median,
+            min: gaps[0],
+            max: gaps[gaps.len() - 1],
+        })
+    }
+}
+
+fn size_change(previous: u64, size: u64) -> i64 {
+    if size >= previous {
+        i64::try_from(size - previous).unwrap_or(i64::MAX)
+    } else {
+        i64::try_from(previous - size).map_or(i64::MIN, |it| -it)
+    }
+}
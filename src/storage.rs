@@ -0,0 +1,222 @@
+//! Object storage that archived files are mirrored to.
+
+use crate::hash::hash_reader;
+use crate::http::file_body;
+use crate::release::format_date;
+use crate::{ArchiveError, RetryPolicy};
+use hmac::Mac;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::CONTENT_LENGTH;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Seek};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Characters that are percent-encoded within an S3 object key, i.e. everything but the
+/// unreserved characters of RFC 3986.
+const KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Headers signed in every S3 request.
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// Default region of an S3-compatible API, which MinIO also defaults to.
+pub const DEFAULT_S3_REGION: &str = "us-east-1";
+
+/// A storage backend archived files are uploaded to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Storage {
+    /// A bucket of an S3-compatible API, e.g. AWS S3, MinIO, or Cloudflare R2.
+    S3(S3Storage),
+}
+
+/// A bucket of an S3-compatible API, addressed path-style.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct S3Storage {
+    /// Endpoint of the API, e.g. `https://s3.us-east-1.amazonaws.com`.
+    pub endpoint: url::Url,
+    /// Name of the bucket.
+    pub bucket: String,
+    /// Prefix of the key of every uploaded object, e.g. `jars/`.
+    pub prefix: String,
+    /// Region the bucket is in.
+    pub region: String,
+    /// Access key id to sign requests with.
+    pub access_key: String,
+    /// Secret access key to sign requests with.
+    pub secret_key: String,
+    /// Public URL the bucket is served from, if other than the endpoint.
+    pub public_url: Option<url::Url>,
+}
+
+impl Storage {
+    /// Returns the URL every uploaded file is publicly downloaded relative to.
+    pub fn base_url(&self) -> Result<url::Url, ArchiveError> {
+        match self {
+            Self::S3(s3) => s3.base_url(),
+        }
+    }
+
+    /// Uploads the whole `file` of `content_type` as the file named `name`, streaming it rather
+    /// than reading it into memory, and returns where it can be publicly downloaded from.
+    pub async fn put(
+        &self,
+        client: &itch_io::Client,
+        name: &str,
+        file: File,
+        content_type: &str,
+        retry: &RetryPolicy,
+    ) -> Result<url::Url, ArchiveError> {
+        match self {
+            Self::S3(s3) => s3.put(client, name, file, content_type, retry).await?,
+        }
+
+        let url = self.base_url()?.join(&encode_key(name))?;
+        warn!("Uploaded '{name}' to {url}");
+        Ok(url)
+    }
+}
+
+impl S3Storage {
+    fn base_url(&self) -> Result<url::Url, ArchiveError> {
+        let base = match &self.public_url {
+            Some(url) => url.to_string(),
+            None => format!(
+                "{}/{}",
+                self.endpoint.as_str().trim_end_matches('/'),
+                self.bucket
+            ),
+        };
+        let base = format!(
+            "{}/{}",
+            base.trim_end_matches('/'),
+            encode_key(&self.prefix())
+        );
+        Ok(url::Url::parse(&base)?)
+    }
+
+    fn object_url(&self, name: &str) -> Result<url::Url, ArchiveError> {
+        let key = format!("{}{name}", self.prefix());
+        Ok(url::Url::parse(&format!(
+            "{}/{}/{}",
+            self.endpoint.as_str().trim_end_matches('/'),
+            self.bucket,
+            encode_key(&key)
+        ))?)
+    }
+
+    /// Returns the prefix of every key, ending with `/` unless empty.
+    fn prefix(&self) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}/")
+        }
+    }
+
+    async fn put(
+        &self,
+        client: &itch_io::Client,
+        name: &str,
+        mut file: File,
+        content_type: &str,
+        retry: &RetryPolicy,
+    ) -> Result<(), ArchiveError> {
+        let url = self.object_url(name)?;
+        file.rewind()?;
+        let (sha256, size) = hash_reader(io::BufReader::new(&mut file))?;
+        let payload_hash = sha256.to_string();
+        let (url, payload_hash, file) = (&url, &payload_hash, &file);
+
+        retry
+            .retry("upload to S3", || async move {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |it| it.as_secs());
+                let (amz_date, authorization) =
+                    self.authorization("PUT", url, payload_hash, timestamp);
+                let (body, _) = file_body(file)?;
+
+                info!("Sending PUT request to S3 ({url})...");
+                client
+                    .client
+                    .put(url.clone())
+                    .header("x-amz-date", amz_date)
+                    .header("x-amz-content-sha256", payload_hash)
+                    .header("authorization", authorization)
+                    .header("content-type", content_type)
+                    .header(CONTENT_LENGTH, size)
+                    .body(body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok::<_, ArchiveError>(())
+            })
+            .await
+    }
+
+    /// Signs a request with AWS Signature Version 4 at `timestamp`, returning its `x-amz-date`
+    /// and `authorization` headers.
+    fn authorization(
+        &self,
+        method: &str,
+        url: &url::Url,
+        payload_hash: &str,
+        timestamp: u64,
+    ) -> (String, String) {
+        let date = format_date(timestamp).replace('-', "");
+        let seconds = timestamp % 86400;
+        let amz_date = format!(
+            "{date}T{:02}{:02}{:02}Z",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+        let host = url.host_str().unwrap_or_default();
+        let host = match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_owned(),
+        };
+
+        let canonical_request = format!(
+            "{method}\n{}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{SIGNED_HEADERS}\n{payload_hash}",
+            url.path()
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request))
+        );
+
+        let key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        let key = hmac(&key, &self.region);
+        let key = hmac(&key, "s3");
+        let key = hmac(&key, "aws4_request");
+        let signature = hex::encode(hmac(&key, &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, Signature={signature}",
+            self.access_key
+        );
+        (amz_date, authorization)
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = hmac::Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes every segment of `key`, keeping the `/` between them.
+fn encode_key(key: &str) -> String {
+    key.split('/')
+        .map(|it| utf8_percent_encode(it, KEY_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
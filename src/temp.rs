@@ -0,0 +1,21 @@
+//! Temporary files that downloads and archive entries are spilled into instead of memory, so that
+//! memory use stays bounded regardless of how large the game gets.
+
+use crate::ArchiveError;
+use std::fs::{self, File};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Creates an anonymous temporary file within `dir`, creating it if missing, which is removed
+/// once closed.
+pub fn tempfile(dir: &Path) -> Result<File, ArchiveError> {
+    fs::create_dir_all(dir)?;
+    Ok(tempfile::tempfile_in(dir)?)
+}
+
+/// Creates a named temporary file within `dir`, creating it if missing, which is removed once
+/// dropped.
+pub fn named_tempfile(dir: &Path) -> Result<NamedTempFile, ArchiveError> {
+    fs::create_dir_all(dir)?;
+    Ok(NamedTempFile::new_in(dir)?)
+}
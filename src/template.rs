@@ -0,0 +1,49 @@
+//! Rendering of the templates of commit messages, pull requests, and notifications, replacing
+//! every `{name}` placeholder by the value of its variable.
+//!
+//! Placeholders of unknown variables are kept as is, leaving e.g. the braces of JSON intact.
+
+/// Renders `template`, replacing every placeholder of the `variables` by its value passed through
+/// `escape`.
+pub fn render_with(
+    template: &str,
+    variables: &[(&str, String)],
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let placeholder = tail.find('}').map(|end| &tail[..=end]);
+        let value = placeholder.and_then(|placeholder| {
+            let name = &placeholder[1..placeholder.len() - 1];
+            variables.iter().find(|(it, _)| *it == name)
+        });
+        match (placeholder, value) {
+            (Some(placeholder), Some((_, value))) => {
+                rendered.push_str(&escape(value));
+                rest = &tail[placeholder.len()..];
+            }
+            _ => {
+                rendered.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Renders `template` as is, see [`render_with`].
+pub fn render(template: &str, variables: &[(&str, String)]) -> String {
+    render_with(template, variables, str::to_owned)
+}
+
+/// Renders the JSON `template`, escaping every value to be within a JSON string.
+pub fn render_json(template: &str, variables: &[(&str, String)]) -> String {
+    render_with(template, variables, |value| {
+        let quoted = serde_json::Value::from(value).to_string();
+        quoted[1..quoted.len() - 1].to_owned()
+    })
+}
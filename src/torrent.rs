@@ -0,0 +1,187 @@
+//! BitTorrent metainfo files and magnet links of archived game JARs.
+
+use crate::atomic::write_atomically;
+use crate::ArchiveError;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Size in bytes of every piece of a torrent when none is given.
+pub const DEFAULT_PIECE_LENGTH: u64 = 256 * 1024;
+
+/// Smallest size in bytes of a piece that BitTorrent clients accept.
+pub const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+
+/// A single-file torrent.
+///
+/// No creation date is recorded, so that the torrent of the same file is always the same.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Torrent {
+    /// File name of the file.
+    pub name: String,
+    /// Size in bytes of the file.
+    pub length: u64,
+    /// Size in bytes of every piece but the last one.
+    pub piece_length: u64,
+    /// The sha1 hash of every piece, in order.
+    pub pieces: Vec<[u8; 20]>,
+    /// Announce URLs of the trackers, in order of preference.
+    pub trackers: Vec<url::Url>,
+    /// URLs the file itself can be downloaded from, see BEP 19.
+    pub web_seeds: Vec<url::Url>,
+}
+
+/// A bencoded value.
+enum Bencode {
+    Int(u64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<&'static str, Bencode>),
+}
+
+impl Bencode {
+    fn string(s: &str) -> Self {
+        Self::Bytes(s.as_bytes().to_vec())
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Int(it) => out.extend_from_slice(format!("i{it}e").as_bytes()),
+            Self::Bytes(it) => {
+                out.extend_from_slice(format!("{}:", it.len()).as_bytes());
+                out.extend_from_slice(it);
+            }
+            Self::List(it) => {
+                out.push(b'l');
+                it.iter().for_each(|it| it.encode(out));
+                out.push(b'e');
+            }
+            Self::Dict(it) => {
+                out.push(b'd');
+                for (key, value) in it {
+                    Self::string(key).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+impl Torrent {
+    /// Creates the torrent of the file named `name` read from `reader`, split into pieces of
+    /// `piece_length` bytes.
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        name: String,
+        piece_length: u64,
+    ) -> Result<Self, ArchiveError> {
+        info!("Hashing pieces of '{name}'...");
+        let mut pieces = Vec::new();
+        let mut length = 0;
+        let mut piece = Vec::new();
+        loop {
+            piece.clear();
+            let read = (&mut reader).take(piece_length).read_to_end(&mut piece)?;
+            if read == 0 {
+                break;
+            }
+            length += read as u64;
+            pieces.push(Sha1::digest(&piece).into());
+        }
+
+        Ok(Self {
+            name,
+            length,
+            piece_length,
+            pieces,
+            trackers: Vec::new(),
+            web_seeds: Vec::new(),
+        })
+    }
+
+    fn info(&self) -> Bencode {
+        Bencode::Dict(BTreeMap::from([
+            ("length", Bencode::Int(self.length)),
+            ("name", Bencode::string(&self.name)),
+            ("piece length", Bencode::Int(self.piece_length)),
+            ("pieces", Bencode::Bytes(self.pieces.concat())),
+        ]))
+    }
+
+    /// Returns the sha1 hash of the bencoded info dictionary identifying the torrent.
+    pub fn info_hash(&self) -> [u8; 20] {
+        let mut info = Vec::new();
+        self.info().encode(&mut info);
+        Sha1::digest(&info).into()
+    }
+
+    /// Returns the bencoded metainfo file of the torrent.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let urls = |urls: &[url::Url]| {
+            Bencode::List(urls.iter().map(|it| Bencode::string(it.as_str())).collect())
+        };
+
+        let mut metainfo = BTreeMap::from([
+            (
+                "created by",
+                Bencode::string(concat!("cosmicarchive-updater/", env!("CARGO_PKG_VERSION"))),
+            ),
+            ("info", self.info()),
+        ]);
+        if let Some(tracker) = self.trackers.first() {
+            metainfo.insert("announce", Bencode::string(tracker.as_str()));
+            let tiers = self
+                .trackers
+                .iter()
+                .map(|it| urls(std::slice::from_ref(it)));
+            metainfo.insert("announce-list", Bencode::List(tiers.collect()));
+        }
+        if !self.web_seeds.is_empty() {
+            metainfo.insert("url-list", urls(&self.web_seeds));
+        }
+
+        let mut bytes = Vec::new();
+        Bencode::Dict(metainfo).encode(&mut bytes);
+        bytes
+    }
+
+    /// Returns the magnet link of the torrent, along with its trackers and web seeds.
+    pub fn magnet_link(&self) -> String {
+        let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
+
+        let mut link = format!(
+            "magnet:?xt=urn:btih:{}&dn={}&xl={}",
+            hex::encode(self.info_hash()),
+            encode(&self.name),
+            self.length
+        );
+        for tracker in &self.trackers {
+            link.push_str(&format!("&tr={}", encode(tracker.as_str())));
+        }
+        for web_seed in &self.web_seeds {
+            link.push_str(&format!("&ws={}", encode(web_seed.as_str())));
+        }
+        link
+    }
+
+    /// Writes the metainfo file of the torrent to the local file at `path`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ArchiveError> {
+        let bytes = self.to_bytes();
+        write_atomically(path.as_ref(), |file| Ok(file.write_all(&bytes)?))
+    }
+}
+
+/// Returns the path of the metainfo file of the file named `file_name` within `directory`, e.g.
+/// `Cosmic Reach-0.1.44.jar.torrent`.
+pub fn torrent_path(directory: &Path, file_name: &str) -> PathBuf {
+    directory.join(format!("{file_name}.torrent"))
+}
+
+/// Checks whether `piece_length` is a power of two of at least [`MIN_PIECE_LENGTH`] bytes.
+pub fn is_valid_piece_length(piece_length: u64) -> bool {
+    piece_length >= MIN_PIECE_LENGTH && piece_length.is_power_of_two()
+}
@@ -0,0 +1,240 @@
+//! Updating of the updater itself from the binaries attached to its GitHub Releases.
+
+use crate::atomic::write_atomically;
+use crate::github::API_URL;
+use crate::hash::hash_reader;
+use crate::manifest::compare_version_ids;
+use crate::{ArchiveError, RetryPolicy, Sha256Hash};
+use std::cmp::Ordering;
+use std::env::consts::{ARCH, EXE_SUFFIX, OS};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Repository whose releases the updater is published as.
+pub const UPDATER_REPOSITORY: &str = "CRModders/CosmicArchive";
+
+/// Prefix of the tags of the releases of the updater, followed by its version, e.g.
+/// `updater-v0.1.0`.
+///
+/// Releases of archived versions are tagged with just their version ids instead.
+pub const UPDATER_TAG_PREFIX: &str = "updater-v";
+
+/// A release of the updater, as returned by the REST API.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+pub struct UpdaterRelease {
+    /// Name of the tag of the release.
+    pub tag_name: String,
+    /// URL to the page of the release.
+    pub html_url: String,
+    /// Whether the release is a draft.
+    #[serde(default)]
+    pub draft: bool,
+    /// Whether the release is a pre-release.
+    #[serde(default)]
+    pub prerelease: bool,
+    /// Files attached to the release.
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a release, as returned by the REST API.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Deserialize)]
+pub struct ReleaseAsset {
+    /// File name of the asset.
+    pub name: String,
+    /// Where the asset can be downloaded from.
+    pub browser_download_url: String,
+    /// Size in bytes of the asset.
+    pub size: u64,
+}
+
+impl UpdaterRelease {
+    /// Returns the version of the updater released, e.g. `0.1.0`.
+    pub fn version(&self) -> Option<&str> {
+        self.tag_name.strip_prefix(UPDATER_TAG_PREFIX)
+    }
+
+    /// Returns the asset named `name`, if attached.
+    pub fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|it| it.name == name)
+    }
+}
+
+/// Returns the file name of the binary of the updater for the current platform, e.g.
+/// `cosmic-archive-x86_64-linux` or `cosmic-archive-x86_64-windows.exe`.
+pub fn binary_name() -> String {
+    format!("cosmic-archive-{ARCH}-{OS}{EXE_SUFFIX}")
+}
+
+/// Checks whether the updater `version` is newer than the running one.
+pub fn is_newer(version: &str) -> bool {
+    compare_version_ids(version, env!("CARGO_PKG_VERSION")) == Ordering::Greater
+}
+
+/// Finds the newest release of the updater in `repository`, skipping drafts and pre-releases
+/// unless `prerelease`.
+pub async fn get_latest_release(
+    client: &itch_io::Client,
+    repository: &str,
+    token: Option<&str>,
+    prerelease: bool,
+    retry: &RetryPolicy,
+) -> Result<Option<UpdaterRelease>, ArchiveError> {
+    let url = format!("{API_URL}/repos/{repository}/releases");
+    let url = &url;
+
+    let releases = retry
+        .retry("list GitHub releases", || async move {
+            info!("Sending GET request to GitHub ({url})...");
+            let mut request = client
+                .client
+                .get(url)
+                .query(&[("per_page", "100")])
+                .header("accept", "application/vnd.github+json");
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let response = request.send().await?.error_for_status()?;
+            Ok::<_, ArchiveError>(response.json::<Vec<UpdaterRelease>>().await?)
+        })
+        .await?;
+
+    Ok(releases
+        .into_iter()
+        .filter(|it| !it.draft && (prerelease || !it.prerelease))
+        .filter(|it| it.version().is_some())
+        .max_by(|a, b| {
+            compare_version_ids(
+                a.version().unwrap_or_default(),
+                b.version().unwrap_or_default(),
+            )
+        }))
+}
+
+/// Downloads the binary for the current platform attached to `release`, verifying it against the
+/// checksum file attached next to it.
+pub async fn download_binary(
+    client: &itch_io::Client,
+    release: &UpdaterRelease,
+    retry: &RetryPolicy,
+) -> Result<Vec<u8>, ArchiveError> {
+    let name = binary_name();
+    let Some(binary) = release.asset(&name) else {
+        return Err(ArchiveError::SelfUpdate(format!(
+            "release {} has NO binary for this platform ({name})",
+            release.tag_name
+        )));
+    };
+    let checksum_name = format!("{name}.sha256");
+    let Some(checksum) = release.asset(&checksum_name) else {
+        return Err(ArchiveError::SelfUpdate(format!(
+            "release {} has NO checksum file of the binary ({checksum_name})",
+            release.tag_name
+        )));
+    };
+
+    let checksum = download_asset(client, checksum, retry).await?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksum), &name)?;
+    let bytes = download_asset(client, binary, retry).await?;
+    if bytes.len() as u64 != binary.size {
+        return Err(ArchiveError::SizeMismatch {
+            subject: name,
+            expected: binary.size,
+            actual: bytes.len() as u64,
+        });
+    }
+    let (actual, _) = hash_reader(bytes.as_slice())?;
+    if actual != expected {
+        return Err(ArchiveError::HashMismatch {
+            subject: name,
+            expected,
+            actual,
+        });
+    }
+    info!("Verified '{name}' has sha256 hash {actual}");
+    Ok(bytes)
+}
+
+async fn download_asset(
+    client: &itch_io::Client,
+    asset: &ReleaseAsset,
+    retry: &RetryPolicy,
+) -> Result<Vec<u8>, ArchiveError> {
+    let url = &asset.browser_download_url;
+    retry
+        .retry("download GitHub release asset", || async move {
+            info!("Sending GET request to GitHub ({url})...");
+            let response = client.client.get(url).send().await?.error_for_status()?;
+            Ok::<_, ArchiveError>(response.bytes().await?.to_vec())
+        })
+        .await
+}
+
+/// Finds the hash of the file named `name` within the checksum file `text`, in the format that
+/// `sha256sum` prints.
+fn parse_checksum(text: &str, name: &str) -> Result<Sha256Hash, ArchiveError> {
+    let hash = text.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let hash = fields.next()?;
+        match fields.next() {
+            Some(file_name) => (file_name.trim_start_matches('*') == name).then_some(hash),
+            None => Some(hash),
+        }
+    });
+    let Some(hash) = hash else {
+        return Err(ArchiveError::SelfUpdate(format!(
+            "checksum file lists NO hash of '{name}'"
+        )));
+    };
+    hash.parse()
+        .map_err(|cause| ArchiveError::SelfUpdate(format!("invalid hash of '{name}': {cause}")))
+}
+
+/// Replaces the executable at `executable` by `bytes`, keeping the replaced one as `.old` on
+/// Windows, which refuses to overwrite a running executable.
+pub fn replace_executable(executable: &Path, bytes: &[u8]) -> Result<(), ArchiveError> {
+    let permissions = fs::metadata(executable)?.permissions();
+    if cfg!(windows) {
+        let old = executable.with_extension("old");
+        if old.exists() {
+            fs::remove_file(&old)?;
+        }
+        fs::rename(executable, &old)?;
+    }
+    write_atomically(executable, |file| {
+        file.write_all(bytes)?;
+        Ok(file.set_permissions(permissions)?)
+    })?;
+    warn!("Replaced executable '{}'", executable.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_checksum_of_name() {
+        let a = "11".repeat(32);
+        let b = "22".repeat(32);
+        let text = format!("{a}  updater-linux\n{b} *updater-windows.exe\n");
+        assert_eq!(
+            parse_checksum(&text, "updater-windows.exe").unwrap(),
+            b.parse().unwrap()
+        );
+        assert_eq!(
+            parse_checksum(&format!("{a}\n"), "updater-linux").unwrap(),
+            a.parse().unwrap()
+        );
+        assert!(matches!(
+            parse_checksum(&text, "updater-macos"),
+            Err(ArchiveError::SelfUpdate(_))
+        ));
+        assert!(matches!(
+            parse_checksum("nothex  updater-linux\n", "updater-linux"),
+            Err(ArchiveError::SelfUpdate(_))
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+//! Checksums published upstream, on the itch.io game page or in devlog posts, cross-checked against
+//! downloads before archiving them.
+
+use crate::hash::{Digests, HashAlgorithm};
+use crate::ArchiveError;
+use regex::Regex;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// A checksum published upstream, e.g. `SHA-256: 3f1a…`.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct PublishedHash {
+    /// Algorithm of the checksum.
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex string of the checksum.
+    pub hex: String,
+}
+
+/// Every algorithm of which checksums may be published upstream.
+pub const PUBLISHED_ALGORITHMS: [HashAlgorithm; 4] = [
+    HashAlgorithm::Md5,
+    HashAlgorithm::Sha1,
+    HashAlgorithm::Sha256,
+    HashAlgorithm::Sha512,
+];
+
+/// Finds every checksum labelled by its algorithm within the HTML or Markdown `text`, e.g.
+/// `md5: …` or `<b>SHA-256</b> …`.
+pub fn find_published_hashes(text: &str) -> Vec<PublishedHash> {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    static LABELLED: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"<[^>]*>").expect("HTML tag regex should be valid"));
+    let labelled = LABELLED.get_or_init(|| {
+        Regex::new(r"(?i)\b(md5|sha-?1|sha-?256|sha-?512)\b[^0-9a-z]{0,32}([0-9a-f]{32,128})\b")
+            .expect("labelled hash regex should be valid")
+    });
+
+    let text = tag.replace_all(text, " ");
+    let mut hashes = Vec::new();
+    for captures in labelled.captures_iter(&text) {
+        let Ok(algorithm) = captures[1].replace('-', "").parse::<HashAlgorithm>() else {
+            continue;
+        };
+        let hash = PublishedHash {
+            algorithm,
+            hex: captures[2].to_ascii_lowercase(),
+        };
+        if hash.hex.len() == hex_len(algorithm) && !hashes.contains(&hash) {
+            hashes.push(hash);
+        }
+    }
+    hashes
+}
+
+fn hex_len(algorithm: HashAlgorithm) -> usize {
+    match algorithm {
+        HashAlgorithm::Md5 => 32,
+        HashAlgorithm::Sha1 => 40,
+        HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+        HashAlgorithm::Sha512 => 128,
+    }
+}
+
+/// Checks whether any of the `published` hashes is one of the `digests` of the downloaded files,
+/// e.g. of the zip archive and the game JAR within.
+///
+/// Upstream may publish the checksums of several versions at once, so a download only mismatches
+/// if NONE of them are of it. Passes if nothing is published.
+pub fn check_published_hashes(
+    published: &[PublishedHash],
+    digests: &[Digests],
+) -> Result<(), ArchiveError> {
+    if published.is_empty() {
+        info!("Found NO checksums published upstream");
+        return Ok(());
+    }
+
+    let matched = published.iter().find(|hash| {
+        digests
+            .iter()
+            .any(|it| it.hex(hash.algorithm).as_deref() == Some(hash.hex.as_str()))
+    });
+    match matched {
+        Some(hash) => {
+            info!("Download matches the {} published upstream", hash.algorithm);
+            Ok(())
+        }
+        None => Err(ArchiveError::UpstreamHashMismatch(
+            published
+                .iter()
+                .map(|it| format!("{} {}", it.algorithm, it.hex))
+                .collect(),
+        )),
+    }
+}
@@ -0,0 +1,239 @@
+//! Auditing of archived versions against their recorded hashes and sizes.
+
+use crate::download::download_file;
+use crate::hash::{hash_file, hash_reader};
+use crate::{ArchiveError, RetryPolicy, Version, Versions};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Differences between the archived versions data and a directory of archived game JARs, see
+/// [`check_directory`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Report {
+    /// Game JARs that no archived version refers to, neither by hash nor by file name.
+    pub unreferenced: Vec<PathBuf>,
+    /// Ids of the archived versions without a game JAR of their hash.
+    pub missing: Vec<String>,
+    /// Game JARs named after an archived version, along with its id, but NOT of its hash.
+    pub mismatched: Vec<(PathBuf, String)>,
+}
+
+impl Report {
+    /// Checks whether the archived versions data and the directory agree.
+    pub fn is_clean(&self) -> bool {
+        self.unreferenced.is_empty() && self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Verifies the archived game JAR of `version` against its recorded sha256 hash and size.
+///
+/// The game JAR is read from within `mirror` by the file name of its URL if present, otherwise
+/// downloaded from its URL into a temporary file within `temp_dir`.
+pub async fn verify_version(
+    client: &itch_io::Client,
+    version: &Version,
+    mirror: Option<&Path>,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<(), ArchiveError> {
+    let (subject, mut file) = open_archived_jar(client, version, mirror, temp_dir, retry).await?;
+    check_archived_jar(version, &subject, &mut file)
+}
+
+/// Opens the archived game JAR of `version` along with where it was read from, see
+/// [`verify_version`].
+pub async fn open_archived_jar(
+    client: &itch_io::Client,
+    version: &Version,
+    mirror: Option<&Path>,
+    temp_dir: &Path,
+    retry: &RetryPolicy,
+) -> Result<(String, File), ArchiveError> {
+    Ok(match mirror {
+        Some(mirror) => {
+            let path = mirror.join(url_file_name(&version.url)?);
+            if !path.is_file() {
+                return Err(ArchiveError::MissingFile(path));
+            }
+
+            info!(
+                "Reading mirrored game JAR of {} ({})...",
+                version.id,
+                path.display()
+            );
+            (path.display().to_string(), File::open(path)?)
+        }
+        None => {
+            let url = version.url.as_str();
+            let file = download_file(
+                client,
+                url,
+                Some(version.size),
+                None,
+                "download archived game JAR",
+                temp_dir,
+                retry,
+            )
+            .await?;
+            (url.to_owned(), file)
+        }
+    })
+}
+
+/// Checks the game JAR read from `file` at `subject` against the recorded sha256 hash and size of
+/// `version`, leaving `file` at its end.
+pub fn check_archived_jar(
+    version: &Version,
+    subject: &str,
+    file: &mut File,
+) -> Result<(), ArchiveError> {
+    let (sha256, size) = hash_reader(io::BufReader::new(file))?;
+    if size != version.size {
+        return Err(ArchiveError::SizeMismatch {
+            subject: subject.to_owned(),
+            expected: version.size,
+            actual: size,
+        });
+    }
+    if sha256 != version.sha256 {
+        return Err(ArchiveError::HashMismatch {
+            subject: subject.to_owned(),
+            expected: version.sha256,
+            actual: sha256,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares `versions` against every game JAR within `directory` and its subdirectories.
+///
+/// Game JARs are matched to archived versions by their sha256 hashes, and only otherwise by the
+/// file names of their URLs, see [`Report`].
+pub fn check_directory(versions: &Versions, directory: &Path) -> Result<Report, ArchiveError> {
+    let mut by_hash = BTreeMap::new();
+    for path in find_jars(directory)? {
+        info!("Hashing '{}'...", path.display());
+        by_hash
+            .entry(hash_file(&path)?)
+            .or_insert_with(Vec::new)
+            .push(path);
+    }
+
+    let mut report = Report::default();
+    for version in &versions.versions {
+        if by_hash.remove(&version.sha256).is_none() {
+            report.missing.push(version.id.clone());
+        }
+    }
+    for path in by_hash.into_values().flatten() {
+        let file_name = path.file_name().and_then(|it| it.to_str());
+        let named = versions.versions.iter().find(|version| {
+            url_file_name(&version.url).is_ok_and(|it| Some(it.as_str()) == file_name)
+        });
+        match named {
+            Some(version) => report.mismatched.push((path, version.id.clone())),
+            None => report.unreferenced.push(path),
+        }
+    }
+
+    report.unreferenced.sort();
+    report.mismatched.sort();
+    Ok(report)
+}
+
+/// Lists every file ending with `.jar` within `directory` and its subdirectories, skipping hidden
+/// ones such as `.git`.
+fn find_jars(directory: &Path) -> Result<Vec<PathBuf>, ArchiveError> {
+    let mut jars = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|it| it == "jar") {
+                jars.push(path);
+            }
+        }
+    }
+    Ok(jars)
+}
+
+/// Returns the percent-decoded last path segment of `url`.
+pub fn url_file_name(url: &url::Url) -> Result<String, ArchiveError> {
+    url.path_segments()
+        .and_then(|mut it| it.next_back())
+        .filter(|it| !it.is_empty())
+        .map(|it| {
+            percent_encoding::percent_decode_str(it)
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .ok_or_else(|| ArchiveError::NoFileName(url.clone()))
+}
+
+/// Result of requesting an archived URL, see [`check_link`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LinkStatus {
+    /// The archived URL.
+    pub url: url::Url,
+    /// HTTP status of the response, if any was received.
+    pub status: Option<u16>,
+    /// Where the archived URL redirected to, if anywhere else.
+    pub redirected_to: Option<url::Url>,
+    /// Why NO response was received, if so.
+    pub error: Option<String>,
+}
+
+impl LinkStatus {
+    /// Checks whether the archived URL responded successfully.
+    pub fn is_healthy(&self) -> bool {
+        self.status.is_some_and(|it| (200..300).contains(&it))
+    }
+}
+
+/// Sends a HEAD request to the archived `url` without downloading it, following redirects.
+///
+/// Only failures to receive any response are retried, as any status is worth reporting as is.
+pub async fn check_link(
+    client: &itch_io::Client,
+    url: &url::Url,
+    retry: &RetryPolicy,
+) -> LinkStatus {
+    let response = retry
+        .retry("check archived link", || async move {
+            info!("Sending HEAD request to {url}...");
+            Ok::<_, ArchiveError>(client.client.head(url.clone()).send().await?)
+        })
+        .await;
+    match response {
+        Ok(response) => LinkStatus {
+            url: url.clone(),
+            status: Some(response.status().as_u16()),
+            redirected_to: (response.url() != url).then(|| response.url().clone()),
+            error: None,
+        },
+        Err(cause) => LinkStatus {
+            url: url.clone(),
+            status: None,
+            redirected_to: None,
+            error: Some(cause.to_string()),
+        },
+    }
+}
+
+/// Returns every archived URL of `version`, e.g. of its game JAR and devlog post.
+pub fn version_links(version: &Version) -> Vec<&url::Url> {
+    let mut links = vec![&version.url];
+    links.extend(&version.devlog);
+    links.extend(version.slim.as_ref().map(|it| &it.url));
+    links
+}